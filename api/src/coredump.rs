@@ -0,0 +1,531 @@
+//! Core dumps of processes killed by signals, see `core(5)`.
+//!
+//! The core file is an ELF file of type `ET_CORE`. Its `PT_NOTE` segment
+//! describes the process, the signal and the registers of each thread, and
+//! there is a `PT_LOAD` segment for each user memory area, with the contents
+//! of the writable and anonymous ones. Read-only file mappings are left for
+//! the debugger to read from the files listed in the `NT_FILE` note.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::{fmt::Write as _, mem::size_of, slice};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::{
+    arch::TrapFrame,
+    paging::MappingFlags,
+    time::{NANOS_PER_MICROS, NANOS_PER_SEC},
+};
+use axio::Write;
+use axprocess::Process;
+use axsignal::{SignalInfo, Signo};
+use axtask::{AxTaskRef, TaskExtRef, current};
+use linux_raw_sys::{
+    elf_uapi::*,
+    general::{AT_FDCWD, RLIMIT_CORE, SI_KERNEL, W_OK, kernel_sigset_t},
+};
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddrRange};
+use starry_core::{
+    ptrace::{FpState, save_fp_state},
+    task::{ThreadData, user_trap_frame},
+    time::wall_time,
+};
+
+use crate::{
+    path::handle_file_path,
+    perm::{check_create, file_meta, set_file_meta},
+    regs::{UserRegs, fp_regs, user_regs},
+    signal::send_signal_thread,
+};
+
+/// The path of the file holding the name pattern of core files.
+const CORE_PATTERN_PATH: &str = "/proc/sys/kernel/core_pattern";
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        const ELF_MACHINE: u32 = EM_X86_64;
+    } else if #[cfg(target_arch = "aarch64")] {
+        const ELF_MACHINE: u32 = EM_AARCH64;
+    } else if #[cfg(target_arch = "riscv64")] {
+        const ELF_MACHINE: u32 = EM_RISCV;
+    } else if #[cfg(target_arch = "loongarch64")] {
+        const ELF_MACHINE: u32 = EM_LOONGARCH;
+    }
+}
+
+/// `struct elf_prstatus`, the `NT_PRSTATUS` note of a thread.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ElfPrstatus {
+    /// `si_signo`, `si_code` and `si_errno` of the signal
+    info: [i32; 3],
+    cursig: u16,
+    _pad: u16,
+    sigpend: u64,
+    sighold: u64,
+    pid: i32,
+    ppid: i32,
+    pgrp: i32,
+    sid: i32,
+    /// The user, system, and children's user and system times
+    times: [[i64; 2]; 4],
+    reg: UserRegs,
+    fpvalid: i32,
+    _pad2: i32,
+}
+
+/// `struct elf_prpsinfo`, the `NT_PRPSINFO` note of a process.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ElfPrpsinfo {
+    state: i8,
+    sname: u8,
+    zomb: i8,
+    nice: i8,
+    _pad: u32,
+    flag: u64,
+    uid: u32,
+    gid: u32,
+    pid: i32,
+    ppid: i32,
+    pgrp: i32,
+    sid: i32,
+    fname: [u8; 16],
+    psargs: [u8; 80],
+}
+
+/// Views a structure without padding bytes as bytes.
+fn as_bytes<T>(value: &T) -> &[u8] {
+    // SAFETY: the structures written to core files have no padding bytes, so
+    // all their bytes are initialized.
+    unsafe { slice::from_raw_parts((value as *const T).cast(), size_of::<T>()) }
+}
+
+/// The contents of a `PT_NOTE` segment.
+#[derive(Default)]
+struct Notes(Vec<u8>);
+
+impl Notes {
+    fn push(&mut self, ty: u32, desc: &[u8]) {
+        const NAME: &[u8] = b"CORE\0";
+        let note = elf64_note {
+            n_namesz: NAME.len() as _,
+            n_descsz: desc.len() as _,
+            n_type: ty,
+        };
+        self.0.extend_from_slice(as_bytes(&note));
+        self.0.extend_from_slice(NAME);
+        self.align();
+        self.0.extend_from_slice(desc);
+        self.align();
+    }
+
+    fn align(&mut self) {
+        self.0.resize(self.0.len().next_multiple_of(4), 0);
+    }
+}
+
+fn sigset_bits(set: axsignal::SignalSet) -> u64 {
+    let mut sigset = kernel_sigset_t { sig: [0] };
+    set.to_ctype(&mut sigset);
+    sigset.sig[0] as _
+}
+
+/// Converts a time in nanoseconds to the seconds and microseconds of a
+/// `struct timeval`.
+fn timeval(nanos: usize) -> [i64; 2] {
+    [
+        (nanos / NANOS_PER_SEC as usize) as _,
+        (nanos % NANOS_PER_SEC as usize / NANOS_PER_MICROS as usize) as _,
+    ]
+}
+
+/// The status of the thread run by `task`, which has saved its FP/SIMD
+/// registers if `fpvalid`.
+///
+/// The CPU times are those of the thread, or of the whole process for the
+/// main thread, as on Linux. The times of waited-for children are not
+/// accounted, and reported as zero.
+fn prstatus(task: &AxTaskRef, sig: &SignalInfo, tf: &TrapFrame, fpvalid: bool) -> ElfPrstatus {
+    let thread = &task.task_ext().thread;
+    let process = thread.process();
+    let data = task.task_ext().thread_data();
+    let (utime, stime) = if thread.tid() == process.pid() {
+        task.task_ext().process_data().cpu_time()
+    } else {
+        task.task_ext().time_stat_output()
+    };
+    ElfPrstatus {
+        info: [sig.signo() as _, sig.code(), 0],
+        cursig: sig.signo() as _,
+        _pad: 0,
+        sigpend: sigset_bits(data.signal.pending()),
+        sighold: sigset_bits(data.signal.blocked()),
+        pid: thread.tid() as _,
+        ppid: process.parent().map_or(0, |it| it.pid()) as _,
+        pgrp: process.group().pgid() as _,
+        sid: process.group().session().sid() as _,
+        times: [timeval(utime), timeval(stime), [0; 2], [0; 2]],
+        reg: user_regs(tf),
+        fpvalid: fpvalid as _,
+        _pad2: 0,
+    }
+}
+
+/// Adds the notes of the thread run by `task` to `notes`: its status, and
+/// its FP/SIMD registers `fp` if they were saved.
+fn push_thread_notes(
+    notes: &mut Notes,
+    task: &AxTaskRef,
+    sig: &SignalInfo,
+    tf: &TrapFrame,
+    fp: Option<&FpState>,
+) {
+    notes.push(
+        NT_PRSTATUS,
+        as_bytes(&prstatus(task, sig, tf, fp.is_some())),
+    );
+    if let Some(fp) = fp {
+        notes.push(NT_PRFPREG, as_bytes(&fp_regs(fp)));
+    }
+}
+
+fn prpsinfo(process: &Process) -> ElfPrpsinfo {
+    let curr = current();
+    let process_data = curr.task_ext().process_data();
+    let cred = process_data.cred();
+    let mut info = ElfPrpsinfo {
+        state: 0,
+        sname: b'R',
+        zomb: 0,
+        nice: 0,
+        _pad: 0,
+        flag: 0,
+        uid: cred.uid,
+        gid: cred.gid,
+        pid: process.pid() as _,
+        ppid: process.parent().map_or(0, |it| it.pid()) as _,
+        pgrp: process.group().pgid() as _,
+        sid: process.group().session().sid() as _,
+        fname: [0; 16],
+        psargs: [0; 80],
+    };
+    let name = curr.name();
+    let len = name.len().min(info.fname.len() - 1);
+    info.fname[..len].copy_from_slice(&name.as_bytes()[..len]);
+    let exe_path = process_data.exe_path.read();
+    let len = exe_path.len().min(info.psargs.len() - 1);
+    info.psargs[..len].copy_from_slice(&exe_path.as_bytes()[..len]);
+    info
+}
+
+/// The `NT_AUXV` note: the auxiliary vector, terminated by `AT_NULL`.
+fn auxv() -> Vec<u8> {
+    let curr = current();
+    let auxv = curr.task_ext().process_data().auxv.lock();
+    let mut desc = Vec::new();
+    for entry in auxv.iter() {
+        desc.extend_from_slice(&(entry.get_type() as usize).to_ne_bytes());
+        desc.extend_from_slice(&entry.value().to_ne_bytes());
+    }
+    desc.extend_from_slice(&[0; 2 * size_of::<usize>()]);
+    desc
+}
+
+/// The `NT_FILE` note: the number of file mappings and the page size, the
+/// start, end and page offset of each mapping, and then their paths.
+fn file_mappings() -> Vec<u8> {
+    let curr = current();
+    let mappings = curr.task_ext().process_data().file_mappings.lock();
+    let mut desc = Vec::new();
+    desc.extend_from_slice(&(mappings.iter().count() as u64).to_ne_bytes());
+    desc.extend_from_slice(&(PAGE_SIZE_4K as u64).to_ne_bytes());
+    for mapping in mappings.iter() {
+        desc.extend_from_slice(&(mapping.range.start.as_usize() as u64).to_ne_bytes());
+        desc.extend_from_slice(&(mapping.range.end.as_usize() as u64).to_ne_bytes());
+        desc.extend_from_slice(&((mapping.offset / PAGE_SIZE_4K) as u64).to_ne_bytes());
+    }
+    for mapping in mappings.iter() {
+        desc.extend_from_slice(mapping.path.as_bytes());
+        desc.push(0);
+    }
+    desc
+}
+
+/// Expands the `%` specifiers of the core file name pattern: `%%`, `%p` and
+/// `%P` for the PID, `%i` and `%I` for the TID, `%u` and `%g` for the real
+/// user and group IDs, `%s` for the signal, `%t` for the time of the dump,
+/// `%e` for the thread name and `%E` for the executable path with `/`
+/// replaced by `!`.
+fn expand_core_pattern(pattern: &str, signo: Signo) -> String {
+    let curr = current();
+    let thread = &curr.task_ext().thread;
+    let process_data = curr.task_ext().process_data();
+    let cred = process_data.cred();
+
+    let mut name = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            name.push(c);
+            continue;
+        }
+        let _ = match chars.next() {
+            Some('%') => write!(name, "%"),
+            Some('p' | 'P') => write!(name, "{}", thread.process().pid()),
+            Some('i' | 'I') => write!(name, "{}", thread.tid()),
+            Some('u') => write!(name, "{}", cred.uid),
+            Some('g') => write!(name, "{}", cred.gid),
+            Some('s') => write!(name, "{}", signo as u8),
+            Some('t') => write!(name, "{}", wall_time().as_secs()),
+            Some('e') => write!(name, "{}", curr.name().replace('/', "!")),
+            Some('E') => write!(name, "{}", process_data.exe_path.read().replace('/', "!")),
+            // Unknown specifiers are dropped.
+            _ => Ok(()),
+        };
+    }
+    name
+}
+
+/// Creates the core file named by the core file name pattern, relative to
+/// the current directory, as the filesystem user of the process.
+///
+/// An existing file is replaced only if it is a regular file owned by that
+/// user. Piping the core to a program is not supported.
+fn create_core_file(signo: Signo) -> LinuxResult<axfs::api::File> {
+    let pattern = axfs::api::read_to_string(CORE_PATTERN_PATH).unwrap_or_default();
+    let pattern = match pattern.trim_end_matches('\n') {
+        "" => "core",
+        pattern if pattern.starts_with('|') => return Err(LinuxError::EOPNOTSUPP),
+        pattern => pattern,
+    };
+    let name = expand_core_pattern(pattern, signo);
+    let path = handle_file_path(AT_FDCWD, &name)?;
+
+    let cred = current().task_ext().process_data().cred();
+    let new_meta = match file_meta(&path) {
+        Ok(meta) => {
+            if meta.dir || meta.uid != cred.fsuid {
+                return Err(LinuxError::EACCES);
+            }
+            meta.check_access(&cred, W_OK)?;
+            None
+        }
+        Err(LinuxError::ENOENT) => Some(check_create(&path, 0o600, false)?),
+        Err(e) => return Err(e),
+    };
+    let file = axfs::api::File::create(path.as_str())?;
+    if let Some(meta) = &new_meta {
+        set_file_meta(&path, meta);
+    }
+    Ok(file)
+}
+
+/// A core file, which is cut short at the size allowed by `RLIMIT_CORE`.
+struct CoreFile {
+    file: axfs::api::File,
+    /// The number of bytes that may still be written
+    left: u64,
+}
+
+impl Write for CoreFile {
+    fn write(&mut self, buf: &[u8]) -> axio::Result<usize> {
+        if buf.len() as u64 > self.left {
+            // Fails the dump, like a full disk.
+            return Ok(0);
+        }
+        let written = self.file.write(buf)?;
+        self.left -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> axio::Result {
+        self.file.flush()
+    }
+}
+
+/// A `PT_LOAD` segment, with whether its contents are dumped.
+struct Segment {
+    range: VirtAddrRange,
+    flags: MappingFlags,
+    dumped: bool,
+}
+
+fn write_core(file: &mut CoreFile, notes: &Notes, segments: &[Segment]) -> LinuxResult {
+    let curr = current();
+    let aspace = curr.task_ext().process_data().aspace();
+    let aspace = aspace.lock();
+
+    let phnum = segments.len() + 1;
+    let notes_offset = size_of::<elf64_hdr>() + phnum * size_of::<elf64_phdr>();
+    let data_offset = (notes_offset + notes.0.len()).align_up_4k();
+
+    let mut ident = [0; 16];
+    ident[..4].copy_from_slice(&ELFMAG[..4]);
+    ident[EI_CLASS as usize] = ELFCLASS64 as _;
+    ident[EI_DATA as usize] = ELFDATA2LSB as _;
+    ident[EI_VERSION as usize] = EV_CURRENT as _;
+    ident[EI_OSABI as usize] = ELFOSABI_NONE as _;
+    let ehdr = elf64_hdr {
+        e_ident: ident,
+        e_type: ET_CORE as _,
+        e_machine: ELF_MACHINE as _,
+        e_version: EV_CURRENT,
+        e_entry: 0,
+        e_phoff: size_of::<elf64_hdr>() as _,
+        e_shoff: 0,
+        e_flags: 0,
+        e_ehsize: size_of::<elf64_hdr>() as _,
+        e_phentsize: size_of::<elf64_phdr>() as _,
+        e_phnum: phnum as _,
+        e_shentsize: 0,
+        e_shnum: 0,
+        e_shstrndx: 0,
+    };
+    file.write_all(as_bytes(&ehdr))?;
+
+    let note_phdr = elf64_phdr {
+        p_type: PT_NOTE,
+        p_flags: 0,
+        p_offset: notes_offset as _,
+        p_vaddr: 0,
+        p_paddr: 0,
+        p_filesz: notes.0.len() as _,
+        p_memsz: 0,
+        p_align: 0,
+    };
+    file.write_all(as_bytes(&note_phdr))?;
+    let mut offset = data_offset;
+    for segment in segments {
+        let mut p_flags = 0;
+        if segment.flags.contains(MappingFlags::READ) {
+            p_flags |= PF_R;
+        }
+        if segment.flags.contains(MappingFlags::WRITE) {
+            p_flags |= PF_W;
+        }
+        if segment.flags.contains(MappingFlags::EXECUTE) {
+            p_flags |= PF_X;
+        }
+        let size = segment.range.size();
+        let filesz = if segment.dumped { size } else { 0 };
+        let phdr = elf64_phdr {
+            p_type: PT_LOAD,
+            p_flags,
+            p_offset: offset as _,
+            p_vaddr: segment.range.start.as_usize() as _,
+            p_paddr: 0,
+            p_filesz: filesz as _,
+            p_memsz: size as _,
+            p_align: PAGE_SIZE_4K as _,
+        };
+        file.write_all(as_bytes(&phdr))?;
+        offset += filesz;
+    }
+
+    file.write_all(&notes.0)?;
+    file.write_all(&vec![0; data_offset - notes_offset - notes.0.len()])?;
+
+    let mut page = vec![0; PAGE_SIZE_4K];
+    for segment in segments.iter().filter(|it| it.dumped) {
+        let mut vaddr = segment.range.start;
+        while vaddr < segment.range.end {
+            // Pages never touched are not allocated yet, and read as zeros.
+            if aspace.read(vaddr, &mut page).is_err() {
+                page.fill(0);
+            }
+            file.write_all(&page)?;
+            vaddr += PAGE_SIZE_4K;
+        }
+    }
+    Ok(())
+}
+
+/// Dumps the core of the current process, which is being killed by the
+/// signal `sig` raised with the user registers `tf`.
+///
+/// The caller must have marked the process with
+/// [`ProcessData::start_core_dump`], so that the other threads exit quietly
+/// when they are killed here. They are waited for, so that their registers
+/// are saved and the memory no longer changes.
+///
+/// Returns whether the core file was written, which it is not if the process
+/// is not dumpable, see `PR_SET_DUMPABLE`, or its `RLIMIT_CORE` is less than
+/// a page.
+///
+/// [`ProcessData::start_core_dump`]: starry_core::task::ProcessData::start_core_dump
+pub fn dump_core(sig: &SignalInfo, tf: &TrapFrame) -> bool {
+    let curr = current();
+    let thread = &curr.task_ext().thread;
+    let process = thread.process();
+
+    let kill = SignalInfo::new(Signo::SIGKILL, SI_KERNEL as _);
+    let others: Vec<_> = process
+        .threads()
+        .into_iter()
+        .filter(|thr| thr.tid() != thread.tid())
+        .filter_map(|thr| {
+            let task = thr.data::<ThreadData>()?.task()?;
+            let _ = send_signal_thread(&thr, kill.clone());
+            Some((thr, task))
+        })
+        .collect();
+
+    let mut notes = Notes::default();
+    let fp = save_fp_state();
+    push_thread_notes(&mut notes, curr.as_task_ref(), sig, tf, Some(&fp));
+    for (thr, task) in &others {
+        task.join();
+        // SAFETY: the thread has exited, so its trap frame no longer changes.
+        let tf = unsafe { &*user_trap_frame(task) };
+        let fp = thr.data::<ThreadData>().unwrap().core_fp.lock().take();
+        push_thread_notes(&mut notes, task, sig, tf, fp.as_deref());
+    }
+    let process_data = curr.task_ext().process_data();
+    let limit = process_data
+        .rlimits
+        .read()
+        .get(RLIMIT_CORE)
+        .map_or(0, |it| it.rlim_cur);
+    if !process_data.is_dumpable() || limit < PAGE_SIZE_4K as u64 {
+        // The other threads are killed all the same.
+        return false;
+    }
+
+    notes.push(NT_PRPSINFO, as_bytes(&prpsinfo(process)));
+    notes.push(NT_SIGINFO, as_bytes(&sig.0));
+    notes.push(NT_AUXV, &auxv());
+    notes.push(NT_FILE, &file_mappings());
+
+    let segments: Vec<_> = {
+        let aspace = process_data.aspace();
+        let aspace = aspace.lock();
+        let file_mappings = process_data.file_mappings.lock();
+        aspace
+            .areas()
+            .filter(|(_, flags)| flags.contains(MappingFlags::USER))
+            .map(|(range, flags)| {
+                let file_backed = file_mappings
+                    .iter()
+                    .any(|mapping| mapping.range.contains_range(range));
+                Segment {
+                    range,
+                    flags,
+                    dumped: flags.contains(MappingFlags::WRITE) || !file_backed,
+                }
+            })
+            .collect()
+    };
+
+    let result = create_core_file(sig.signo())
+        .and_then(|file| write_core(&mut CoreFile { file, left: limit }, &notes, &segments));
+    match result {
+        Ok(()) => {
+            info!("Process {} dumped core", process.pid());
+            true
+        }
+        Err(e) => {
+            warn!("Process {} failed to dump core: {:?}", process.pid(), e);
+            false
+        }
+    }
+}
@@ -8,6 +8,7 @@ use axsync::{Mutex, MutexGuard};
 use linux_raw_sys::general::S_IFDIR;
 
 use super::{FileLike, Kstat, get_file_like};
+use crate::perm::{FileMeta, file_meta};
 
 /// File wrapper for `axfs::fops::File`.
 pub struct File {
@@ -46,10 +47,17 @@ impl FileLike for File {
     fn stat(&self) -> LinuxResult<Kstat> {
         let metadata = self.inner().get_attr()?;
         let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
+        let meta = file_meta(&self.path).unwrap_or(FileMeta {
+            mode: metadata.perm().bits() as u32,
+            uid: 0,
+            gid: 0,
+            dir: false,
+        });
 
         Ok(Kstat {
-            mode: ((ty as u32) << 12) | perm,
+            mode: ((ty as u32) << 12) | meta.mode,
+            uid: meta.uid,
+            gid: meta.gid,
             size: metadata.size(),
             blocks: metadata.blocks(),
             blksize: 512,
@@ -115,8 +123,16 @@ impl FileLike for Directory {
     }
 
     fn stat(&self) -> LinuxResult<Kstat> {
+        let meta = file_meta(&self.path).unwrap_or(FileMeta {
+            mode: 0o755, // rwxr-xr-x
+            uid: 0,
+            gid: 0,
+            dir: true,
+        });
         Ok(Kstat {
-            mode: S_IFDIR | 0o755u32, // rwxr-xr-x
+            mode: S_IFDIR | meta.mode,
+            uid: meta.uid,
+            gid: meta.gid,
             ..Default::default()
         })
     }
@@ -1,26 +1,76 @@
 mod fs;
 mod net;
+mod pidfd;
 mod pipe;
 mod stdio;
+mod tty;
 
-use core::{any::Any, ffi::c_int};
+use core::{
+    any::Any,
+    ffi::c_int,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use alloc::{sync::Arc, vec::Vec};
 use axerrno::{LinuxError, LinuxResult};
+use axhal::time::TimeValue;
 use axio::PollState;
 use axns::{ResArc, def_resource};
+use axtask::WaitQueue;
 use flatten_objects::FlattenObjects;
 use linux_raw_sys::general::{stat, statx};
 use spin::RwLock;
+use starry_core::signal::interruptible_wait_until;
 
 pub use self::{
     fs::{Directory, File},
     net::Socket,
+    pidfd::PidFd,
     pipe::Pipe,
+    stdio::console,
+    tty::{PtyMaster, Tty, TtyDriver, TtyFile, controlling_tty, mount_devpts, open_tty},
 };
 
 pub const AX_FILE_LIMIT: usize = 1024;
 
+/// A wait queue for state guarded by sleeping locks, which can not be checked
+/// in the wait condition. Waiters take the event count before checking the
+/// state, and sleep until it changes.
+struct EventQueue {
+    wq: WaitQueue,
+    events: AtomicUsize,
+}
+
+impl EventQueue {
+    const fn new() -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            events: AtomicUsize::new(0),
+        }
+    }
+
+    fn events(&self) -> usize {
+        self.events.load(Ordering::Acquire)
+    }
+
+    fn notify(&self) {
+        self.events.fetch_add(1, Ordering::AcqRel);
+        self.wq.notify_all(true);
+    }
+
+    /// Blocks until an event occurs after `events` was taken, a signal
+    /// interrupts the wait, or the optional `timeout` elapses.
+    fn wait(&self, events: usize, timeout: Option<TimeValue>) -> LinuxResult {
+        interruptible_wait_until(&self.wq, timeout, || self.events() != events)
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Kstat {
     ino: u64,
@@ -38,8 +88,8 @@ impl Default for Kstat {
         Self {
             ino: 1,
             nlink: 1,
-            uid: 1,
-            gid: 1,
+            uid: 0,
+            gid: 0,
             mode: 0,
             size: 0,
             blocks: 0,
@@ -92,6 +142,12 @@ pub trait FileLike: Send + Sync {
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
 
+    /// Manipulates the underlying device, with the request code `cmd` and the
+    /// argument `arg`, usually a pointer.
+    fn ioctl(&self, _cmd: u32, _arg: usize) -> LinuxResult<usize> {
+        Err(LinuxError::ENOTTY)
+    }
+
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>>
     where
         Self: Sized + 'static,
@@ -161,14 +217,12 @@ pub fn close_file_like(fd: c_int) -> LinuxResult {
 #[ctor_bare::register_ctor]
 fn init_stdio() {
     let mut fd_table = flatten_objects::FlattenObjects::new();
-    fd_table
-        .add_at(0, Arc::new(stdio::stdin()) as _)
-        .unwrap_or_else(|_| panic!()); // stdin
-    fd_table
-        .add_at(1, Arc::new(stdio::stdout()) as _)
-        .unwrap_or_else(|_| panic!()); // stdout
-    fd_table
-        .add_at(2, Arc::new(stdio::stdout()) as _)
-        .unwrap_or_else(|_| panic!()); // stderr
+    let console = Arc::new(TtyFile::new(stdio::console().clone()));
+    for fd in 0..3 {
+        // stdin, stdout and stderr
+        fd_table
+            .add_at(fd, console.clone() as _)
+            .unwrap_or_else(|_| panic!());
+    }
     FD_TABLE.init_new(spin::RwLock::new(fd_table));
 }
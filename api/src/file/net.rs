@@ -2,13 +2,19 @@ use core::net::SocketAddr;
 
 use alloc::sync::Arc;
 use axerrno::{LinuxError, LinuxResult};
+use axhal::time::TimeValue;
 use axio::PollState;
 use axnet::{TcpSocket, UdpSocket};
 use axsync::Mutex;
-use linux_raw_sys::general::S_IFSOCK;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::general::{CAP_NET_BIND_SERVICE, S_IFSOCK};
+use starry_core::signal::interruptible_sleep;
 
 use super::{FileLike, Kstat};
 
+/// How long a blocking `accept` sleeps between polls for a connection.
+const ACCEPT_POLL_INTERVAL: TimeValue = TimeValue::from_millis(10);
+
 pub enum Socket {
     Udp(Mutex<UdpSocket>),
     Tcp(Mutex<TcpSocket>),
@@ -62,7 +68,22 @@ impl Socket {
     pub fn accept(&self) -> LinuxResult<TcpSocket> {
         match self {
             Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+            Socket::Tcp(tcpsocket) => {
+                let socket = tcpsocket.lock();
+                if socket.is_nonblocking() {
+                    return Ok(socket.accept()?);
+                }
+                // Poll here rather than blocking in `accept`, so that signals
+                // can interrupt the wait. The network stack does not notify
+                // of incoming connections, so sleep between the polls.
+                loop {
+                    axnet::poll_interfaces();
+                    if socket.poll()?.readable {
+                        return Ok(socket.accept()?);
+                    }
+                    interruptible_sleep(ACCEPT_POLL_INTERVAL)?;
+                }
+            }
         }
     }
 
@@ -70,9 +91,27 @@ impl Socket {
     impl_socket!(pub fn poll(&self) -> LinuxResult<PollState>);
     impl_socket!(pub fn local_addr(&self) -> LinuxResult<SocketAddr>);
     impl_socket!(pub fn peer_addr(&self) -> LinuxResult<SocketAddr>);
-    impl_socket!(pub fn bind(&self, addr: SocketAddr) -> LinuxResult);
     impl_socket!(pub fn connect(&self, addr: SocketAddr) -> LinuxResult);
     impl_socket!(pub fn shutdown(&self) -> LinuxResult);
+
+    /// Binds the socket to `addr`. Ports below 1024 are reserved for
+    /// processes with `CAP_NET_BIND_SERVICE`.
+    pub fn bind(&self, addr: SocketAddr) -> LinuxResult {
+        if addr.port() != 0
+            && addr.port() < 1024
+            && !current()
+                .task_ext()
+                .process_data()
+                .cred()
+                .has_cap(CAP_NET_BIND_SERVICE)
+        {
+            return Err(LinuxError::EACCES);
+        }
+        match self {
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+        }
+    }
 }
 
 impl FileLike for Socket {
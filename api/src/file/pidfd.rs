@@ -0,0 +1,73 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axprocess::Process;
+
+use super::{FileLike, Kstat};
+
+/// A file descriptor referring to a process, see `pidfd_open(2)`.
+///
+/// Unlike the PID, it keeps referring to the same process after it exits,
+/// and becomes readable then.
+pub struct PidFd {
+    process: Arc<Process>,
+    nonblocking: AtomicBool,
+}
+
+impl PidFd {
+    pub fn new(process: Arc<Process>, nonblocking: bool) -> Self {
+        Self {
+            process,
+            nonblocking: AtomicBool::new(nonblocking),
+        }
+    }
+
+    /// The process the file descriptor refers to.
+    pub fn process(&self) -> &Arc<Process> {
+        &self.process
+    }
+
+    /// Whether waiting for the process fails with `EAGAIN` instead of
+    /// blocking, see `PIDFD_NONBLOCK`.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+}
+
+impl FileLike for PidFd {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: self.process.is_zombie(),
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use core::any::Any;
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use alloc::sync::Arc;
 use axerrno::{LinuxError, LinuxResult};
@@ -6,7 +9,7 @@ use axio::PollState;
 use axsync::Mutex;
 use linux_raw_sys::general::S_IFIFO;
 
-use super::{FileLike, Kstat};
+use super::{EventQueue, FileLike, Kstat};
 
 #[derive(Copy, Clone, PartialEq)]
 enum RingBufferStatus {
@@ -74,21 +77,34 @@ impl PipeRingBuffer {
     }
 }
 
+/// The state shared by both ends of a pipe.
+struct PipeInner {
+    buffer: Mutex<PipeRingBuffer>,
+    /// Whether either end has been closed.
+    closed: AtomicBool,
+    /// Notified when data is read or written, or an end is closed.
+    events: EventQueue,
+}
+
 pub struct Pipe {
     readable: bool,
-    buffer: Arc<Mutex<PipeRingBuffer>>,
+    inner: Arc<PipeInner>,
 }
 
 impl Pipe {
     pub fn new() -> (Pipe, Pipe) {
-        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+        let inner = Arc::new(PipeInner {
+            buffer: Mutex::new(PipeRingBuffer::new()),
+            closed: AtomicBool::new(false),
+            events: EventQueue::new(),
+        });
         let read_end = Pipe {
             readable: true,
-            buffer: buffer.clone(),
+            inner: inner.clone(),
         };
         let write_end = Pipe {
             readable: false,
-            buffer,
+            inner,
         };
         (read_end, write_end)
     }
@@ -102,7 +118,14 @@ impl Pipe {
     }
 
     pub fn closed(&self) -> bool {
-        Arc::strong_count(&self.buffer) == 1
+        self.inner.closed.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        self.inner.closed.store(true, Ordering::Release);
+        self.inner.events.notify();
     }
 }
 
@@ -116,7 +139,8 @@ impl FileLike for Pipe {
         }
 
         loop {
-            let mut ring_buffer = self.buffer.lock();
+            let events = self.inner.events.events();
+            let mut ring_buffer = self.inner.buffer.lock();
             let read_size = ring_buffer.available_read().min(buf.len());
             if read_size == 0 {
                 if self.closed() {
@@ -124,12 +148,14 @@ impl FileLike for Pipe {
                 }
                 drop(ring_buffer);
                 // Data not ready, wait for write end
-                axtask::yield_now(); // TODO: use synconize primitive
+                self.inner.events.wait(events, None)?;
                 continue;
             }
             for c in buf.iter_mut().take(read_size) {
                 *c = ring_buffer.read_byte();
             }
+            drop(ring_buffer);
+            self.inner.events.notify();
             return Ok(read_size);
         }
     }
@@ -148,24 +174,31 @@ impl FileLike for Pipe {
         let mut write_size = 0usize;
         let total_len = buf.len();
         loop {
-            let mut ring_buffer = self.buffer.lock();
-            let loop_write = ring_buffer.available_write();
+            let events = self.inner.events.events();
+            let mut ring_buffer = self.inner.buffer.lock();
+            let loop_write = ring_buffer.available_write().min(total_len - write_size);
             if loop_write == 0 {
                 if self.closed() {
                     return Ok(write_size);
                 }
                 drop(ring_buffer);
                 // Buffer is full, wait for read end to consume
-                axtask::yield_now(); // TODO: use synconize primitive
+                match self.inner.events.wait(events, None) {
+                    // Report a partial write rather than losing it
+                    Err(_) if write_size > 0 => return Ok(write_size),
+                    result => result?,
+                }
                 continue;
             }
             for _ in 0..loop_write {
-                if write_size == total_len {
-                    return Ok(write_size);
-                }
                 ring_buffer.write_byte(buf[write_size]);
                 write_size += 1;
             }
+            drop(ring_buffer);
+            self.inner.events.notify();
+            if write_size == total_len {
+                return Ok(write_size);
+            }
         }
     }
 
@@ -181,7 +214,7 @@ impl FileLike for Pipe {
     }
 
     fn poll(&self) -> LinuxResult<PollState> {
-        let buf = self.buffer.lock();
+        let buf = self.inner.buffer.lock();
         Ok(PollState {
             readable: self.readable() && buf.available_read() > 0,
             writable: self.writable() && buf.available_write() > 0,
@@ -1,170 +1,61 @@
-use core::any::Any;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use alloc::sync::Arc;
-use axerrno::{AxResult, LinuxError, LinuxResult};
-use axio::{BufReader, PollState, prelude::*};
-use axsync::Mutex;
-use linux_raw_sys::general::S_IFCHR;
+use alloc::{boxed::Box, sync::Arc};
+use axtask::WaitQueue;
+use spin::Once;
 
-use super::Kstat;
+use super::tty::{Tty, TtyDriver};
 
-fn console_read_bytes(buf: &mut [u8]) -> AxResult<usize> {
-    let len = axhal::console::read_bytes(buf);
-    for c in &mut buf[..len] {
-        if *c == b'\r' {
-            *c = b'\n';
-        }
-    }
-    Ok(len)
-}
+/// The system console, as the terminal device of the standard input and
+/// output of the first process.
+struct Console;
 
-fn console_write_bytes(buf: &[u8]) -> AxResult<usize> {
-    axhal::console::write_bytes(buf);
-    Ok(buf.len())
-}
-
-struct StdinRaw;
-struct StdoutRaw;
-
-impl Read for StdinRaw {
-    // Non-blocking read, returns number of bytes read.
-    fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        let mut read_len = 0;
-        while read_len < buf.len() {
-            let len = console_read_bytes(buf[read_len..].as_mut())?;
-            if len == 0 {
-                break;
-            }
-            read_len += len;
-        }
-        Ok(read_len)
+impl TtyDriver for Console {
+    fn write(&self, buf: &[u8]) {
+        axhal::console::write_bytes(buf);
     }
 }
 
-impl Write for StdoutRaw {
-    fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
-        console_write_bytes(buf)
-    }
-
-    fn flush(&mut self) -> AxResult {
-        Ok(())
-    }
-}
+/// Whether input may have been received since the worker last checked.
+/// Initially set to pass on input received before the handler is set.
+static INPUT_PENDING: AtomicBool = AtomicBool::new(true);
+static INPUT_WQ: WaitQueue = WaitQueue::new();
 
-pub struct Stdin {
-    inner: &'static Mutex<BufReader<StdinRaw>>,
+/// Called by axhal in interrupt context when console input is received.
+fn input_handler() {
+    INPUT_PENDING.store(true, Ordering::Release);
+    INPUT_WQ.notify_one(true);
 }
 
-impl Stdin {
-    // Block until at least one byte is read.
-    fn read_blocked(&self, buf: &mut [u8]) -> AxResult<usize> {
-        let read_len = self.inner.lock().read(buf)?;
-        if buf.is_empty() || read_len > 0 {
-            return Ok(read_len);
-        }
-        // try again until we get something
+/// Passes console input to the terminal as it is received. Running in a task
+/// rather than in the interrupt handler allows the line discipline to take
+/// sleeping locks, e.g. to send signals for control characters.
+fn input_worker(tty: Arc<Tty>) {
+    let mut buf = [0; 64];
+    loop {
+        INPUT_WQ.wait_until(|| INPUT_PENDING.swap(false, Ordering::AcqRel));
         loop {
-            let read_len = self.inner.lock().read(buf)?;
-            if read_len > 0 {
-                return Ok(read_len);
+            let len = axhal::console::read_bytes(&mut buf);
+            if len == 0 {
+                break;
             }
-            axtask::yield_now();
+            tty.receive(&buf[..len]);
         }
     }
 }
 
-impl Read for Stdin {
-    fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        self.read_blocked(buf)
-    }
-}
-
-pub struct Stdout {
-    inner: &'static Mutex<StdoutRaw>,
-}
-
-impl Write for Stdout {
-    fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
-        self.inner.lock().write(buf)
-    }
-
-    fn flush(&mut self) -> AxResult {
-        self.inner.lock().flush()
-    }
-}
-
-/// Constructs a new handle to the standard input of the current process.
-pub fn stdin() -> Stdin {
-    static INSTANCE: Mutex<BufReader<StdinRaw>> = Mutex::new(BufReader::new(StdinRaw));
-    Stdin { inner: &INSTANCE }
-}
-
-/// Constructs a new handle to the standard output of the current process.
-pub fn stdout() -> Stdout {
-    static INSTANCE: Mutex<StdoutRaw> = Mutex::new(StdoutRaw);
-    Stdout { inner: &INSTANCE }
-}
-
-impl super::FileLike for Stdin {
-    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.read_blocked(buf)?)
-    }
-
-    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
-        Err(LinuxError::EPERM)
-    }
-
-    fn stat(&self) -> LinuxResult<Kstat> {
-        Ok(Kstat {
-            mode: S_IFCHR | 0o444u32, // r--r--r--
-            ..Default::default()
-        })
-    }
-
-    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
-        self
-    }
-
-    fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: true,
-        })
-    }
-
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
-        Ok(())
-    }
-}
-
-impl super::FileLike for Stdout {
-    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
-        Err(LinuxError::EPERM)
-    }
-
-    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        Ok(self.inner.lock().write(buf)?)
-    }
-
-    fn stat(&self) -> LinuxResult<Kstat> {
-        Ok(Kstat {
-            mode: S_IFCHR | 0o220u32, // -w--w----
-            ..Default::default()
-        })
-    }
-
-    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
-        self
-    }
-
-    fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: true,
-        })
-    }
-
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
-        Ok(())
-    }
+/// The terminal of the system console.
+pub fn console() -> &'static Arc<Tty> {
+    static CONSOLE: Once<Arc<Tty>> = Once::new();
+    CONSOLE.call_once(|| {
+        let tty = Tty::new(Box::new(Console));
+        let worker_tty = tty.clone();
+        axtask::spawn_raw(
+            move || input_worker(worker_tty),
+            "console".into(),
+            axconfig::plat::KERNEL_STACK_SIZE,
+        );
+        axhal::console::set_input_handler(input_handler);
+        tty
+    })
 }
@@ -0,0 +1,286 @@
+//! The line discipline: input editing, echo and output processing of a
+//! terminal as configured by its `termios`.
+
+use alloc::{collections::vec_deque::VecDeque, vec::Vec};
+
+use axsignal::Signo;
+use linux_raw_sys::general::{
+    B38400, CREAD, CS8, ECHO, ECHOCTL, ECHOE, ECHOK, ECHOKE, ECHONL, HUPCL, ICANON, ICRNL, IEXTEN,
+    IGNCR, INLCR, ISIG, IXON, NOFLSH, ONLCR, OPOST, VEOF, VEOL, VEOL2, VERASE, VINTR, VKILL, VMIN,
+    VQUIT, VSUSP, VTIME, VWERASE, termios, winsize,
+};
+
+/// The maximum length of a line in canonical mode.
+const MAX_CANON: usize = 4095;
+/// The maximum length of pending input in non-canonical mode.
+const MAX_INPUT: usize = 4096;
+
+/// The default control characters, as in Linux.
+const INIT_C_CC: [u8; 17] =
+    *b"\x03\x1c\x7f\x15\x04\x00\x01\x00\x11\x13\x1a\x00\x12\x0f\x17\x16\x00";
+
+/// The default terminal settings: canonical mode with echo and signals.
+pub fn default_termios() -> termios {
+    let mut c_cc = [0; 19];
+    c_cc[..INIT_C_CC.len()].copy_from_slice(&INIT_C_CC);
+    termios {
+        c_iflag: ICRNL | IXON,
+        c_oflag: OPOST | ONLCR,
+        c_cflag: B38400 | CS8 | CREAD | HUPCL,
+        c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK | ECHOCTL | ECHOKE | IEXTEN,
+        c_line: 0,
+        c_cc,
+    }
+}
+
+/// Whether `c` is echoed as `^X` with `ECHOCTL`.
+fn is_ctl_echoed(c: u8) -> bool {
+    (c < b' ' && c != b'\t' && c != b'\n') || c == 0x7f
+}
+
+/// The result of receiving input.
+#[derive(Default)]
+pub struct InputEffects {
+    /// Bytes to echo, already processed for output
+    pub echo: Vec<u8>,
+    /// Signals to send to the foreground process group
+    pub signals: Vec<Signo>,
+}
+
+pub struct LineDiscipline {
+    pub termios: termios,
+    pub winsize: winsize,
+    /// Input ready to be read in non-canonical mode
+    raw: VecDeque<u8>,
+    /// Lines ready to be read in canonical mode. A line ended by `VEOF` has
+    /// no terminator, so an empty line is an end of file.
+    lines: VecDeque<Vec<u8>>,
+    /// The line being edited in canonical mode
+    line: Vec<u8>,
+}
+
+impl LineDiscipline {
+    pub fn new() -> Self {
+        Self {
+            termios: default_termios(),
+            winsize: winsize {
+                ws_row: 24,
+                ws_col: 80,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            },
+            raw: VecDeque::new(),
+            lines: VecDeque::new(),
+            line: Vec::new(),
+        }
+    }
+
+    fn lflag(&self, flag: u32) -> bool {
+        self.termios.c_lflag & flag != 0
+    }
+
+    fn cc(&self, index: u32) -> u8 {
+        self.termios.c_cc[index as usize]
+    }
+
+    /// Checks whether `c` is the control character `index`. Disabled control
+    /// characters are 0.
+    fn is_cc(&self, c: u8, index: u32) -> bool {
+        c != 0 && c == self.cc(index)
+    }
+
+    pub fn is_canonical(&self) -> bool {
+        self.lflag(ICANON)
+    }
+
+    /// The `VMIN` and `VTIME` settings of non-canonical mode.
+    pub fn min_time(&self) -> (u8, u8) {
+        (self.cc(VMIN), self.cc(VTIME))
+    }
+
+    /// Changes the settings, moving pending input between the canonical and
+    /// non-canonical buffers if the mode changes.
+    pub fn set_termios(&mut self, termios: termios) {
+        let was_canonical = self.is_canonical();
+        self.termios = termios;
+        match (was_canonical, self.is_canonical()) {
+            (true, false) => {
+                for line in self.lines.drain(..) {
+                    self.raw.extend(line);
+                }
+                self.raw.extend(self.line.drain(..));
+            }
+            (false, true) => self.line.extend(self.raw.drain(..)),
+            _ => {}
+        }
+    }
+
+    /// Discards all pending input.
+    pub fn flush_input(&mut self) {
+        self.raw.clear();
+        self.lines.clear();
+        self.line.clear();
+    }
+
+    /// The number of bytes that can be read.
+    pub fn available(&self) -> usize {
+        if self.is_canonical() {
+            self.lines.iter().map(Vec::len).sum()
+        } else {
+            self.raw.len()
+        }
+    }
+
+    /// Checks whether a read would return without blocking, including at end
+    /// of file.
+    pub fn readable(&self) -> bool {
+        if self.is_canonical() {
+            !self.lines.is_empty()
+        } else {
+            !self.raw.is_empty()
+        }
+    }
+
+    /// Reads pending input. In canonical mode, at most one line is read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        if !self.is_canonical() {
+            let len = buf.len().min(self.raw.len());
+            for (dst, src) in buf.iter_mut().zip(self.raw.drain(..len)) {
+                *dst = src;
+            }
+            return len;
+        }
+
+        let Some(line) = self.lines.front_mut() else {
+            return 0;
+        };
+        let len = buf.len().min(line.len());
+        buf[..len].copy_from_slice(&line[..len]);
+        line.drain(..len);
+        if line.is_empty() {
+            self.lines.pop_front();
+        }
+        len
+    }
+
+    /// Applies output processing to `buf`, appending the result to `out`.
+    pub fn process_output(&self, buf: &[u8], out: &mut Vec<u8>) {
+        let oflag = self.termios.c_oflag;
+        if oflag & OPOST == 0 || oflag & ONLCR == 0 {
+            out.extend_from_slice(buf);
+            return;
+        }
+        for &c in buf {
+            if c == b'\n' {
+                out.push(b'\r');
+            }
+            out.push(c);
+        }
+    }
+
+    fn echo(&self, c: u8, effects: &mut InputEffects) {
+        if !self.lflag(ECHO) {
+            if c == b'\n' && self.lflag(ECHONL) && self.is_canonical() {
+                self.process_output(b"\n", &mut effects.echo);
+            }
+            return;
+        }
+        if self.lflag(ECHOCTL) && is_ctl_echoed(c) {
+            effects.echo.extend_from_slice(&[b'^', c ^ 0x40]);
+        } else {
+            self.process_output(&[c], &mut effects.echo);
+        }
+    }
+
+    /// Erases the last character of the line being edited, returning whether
+    /// there was one.
+    fn erase_char(&mut self, effects: &mut InputEffects) -> bool {
+        let Some(c) = self.line.pop() else {
+            return false;
+        };
+        if self.lflag(ECHO) && self.lflag(ECHOE) {
+            let width = if self.lflag(ECHOCTL) && is_ctl_echoed(c) {
+                2
+            } else {
+                1
+            };
+            for _ in 0..width {
+                effects.echo.extend_from_slice(b"\x08 \x08");
+            }
+        }
+        true
+    }
+
+    /// Handles `c` in canonical mode.
+    fn receive_canonical(&mut self, c: u8, effects: &mut InputEffects) {
+        if self.is_cc(c, VERASE) {
+            self.erase_char(effects);
+        } else if self.is_cc(c, VWERASE) && self.lflag(IEXTEN) {
+            while self.line.last().is_some_and(u8::is_ascii_whitespace) {
+                self.erase_char(effects);
+            }
+            while self.line.last().is_some_and(|c| !c.is_ascii_whitespace()) {
+                self.erase_char(effects);
+            }
+        } else if self.is_cc(c, VKILL) {
+            if self.lflag(ECHOKE) && self.lflag(ECHOE) {
+                while self.erase_char(effects) {}
+            } else {
+                self.line.clear();
+                self.echo(c, effects);
+                if self.lflag(ECHOK) {
+                    self.process_output(b"\n", &mut effects.echo);
+                }
+            }
+        } else if self.is_cc(c, VEOF) {
+            self.lines.push_back(core::mem::take(&mut self.line));
+        } else if c == b'\n' || self.is_cc(c, VEOL) || self.is_cc(c, VEOL2) {
+            self.echo(c, effects);
+            self.line.push(c);
+            self.lines.push_back(core::mem::take(&mut self.line));
+        } else if self.line.len() < MAX_CANON {
+            self.echo(c, effects);
+            self.line.push(c);
+        }
+    }
+
+    /// Processes input received from the terminal device.
+    pub fn receive(&mut self, input: &[u8], effects: &mut InputEffects) {
+        let iflag = self.termios.c_iflag;
+        for &c in input {
+            let c = match c {
+                b'\r' if iflag & IGNCR != 0 => continue,
+                b'\r' if iflag & ICRNL != 0 => b'\n',
+                b'\n' if iflag & INLCR != 0 => b'\r',
+                c => c,
+            };
+
+            if self.lflag(ISIG) {
+                let signo = if self.is_cc(c, VINTR) {
+                    Some(Signo::SIGINT)
+                } else if self.is_cc(c, VQUIT) {
+                    Some(Signo::SIGQUIT)
+                } else if self.is_cc(c, VSUSP) {
+                    Some(Signo::SIGTSTP)
+                } else {
+                    None
+                };
+                if let Some(signo) = signo {
+                    if !self.lflag(NOFLSH) {
+                        self.flush_input();
+                    }
+                    self.echo(c, effects);
+                    effects.signals.push(signo);
+                    continue;
+                }
+            }
+
+            if self.is_canonical() {
+                self.receive_canonical(c, effects);
+            } else if self.raw.len() < MAX_INPUT {
+                self.echo(c, effects);
+                self.raw.push_back(c);
+            }
+        }
+    }
+}
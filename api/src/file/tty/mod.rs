@@ -0,0 +1,476 @@
+//! Terminals: the line discipline, termios ioctls and job control.
+//!
+//! A [`Tty`] sits between a terminal device, which provides a
+//! [`TtyDriver`], and the processes using it. It may be the controlling
+//! terminal of a session, in which case input control characters send signals
+//! to the foreground process group, and background processes are stopped when
+//! they use it.
+
+mod ldisc;
+mod pty;
+
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::{
+    boxed::Box,
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{TimeValue, monotonic_time};
+use axio::PollState;
+use axprocess::{Pid, Process, ProcessGroup, Session};
+use axsignal::{SignalDisposition, SignalInfo, Signo};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    general::{
+        O_NOCTTY, S_IFCHR, SI_KERNEL, TCIFLUSH, TCIOFLUSH, TCOFLUSH, TOSTOP, termios, winsize,
+    },
+    ioctl::{
+        FIONREAD, TCFLSH, TCGETS, TCSBRK, TCSBRKP, TCSETS, TCSETSF, TCSETSW, TCXONC, TIOCGPGRP,
+        TIOCGSID, TIOCGWINSZ, TIOCNOTTY, TIOCOUTQ, TIOCSCTTY, TIOCSPGRP, TIOCSWINSZ,
+    },
+};
+use starry_core::task::get_process_group;
+
+pub use self::pty::{PtyMaster, mount_devpts};
+
+use self::ldisc::{InputEffects, LineDiscipline};
+use super::{EventQueue, FileLike, Kstat, stdio::console};
+use crate::{
+    ptr::{UserConstPtr, UserPtr},
+    signal::send_signal_process_group,
+};
+
+/// A terminal device. Input from the device is passed to [`Tty::receive`].
+pub trait TtyDriver: Send + Sync {
+    /// Writes output to the device.
+    fn write(&self, buf: &[u8]);
+
+    /// Called when a file of the terminal is opened.
+    fn open(&self) {}
+
+    /// Called when a file of the terminal is closed.
+    fn release(&self) {}
+}
+
+/// The session a terminal controls, and its foreground process group.
+#[derive(Default)]
+struct JobState {
+    session: Weak<Session>,
+    foreground: Weak<ProcessGroup>,
+}
+
+impl JobState {
+    fn controls(&self, session: &Arc<Session>) -> bool {
+        self.session
+            .upgrade()
+            .is_some_and(|it| Arc::ptr_eq(&it, session))
+    }
+}
+
+/// The controlling terminals, by session ID. Locked before the job state of
+/// any terminal.
+static CONTROLLING_TTYS: Mutex<BTreeMap<Pid, Weak<Tty>>> = Mutex::new(BTreeMap::new());
+
+fn find_controlling_tty(
+    ttys: &BTreeMap<Pid, Weak<Tty>>,
+    session: &Arc<Session>,
+) -> Option<Arc<Tty>> {
+    ttys.get(&session.sid())
+        .and_then(Weak::upgrade)
+        .filter(|tty| tty.job.lock().controls(session))
+}
+
+/// Finds the controlling terminal of a session.
+pub fn controlling_tty(session: &Arc<Session>) -> Option<Arc<Tty>> {
+    find_controlling_tty(&CONTROLLING_TTYS.lock(), session)
+}
+
+/// Opens the terminal device at `path`, if it is one of `/dev/tty`,
+/// `/dev/console`, `/dev/ptmx` or `/dev/pts/N`.
+///
+/// Unless `O_NOCTTY` is given, a terminal opened by a session leader becomes
+/// the controlling terminal of its session if it has none.
+pub fn open_tty(path: &str, flags: u32) -> LinuxResult<Option<Arc<dyn FileLike>>> {
+    let curr = current();
+    let proc = curr.task_ext().thread.process();
+    let tty = match path {
+        "/dev/tty" => controlling_tty(&proc.group().session()).ok_or(LinuxError::ENXIO)?,
+        "/dev/console" => console().clone(),
+        "/dev/ptmx" | "/dev/pts/ptmx" => return Ok(Some(PtyMaster::new()?)),
+        _ => match path.strip_prefix("/dev/pts/") {
+            Some(name) => pty::open_slave(name)?,
+            None => return Ok(None),
+        },
+    };
+    if flags & O_NOCTTY == 0 {
+        // Fails if the caller can not acquire a controlling terminal.
+        let _ = tty.set_controlling(proc, false);
+    }
+    Ok(Some(Arc::new(TtyFile::new(tty))))
+}
+
+pub struct Tty {
+    this: Weak<Tty>,
+    driver: Box<dyn TtyDriver>,
+    ldisc: Mutex<LineDiscipline>,
+    job: Mutex<JobState>,
+    /// Whether the device has gone away
+    hung_up: AtomicBool,
+    /// Notified when input can be read
+    input_events: EventQueue,
+}
+
+impl Tty {
+    pub fn new(driver: Box<dyn TtyDriver>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            driver,
+            ldisc: Mutex::new(LineDiscipline::new()),
+            job: Mutex::new(JobState::default()),
+            hung_up: AtomicBool::new(false),
+            input_events: EventQueue::new(),
+        })
+    }
+
+    /// Hangs up the terminal when its device goes away: the session it
+    /// controls loses it, reads return end of file and writes fail with
+    /// `EIO`.
+    pub fn hangup(&self) {
+        self.hung_up.store(true, Ordering::Release);
+        self.input_events.notify();
+        self.disassociate();
+    }
+
+    fn is_hung_up(&self) -> bool {
+        self.hung_up.load(Ordering::Acquire)
+    }
+
+    /// Processes input received from the device: echoes it, sends signals
+    /// for control characters and makes it available to readers.
+    pub fn receive(&self, input: &[u8]) {
+        let mut effects = InputEffects::default();
+        self.ldisc.lock().receive(input, &mut effects);
+        self.input_events.notify();
+        if !effects.echo.is_empty() {
+            self.driver.write(&effects.echo);
+        }
+        for signo in effects.signals {
+            self.signal_foreground(signo);
+        }
+    }
+
+    fn signal_foreground(&self, signo: Signo) {
+        let foreground = self.job.lock().foreground.upgrade();
+        if let Some(pg) = foreground {
+            send_signal_process_group(&pg, SignalInfo::new(signo, SI_KERNEL as _));
+        }
+    }
+
+    /// Makes the terminal the controlling terminal of the session led by
+    /// `proc`, with the process group of `proc` in the foreground.
+    ///
+    /// Fails with `EPERM` if `proc` is not a session leader, if its session
+    /// already has another controlling terminal, or if the terminal controls
+    /// another session and `steal` is false.
+    pub fn set_controlling(&self, proc: &Process, steal: bool) -> LinuxResult {
+        let group = proc.group();
+        let session = group.session();
+        if session.sid() != proc.pid() {
+            return Err(LinuxError::EPERM);
+        }
+        let mut ttys = CONTROLLING_TTYS.lock();
+        if let Some(tty) = find_controlling_tty(&ttys, &session) {
+            return if core::ptr::eq(Arc::as_ptr(&tty), self) {
+                Ok(())
+            } else {
+                Err(LinuxError::EPERM)
+            };
+        }
+
+        let mut job = self.job.lock();
+        if job.session.strong_count() > 0 && !steal {
+            return Err(LinuxError::EPERM);
+        }
+        job.session = Arc::downgrade(&session);
+        job.foreground = Arc::downgrade(&group);
+        ttys.insert(session.sid(), self.this.clone());
+        Ok(())
+    }
+
+    /// Detaches the terminal from the session it controls, sending `SIGHUP`
+    /// and `SIGCONT` to the foreground process group.
+    fn disassociate(&self) {
+        let foreground = {
+            let mut ttys = CONTROLLING_TTYS.lock();
+            let job = core::mem::take(&mut *self.job.lock());
+            if let Some(session) = job.session.upgrade() {
+                ttys.remove(&session.sid());
+            }
+            job.foreground.upgrade()
+        };
+        if let Some(pg) = foreground {
+            send_signal_process_group(&pg, SignalInfo::new(Signo::SIGHUP, SI_KERNEL as _));
+            send_signal_process_group(&pg, SignalInfo::new(Signo::SIGCONT, SI_KERNEL as _));
+        }
+    }
+
+    /// Returns the session of the calling process if the terminal controls
+    /// it, and `ENOTTY` otherwise.
+    fn current_session(&self) -> LinuxResult<Arc<Session>> {
+        let session = current().task_ext().thread.process().group().session();
+        if self.job.lock().controls(&session) {
+            Ok(session)
+        } else {
+            Err(LinuxError::ENOTTY)
+        }
+    }
+
+    /// Stops the process group of the calling process with `signo`
+    /// (`SIGTTIN` or `SIGTTOU`) if it uses its controlling terminal from the
+    /// background.
+    ///
+    /// If the signal is blocked or ignored, reads fail with `EIO` while other
+    /// operations go on. Otherwise the signal is sent and the syscall is
+    /// restarted once the process group is continued.
+    fn check_job_control(&self, signo: Signo) -> LinuxResult {
+        let curr = current();
+        let group = curr.task_ext().thread.process().group();
+        let foreground = {
+            let job = self.job.lock();
+            if !job.controls(&group.session()) {
+                return Ok(());
+            }
+            job.foreground.upgrade()
+        };
+        if foreground.is_none_or(|fg| Arc::ptr_eq(&fg, &group)) {
+            return Ok(());
+        }
+
+        let blocked = curr.task_ext().thread_data().signal.blocked().has(signo);
+        let ignored = matches!(
+            curr.task_ext().process_data().signal.actions().lock()[signo].disposition,
+            SignalDisposition::Ignore
+        );
+        if blocked || ignored {
+            return if signo == Signo::SIGTTIN {
+                Err(LinuxError::EIO)
+            } else {
+                Ok(())
+            };
+        }
+        send_signal_process_group(&group, SignalInfo::new(signo, SI_KERNEL as _));
+        Err(LinuxError::ERESTART)
+    }
+
+    /// Sets the foreground process group, as done by `tcsetpgrp(3)`.
+    fn set_foreground(&self, pgid: i32) -> LinuxResult {
+        let session = self.current_session()?;
+        self.check_job_control(Signo::SIGTTOU)?;
+        if pgid < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let group = get_process_group(pgid as Pid)?;
+        if !Arc::ptr_eq(&group.session(), &session) {
+            return Err(LinuxError::EPERM);
+        }
+        self.job.lock().foreground = Arc::downgrade(&group);
+        Ok(())
+    }
+
+    fn set_termios(&self, cmd: u32, termios: termios) -> LinuxResult {
+        self.check_job_control(Signo::SIGTTOU)?;
+        let mut ldisc = self.ldisc.lock();
+        if cmd == TCSETSF {
+            ldisc.flush_input();
+        }
+        ldisc.set_termios(termios);
+        drop(ldisc);
+        // Switching the mode may make pending input readable.
+        self.input_events.notify();
+        Ok(())
+    }
+}
+
+impl Tty {
+    fn read(&self, buf: &mut [u8], nonblocking: bool) -> LinuxResult<usize> {
+        self.check_job_control(Signo::SIGTTIN)?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let start = monotonic_time();
+        loop {
+            let events = self.input_events.events();
+            if self.is_hung_up() {
+                return Ok(0);
+            }
+            let timeout = {
+                let mut ldisc = self.ldisc.lock();
+                if ldisc.readable() {
+                    return Ok(ldisc.read(buf));
+                }
+                // Non-canonical reads with `VMIN` 0 return once `VTIME`
+                // tenths of a second have elapsed.
+                let (min, time) = ldisc.min_time();
+                if !ldisc.is_canonical() && min == 0 {
+                    let elapsed = monotonic_time() - start;
+                    match TimeValue::from_millis(time as u64 * 100).checked_sub(elapsed) {
+                        Some(timeout) if !timeout.is_zero() => Some(timeout),
+                        _ => return Ok(0),
+                    }
+                } else {
+                    None
+                }
+            };
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            match self.input_events.wait(events, timeout) {
+                Err(LinuxError::ETIMEDOUT) => return Ok(0),
+                result => result?,
+            }
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if self.is_hung_up() {
+            return Err(LinuxError::EIO);
+        }
+        if self.ldisc.lock().termios.c_lflag & TOSTOP != 0 {
+            self.check_job_control(Signo::SIGTTOU)?;
+        }
+        let mut out = Vec::with_capacity(buf.len());
+        self.ldisc.lock().process_output(buf, &mut out);
+        self.driver.write(&out);
+        Ok(buf.len())
+    }
+
+    fn poll(&self) -> PollState {
+        PollState {
+            readable: self.is_hung_up() || self.ldisc.lock().readable(),
+            writable: true,
+        }
+    }
+
+    fn ioctl(&self, cmd: u32, arg: usize) -> LinuxResult<usize> {
+        match cmd {
+            TCGETS => {
+                *UserPtr::<termios>::from(arg).get_as_mut()? = self.ldisc.lock().termios;
+            }
+            TCSETS | TCSETSW | TCSETSF => {
+                let termios = *UserConstPtr::<termios>::from(arg).get_as_ref()?;
+                self.set_termios(cmd, termios)?;
+            }
+            TIOCGWINSZ => {
+                *UserPtr::<winsize>::from(arg).get_as_mut()? = self.ldisc.lock().winsize;
+            }
+            TIOCSWINSZ => {
+                let new = *UserConstPtr::<winsize>::from(arg).get_as_ref()?;
+                let old = core::mem::replace(&mut self.ldisc.lock().winsize, new);
+                if (old.ws_row, old.ws_col, old.ws_xpixel, old.ws_ypixel)
+                    != (new.ws_row, new.ws_col, new.ws_xpixel, new.ws_ypixel)
+                {
+                    self.signal_foreground(Signo::SIGWINCH);
+                }
+            }
+            TIOCGPGRP => {
+                self.current_session()?;
+                let foreground = self.job.lock().foreground.upgrade();
+                *UserPtr::<i32>::from(arg).get_as_mut()? =
+                    foreground.map_or(0, |pg| pg.pgid() as _);
+            }
+            TIOCSPGRP => {
+                self.set_foreground(*UserConstPtr::<i32>::from(arg).get_as_ref()?)?;
+            }
+            TIOCGSID => {
+                *UserPtr::<i32>::from(arg).get_as_mut()? = self.current_session()?.sid() as _;
+            }
+            TIOCSCTTY => {
+                self.set_controlling(current().task_ext().thread.process(), arg == 1)?;
+            }
+            TIOCNOTTY => {
+                let session = self.current_session()?;
+                if session.sid() == current().task_ext().thread.process().pid() {
+                    self.disassociate();
+                }
+            }
+            FIONREAD => {
+                *UserPtr::<i32>::from(arg).get_as_mut()? = self.ldisc.lock().available() as _;
+            }
+            TIOCOUTQ => {
+                *UserPtr::<i32>::from(arg).get_as_mut()? = 0;
+            }
+            TCFLSH => match arg as u32 {
+                TCIFLUSH | TCIOFLUSH => self.ldisc.lock().flush_input(),
+                TCOFLUSH => {}
+                _ => return Err(LinuxError::EINVAL),
+            },
+            TCSBRK | TCSBRKP | TCXONC => {}
+            _ => return Err(LinuxError::ENOTTY),
+        }
+        Ok(0)
+    }
+}
+
+/// An open terminal.
+pub struct TtyFile {
+    tty: Arc<Tty>,
+    nonblocking: AtomicBool,
+}
+
+impl TtyFile {
+    pub fn new(tty: Arc<Tty>) -> Self {
+        tty.driver.open();
+        Self {
+            tty,
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Drop for TtyFile {
+    fn drop(&mut self) {
+        self.tty.driver.release();
+    }
+}
+
+impl FileLike for TtyFile {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        self.tty.read(buf, self.nonblocking.load(Ordering::Acquire))
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.tty.write(buf)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: S_IFCHR | 0o620u32, // rw--w----
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(self.tty.poll())
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: u32, arg: usize) -> LinuxResult<usize> {
+        self.tty.ioctl(cmd, arg)
+    }
+}
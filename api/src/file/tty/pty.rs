@@ -0,0 +1,332 @@
+//! Pseudoterminals: the master side opened through `/dev/ptmx`, and the
+//! devpts filesystem exposing the slave sides as `/dev/pts/N`.
+
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use alloc::{
+    boxed::Box,
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    format,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axfs_vfs::{
+    VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps,
+    VfsResult,
+};
+use axio::PollState;
+use axsync::Mutex;
+use linux_raw_sys::{
+    general::{O_NONBLOCK, S_IFCHR},
+    ioctl::{TIOCGPTLCK, TIOCGPTN, TIOCGPTPEER, TIOCSPTLCK},
+};
+use spin::{Once, RwLock};
+
+use super::{EventQueue, Tty, TtyDriver, TtyFile};
+use crate::{
+    file::{FileLike, Kstat, add_file_like},
+    ptr::{UserConstPtr, UserPtr},
+};
+
+/// The maximum number of pseudoterminals.
+const MAX_PTYS: u32 = 4096;
+
+/// The masters of the open pseudoterminals, by index.
+static PTYS: Mutex<BTreeMap<u32, Weak<PtyMaster>>> = Mutex::new(BTreeMap::new());
+
+/// The terminal device of a slave: its output goes to the master.
+#[derive(Default)]
+struct PtySlave {
+    /// The output not read by the master yet
+    output: Mutex<VecDeque<u8>>,
+    /// The number of open files of the slave
+    files: AtomicUsize,
+    /// Whether the slave has been opened
+    opened: AtomicBool,
+    /// Notified on output, and when the last file of the slave is closed
+    events: EventQueue,
+}
+
+impl PtySlave {
+    /// Whether all files of the slave have been closed after it was opened.
+    fn closed(&self) -> bool {
+        self.opened.load(Ordering::Acquire) && self.files.load(Ordering::Acquire) == 0
+    }
+}
+
+impl TtyDriver for Arc<PtySlave> {
+    fn write(&self, buf: &[u8]) {
+        self.output.lock().extend(buf);
+        self.events.notify();
+    }
+
+    fn open(&self) {
+        self.files.fetch_add(1, Ordering::AcqRel);
+        self.opened.store(true, Ordering::Release);
+    }
+
+    fn release(&self) {
+        if self.files.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.events.notify();
+        }
+    }
+}
+
+/// The master side of a pseudoterminal. Writes to it are input of the slave
+/// terminal, and reads from it return the output of the slave.
+pub struct PtyMaster {
+    index: u32,
+    slave: Arc<Tty>,
+    device: Arc<PtySlave>,
+    /// Whether the slave can not be opened, until `unlockpt(3)`
+    locked: AtomicBool,
+    nonblocking: AtomicBool,
+}
+
+impl PtyMaster {
+    /// Allocates a new pseudoterminal, with the lowest free index.
+    pub fn new() -> LinuxResult<Arc<Self>> {
+        let mut ptys = PTYS.lock();
+        let index = (0..MAX_PTYS)
+            .find(|index| ptys.get(index).is_none_or(|it| it.strong_count() == 0))
+            .ok_or(LinuxError::ENOSPC)?;
+
+        let device = Arc::new(PtySlave::default());
+        let master = Arc::new(Self {
+            index,
+            slave: Tty::new(Box::new(device.clone())),
+            device,
+            locked: AtomicBool::new(true),
+            nonblocking: AtomicBool::new(false),
+        });
+        ptys.insert(index, Arc::downgrade(&master));
+        Ok(master)
+    }
+}
+
+impl Drop for PtyMaster {
+    fn drop(&mut self) {
+        let mut ptys = PTYS.lock();
+        if ptys
+            .get(&self.index)
+            .is_some_and(|it| it.strong_count() == 0)
+        {
+            ptys.remove(&self.index);
+        }
+        drop(ptys);
+        self.slave.hangup();
+    }
+}
+
+/// Opens the slave of the pseudoterminal `/dev/pts/{name}`.
+pub fn open_slave(name: &str) -> LinuxResult<Arc<Tty>> {
+    let master = name
+        .parse::<u32>()
+        .ok()
+        .and_then(|index| PTYS.lock().get(&index)?.upgrade())
+        .ok_or(LinuxError::ENOENT)?;
+    if master.locked.load(Ordering::Acquire) {
+        return Err(LinuxError::EIO);
+    }
+    Ok(master.slave.clone())
+}
+
+impl FileLike for PtyMaster {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let events = self.device.events.events();
+            {
+                let mut output = self.device.output.lock();
+                if !output.is_empty() {
+                    let len = buf.len().min(output.len());
+                    for (dst, src) in buf.iter_mut().zip(output.drain(..len)) {
+                        *dst = src;
+                    }
+                    return Ok(len);
+                }
+            }
+            if self.device.closed() {
+                return Err(LinuxError::EIO);
+            }
+            if self.nonblocking.load(Ordering::Acquire) {
+                return Err(LinuxError::EAGAIN);
+            }
+            self.device.events.wait(events, None)?;
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.slave.receive(buf);
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: S_IFCHR | 0o666u32, // rw-rw-rw-
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: !self.device.output.lock().is_empty() || self.device.closed(),
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: u32, arg: usize) -> LinuxResult<usize> {
+        match cmd {
+            TIOCGPTN => {
+                *UserPtr::<u32>::from(arg).get_as_mut()? = self.index;
+            }
+            TIOCSPTLCK => {
+                let lock = *UserConstPtr::<i32>::from(arg).get_as_ref()?;
+                self.locked.store(lock != 0, Ordering::Release);
+            }
+            TIOCGPTLCK => {
+                *UserPtr::<i32>::from(arg).get_as_mut()? = self.locked.load(Ordering::Acquire) as _;
+            }
+            TIOCGPTPEER => {
+                let file = TtyFile::new(self.slave.clone());
+                file.set_nonblocking(arg as u32 & O_NONBLOCK != 0)?;
+                return Ok(add_file_like(Arc::new(file))? as _);
+            }
+            // The master shares the settings of the slave.
+            _ => return self.slave.ioctl(cmd, arg),
+        }
+        Ok(0)
+    }
+}
+
+/// A device node in devpts. The device itself is opened by path, see
+/// [`super::open_tty`].
+struct PtsNode {
+    perm: u16,
+}
+
+impl VfsNodeOps for PtsNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(self.perm),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The root directory of devpts, listing `ptmx` and the open
+/// pseudoterminals.
+struct PtsDir {
+    parent: RwLock<Weak<dyn VfsNodeOps>>,
+}
+
+impl PtsDir {
+    fn indices() -> Vec<u32> {
+        PTYS.lock()
+            .iter()
+            .filter(|(_, master)| master.strong_count() > 0)
+            .map(|(index, _)| *index)
+            .collect()
+    }
+}
+
+impl VfsNodeOps for PtsDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(0, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.read().upgrade()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let path = path.trim_start_matches('/');
+        let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+        let node: VfsNodeRef = match name {
+            "" | "." => self.clone(),
+            ".." => self.parent().ok_or(VfsError::NotFound)?,
+            "ptmx" => Arc::new(PtsNode { perm: 0o666 }),
+            _ => {
+                let index = name.parse::<u32>().map_err(|_| VfsError::NotFound)?;
+                if !Self::indices().contains(&index) {
+                    return Err(VfsError::NotFound);
+                }
+                Arc::new(PtsNode { perm: 0o620 })
+            }
+        };
+        if rest.is_empty() {
+            Ok(node)
+        } else {
+            node.lookup(rest)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let indices = Self::indices();
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            *ent = match i + start_idx {
+                0 => VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => VfsDirEntry::new("..", VfsNodeType::Dir),
+                2 => VfsDirEntry::new("ptmx", VfsNodeType::CharDevice),
+                n => match indices.get(n - 3) {
+                    Some(index) => VfsDirEntry::new(&format!("{index}"), VfsNodeType::CharDevice),
+                    None => return Ok(i),
+                },
+            };
+        }
+        Ok(dirents.len())
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// The devpts filesystem.
+struct DevPts {
+    parent: Once<VfsNodeRef>,
+    root: Arc<PtsDir>,
+}
+
+impl VfsOps for DevPts {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        let parent = mount_point
+            .parent()
+            .map(|parent| Arc::downgrade(self.parent.call_once(|| parent)));
+        *self.root.parent.write() = parent.unwrap_or_else(|| Weak::<PtsDir>::new() as _);
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Mounts devpts at `/dev/pts`.
+pub fn mount_devpts() -> LinuxResult {
+    let devpts = DevPts {
+        parent: Once::new(),
+        root: Arc::new(PtsDir {
+            parent: RwLock::new(Weak::<PtsDir>::new()),
+        }),
+    };
+    axfs::api::mount("/dev/pts", Arc::new(devpts))?;
+    Ok(())
+}
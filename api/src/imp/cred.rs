@@ -0,0 +1,398 @@
+use core::sync::atomic::Ordering;
+
+use alloc::{sync::Arc, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::general::{
+    __user_cap_data_struct, __user_cap_header_struct, _LINUX_CAPABILITY_U32S_1,
+    _LINUX_CAPABILITY_U32S_3, _LINUX_CAPABILITY_VERSION_1, _LINUX_CAPABILITY_VERSION_2,
+    _LINUX_CAPABILITY_VERSION_3, CAP_LAST_CAP, CAP_SETGID, CAP_SETPCAP, CAP_SETUID,
+};
+use starry_core::{
+    cred::{CapSet, Credentials, Gid, NGROUPS_MAX, Uid},
+    task::{ProcessData, get_thread},
+};
+
+use crate::ptr::{UserConstPtr, UserPtr};
+
+/// The ID passed to leave an ID unchanged, `(uid_t) -1`.
+pub(crate) const UNCHANGED: u32 = u32::MAX;
+
+pub(crate) fn current_cred() -> Arc<Credentials> {
+    current().task_ext().process_data().cred()
+}
+
+/// Updates the credentials of the calling process with `f`, which works on
+/// a copy so that a failed update changes nothing. The capabilities are then
+/// adjusted to the new user IDs, and the parent-death signal is cleared if
+/// the effective or filesystem IDs change.
+fn update_cred(f: impl FnOnce(&mut Credentials) -> LinuxResult) -> LinuxResult<isize> {
+    let curr = current();
+    let process_data = curr.task_ext().process_data();
+    let old = process_data.cred();
+    let mut cred = Credentials::clone(&old);
+    f(&mut cred)?;
+    cred.update_caps_for_uid_change(&old);
+    if cred.ids_changed_from(&old) {
+        curr.task_ext()
+            .thread_data()
+            .pdeathsig
+            .store(0, Ordering::Relaxed);
+    }
+    process_data.set_cred(cred);
+    Ok(0)
+}
+
+pub fn sys_getuid() -> LinuxResult<isize> {
+    Ok(current_cred().uid as _)
+}
+
+pub fn sys_geteuid() -> LinuxResult<isize> {
+    Ok(current_cred().euid as _)
+}
+
+pub fn sys_getgid() -> LinuxResult<isize> {
+    Ok(current_cred().gid as _)
+}
+
+pub fn sys_getegid() -> LinuxResult<isize> {
+    Ok(current_cred().egid as _)
+}
+
+/// Sets all user IDs to `uid` if the caller has `CAP_SETUID`, and otherwise
+/// only the effective one, to the real or saved user ID.
+pub fn sys_setuid(uid: Uid) -> LinuxResult<isize> {
+    if uid == UNCHANGED {
+        return Err(LinuxError::EINVAL);
+    }
+    update_cred(|cred| {
+        if cred.has_cap(CAP_SETUID) {
+            cred.uid = uid;
+            cred.suid = uid;
+        } else if uid != cred.uid && uid != cred.suid {
+            return Err(LinuxError::EPERM);
+        }
+        cred.set_euid(uid);
+        Ok(())
+    })
+}
+
+pub fn sys_setgid(gid: Gid) -> LinuxResult<isize> {
+    if gid == UNCHANGED {
+        return Err(LinuxError::EINVAL);
+    }
+    update_cred(|cred| {
+        if cred.has_cap(CAP_SETGID) {
+            cred.gid = gid;
+            cred.sgid = gid;
+        } else if gid != cred.gid && gid != cred.sgid {
+            return Err(LinuxError::EPERM);
+        }
+        cred.set_egid(gid);
+        Ok(())
+    })
+}
+
+/// Sets the real and effective user IDs, leaving those passed as -1
+/// unchanged.
+///
+/// A caller without `CAP_SETUID` may only set the real user ID to the real
+/// or effective one, and the effective user ID to the real, effective or
+/// saved one. The saved user ID becomes the new effective one if the real
+/// user ID is set, or the effective one is set to something else than the
+/// previous real user ID.
+pub fn sys_setreuid(ruid: Uid, euid: Uid) -> LinuxResult<isize> {
+    update_cred(|cred| {
+        let old = cred.clone();
+        if !old.has_cap(CAP_SETUID)
+            && ((ruid != UNCHANGED && ruid != old.uid && ruid != old.euid)
+                || (euid != UNCHANGED && !old.has_uid(euid)))
+        {
+            return Err(LinuxError::EPERM);
+        }
+        if ruid != UNCHANGED {
+            cred.uid = ruid;
+        }
+        if euid != UNCHANGED {
+            cred.set_euid(euid);
+        }
+        if ruid != UNCHANGED || (euid != UNCHANGED && euid != old.uid) {
+            cred.suid = cred.euid;
+        }
+        Ok(())
+    })
+}
+
+pub fn sys_setregid(rgid: Gid, egid: Gid) -> LinuxResult<isize> {
+    update_cred(|cred| {
+        let old = cred.clone();
+        if !old.has_cap(CAP_SETGID)
+            && ((rgid != UNCHANGED && rgid != old.gid && rgid != old.egid)
+                || (egid != UNCHANGED && !old.has_gid(egid)))
+        {
+            return Err(LinuxError::EPERM);
+        }
+        if rgid != UNCHANGED {
+            cred.gid = rgid;
+        }
+        if egid != UNCHANGED {
+            cred.set_egid(egid);
+        }
+        if rgid != UNCHANGED || (egid != UNCHANGED && egid != old.gid) {
+            cred.sgid = cred.egid;
+        }
+        Ok(())
+    })
+}
+
+/// Sets the real, effective and saved user IDs, leaving those passed as -1
+/// unchanged. A caller without `CAP_SETUID` may only set each of them to one
+/// of the current ones.
+pub fn sys_setresuid(ruid: Uid, euid: Uid, suid: Uid) -> LinuxResult<isize> {
+    update_cred(|cred| {
+        if !cred.has_cap(CAP_SETUID)
+            && [ruid, euid, suid]
+                .iter()
+                .any(|&id| id != UNCHANGED && !cred.has_uid(id))
+        {
+            return Err(LinuxError::EPERM);
+        }
+        if ruid != UNCHANGED {
+            cred.uid = ruid;
+        }
+        if euid != UNCHANGED {
+            cred.set_euid(euid);
+        }
+        if suid != UNCHANGED {
+            cred.suid = suid;
+        }
+        Ok(())
+    })
+}
+
+pub fn sys_setresgid(rgid: Gid, egid: Gid, sgid: Gid) -> LinuxResult<isize> {
+    update_cred(|cred| {
+        if !cred.has_cap(CAP_SETGID)
+            && [rgid, egid, sgid]
+                .iter()
+                .any(|&id| id != UNCHANGED && !cred.has_gid(id))
+        {
+            return Err(LinuxError::EPERM);
+        }
+        if rgid != UNCHANGED {
+            cred.gid = rgid;
+        }
+        if egid != UNCHANGED {
+            cred.set_egid(egid);
+        }
+        if sgid != UNCHANGED {
+            cred.sgid = sgid;
+        }
+        Ok(())
+    })
+}
+
+fn write_ids(ids: [u32; 3], ptrs: [UserPtr<u32>; 3]) -> LinuxResult<isize> {
+    for (id, ptr) in ids.into_iter().zip(ptrs) {
+        *ptr.get_as_mut()? = id;
+    }
+    Ok(0)
+}
+
+pub fn sys_getresuid(
+    ruid: UserPtr<Uid>,
+    euid: UserPtr<Uid>,
+    suid: UserPtr<Uid>,
+) -> LinuxResult<isize> {
+    let cred = current_cred();
+    write_ids([cred.uid, cred.euid, cred.suid], [ruid, euid, suid])
+}
+
+pub fn sys_getresgid(
+    rgid: UserPtr<Gid>,
+    egid: UserPtr<Gid>,
+    sgid: UserPtr<Gid>,
+) -> LinuxResult<isize> {
+    let cred = current_cred();
+    write_ids([cred.gid, cred.egid, cred.sgid], [rgid, egid, sgid])
+}
+
+/// Sets the filesystem user ID, returning the previous one whether or not it
+/// was changed.
+///
+/// A caller without `CAP_SETUID` may only set it to its real, effective,
+/// saved or current filesystem user ID.
+pub fn sys_setfsuid(fsuid: Uid) -> LinuxResult<isize> {
+    let old = current_cred().fsuid;
+    let _ = update_cred(|cred| {
+        if fsuid == UNCHANGED || !(cred.has_cap(CAP_SETUID) || cred.has_uid(fsuid) || fsuid == old)
+        {
+            return Err(LinuxError::EPERM);
+        }
+        cred.fsuid = fsuid;
+        Ok(())
+    });
+    Ok(old as _)
+}
+
+pub fn sys_setfsgid(fsgid: Gid) -> LinuxResult<isize> {
+    let old = current_cred().fsgid;
+    let _ = update_cred(|cred| {
+        if fsgid == UNCHANGED || !(cred.has_cap(CAP_SETGID) || cred.has_gid(fsgid) || fsgid == old)
+        {
+            return Err(LinuxError::EPERM);
+        }
+        cred.fsgid = fsgid;
+        Ok(())
+    });
+    Ok(old as _)
+}
+
+/// Gets the supplementary group IDs. With `size` 0 only their number is
+/// returned.
+pub fn sys_getgroups(size: i32, list: UserPtr<Gid>) -> LinuxResult<isize> {
+    if size < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let cred = current_cred();
+    let groups = &cred.groups;
+    if size == 0 {
+        return Ok(groups.len() as _);
+    }
+    if (size as usize) < groups.len() {
+        return Err(LinuxError::EINVAL);
+    }
+    list.get_as_mut_slice(groups.len())?.copy_from_slice(groups);
+    Ok(groups.len() as _)
+}
+
+/// Sets the supplementary group IDs, which requires `CAP_SETGID`.
+pub fn sys_setgroups(size: usize, list: UserConstPtr<Gid>) -> LinuxResult<isize> {
+    if size > NGROUPS_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut groups = if size == 0 {
+        Vec::new()
+    } else {
+        list.get_as_slice(size)?.to_vec()
+    };
+    groups.sort_unstable();
+    groups.dedup();
+    update_cred(|cred| {
+        if !cred.has_cap(CAP_SETGID) {
+            return Err(LinuxError::EPERM);
+        }
+        cred.groups = groups;
+        Ok(())
+    })
+}
+
+/// Gets the number of words of capability data for the version in `header`.
+/// For an unknown version, the preferred one is written back to `header`.
+fn cap_data_len(header: &mut __user_cap_header_struct) -> LinuxResult<usize> {
+    match header.version {
+        _LINUX_CAPABILITY_VERSION_1 => Ok(_LINUX_CAPABILITY_U32S_1 as _),
+        _LINUX_CAPABILITY_VERSION_2 | _LINUX_CAPABILITY_VERSION_3 => {
+            Ok(_LINUX_CAPABILITY_U32S_3 as _)
+        }
+        _ => {
+            header.version = _LINUX_CAPABILITY_VERSION_3;
+            Err(LinuxError::EINVAL)
+        }
+    }
+}
+
+/// Gets the capabilities of the thread `header.pid`, or of the caller if it
+/// is 0. A null `data` only checks the version in `header`.
+pub fn sys_capget(
+    header: UserPtr<__user_cap_header_struct>,
+    data: UserPtr<__user_cap_data_struct>,
+) -> LinuxResult<isize> {
+    let header = header.get_as_mut()?;
+    match cap_data_len(header) {
+        Err(LinuxError::EINVAL) | Ok(_) if data.is_null() => Ok(0),
+        Err(e) => Err(e),
+        Ok(len) => {
+            let cred = match header.pid {
+                0 => current_cred(),
+                pid if pid < 0 => return Err(LinuxError::EINVAL),
+                pid => get_thread(pid as _)?
+                    .process()
+                    .data::<ProcessData>()
+                    .ok_or(LinuxError::ESRCH)?
+                    .cred(),
+            };
+            for (i, word) in data.get_as_mut_slice(len)?.iter_mut().enumerate() {
+                let part = |set: CapSet| (set.0 >> (32 * i)) as u32;
+                *word = __user_cap_data_struct {
+                    effective: part(cred.cap_effective),
+                    permitted: part(cred.cap_permitted),
+                    inheritable: part(cred.cap_inheritable),
+                };
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Sets the capabilities of the caller, which may only drop permitted
+/// capabilities, make effective permitted ones, and make inheritable ones
+/// that are permitted, or with `CAP_SETPCAP` any within the bounding set.
+pub fn sys_capset(
+    header: UserPtr<__user_cap_header_struct>,
+    data: UserConstPtr<__user_cap_data_struct>,
+) -> LinuxResult<isize> {
+    let header = header.get_as_mut()?;
+    let len = cap_data_len(header)?;
+    if header.pid != 0 && header.pid as u32 != current().task_ext().thread.tid() {
+        return Err(LinuxError::EPERM);
+    }
+    let data = data.get_as_slice(len)?;
+    let set = |part: fn(&__user_cap_data_struct) -> u32| {
+        let bits = data.iter().enumerate().fold(0, |bits, (i, word)| {
+            bits | ((part(word) as u64) << (32 * i))
+        });
+        CapSet(bits).intersection(CapSet::FULL)
+    };
+    let effective = set(|word| word.effective);
+    let permitted = set(|word| word.permitted);
+    let inheritable = set(|word| word.inheritable);
+
+    update_cred(|cred| {
+        if (!cred.has_cap(CAP_SETPCAP)
+            && !inheritable.is_subset(cred.cap_inheritable.union(cred.cap_permitted)))
+            || !inheritable.is_subset(cred.cap_inheritable.union(cred.cap_bounding))
+            || !permitted.is_subset(cred.cap_permitted)
+            || !effective.is_subset(permitted)
+        {
+            return Err(LinuxError::EPERM);
+        }
+        cred.cap_effective = effective;
+        cred.cap_permitted = permitted;
+        cred.cap_inheritable = inheritable;
+        Ok(())
+    })
+}
+
+/// Checks whether the capability `cap` is in the bounding set of the caller.
+pub(crate) fn capbset_read(cap: u32) -> LinuxResult<isize> {
+    if cap > CAP_LAST_CAP {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(current_cred().cap_bounding.contains(cap) as _)
+}
+
+/// Removes the capability `cap` from the bounding set of the caller, which
+/// requires `CAP_SETPCAP`.
+pub(crate) fn capbset_drop(cap: u32) -> LinuxResult<isize> {
+    if cap > CAP_LAST_CAP {
+        return Err(LinuxError::EINVAL);
+    }
+    update_cred(|cred| {
+        if !cred.has_cap(CAP_SETPCAP) {
+            return Err(LinuxError::EPERM);
+        }
+        cred.cap_bounding = cred.cap_bounding.difference(CapSet(1 << cap));
+        Ok(())
+    })
+}
@@ -6,14 +6,19 @@ use core::{
 use alloc::ffi::CString;
 use axerrno::{LinuxError, LinuxResult};
 use axfs::fops::DirEntry;
-use linux_raw_sys::general::{
-    AT_FDCWD, AT_REMOVEDIR, DT_BLK, DT_CHR, DT_DIR, DT_FIFO, DT_LNK, DT_REG, DT_SOCK, DT_UNKNOWN,
-    linux_dirent64,
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    general::{
+        AT_FDCWD, AT_REMOVEDIR, DT_BLK, DT_CHR, DT_DIR, DT_FIFO, DT_LNK, DT_REG, DT_SOCK,
+        DT_UNKNOWN, X_OK, linux_dirent64,
+    },
+    ioctl::FIONBIO,
 };
 
 use crate::{
-    file::{Directory, FileLike},
+    file::{Directory, FileLike, get_file_like},
     path::{HARDLINK_MANAGER, handle_file_path},
+    perm::{check_create, check_link, check_remove, file_meta, remove_file_meta, set_file_meta},
     ptr::{UserConstPtr, UserPtr, nullable},
 };
 
@@ -25,16 +30,36 @@ use crate::{
 /// * `op` - The request code. It is of type unsigned long in glibc and BSD,
 ///   and of type int in musl and other UNIX systems.
 /// * `argp` - The argument to the request. It is a pointer to a memory location
-pub fn sys_ioctl(_fd: i32, _op: usize, _argp: UserPtr<c_void>) -> LinuxResult<isize> {
-    warn!("Unimplemented syscall: SYS_IOCTL");
-    Ok(0)
+pub fn sys_ioctl(fd: i32, op: usize, argp: UserPtr<c_void>) -> LinuxResult<isize> {
+    // The request code is an int in musl, which may be sign-extended
+    let op = op as u32;
+    debug!(
+        "sys_ioctl <= fd: {}, op: {:#x}, argp: {:?}",
+        fd,
+        op,
+        argp.address()
+    );
+
+    let f = get_file_like(fd)?;
+    if op == FIONBIO {
+        let nonblocking = *UserConstPtr::<c_int>::from(argp.address().as_usize()).get_as_ref()?;
+        f.set_nonblocking(nonblocking != 0)?;
+        return Ok(0);
+    }
+    Ok(f.ioctl(op, argp.address().as_usize())? as _)
 }
 
 pub fn sys_chdir(path: UserConstPtr<c_char>) -> LinuxResult<isize> {
     let path = path.get_as_str()?;
     debug!("sys_chdir <= {:?}", path);
 
-    axfs::api::set_current_dir(path)?;
+    let path = handle_file_path(AT_FDCWD, path)?;
+    let meta = file_meta(&path)?;
+    if !meta.dir {
+        return Err(LinuxError::ENOTDIR);
+    }
+    meta.check_access(&current().task_ext().process_data().cred(), X_OK)?;
+    axfs::api::set_current_dir(path.as_str())?;
     Ok(0)
 }
 
@@ -45,12 +70,14 @@ pub fn sys_mkdirat(dirfd: i32, path: UserConstPtr<c_char>, mode: u32) -> LinuxRe
         dirfd, path, mode
     );
 
-    if mode != 0 {
-        warn!("directory mode not supported.");
-    }
-
     let path = handle_file_path(dirfd, path)?;
+    if path.exists() {
+        return Err(LinuxError::EEXIST);
+    }
+    // Only the permission and sticky bits apply to a new directory.
+    let meta = check_create(&path, mode & 0o1777, true)?;
     axfs::api::create_dir(path.as_str())?;
+    set_file_meta(&path, &meta);
 
     Ok(0)
 }
@@ -194,6 +221,7 @@ pub fn sys_linkat(
     let old_path = handle_file_path(old_dirfd, old_path)?;
     // handle new path
     let new_path = handle_file_path(new_dirfd, new_path)?;
+    check_link(&new_path)?;
 
     HARDLINK_MANAGER.create_link(&new_path, &old_path)?;
 
@@ -220,9 +248,11 @@ pub fn sys_unlinkat(dirfd: c_int, path: UserConstPtr<c_char>, flags: u32) -> Lin
     );
 
     let path = handle_file_path(dirfd, path)?;
+    check_remove(&path)?;
 
     if flags == AT_REMOVEDIR {
         axfs::api::remove_dir(path.as_str())?;
+        remove_file_meta(&path);
     } else {
         let metadata = axfs::api::metadata(path.as_str())?;
         if metadata.is_dir() {
@@ -232,6 +262,9 @@ pub fn sys_unlinkat(dirfd: c_int, path: UserConstPtr<c_char>, flags: u32) -> Lin
             HARDLINK_MANAGER
                 .remove_link(&path)
                 .ok_or(LinuxError::ENOENT)?;
+            if !path.exists() {
+                remove_file_meta(&path);
+            }
         }
     }
     Ok(0)
@@ -6,21 +6,26 @@ use core::{
 use alloc::string::ToString;
 use axerrno::{AxError, LinuxError, LinuxResult};
 use axfs::fops::OpenOptions;
+use axtask::{TaskExtRef, current};
 use linux_raw_sys::general::{
     __kernel_mode_t, AT_FDCWD, F_DUPFD, F_DUPFD_CLOEXEC, F_SETFL, O_APPEND, O_CREAT, O_DIRECTORY,
-    O_NONBLOCK, O_PATH, O_RDONLY, O_TRUNC, O_WRONLY,
+    O_EXCL, O_NONBLOCK, O_PATH, O_RDONLY, O_TRUNC, O_WRONLY, R_OK, W_OK,
 };
 
 use crate::{
-    file::{Directory, FD_TABLE, File, FileLike, add_file_like, close_file_like, get_file_like},
-    path::handle_file_path,
+    file::{
+        Directory, FD_TABLE, File, FileLike, add_file_like, close_file_like, get_file_like,
+        open_tty,
+    },
+    path::{FilePath, handle_file_path},
+    perm::{FileMeta, check_create, file_meta, set_file_meta},
     ptr::UserConstPtr,
 };
 
 const O_EXEC: u32 = O_PATH;
 
 /// Convert open flags to [`OpenOptions`].
-fn flags_to_options(flags: c_int, _mode: __kernel_mode_t) -> OpenOptions {
+fn flags_to_options(flags: c_int) -> OpenOptions {
     let flags = flags as u32;
     let mut options = OpenOptions::new();
     match flags & 0b11 {
@@ -50,6 +55,30 @@ fn flags_to_options(flags: c_int, _mode: __kernel_mode_t) -> OpenOptions {
     options
 }
 
+/// Checks whether the calling process may open the file at `path` with
+/// `flags`, and returns the mode and ownership of the file to create if it
+/// does not exist.
+fn check_open(path: &FilePath, flags: u32, mode: __kernel_mode_t) -> LinuxResult<Option<FileMeta>> {
+    match file_meta(path) {
+        Ok(_) if flags & (O_CREAT | O_EXCL) == O_CREAT | O_EXCL => Err(LinuxError::EEXIST),
+        Ok(meta) => {
+            let mut access = 0;
+            if flags & 0b11 != O_WRONLY {
+                access |= R_OK;
+            }
+            if flags & 0b11 != O_RDONLY || flags & O_TRUNC != 0 {
+                access |= W_OK;
+            }
+            meta.check_access(&current().task_ext().process_data().cred(), access)?;
+            Ok(None)
+        }
+        Err(LinuxError::ENOENT) if flags & O_CREAT != 0 => {
+            check_create(path, mode as _, false).map(Some)
+        }
+        Err(e) => Err(e),
+    }
+}
+
 /// Open or create a file.
 /// fd: file descriptor
 /// filename: file path to be opened or created
@@ -63,7 +92,7 @@ pub fn sys_openat(
     mode: __kernel_mode_t,
 ) -> LinuxResult<isize> {
     let path = path.get_as_str()?;
-    let opts = flags_to_options(flags, mode);
+    let opts = flags_to_options(flags);
     debug!("sys_openat <= {} {} {:?}", dirfd, path, opts);
 
     let dir = if path.starts_with('/') || dirfd == AT_FDCWD {
@@ -73,6 +102,20 @@ pub fn sys_openat(
     };
     let real_path = handle_file_path(dirfd, path)?;
 
+    if let Some(f) = open_tty(real_path.as_str(), flags as u32)? {
+        if flags as u32 & O_NONBLOCK != 0 {
+            f.set_nonblocking(true)?;
+        }
+        return Ok(add_file_like(f)? as _);
+    }
+
+    // A path descriptor does not give access to the file itself.
+    let new_meta = if flags as u32 & O_PATH != 0 {
+        None
+    } else {
+        check_open(&real_path, flags as u32, mode)?
+    };
+
     if !opts.has_directory() {
         match dir.as_ref().map_or_else(
             || axfs::fops::File::open(path, &opts),
@@ -80,7 +123,11 @@ pub fn sys_openat(
         ) {
             Err(AxError::IsADirectory) => {}
             r => {
-                let fd = File::new(r?, real_path.to_string()).add_to_fd_table()?;
+                let file = r?;
+                if let Some(meta) = &new_meta {
+                    set_file_meta(&real_path, meta);
+                }
+                let fd = File::new(file, real_path.to_string()).add_to_fd_table()?;
                 return Ok(fd as _);
             }
         }
@@ -2,6 +2,7 @@ mod ctl;
 mod fd_ops;
 mod io;
 mod mount;
+mod perm;
 mod pipe;
 mod stat;
 
@@ -9,5 +10,6 @@ pub use self::ctl::*;
 pub use self::fd_ops::*;
 pub use self::io::*;
 pub use self::mount::*;
+pub use self::perm::*;
 pub use self::pipe::*;
 pub use self::stat::*;
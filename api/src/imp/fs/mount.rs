@@ -3,13 +3,28 @@ use core::ffi::{c_char, c_void};
 use alloc::vec::Vec;
 use axerrno::{LinuxError, LinuxResult};
 use axsync::Mutex;
-use linux_raw_sys::general::AT_FDCWD;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::general::{AT_FDCWD, CAP_SYS_ADMIN};
 
 use crate::{
     path::{FilePath, handle_file_path},
     ptr::UserConstPtr,
 };
 
+/// Mounting and unmounting filesystems requires `CAP_SYS_ADMIN`.
+fn check_admin() -> LinuxResult {
+    if current()
+        .task_ext()
+        .process_data()
+        .cred()
+        .has_cap(CAP_SYS_ADMIN)
+    {
+        Ok(())
+    } else {
+        Err(LinuxError::EPERM)
+    }
+}
+
 pub fn sys_mount(
     source: UserConstPtr<c_char>,
     target: UserConstPtr<c_char>,
@@ -25,6 +40,7 @@ pub fn sys_mount(
         source, target, fs_type, flags
     );
 
+    check_admin()?;
     let device_path = handle_file_path(AT_FDCWD, source)?;
     let mount_path = handle_file_path(AT_FDCWD, target)?;
     info!(
@@ -58,6 +74,7 @@ pub fn sys_umount2(target: UserConstPtr<c_char>, flags: i32) -> LinuxResult<isiz
     let target = target.get_as_str()?;
     info!("sys_umount2 <= target: {}, flags: {}", target, flags);
 
+    check_admin()?;
     let mount_path = handle_file_path(AT_FDCWD, target)?;
     if flags != 0 {
         debug!("flags unimplemented");
@@ -0,0 +1,203 @@
+use core::ffi::{c_char, c_int};
+
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+#[cfg(target_arch = "x86_64")]
+use linux_raw_sys::general::AT_FDCWD;
+use linux_raw_sys::general::{
+    AT_EACCESS, AT_EMPTY_PATH, AT_SYMLINK_NOFOLLOW, CAP_CHOWN, CAP_FOWNER, CAP_FSETID, F_OK, R_OK,
+    S_ISGID, S_ISUID, S_IXGRP, W_OK, X_OK,
+};
+use starry_core::cred::{CapSet, Credentials, Gid, Uid};
+
+use crate::{
+    imp::cred::{UNCHANGED, current_cred},
+    path::{FilePath, handle_file_path, join_file_path},
+    perm::{check_search, file_meta, set_file_meta},
+    ptr::{UserConstPtr, nullable},
+};
+
+/// Resolves the target of a `*at` call, which is `dirfd` itself if `path` is
+/// empty and `flags` has `AT_EMPTY_PATH`.
+fn target_path(dirfd: c_int, path: UserConstPtr<c_char>, flags: u32) -> LinuxResult<FilePath> {
+    let path = nullable!(path.get_as_str())?.unwrap_or_default();
+    if path.is_empty() && flags & AT_EMPTY_PATH == 0 {
+        return Err(LinuxError::ENOENT);
+    }
+    handle_file_path(dirfd, path)
+}
+
+/// Changes the permission bits of the file at `path`, which only its owner
+/// or a process with `CAP_FOWNER` may do.
+fn chmod(path: &FilePath, mode: u32) -> LinuxResult<isize> {
+    let cred = current_cred();
+    let mut meta = file_meta(path)?;
+    if cred.fsuid != meta.uid && !cred.has_cap(CAP_FOWNER) {
+        return Err(LinuxError::EPERM);
+    }
+    meta.mode = mode & 0o7777;
+    // Only members of its group may make a file set-group-ID.
+    if !meta.dir && !cred.has_cap(CAP_FSETID) && !cred.in_group(meta.gid) {
+        meta.mode &= !S_ISGID;
+    }
+    set_file_meta(path, &meta);
+    Ok(0)
+}
+
+pub fn sys_fchmodat(
+    dirfd: c_int,
+    path: UserConstPtr<c_char>,
+    mode: u32,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_fchmodat <= dirfd: {}, mode: {:#o}, flags: {}",
+        dirfd, mode, flags
+    );
+    if flags & !(AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    chmod(&target_path(dirfd, path, flags)?, mode)
+}
+
+pub fn sys_fchmod(fd: c_int, mode: u32) -> LinuxResult<isize> {
+    debug!("sys_fchmod <= fd: {}, mode: {:#o}", fd, mode);
+    chmod(&handle_file_path(fd, "")?, mode)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_chmod(path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    sys_fchmodat(AT_FDCWD, path, mode, 0)
+}
+
+/// Changes the owner and group of the file at `path`, leaving those passed
+/// as -1 unchanged.
+///
+/// Only a process with `CAP_CHOWN` may change the owner. The owner may
+/// change the group to one it is a member of.
+fn chown(path: &FilePath, uid: Uid, gid: Gid) -> LinuxResult<isize> {
+    let cred = current_cred();
+    let mut meta = file_meta(path)?;
+    let privileged = cred.has_cap(CAP_CHOWN);
+    let owner = cred.fsuid == meta.uid;
+    if (uid != UNCHANGED && !(privileged || (owner && uid == meta.uid)))
+        || (gid != UNCHANGED && !(privileged || (owner && (gid == meta.gid || cred.in_group(gid)))))
+    {
+        return Err(LinuxError::EPERM);
+    }
+    if uid != UNCHANGED {
+        meta.uid = uid;
+    }
+    if gid != UNCHANGED {
+        meta.gid = gid;
+    }
+    // A new owner or group does not inherit the set-user-ID and
+    // set-group-ID privileges of the file.
+    if !meta.dir && (uid != UNCHANGED || gid != UNCHANGED) {
+        meta.mode &= !S_ISUID;
+        if meta.mode & S_IXGRP != 0 {
+            meta.mode &= !S_ISGID;
+        }
+    }
+    set_file_meta(path, &meta);
+    Ok(0)
+}
+
+pub fn sys_fchownat(
+    dirfd: c_int,
+    path: UserConstPtr<c_char>,
+    uid: Uid,
+    gid: Gid,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_fchownat <= dirfd: {}, uid: {}, gid: {}, flags: {}",
+        dirfd, uid, gid, flags
+    );
+    if flags & !(AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    chown(&target_path(dirfd, path, flags)?, uid, gid)
+}
+
+pub fn sys_fchown(fd: c_int, uid: Uid, gid: Gid) -> LinuxResult<isize> {
+    debug!("sys_fchown <= fd: {}, uid: {}, gid: {}", fd, uid, gid);
+    chown(&handle_file_path(fd, "")?, uid, gid)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_chown(path: UserConstPtr<c_char>, uid: Uid, gid: Gid) -> LinuxResult<isize> {
+    sys_fchownat(AT_FDCWD, path, uid, gid, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_lchown(path: UserConstPtr<c_char>, uid: Uid, gid: Gid) -> LinuxResult<isize> {
+    sys_fchownat(AT_FDCWD, path, uid, gid, AT_SYMLINK_NOFOLLOW)
+}
+
+/// Sets the file mode creation mask, returning the previous one.
+pub fn sys_umask(mask: u32) -> LinuxResult<isize> {
+    let old = current()
+        .task_ext()
+        .process_data()
+        .replace_umask(mask & 0o777);
+    Ok(old as _)
+}
+
+/// Checks whether the calling process may access the file at `path` as
+/// requested by `mode`, a mask of `R_OK`, `W_OK` and `X_OK`, or whether it
+/// exists for `F_OK`.
+///
+/// The check is done with the real user and group IDs, unless `flags` has
+/// `AT_EACCESS`, so that a set-user-ID program can check the permissions of
+/// its caller.
+pub fn sys_faccessat2(
+    dirfd: c_int,
+    path: UserConstPtr<c_char>,
+    mode: u32,
+    flags: u32,
+) -> LinuxResult<isize> {
+    let path = nullable!(path.get_as_str())?.unwrap_or_default();
+    debug!(
+        "sys_faccessat2 <= dirfd: {}, path: {}, mode: {}, flags: {}",
+        dirfd, path, mode, flags
+    );
+    if mode & !(R_OK | W_OK | X_OK) != 0
+        || flags & !(AT_EACCESS | AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    if path.is_empty() && flags & AT_EMPTY_PATH == 0 {
+        return Err(LinuxError::ENOENT);
+    }
+
+    let mut cred = Credentials::clone(&current_cred());
+    if flags & AT_EACCESS == 0 {
+        cred.fsuid = cred.uid;
+        cred.fsgid = cred.gid;
+        // Only the real superuser has its permitted capabilities.
+        cred.cap_effective = if cred.uid == 0 {
+            cred.cap_permitted
+        } else {
+            CapSet::EMPTY
+        };
+    }
+    let file_path = join_file_path(dirfd, path)?;
+    if !path.is_empty() {
+        check_search(&file_path, &cred)?;
+    }
+    let meta = file_meta(&file_path)?;
+    if mode != F_OK {
+        meta.check_access(&cred, mode)?;
+    }
+    Ok(0)
+}
+
+pub fn sys_faccessat(dirfd: c_int, path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    sys_faccessat2(dirfd, path, mode, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_access(path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    sys_faccessat2(AT_FDCWD, path, mode, 0)
+}
@@ -1,22 +1,23 @@
 use core::ffi::{c_char, c_int};
 
+use alloc::string::ToString;
 use axerrno::{AxError, LinuxError, LinuxResult};
 use axfs::fops::OpenOptions;
-use linux_raw_sys::general::{AT_EMPTY_PATH, stat, statx};
+use linux_raw_sys::general::{AT_EMPTY_PATH, AT_FDCWD, stat, statx};
 
 use crate::{
     file::{Directory, File, FileLike, Kstat, get_file_like},
-    path::handle_file_path,
+    path::{FilePath, handle_file_path},
     ptr::{UserConstPtr, UserPtr, nullable},
 };
 
-fn stat_at_path(path: &str) -> LinuxResult<Kstat> {
+fn stat_at_path(path: &FilePath) -> LinuxResult<Kstat> {
     let opts = OpenOptions::new().set_read(true);
     match axfs::fops::File::open(path, &opts) {
-        Ok(file) => File::new(file, path.into()).stat(),
+        Ok(file) => File::new(file, path.to_string()).stat(),
         Err(AxError::IsADirectory) => {
             let dir = axfs::fops::Directory::open_dir(path, &opts)?;
-            Directory::new(dir, path.into()).stat()
+            Directory::new(dir, path.to_string()).stat()
         }
         Err(e) => Err(e.into()),
     }
@@ -29,7 +30,8 @@ pub fn sys_stat(path: UserConstPtr<c_char>, statbuf: UserPtr<stat>) -> LinuxResu
     let path = path.get_as_str()?;
     debug!("sys_stat <= path: {}", path);
 
-    *statbuf.get_as_mut()? = stat_at_path(path)?.into();
+    let path = handle_file_path(AT_FDCWD, path)?;
+    *statbuf.get_as_mut()? = stat_at_path(&path)?.into();
 
     Ok(0)
 }
@@ -71,7 +73,7 @@ pub fn sys_fstatat(
         f.stat()?.into()
     } else {
         let path = handle_file_path(dirfd, path.unwrap_or_default())?;
-        stat_at_path(path.as_str())?.into()
+        stat_at_path(&path)?.into()
     };
 
     Ok(0)
@@ -125,7 +127,7 @@ pub fn sys_statx(
         f.stat()?.into()
     } else {
         let path = handle_file_path(dirfd, path.unwrap_or_default())?;
-        stat_at_path(path.as_str())?.into()
+        stat_at_path(&path)?.into()
     };
 
     Ok(0)
@@ -3,6 +3,7 @@ use axtask::{TaskExtRef, current};
 use linux_raw_sys::general::{
     FUTEX_CMD_MASK, FUTEX_CMP_REQUEUE, FUTEX_REQUEUE, FUTEX_WAIT, FUTEX_WAKE, timespec,
 };
+use starry_core::signal::interruptible_wait;
 
 use crate::{
     ptr::{UserConstPtr, UserPtr, nullable},
@@ -32,9 +33,16 @@ pub fn sys_futex(
             let wq = futex_table.get_or_insert(addr);
 
             if let Some(timeout) = nullable!(timeout.get_as_ref())? {
-                wq.wait_timeout(timeout.to_time_value());
+                // Timed waits are not restarted, as the timeout is relative.
+                interruptible_wait(&wq, Some(timeout.to_time_value())).map_err(|err| {
+                    if err == LinuxError::ERESTART {
+                        LinuxError::EINTR
+                    } else {
+                        err
+                    }
+                })?;
             } else {
-                wq.wait();
+                interruptible_wait(&wq, None)?;
             }
 
             Ok(0)
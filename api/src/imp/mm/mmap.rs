@@ -7,6 +7,7 @@ use linux_raw_sys::general::{
     PROT_GROWSDOWN, PROT_GROWSUP, PROT_READ, PROT_WRITE,
 };
 use memory_addr::{VirtAddr, VirtAddrRange};
+use starry_core::mm::FileMapping;
 
 use crate::file::{File, FileLike};
 
@@ -76,7 +77,8 @@ pub fn sys_mmap(
 ) -> LinuxResult<isize> {
     let curr = current();
     let process_data = curr.task_ext().process_data();
-    let mut aspace = process_data.aspace.lock();
+    let aspace = process_data.aspace();
+    let mut aspace = aspace.lock();
     let permission_flags = MmapProt::from_bits_truncate(prot);
     // TODO: check illegal flags for mmap
     // An example is the flags contained none of MAP_PRIVATE, MAP_SHARED, or MAP_SHARED_VALIDATE.
@@ -101,6 +103,10 @@ pub fn sys_mmap(
         }
         let dst_addr = VirtAddr::from(start);
         aspace.unmap(dst_addr, aligned_length)?;
+        process_data
+            .file_mappings
+            .lock()
+            .remove(VirtAddrRange::from_start_size(dst_addr, aligned_length));
         dst_addr
     } else {
         aspace
@@ -132,6 +138,7 @@ pub fn sys_mmap(
 
     if populate {
         let file = File::from_fd(fd)?;
+        let path = file.path().into();
         let file = file.inner();
         let file_size = file.get_attr()?.size() as usize;
         if offset < 0 || offset as usize >= file_size {
@@ -142,6 +149,11 @@ pub fn sys_mmap(
         let mut buf = vec![0u8; length];
         file.read_at(offset as u64, &mut buf)?;
         aspace.write(start_addr, &buf)?;
+        process_data.file_mappings.lock().insert(FileMapping {
+            range: VirtAddrRange::from_start_size(start_addr, aligned_length),
+            offset,
+            path,
+        });
     }
     Ok(start_addr.as_usize() as _)
 }
@@ -149,10 +161,15 @@ pub fn sys_mmap(
 pub fn sys_munmap(addr: usize, length: usize) -> LinuxResult<isize> {
     let curr = current();
     let process_data = curr.task_ext().process_data();
-    let mut aspace = process_data.aspace.lock();
+    let aspace = process_data.aspace();
+    let mut aspace = aspace.lock();
     let length = memory_addr::align_up_4k(length);
     let start_addr = VirtAddr::from(addr);
     aspace.unmap(start_addr, length)?;
+    process_data
+        .file_mappings
+        .lock()
+        .remove(VirtAddrRange::from_start_size(start_addr, length));
     axhal::arch::flush_tlb(None);
     Ok(0)
 }
@@ -168,7 +185,8 @@ pub fn sys_mprotect(addr: usize, length: usize, prot: u32) -> LinuxResult<isize>
 
     let curr = current();
     let process_data = curr.task_ext().process_data();
-    let mut aspace = process_data.aspace.lock();
+    let aspace = process_data.aspace();
+    let mut aspace = aspace.lock();
     let length = memory_addr::align_up_4k(length);
     let start_addr = VirtAddr::from(addr);
     aspace.protect(start_addr, length, permission_flags.into())?;
@@ -1,3 +1,4 @@
+mod cred;
 mod fs;
 mod futex;
 mod mm;
@@ -6,4 +7,4 @@ mod sys;
 mod task;
 mod time;
 
-pub use self::{fs::*, futex::*, mm::*, signal::*, sys::*, task::*, time::*};
+pub use self::{cred::*, fs::*, futex::*, mm::*, signal::*, sys::*, task::*, time::*};
@@ -1,20 +1,21 @@
-use core::{mem, time::Duration};
+use core::{ffi::c_int, mem, time::Duration};
 
 use alloc::sync::Arc;
 use axerrno::{LinuxError, LinuxResult};
 use axhal::arch::TrapFrame;
-use axprocess::{Pid, Thread};
+use axprocess::{Pid, Process, Thread};
 use axsignal::{SignalInfo, SignalSet, SignalStack, Signo};
 use axtask::{TaskExtRef, current};
 use linux_raw_sys::general::{
     MINSIGSTKSZ, SI_TKILL, SI_USER, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK, kernel_sigaction, siginfo,
     timespec,
 };
-use starry_core::task::{get_process, get_process_group, get_thread, processes};
+use starry_core::task::{ProcessData, get_process, get_process_group, get_thread, processes};
 
 use crate::{
+    file::{FileLike, PidFd},
     ptr::{UserConstPtr, UserPtr, nullable},
-    signal::{check_signals, send_signal_process, send_signal_process_group, send_signal_thread},
+    signal::{check_signals, send_signal_process, send_signal_thread},
     time::TimeValueLike,
 };
 
@@ -74,7 +75,8 @@ pub fn sys_rt_sigaction(
     }
 
     let curr = current();
-    let mut actions = curr.task_ext().process_data().signal.actions.lock();
+    let actions = curr.task_ext().process_data().signal.actions();
+    let mut actions = actions.lock();
     if let Some(oldact) = nullable!(oldact.get_as_mut())? {
         actions[signo].to_ctype(oldact);
     }
@@ -98,60 +100,99 @@ fn make_siginfo(signo: u32, code: i32) -> LinuxResult<Option<SignalInfo>> {
     Ok(Some(SignalInfo::new(signo, code)))
 }
 
-pub fn sys_kill(pid: i32, signo: u32) -> LinuxResult<isize> {
-    let Some(sig) = make_siginfo(signo, SI_USER as _)? else {
-        // TODO: should also check permissions
-        return Ok(0);
+/// Checks whether the calling process may send `signo` to `proc`: its real
+/// or effective user ID must match the real or saved user ID of `proc`,
+/// unless it is privileged. `SIGCONT` may also be sent within a session.
+fn check_kill_permission(proc: &Process, signo: Option<Signo>) -> LinuxResult {
+    let Some(target) = proc.data::<ProcessData>() else {
+        return Err(LinuxError::EPERM);
     };
+    let curr = current();
+    if curr
+        .task_ext()
+        .process_data()
+        .cred()
+        .can_signal(&target.cred())
+    {
+        return Ok(());
+    }
+    let sender = curr.task_ext().thread.process();
+    if signo == Some(Signo::SIGCONT)
+        && Arc::ptr_eq(&sender.group().session(), &proc.group().session())
+    {
+        return Ok(());
+    }
+    Err(LinuxError::EPERM)
+}
+
+/// Sends a signal to each of `procs` the caller has permission to signal,
+/// or only checks the permissions if `sig` is `None`.
+///
+/// Fails with `EPERM` if there is none, or with `ESRCH` if `procs` is empty.
+fn kill_processes(
+    procs: impl IntoIterator<Item = Arc<Process>>,
+    sig: Option<SignalInfo>,
+) -> LinuxResult<isize> {
+    let signo = sig.as_ref().map(SignalInfo::signo);
+    let mut result = Err(LinuxError::ESRCH);
+    for proc in procs {
+        if let Err(err) = check_kill_permission(&proc, signo) {
+            result = result.or(Err(err));
+            continue;
+        }
+        if let Some(sig) = &sig {
+            send_signal_process(&proc, sig.clone())?;
+        }
+        result = Ok(0);
+    }
+    result
+}
+
+pub fn sys_kill(pid: i32, signo: u32) -> LinuxResult<isize> {
+    let sig = make_siginfo(signo, SI_USER as _)?;
 
     let curr = current();
     match pid {
-        1.. => {
-            let proc = get_process(pid as Pid)?;
-            send_signal_process(&proc, sig)?;
-            Ok(1)
-        }
+        1.. => kill_processes([get_process(pid as Pid)?], sig),
         0 => {
             let pg = curr.task_ext().thread.process().group();
-            Ok(send_signal_process_group(&pg, sig) as _)
+            kill_processes(pg.processes(), sig)
         }
         -1 => {
-            let mut count = 0;
-            for proc in processes() {
-                if proc.is_init() {
-                    // init process
-                    continue;
-                }
-                send_signal_process(&proc, sig.clone())?;
-                count += 1;
-            }
-            Ok(count)
+            let caller = curr.task_ext().thread.process().pid();
+            kill_processes(
+                processes()
+                    .into_iter()
+                    .filter(|proc| !proc.is_init() && proc.pid() != caller),
+                sig,
+            )
         }
         ..-1 => {
             let pg = get_process_group((-pid) as Pid)?;
-            Ok(send_signal_process_group(&pg, sig) as _)
+            kill_processes(pg.processes(), sig)
         }
     }
 }
 
 pub fn sys_tkill(tid: Pid, signo: u32) -> LinuxResult<isize> {
-    let Some(sig) = make_siginfo(signo, SI_TKILL)? else {
-        // TODO: should also check permissions
-        return Ok(0);
-    };
+    let sig = make_siginfo(signo, SI_TKILL)?;
 
     let thr = get_thread(tid)?;
-    send_signal_thread(&thr, sig)?;
+    check_kill_permission(thr.process(), sig.as_ref().map(SignalInfo::signo))?;
+    if let Some(sig) = sig {
+        send_signal_thread(&thr, sig)?;
+    }
     Ok(0)
 }
 
 pub fn sys_tgkill(tgid: Pid, tid: Pid, signo: u32) -> LinuxResult<isize> {
-    let Some(sig) = make_siginfo(signo, SI_TKILL)? else {
-        // TODO: should also check permissions
-        return Ok(0);
-    };
+    let sig = make_siginfo(signo, SI_TKILL)?;
 
-    send_signal_thread(find_thread_in_group(tgid, tid)?.as_ref(), sig)?;
+    let thr = find_thread_in_group(tgid, tid)?;
+    check_kill_permission(thr.process(), sig.as_ref().map(SignalInfo::signo))?;
+    if let Some(sig) = sig {
+        send_signal_thread(&thr, sig)?;
+    }
     Ok(0)
 }
 
@@ -188,7 +229,9 @@ pub fn sys_rt_sigqueueinfo(
     check_sigset_size(sigsetsize)?;
 
     let sig = make_queue_signal_info(tgid, signo, sig)?;
-    send_signal_process(get_process(tgid)?.as_ref(), sig)?;
+    let proc = get_process(tgid)?;
+    check_kill_permission(&proc, Some(sig.signo()))?;
+    send_signal_process(&proc, sig)?;
     Ok(0)
 }
 
@@ -202,10 +245,38 @@ pub fn sys_rt_tgsigqueueinfo(
     check_sigset_size(sigsetsize)?;
 
     let sig = make_queue_signal_info(tgid, signo, sig)?;
-    send_signal_thread(find_thread_in_group(tgid, tid)?.as_ref(), sig)?;
+    let thr = find_thread_in_group(tgid, tid)?;
+    check_kill_permission(thr.process(), Some(sig.signo()))?;
+    send_signal_thread(&thr, sig)?;
     Ok(0)
 }
 
+/// Sends the signal `signo` to the process referred to by `pidfd`, with the
+/// information `sig` if it is not null.
+pub fn sys_pidfd_send_signal(
+    pidfd: c_int,
+    signo: u32,
+    sig: UserConstPtr<SignalInfo>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    if flags != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let proc = PidFd::from_fd(pidfd)
+        .map_err(|_| LinuxError::EBADF)?
+        .process()
+        .clone();
+    if proc.is_zombie() {
+        return Err(LinuxError::ESRCH);
+    }
+    let sig = if sig.is_null() {
+        make_siginfo(signo, SI_USER as _)?
+    } else {
+        Some(make_queue_signal_info(proc.pid(), signo, sig)?)
+    };
+    kill_processes([proc], sig)
+}
+
 pub fn sys_rt_sigreturn(tf: &mut TrapFrame) -> LinuxResult<isize> {
     let curr = current();
     curr.task_ext().thread_data().signal.restore(tf);
@@ -1,25 +1,15 @@
 use core::ffi::c_char;
 
-use axerrno::LinuxResult;
-use linux_raw_sys::system::new_utsname;
-
-use crate::ptr::UserPtr;
-
-pub fn sys_getuid() -> LinuxResult<isize> {
-    Ok(0)
-}
-
-pub fn sys_geteuid() -> LinuxResult<isize> {
-    Ok(1)
-}
-
-pub fn sys_getgid() -> LinuxResult<isize> {
-    Ok(0)
-}
+use axerrno::{LinuxError, LinuxResult};
+use axprocess::Pid;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    general::{CAP_SYS_RESOURCE, RLIM_NLIMITS, rlimit64},
+    system::new_utsname,
+};
+use starry_core::task::{ProcessData, get_process};
 
-pub fn sys_getegid() -> LinuxResult<isize> {
-    Ok(1)
-}
+use crate::ptr::{UserConstPtr, UserPtr, nullable};
 
 const fn pad_str(info: &str) -> [c_char; 65] {
     let mut data: [c_char; 65] = [0; 65];
@@ -44,3 +34,63 @@ pub fn sys_uname(name: UserPtr<new_utsname>) -> LinuxResult<isize> {
     *name.get_as_mut()? = UTSNAME;
     Ok(0)
 }
+
+pub fn sys_getcpu(cpu: UserPtr<u32>, node: UserPtr<u32>) -> LinuxResult<isize> {
+    if let Some(cpu) = nullable!(cpu.get_as_mut())? {
+        *cpu = axhal::cpu::this_cpu_id() as _;
+    }
+    if let Some(node) = nullable!(node.get_as_mut())? {
+        *node = 0;
+    }
+    Ok(0)
+}
+
+/// Gets and sets the limits of `resource` of the process `pid`, or of the
+/// calling process if `pid` is 0, see `prlimit(2)`.
+///
+/// The limits of another process are only accessible with the same real,
+/// effective and saved user and group IDs or `CAP_SYS_RESOURCE`, and hard
+/// limits may only be raised with `CAP_SYS_RESOURCE`.
+pub fn sys_prlimit64(
+    pid: Pid,
+    resource: u32,
+    new_limit: UserConstPtr<rlimit64>,
+    old_limit: UserPtr<rlimit64>,
+) -> LinuxResult<isize> {
+    if resource >= RLIM_NLIMITS {
+        return Err(LinuxError::EINVAL);
+    }
+    let new_limit = nullable!(new_limit.get_as_ref())?.copied();
+    if new_limit.is_some_and(|limit| limit.rlim_cur > limit.rlim_max) {
+        return Err(LinuxError::EINVAL);
+    }
+    let old_limit = nullable!(old_limit.get_as_mut())?;
+
+    let curr = current();
+    let cred = curr.task_ext().process_data().cred();
+    let process = if pid == 0 {
+        curr.task_ext().thread.process().clone()
+    } else {
+        get_process(pid)?
+    };
+    let data = process.data::<ProcessData>().ok_or(LinuxError::ESRCH)?;
+    if process.pid() != curr.task_ext().thread.process().pid()
+        && !cred.matches_ids_of(&data.cred())
+        && !cred.has_cap(CAP_SYS_RESOURCE)
+    {
+        return Err(LinuxError::EPERM);
+    }
+
+    let mut rlimits = data.rlimits.write();
+    let old = rlimits.get(resource).ok_or(LinuxError::EINVAL)?;
+    if let Some(limit) = new_limit {
+        if limit.rlim_max > old.rlim_max && !cred.has_cap(CAP_SYS_RESOURCE) {
+            return Err(LinuxError::EPERM);
+        }
+        rlimits.set(resource, limit);
+    }
+    if let Some(old_limit) = old_limit {
+        *old_limit = old;
+    }
+    Ok(0)
+}
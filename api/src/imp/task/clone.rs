@@ -1,108 +1,170 @@
 use alloc::sync::Arc;
+use core::{ffi::c_int, slice, sync::atomic::Ordering};
+
 use axerrno::{LinuxError, LinuxResult};
 use axfs::{CURRENT_DIR, CURRENT_DIR_PATH};
 use axhal::arch::{TrapFrame, UspaceContext};
 use axprocess::Pid;
-use axsignal::Signo;
+use axsignal::{SignalDisposition, Signo, api::SignalActions};
 use axsync::Mutex;
 use axtask::{TaskExtRef, current};
 use bitflags::bitflags;
-use linux_raw_sys::general::*;
+use linux_raw_sys::{
+    general::*,
+    ptrace::{PTRACE_EVENT_CLONE, PTRACE_EVENT_FORK, PTRACE_EVENT_VFORK},
+};
+use memory_addr::PAGE_SIZE_4K;
 use starry_core::{
     mm::copy_from_kernel,
-    task::{ProcessData, TaskExt, ThreadData, add_thread_to_table, new_user_task},
+    task::{
+        PID_MAX, ProcessData, TaskExt, ThreadData, add_thread_to_table, new_user_task, reserve_id,
+    },
 };
 
-use crate::{file::FD_TABLE, ptr::UserPtr};
+use crate::{
+    file::{FD_TABLE, FileLike, PidFd},
+    ptr::{UserConstPtr, UserPtr},
+    ptrace::ptrace_clone,
+};
 
 bitflags! {
-    /// Options for use with [`sys_clone`].
+    /// Options for use with [`sys_clone`] and [`sys_clone3`].
     #[derive(Debug, Clone, Copy, Default)]
-    struct CloneFlags: u32 {
+    struct CloneFlags: u64 {
         /// The calling process and the child process run in the same
         /// memory space.
-        const VM = CLONE_VM;
+        const VM = CLONE_VM as u64;
         /// The caller and the child process share the same  filesystem
         /// information.
-        const FS = CLONE_FS;
+        const FS = CLONE_FS as u64;
         /// The calling process and the child process share the same file
         /// descriptor table.
-        const FILES = CLONE_FILES;
+        const FILES = CLONE_FILES as u64;
         /// The calling process and the child process share the same table
         /// of signal handlers.
-        const SIGHAND = CLONE_SIGHAND;
+        const SIGHAND = CLONE_SIGHAND as u64;
         /// If the calling process is being traced, then trace the child
         /// also.
-        const PTRACE = CLONE_PTRACE;
+        const PTRACE = CLONE_PTRACE as u64;
         /// The execution of the calling process is suspended until the
         /// child releases its virtual memory resources via a call to
         /// execve(2) or _exit(2) (as with vfork(2)).
-        const VFORK = CLONE_VFORK;
+        const VFORK = CLONE_VFORK as u64;
         /// The parent of the new child  (as returned by getppid(2))
         /// will be the same as that of the calling process.
-        const PARENT = CLONE_PARENT;
+        const PARENT = CLONE_PARENT as u64;
         /// The child is placed in the same thread group as the calling
         /// process.
-        const THREAD = CLONE_THREAD;
+        const THREAD = CLONE_THREAD as u64;
         /// The cloned child is started in a new mount namespace.
-        const NEWNS = CLONE_NEWNS;
+        const NEWNS = CLONE_NEWNS as u64;
         /// The child and the calling process share a single list of System
         /// V semaphore adjustment values
-        const SYSVSEM = CLONE_SYSVSEM;
+        const SYSVSEM = CLONE_SYSVSEM as u64;
         /// The TLS (Thread Local Storage) descriptor is set to tls.
-        const SETTLS = CLONE_SETTLS;
+        const SETTLS = CLONE_SETTLS as u64;
         /// Store the child thread ID in the parent's memory.
-        const PARENT_SETTID = CLONE_PARENT_SETTID;
+        const PARENT_SETTID = CLONE_PARENT_SETTID as u64;
         /// Clear (zero) the child thread ID in child memory when the child
         /// exits, and do a wakeup on the futex at that address.
-        const CHILD_CLEARTID = CLONE_CHILD_CLEARTID;
+        const CHILD_CLEARTID = CLONE_CHILD_CLEARTID as u64;
         /// A tracing process cannot force `CLONE_PTRACE` on this child
         /// process.
-        const UNTRACED = CLONE_UNTRACED;
+        const UNTRACED = CLONE_UNTRACED as u64;
         /// Store the child thread ID in the child's memory.
-        const CHILD_SETTID = CLONE_CHILD_SETTID;
+        const CHILD_SETTID = CLONE_CHILD_SETTID as u64;
         /// Create the process in a new cgroup namespace.
-        const NEWCGROUP = CLONE_NEWCGROUP;
+        const NEWCGROUP = CLONE_NEWCGROUP as u64;
         /// Create the process in a new UTS namespace.
-        const NEWUTS = CLONE_NEWUTS;
+        const NEWUTS = CLONE_NEWUTS as u64;
         /// Create the process in a new IPC namespace.
-        const NEWIPC = CLONE_NEWIPC;
+        const NEWIPC = CLONE_NEWIPC as u64;
         /// Create the process in a new user namespace.
-        const NEWUSER = CLONE_NEWUSER;
+        const NEWUSER = CLONE_NEWUSER as u64;
         /// Create the process in a new PID namespace.
-        const NEWPID = CLONE_NEWPID;
+        const NEWPID = CLONE_NEWPID as u64;
         /// Create the process in a new network namespace.
-        const NEWNET = CLONE_NEWNET;
+        const NEWNET = CLONE_NEWNET as u64;
         /// The new process shares an I/O context with the calling process.
-        const IO = CLONE_IO;
+        const IO = CLONE_IO as u64;
+        /// Store a PID file descriptor referring to the child in the
+        /// parent's memory.
+        const PIDFD = CLONE_PIDFD as u64;
+        /// Reset all signal handlers of the child to the default action,
+        /// except for ignored signals (`clone3` only).
+        const CLEAR_SIGHAND = CLONE_CLEAR_SIGHAND;
+        /// Place the child in the cgroup given by a file descriptor
+        /// (`clone3` only).
+        const INTO_CGROUP = CLONE_INTO_CGROUP;
     }
 }
 
-pub fn sys_clone(
-    tf: &TrapFrame,
-    flags: u32,
+/// The arguments of [`sys_clone`] and [`sys_clone3`], see `struct clone_args`.
+#[derive(Debug)]
+struct CloneArgs {
+    flags: CloneFlags,
+    /// The signal sent to the parent when the child exits
+    exit_signal: u64,
+    /// The stack pointer of the child, or 0 to keep the one of the parent
     stack: usize,
-    parent_tid: usize,
-    #[cfg(any(target_arch = "x86_64", target_arch = "loongarch64"))] child_tid: usize,
     tls: usize,
-    #[cfg(not(any(target_arch = "x86_64", target_arch = "loongarch64")))] child_tid: usize,
-) -> LinuxResult<isize> {
-    const FLAG_MASK: u32 = 0xff;
-    let exit_signal = flags & FLAG_MASK;
-    let flags = CloneFlags::from_bits_truncate(flags & !FLAG_MASK);
+    parent_tid: usize,
+    child_tid: usize,
+    /// Where the PID file descriptor of the child is stored with
+    /// `CLONE_PIDFD`
+    pidfd: usize,
+    /// The thread ID chosen for the child, instead of a new one
+    set_tid: Option<Pid>,
+}
 
-    info!(
-        "sys_clone <= flags: {:?}, exit_signal: {}, stack: {:#x}, ptid: {:#x}, ctid: {:#x}, tls: {:#x}",
-        flags, exit_signal, stack, parent_tid, child_tid, tls
-    );
+/// Creates the child described by `args`, returning its thread ID.
+fn do_clone(tf: &TrapFrame, args: CloneArgs) -> LinuxResult<isize> {
+    info!("clone <= {:x?}", args);
+    let CloneArgs {
+        flags,
+        exit_signal,
+        stack,
+        tls,
+        parent_tid,
+        child_tid,
+        pidfd,
+        set_tid,
+    } = args;
 
-    if exit_signal != 0 && flags.contains(CloneFlags::THREAD | CloneFlags::PARENT) {
+    if exit_signal != 0 && flags.intersects(CloneFlags::THREAD | CloneFlags::PARENT) {
         return Err(LinuxError::EINVAL);
     }
     if flags.contains(CloneFlags::THREAD) && !flags.contains(CloneFlags::VM | CloneFlags::SIGHAND) {
         return Err(LinuxError::EINVAL);
     }
-    let exit_signal = Signo::from_repr(exit_signal as u8);
+    if flags.contains(CloneFlags::SIGHAND | CloneFlags::CLEAR_SIGHAND) {
+        return Err(LinuxError::EINVAL);
+    }
+    if flags.contains(CloneFlags::PIDFD | CloneFlags::THREAD) {
+        return Err(LinuxError::EINVAL);
+    }
+    // There are no cgroups to place the child in.
+    if flags.contains(CloneFlags::INTO_CGROUP) {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    // A sibling notifies the parent like the calling process does.
+    let exit_signal = if flags.contains(CloneFlags::PARENT) {
+        curr.task_ext().process_data().exit_signal
+    } else {
+        Signo::from_repr(exit_signal as u8)
+    };
+    // Held until the child is in the thread table.
+    let _reserved_id = match set_tid {
+        Some(tid) => {
+            let cred = curr.task_ext().process_data().cred();
+            if !cred.has_cap(CAP_SYS_ADMIN) && !cred.has_cap(CAP_CHECKPOINT_RESTORE) {
+                return Err(LinuxError::EPERM);
+            }
+            Some(reserve_id(tid)?)
+        }
+        None => None,
+    };
 
     let mut new_uctx = UspaceContext::from(tf);
     if stack != 0 {
@@ -118,11 +180,15 @@ pub fn sys_clone(
     } else {
         None
     };
+    let pidfd = if flags.contains(CloneFlags::PIDFD) {
+        Some(UserPtr::<c_int>::from(pidfd).get_as_mut()?)
+    } else {
+        None
+    };
 
-    let curr = current();
     let mut new_task = new_user_task(curr.name(), new_uctx, set_child_tid);
 
-    let tid = new_task.id().as_u64() as Pid;
+    let tid = set_tid.unwrap_or_else(|| new_task.id().as_u64() as Pid);
     if flags.contains(CloneFlags::PARENT_SETTID) {
         *UserPtr::<Pid>::from(parent_tid).get_as_mut()? = tid;
     }
@@ -131,7 +197,7 @@ pub fn sys_clone(
         new_task.ctx_mut().set_page_table_root(
             curr.task_ext()
                 .process_data()
-                .aspace
+                .aspace()
                 .lock()
                 .page_table_root(),
         );
@@ -150,9 +216,10 @@ pub fn sys_clone(
         let builder = parent.fork(tid);
 
         let aspace = if flags.contains(CloneFlags::VM) {
-            curr.task_ext().process_data().aspace.clone()
+            curr.task_ext().process_data().aspace()
         } else {
-            let mut aspace = curr.task_ext().process_data().aspace.lock();
+            let aspace = curr.task_ext().process_data().aspace();
+            let mut aspace = aspace.lock();
             let mut aspace = aspace.clone_or_err()?;
             copy_from_kernel(&mut aspace)?;
             Arc::new(Mutex::new(aspace))
@@ -162,18 +229,32 @@ pub fn sys_clone(
             .set_page_table_root(aspace.lock().page_table_root());
 
         let signal_actions = if flags.contains(CloneFlags::SIGHAND) {
-            parent
-                .data::<ProcessData>()
-                .map_or_else(Arc::default, |it| it.signal.actions.clone())
+            curr.task_ext().process_data().signal.actions()
         } else {
-            Arc::default()
+            let actions = curr.task_ext().process_data().signal.actions();
+            let actions = actions.lock();
+            let mut new_actions = SignalActions::default();
+            for signo in (1..=64).filter_map(Signo::from_repr) {
+                let ignored = matches!(actions[signo].disposition, SignalDisposition::Ignore);
+                if !flags.contains(CloneFlags::CLEAR_SIGHAND) || ignored {
+                    new_actions[signo] = actions[signo].clone();
+                }
+            }
+            Arc::new(Mutex::new(new_actions))
         };
         let process_data = ProcessData::new(
             curr.task_ext().process_data().exe_path.read().clone(),
             aspace,
             signal_actions,
             exit_signal,
+            curr.task_ext().process_data().cred(),
         );
+        process_data.replace_umask(curr.task_ext().process_data().umask());
+        process_data.set_dumpable(curr.task_ext().process_data().is_dumpable());
+        *process_data.auxv.lock() = curr.task_ext().process_data().auxv.lock().clone();
+        *process_data.rlimits.write() = curr.task_ext().process_data().rlimits.read().clone();
+        *process_data.file_mappings.lock() =
+            curr.task_ext().process_data().file_mappings.lock().clone();
 
         if flags.contains(CloneFlags::FILES) {
             FD_TABLE
@@ -207,15 +288,145 @@ pub fn sys_clone(
     if flags.contains(CloneFlags::CHILD_CLEARTID) {
         thread_data.set_clear_child_tid(child_tid);
     }
+    let curr_data = curr.task_ext().thread_data();
+    *thread_data.seccomp.lock() = curr_data.seccomp.lock().clone();
+    thread_data.no_new_privs.store(
+        curr_data.no_new_privs.load(Ordering::Relaxed),
+        Ordering::Relaxed,
+    );
+
+    if let Some(pidfd) = pidfd {
+        *pidfd = PidFd::new(process.clone(), false).add_to_fd_table()?;
+    }
+
+    // The parent of `vfork` waits for the child to stop using its memory.
+    let vfork_child = (flags.contains(CloneFlags::VFORK) && !flags.contains(CloneFlags::THREAD))
+        .then(|| process.clone());
 
     let thread = process.new_thread(tid).data(thread_data).build();
     add_thread_to_table(&thread);
+    if !flags.contains(CloneFlags::UNTRACED) {
+        let event = if flags.contains(CloneFlags::VFORK) {
+            PTRACE_EVENT_VFORK
+        } else if exit_signal != Some(Signo::SIGCHLD) {
+            PTRACE_EVENT_CLONE
+        } else {
+            PTRACE_EVENT_FORK
+        };
+        ptrace_clone(&thread, event, flags.contains(CloneFlags::PTRACE));
+    }
     new_task.init_task_ext(TaskExt::new(thread));
-    axtask::spawn_task(new_task);
+    let new_task = axtask::spawn_task(new_task);
+    new_task.task_ext().thread_data().set_task(&new_task);
+
+    if let Some(data) = vfork_child.as_ref().and_then(|it| it.data::<ProcessData>()) {
+        data.vfork_done.wait();
+    }
 
     Ok(tid as _)
 }
 
+pub fn sys_clone(
+    tf: &TrapFrame,
+    flags: u32,
+    stack: usize,
+    parent_tid: usize,
+    #[cfg(any(target_arch = "x86_64", target_arch = "loongarch64"))] child_tid: usize,
+    tls: usize,
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "loongarch64")))] child_tid: usize,
+) -> LinuxResult<isize> {
+    // The PID file descriptor is stored in place of the parent thread ID.
+    if flags & CLONE_PIDFD != 0 && flags & (CLONE_PARENT_SETTID | CLONE_DETACHED) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let args = CloneArgs {
+        // Flags only known to `clone3` do not fit.
+        flags: CloneFlags::from_bits_truncate((flags & !CSIGNAL) as u64),
+        // The exit signal is ignored for threads and siblings, which
+        // `clone3` rejects.
+        exit_signal: if flags & (CLONE_THREAD | CLONE_PARENT) != 0 {
+            0
+        } else {
+            (flags & CSIGNAL) as u64
+        },
+        stack,
+        tls,
+        parent_tid,
+        child_tid,
+        pidfd: parent_tid,
+        set_tid: None,
+    };
+    do_clone(tf, args)
+}
+
+pub fn sys_clone3(tf: &TrapFrame, args: usize, size: usize) -> LinuxResult<isize> {
+    /// The number of nested PID namespaces, which is the maximum length of
+    /// the `set_tid` array.
+    const PID_NS_LEVELS: u64 = 1;
+
+    if size < CLONE_ARGS_SIZE_VER0 as usize {
+        return Err(LinuxError::EINVAL);
+    }
+    if size > PAGE_SIZE_4K {
+        return Err(LinuxError::E2BIG);
+    }
+    // The struct grows by new fields at its end, which must be unset if this
+    // kernel does not know them, and are unset if the caller does not.
+    let bytes = UserConstPtr::<u8>::from(args).get_as_slice(size)?;
+    let (bytes, unknown) = bytes.split_at(size.min(size_of::<clone_args>()));
+    if unknown.iter().any(|&it| it != 0) {
+        return Err(LinuxError::E2BIG);
+    }
+    // SAFETY: `clone_args` is plain integers, which may be set to any bytes.
+    let mut args: clone_args = unsafe { core::mem::zeroed() };
+    unsafe { slice::from_raw_parts_mut((&mut args as *mut clone_args).cast::<u8>(), bytes.len()) }
+        .copy_from_slice(bytes);
+
+    // The exit signal has its own field.
+    let known_flags = !(CSIGNAL | CLONE_DETACHED) as u64 | CLONE_CLEAR_SIGHAND | CLONE_INTO_CGROUP;
+    if args.flags & !known_flags != 0
+        || args.exit_signal > 64
+        || (args.exit_signal != 0 && args.flags & (CLONE_THREAD | CLONE_PARENT) as u64 != 0)
+        || (args.stack == 0) != (args.stack_size == 0)
+        || (args.set_tid == 0) != (args.set_tid_size == 0)
+        || args.set_tid_size > PID_NS_LEVELS
+    {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let set_tid = if args.set_tid_size == 0 {
+        None
+    } else {
+        let tid = UserConstPtr::<i32>::from(args.set_tid as usize).get_as_ref()?;
+        if *tid < 1 || *tid as Pid >= PID_MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        Some(*tid as Pid)
+    };
+    // The stack grows down from the end of the given area.
+    let stack = if args.stack == 0 {
+        0
+    } else {
+        (args.stack + args.stack_size) as usize
+    };
+
+    let args = CloneArgs {
+        flags: CloneFlags::from_bits_truncate(args.flags),
+        exit_signal: args.exit_signal,
+        stack,
+        tls: args.tls as usize,
+        parent_tid: args.parent_tid as usize,
+        child_tid: args.child_tid as usize,
+        pidfd: args.pidfd as usize,
+        set_tid,
+    };
+    do_clone(tf, args)
+}
+
 pub fn sys_fork(tf: &TrapFrame) -> LinuxResult<isize> {
     sys_clone(tf, SIGCHLD, 0, 0, 0, 0)
 }
+
+pub fn sys_vfork(tf: &TrapFrame) -> LinuxResult<isize> {
+    sys_clone(tf, CLONE_VM | CLONE_VFORK | SIGCHLD, 0, 0, 0, 0)
+}
@@ -1,12 +1,135 @@
-use core::ffi::c_char;
+use core::{ffi::c_char, sync::atomic::Ordering};
 
-use alloc::{string::ToString, vec::Vec};
-use axerrno::{LinuxError, LinuxResult};
+use alloc::{string::ToString, sync::Arc, vec::Vec};
+use axerrno::{AxError, LinuxError, LinuxResult};
 use axhal::arch::TrapFrame;
+use axsignal::{SignalAction, SignalDisposition, SignalInfo, SignalStack, Signo};
+use axsync::Mutex;
 use axtask::{TaskExtRef, current};
-use starry_core::mm::{load_user_app, map_trampoline};
+use linux_raw_sys::general::{AT_FDCWD, S_ISGID, S_ISUID, S_IXGRP, SI_KERNEL, X_OK};
+use starry_core::{
+    cred::Credentials,
+    mm::{
+        copy_from_kernel, load_user_app, map_trampoline, new_user_aspace_empty, prepare_user_app,
+        switch_user_aspace,
+    },
+    signal::killable_wait_until,
+    task::make_thread_leader,
+    time::clear_posix_timers,
+    vdso::map_vdso,
+};
 
-use crate::ptr::UserConstPtr;
+use crate::{
+    do_exit,
+    path::handle_file_path,
+    perm::{FileMeta, file_meta},
+    ptr::UserConstPtr,
+    ptrace::ptrace_exec,
+    signal::send_signal_thread,
+};
+
+/// The credentials of a process after it executes a file with the mode and
+/// ownership `meta`: the effective user and group IDs change to the owner of
+/// a set-user-ID or set-group-ID file, the saved IDs are set to the
+/// effective ones, and the capabilities are recomputed for the new IDs.
+///
+/// The set-user-ID and set-group-ID bits are ignored unless `set_ids` is
+/// true.
+fn exec_cred(meta: &FileMeta, cred: &Credentials, set_ids: bool) -> Credentials {
+    let mut cred = cred.clone();
+    if set_ids && meta.mode & S_ISUID != 0 {
+        cred.set_euid(meta.uid);
+    }
+    // Without group execute permission, set-group-ID marks mandatory locking
+    // instead.
+    if set_ids && meta.mode & S_ISGID != 0 && meta.mode & S_IXGRP != 0 {
+        cred.set_egid(meta.gid);
+    }
+    cred.suid = cred.euid;
+    cred.sgid = cred.egid;
+    cred.update_caps_for_exec();
+    cred
+}
+
+/// Kills the other threads of the current process and waits for them to
+/// exit, before the current thread replaces the program they run. It then
+/// takes over the thread ID of the thread group leader.
+///
+/// Fails with `EAGAIN` if the current thread is killed meanwhile, or another
+/// thread is executing a program or dumping the core.
+fn de_thread() -> LinuxResult {
+    let curr = current();
+    let thread = &curr.task_ext().thread;
+    let process = thread.process();
+    let process_data = curr.task_ext().process_data();
+
+    if process.threads().len() > 1 {
+        if !process_data.start_group_exec() {
+            return Err(LinuxError::EAGAIN);
+        }
+        let sig = SignalInfo::new(Signo::SIGKILL, SI_KERNEL as _);
+        let killed = loop {
+            let others = process
+                .threads()
+                .into_iter()
+                .filter(|it| !Arc::ptr_eq(it, thread))
+                .collect::<Vec<_>>();
+            if others.is_empty() {
+                break false;
+            }
+            // Threads cloned meanwhile are killed in the next round.
+            for other in &others {
+                let _ = send_signal_thread(other, sig.clone());
+            }
+            let count = others.len() + 1;
+            killable_wait_until(&process_data.thread_exit_wq, || {
+                process.threads().len() != count
+            });
+            if curr
+                .task_ext()
+                .thread_data()
+                .signal
+                .pending()
+                .has(Signo::SIGKILL)
+            {
+                break true;
+            }
+        };
+        process_data.end_group_exec();
+        if killed {
+            return Err(LinuxError::EAGAIN);
+        }
+    }
+
+    if thread.tid() != process.pid() {
+        make_thread_leader(thread);
+    }
+    Ok(())
+}
+
+/// Resets the handlers of signals to the default action, as the code they
+/// run is gone after `execve`, and disables the signal stack. Ignored signals
+/// stay ignored.
+fn reset_signal_handlers() {
+    let curr = current();
+    let signal = &curr.task_ext().process_data().signal;
+    // Processes cloned with `CLONE_SIGHAND` keep their handlers.
+    signal.unshare_actions();
+    let actions = signal.actions();
+    let mut actions = actions.lock();
+    for signo in (1..=64).filter_map(Signo::from_repr) {
+        let ignored = matches!(actions[signo].disposition, SignalDisposition::Ignore);
+        actions[signo] = SignalAction::default();
+        if ignored {
+            actions[signo].disposition = SignalDisposition::Ignore;
+        }
+    }
+    drop(actions);
+    curr.task_ext()
+        .thread_data()
+        .signal
+        .with_stack_mut(|stack| *stack = SignalStack::default());
+}
 
 pub fn sys_execve(
     tf: &mut TrapFrame,
@@ -34,34 +157,81 @@ pub fn sys_execve(
 
     let curr = current();
     let curr_ext = curr.task_ext();
-
-    if curr_ext.thread.process().threads().len() > 1 {
-        // TODO: handle multi-thread case
-        error!("sys_execve: multi-thread not supported");
-        return Err(LinuxError::EAGAIN);
+    let old_cred = curr_ext.process_data().cred();
+    let meta = file_meta(&handle_file_path(AT_FDCWD, &path)?)?;
+    if meta.dir {
+        return Err(LinuxError::EACCES);
     }
+    meta.check_access(&old_cred, X_OK)?;
+    // A traced process does not gain privileges, which its tracer would get
+    // hold of, nor does one that asked not to.
+    let traced = curr_ext.thread_data().ptrace.tracer().is_some();
+    let no_new_privs = curr_ext.thread_data().no_new_privs.load(Ordering::Relaxed);
+    let cred = exec_cred(&meta, &old_cred, !traced && !no_new_privs);
+    // Nothing of the current program is gone yet if the file cannot be
+    // executed.
+    let app = prepare_user_app(&path, &args).map_err(|err| match err {
+        AxError::InvalidData => LinuxError::ENOEXEC,
+        _ => err.into(),
+    })?;
 
-    let mut aspace = curr_ext.process_data().aspace.lock();
+    let old_tid = curr_ext.thread.tid();
+    de_thread()?;
+
+    let process_data = curr_ext.process_data();
+    let aspace = if process_data.is_aspace_shared() {
+        // The other processes using the address space, such as the parent of
+        // `vfork`, keep it.
+        let mut aspace = new_user_aspace_empty()?;
+        copy_from_kernel(&mut aspace)?;
+        switch_user_aspace(&aspace);
+        let aspace = Arc::new(Mutex::new(aspace));
+        process_data.set_aspace(aspace.clone());
+        aspace
+    } else {
+        process_data.aspace()
+    };
+    let mut aspace = aspace.lock();
     aspace.unmap_user_areas()?;
     map_trampoline(&mut aspace)?;
+    map_vdso(&mut aspace)?;
     axhal::arch::flush_tlb(None);
 
-    let (entry_point, user_stack_base) =
-        load_user_app(&mut aspace, &args, &envs).map_err(|_| {
-            error!("Failed to load app {}", path);
-            LinuxError::ENOENT
-        })?;
+    // Past the point of no return, the process has no program to go back to.
+    let app = match load_user_app(&mut aspace, &app, &envs) {
+        Ok(app) => app,
+        Err(err) => {
+            error!("Failed to load app {}: {:?}", path, err);
+            drop(aspace);
+            do_exit(Signo::SIGSEGV as i32, true);
+        }
+    };
     drop(aspace);
 
     let name = path
         .rsplit_once('/')
         .map_or(path.as_str(), |(_, name)| name);
     curr.set_name(name);
-    *curr_ext.process_data().exe_path.write() = path;
+    *process_data.exe_path.write() = path;
+    if cred.ids_changed_from(&old_cred) {
+        curr_ext.thread_data().pdeathsig.store(0, Ordering::Relaxed);
+    }
+    // A program running with the privileges of another user or group must
+    // not leak them through a core dump or to a tracer.
+    let dumpable = cred.euid == cred.uid && cred.egid == cred.gid;
+    process_data.set_cred(cred);
+    process_data.set_dumpable(dumpable);
+    process_data.set_execved();
+    *process_data.auxv.lock() = app.auxv;
+    *process_data.file_mappings.lock() = app.file_mappings;
+    reset_signal_handlers();
+    clear_posix_timers(curr_ext.thread.process());
+    process_data.vfork_done.complete();
+    ptrace_exec(old_tid);
 
     // TODO: fd close-on-exec
 
-    tf.set_ip(entry_point.as_usize());
-    tf.set_sp(user_stack_base.as_usize());
+    tf.set_ip(app.entry.as_usize());
+    tf.set_sp(app.ustack_top.as_usize());
     Ok(0)
 }
@@ -1,15 +1,58 @@
-use axprocess::Pid;
-use axsignal::{SignalInfo, Signo};
+use core::sync::atomic::Ordering;
+
+use axprocess::{Pid, Process};
+use axsignal::{SignalActionFlags, SignalDisposition, SignalInfo, Signo};
 use axtask::{TaskExtRef, current};
-use linux_raw_sys::general::SI_KERNEL;
-use starry_core::task::ProcessData;
+use linux_raw_sys::general::{SI_KERNEL, SI_USER};
+use starry_core::task::{ProcessData, ThreadData};
 
 use crate::{
     file::FD_TABLE,
     ptr::UserPtr,
-    signal::{send_signal_process, send_signal_thread},
+    ptrace::ptrace_exit,
+    signal::{decode_wait_status, send_signal_process, send_signal_thread, sigchld_info},
 };
 
+/// Wakes up `parent` waiting for its zombie child `child`, or reaps the child
+/// right away if the parent does not wait for it.
+///
+/// The init process of the kernel has no program waiting for its children. A
+/// parent ignoring `SIGCHLD` or setting `SA_NOCLDWAIT` does not wait for the
+/// children that exit with `SIGCHLD` either.
+fn notify_parent(child: &Process, parent: &Process) {
+    let Some(data) = parent.data::<ProcessData>() else {
+        // The init process
+        child.free();
+        return;
+    };
+    let action = data.signal.actions().lock()[Signo::SIGCHLD].clone();
+    let exit_signal = child.data::<ProcessData>().and_then(|it| it.exit_signal);
+    if exit_signal == Some(Signo::SIGCHLD)
+        && (matches!(action.disposition, SignalDisposition::Ignore)
+            || action.flags.contains(SignalActionFlags::NOCLDWAIT))
+    {
+        child.free();
+    }
+    data.child_exit_wq.notify_all(false);
+}
+
+/// Sends the parent-death signals the threads of `orphan` asked for with
+/// `PR_SET_PDEATHSIG`, now that its parent has exited.
+///
+/// The parent is the whole process rather than the thread that created the
+/// orphan, as the children belong to the process.
+fn send_pdeathsig(orphan: &Process) {
+    for thr in orphan.threads() {
+        let Some(data) = thr.data::<ThreadData>() else {
+            continue;
+        };
+        let signo = data.pdeathsig.load(Ordering::Relaxed) as u8;
+        if let Some(signo) = Signo::from_repr(signo) {
+            let _ = send_signal_process(orphan, SignalInfo::new(signo, SI_USER as _));
+        }
+    }
+}
+
 pub fn do_exit(exit_code: i32, group_exit: bool) -> ! {
     let curr = current();
     let curr_ext = curr.task_ext();
@@ -32,18 +75,37 @@ pub fn do_exit(exit_code: i32, group_exit: bool) -> ! {
     }
 
     let process = thread.process();
-    if thread.exit(exit_code) {
+    let last = thread.exit(exit_code);
+    curr_ext.process_data().thread_exit_wq.notify_all(false);
+    curr_ext.process_data().vfork_done.complete();
+    ptrace_exit(thread, exit_code, last);
+    if last {
+        let orphans = process.children();
         process.exit();
         if let Some(parent) = process.parent() {
-            if let Some(signo) = process.data::<ProcessData>().and_then(|it| it.exit_signal) {
-                let _ = send_signal_process(&parent, SignalInfo::new(signo, SI_KERNEL as _));
+            let exit_signal = process.data::<ProcessData>().and_then(|it| it.exit_signal);
+            if let Some(signo) = exit_signal {
+                let sig = if signo == Signo::SIGCHLD {
+                    let (code, status) = decode_wait_status(process.exit_code());
+                    sigchld_info(process, process.pid(), code, status)
+                } else {
+                    SignalInfo::new(signo, SI_KERNEL as _)
+                };
+                let _ = send_signal_process(&parent, sig);
             }
-            if let Some(data) = parent.data::<ProcessData>() {
-                data.child_exit_wq.notify_all(false)
+            notify_parent(process, &parent);
+        }
+        for orphan in &orphans {
+            if orphan.is_zombie() {
+                // Now waited for by its new parent
+                if let Some(reaper) = orphan.parent() {
+                    notify_parent(orphan, &reaper);
+                }
+            } else {
+                send_pdeathsig(orphan);
             }
         }
 
-        process.exit();
         // TODO: clear namespace resources
         // FIXME: axns should drop all the resources
         FD_TABLE.clear();
@@ -1,13 +1,21 @@
 mod clone;
 mod execve;
 mod exit;
+mod pidfd;
+mod ptrace;
 mod schedule;
+mod seccomp;
+mod session;
 mod thread;
 mod wait;
 
 pub use self::clone::*;
 pub use self::execve::*;
 pub use self::exit::*;
+pub use self::pidfd::*;
+pub use self::ptrace::*;
 pub use self::schedule::*;
+pub use self::seccomp::*;
+pub use self::session::*;
 pub use self::thread::*;
 pub use self::wait::*;
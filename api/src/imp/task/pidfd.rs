@@ -0,0 +1,50 @@
+use core::ffi::c_int;
+
+use axerrno::{LinuxError, LinuxResult};
+use axprocess::Pid;
+use linux_raw_sys::general::O_NONBLOCK;
+use starry_core::task::{ProcessData, get_process};
+
+use crate::{
+    file::{FD_TABLE, FileLike, PidFd, add_file_like},
+    ptrace::may_trace,
+};
+
+pub fn sys_pidfd_open(pid: i32, flags: u32) -> LinuxResult<isize> {
+    info!("sys_pidfd_open <= pid: {}, flags: {:#x}", pid, flags);
+    if flags & !O_NONBLOCK != 0 || pid <= 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let process = get_process(pid as Pid)?;
+    let pidfd = PidFd::new(process, flags & O_NONBLOCK != 0);
+    Ok(pidfd.add_to_fd_table()? as _)
+}
+
+/// Duplicates the file descriptor `targetfd` of the process referred to by
+/// `pidfd` into the calling process, which must be allowed to trace it.
+pub fn sys_pidfd_getfd(pidfd: c_int, targetfd: c_int, flags: u32) -> LinuxResult<isize> {
+    info!(
+        "sys_pidfd_getfd <= pidfd: {}, targetfd: {}, flags: {:#x}",
+        pidfd, targetfd, flags
+    );
+    if flags != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let pidfd = PidFd::from_fd(pidfd).map_err(|_| LinuxError::EBADF)?;
+    let process = pidfd.process();
+    let data = process.data::<ProcessData>().ok_or(LinuxError::ESRCH)?;
+    if !may_trace(data) {
+        return Err(LinuxError::EPERM);
+    }
+    // The file descriptors are closed once the process exits.
+    if process.is_zombie() {
+        return Err(LinuxError::ESRCH);
+    }
+    let file = FD_TABLE
+        .deref_from(&data.ns)
+        .read()
+        .get(targetfd as usize)
+        .cloned()
+        .ok_or(LinuxError::EBADF)?;
+    Ok(add_file_like(file)? as _)
+}
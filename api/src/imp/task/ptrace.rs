@@ -0,0 +1,427 @@
+use alloc::sync::Arc;
+use core::{mem::size_of, slice};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::arch::TrapFrame;
+use axprocess::{Pid, Thread};
+use axsignal::{SignalInfo, Signo};
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    elf_uapi::{NT_PRFPREG, NT_PRSTATUS},
+    general::{SI_USER, iovec, siginfo},
+    ptrace::*,
+};
+use memory_addr::{MemoryAddr, VirtAddr};
+use starry_core::{
+    ptrace::{ResumeMode, attach, detach},
+    signal::interrupt_thread,
+    task::{ProcessData, ThreadData, get_thread, user_trap_frame},
+};
+
+use crate::{
+    ptr::{UserConstPtr, UserPtr},
+    ptrace::{may_trace, may_trace_by},
+    regs::{UserFpRegs, UserRegs, fp_regs, set_fp_regs, set_user_regs, user_regs},
+    signal::send_signal_thread,
+};
+
+/// Starts tracing the thread `pid` by `PTRACE_ATTACH`, which stops it with
+/// `SIGSTOP`, or by `PTRACE_SEIZE` with the options `options`, which does not.
+fn ptrace_attach(pid: Pid, seize: bool, options: usize) -> LinuxResult<isize> {
+    let thread = get_thread(pid)?;
+    let curr = current();
+    let process = curr.task_ext().thread.process();
+    if Arc::ptr_eq(thread.process(), process) || thread.process().is_init() {
+        return Err(LinuxError::EPERM);
+    }
+    let options = if seize {
+        if options & !(PTRACE_O_MASK as usize) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        options as u32
+    } else {
+        0
+    };
+
+    let target = thread
+        .process()
+        .data::<ProcessData>()
+        .ok_or(LinuxError::EPERM)?;
+    if !may_trace(target) {
+        return Err(LinuxError::EPERM);
+    }
+    if !attach(process, &thread, seize, options) {
+        return Err(LinuxError::EPERM);
+    }
+    if !seize {
+        send_signal_thread(&thread, SignalInfo::new(Signo::SIGSTOP, SI_USER as _))?;
+    }
+    Ok(0)
+}
+
+/// Finds the thread `pid` traced by the calling process.
+fn get_tracee(pid: Pid) -> LinuxResult<Arc<Thread>> {
+    let thread = get_thread(pid)?;
+    let tracer = thread
+        .data::<ThreadData>()
+        .and_then(|data| data.ptrace.tracer())
+        .ok_or(LinuxError::ESRCH)?;
+    if !Arc::ptr_eq(&tracer, current().task_ext().thread.process()) {
+        return Err(LinuxError::ESRCH);
+    }
+    Ok(thread)
+}
+
+/// Runs `f` on the user registers of a stopped tracee, at the top of its
+/// kernel stack.
+///
+/// Fails with `ESRCH` if the tracee is not stopped. It cannot leave the stop
+/// while `f` runs, as it needs the tracing state locked here to do so, even
+/// when it is killed.
+fn with_tracee_trap_frame<R>(
+    thread: &Thread,
+    f: impl FnOnce(&mut TrapFrame) -> R,
+) -> LinuxResult<R> {
+    let data = thread.data::<ThreadData>().unwrap();
+    let task = data.task().ok_or(LinuxError::ESRCH)?;
+    let ptrace = data.ptrace.lock();
+    if !ptrace.is_stopped() {
+        return Err(LinuxError::ESRCH);
+    }
+    // SAFETY: the tracee stays in the kernel and does not touch its trap
+    // frame while stopped, and its kernel stack lives as long as `task`.
+    let result = f(unsafe { &mut *user_trap_frame(&task) });
+    drop(ptrace);
+    Ok(result)
+}
+
+/// Reads a word from the memory of a tracee, or writes `value` to it.
+fn access_tracee_memory(thread: &Thread, addr: usize, value: Option<usize>) -> LinuxResult<usize> {
+    let start = VirtAddr::from(addr);
+    let end = addr
+        .checked_add(size_of::<usize>())
+        .ok_or(LinuxError::EIO)?;
+    let page_start = start.align_down_4k();
+    let page_end = VirtAddr::from(end).align_up_4k();
+
+    let process_data = thread.process().data::<ProcessData>().unwrap();
+    let aspace = process_data.aspace();
+    let mut aspace = aspace.lock();
+    aspace
+        .populate_area(page_start, page_end - page_start)
+        .map_err(|_| LinuxError::EIO)?;
+    let mut buf = [0; size_of::<usize>()];
+    match value {
+        Some(value) => aspace.write(start, &value.to_ne_bytes()),
+        None => aspace.read(start, &mut buf),
+    }
+    .map_err(|_| LinuxError::EIO)?;
+    Ok(usize::from_ne_bytes(buf))
+}
+
+/// Views registers as bytes.
+fn regs_bytes<T>(regs: &mut T) -> &mut [u8] {
+    // SAFETY: the registers are plain integers without padding, so any bytes
+    // are valid.
+    unsafe { slice::from_raw_parts_mut((regs as *mut T).cast(), size_of::<T>()) }
+}
+
+/// Copies a register set to the user buffer described by `iov`, truncated to
+/// its length, which is updated to the length copied.
+fn get_regset<T>(mut regs: T, iov: &mut iovec) -> LinuxResult {
+    let bytes = regs_bytes(&mut regs);
+    let len = bytes.len().min(iov.iov_len as usize);
+    UserPtr::<u8>::from(iov.iov_base as usize)
+        .get_as_mut_slice(len)?
+        .copy_from_slice(&bytes[..len]);
+    iov.iov_len = len as _;
+    Ok(())
+}
+
+/// Updates a register set from the user buffer described by `iov`, which may
+/// cover part of it only, and updates its length to the length copied.
+fn set_regset<T>(regs: &mut T, iov: &mut iovec) -> LinuxResult {
+    let bytes = regs_bytes(regs);
+    let len = bytes.len().min(iov.iov_len as usize);
+    bytes[..len]
+        .copy_from_slice(UserConstPtr::<u8>::from(iov.iov_base as usize).get_as_slice(len)?);
+    iov.iov_len = len as _;
+    Ok(())
+}
+
+/// Gets the general registers of a stopped tracee.
+fn tracee_regs(thread: &Thread) -> LinuxResult<UserRegs> {
+    #[allow(unused_mut)]
+    let mut regs = with_tracee_trap_frame(thread, |tf| user_regs(tf))?;
+    #[cfg(target_arch = "x86_64")]
+    {
+        let syscall_nr = thread
+            .data::<ThreadData>()
+            .unwrap()
+            .ptrace
+            .lock()
+            .syscall_nr;
+        regs[crate::regs::ORIG_RAX] = syscall_nr.map_or(u64::MAX, |nr| nr as _);
+    }
+    Ok(regs)
+}
+
+/// Sets the general registers of a stopped tracee.
+fn set_tracee_regs(thread: &Thread, regs: &UserRegs) -> LinuxResult {
+    with_tracee_trap_frame(thread, |tf| set_user_regs(tf, regs))??;
+    #[cfg(target_arch = "x86_64")]
+    {
+        let mut ptrace = thread.data::<ThreadData>().unwrap().ptrace.lock();
+        if ptrace.syscall_nr.is_some() {
+            ptrace.syscall_nr = Some(regs[crate::regs::ORIG_RAX] as _);
+        }
+    }
+    Ok(())
+}
+
+/// Gets the FP/SIMD registers of a stopped tracee.
+fn tracee_fp_regs(thread: &Thread) -> LinuxResult<UserFpRegs> {
+    let mut ptrace = thread.data::<ThreadData>().unwrap().ptrace.lock();
+    let stop = ptrace.stop_mut().ok_or(LinuxError::ESRCH)?;
+    Ok(fp_regs(&stop.fp))
+}
+
+/// Sets the FP/SIMD registers of a stopped tracee, restored once it resumes.
+fn set_tracee_fp_regs(thread: &Thread, regs: &UserFpRegs) -> LinuxResult {
+    let mut ptrace = thread.data::<ThreadData>().unwrap().ptrace.lock();
+    let stop = ptrace.stop_mut().ok_or(LinuxError::ESRCH)?;
+    set_fp_regs(&mut stop.fp, regs);
+    stop.fp_dirty = true;
+    Ok(())
+}
+
+/// The size of `struct user` on x86_64, which `PTRACE_PEEKUSER` and
+/// `PTRACE_POKEUSER` access.
+#[cfg(target_arch = "x86_64")]
+const USER_SIZE: usize = 912;
+
+/// The offset of the debug registers in `struct user` on x86_64.
+#[cfg(target_arch = "x86_64")]
+const USER_DEBUGREG_OFFSET: usize = 848;
+
+/// Reads a word of `struct user` of a stopped tracee at `offset`.
+///
+/// The debug registers read as 0, as do other fields after the general
+/// registers.
+#[cfg(target_arch = "x86_64")]
+fn peek_user(thread: &Thread, offset: usize) -> LinuxResult<usize> {
+    if !memory_addr::is_aligned(offset, size_of::<usize>()) || offset >= USER_SIZE {
+        return Err(LinuxError::EIO);
+    }
+    let index = offset / size_of::<usize>();
+    let regs = tracee_regs(thread)?;
+    Ok(regs.get(index).map_or(0, |reg| *reg as _))
+}
+
+/// Writes a word of `struct user` of a stopped tracee at `offset`.
+///
+/// Only the general registers can be written, and the debug registers, which
+/// are not supported, accept 0.
+#[cfg(target_arch = "x86_64")]
+fn poke_user(thread: &Thread, offset: usize, value: usize) -> LinuxResult {
+    if !memory_addr::is_aligned(offset, size_of::<usize>()) || offset >= USER_SIZE {
+        return Err(LinuxError::EIO);
+    }
+    let index = offset / size_of::<usize>();
+    let mut regs = tracee_regs(thread)?;
+    if let Some(reg) = regs.get_mut(index) {
+        *reg = value as _;
+        set_tracee_regs(thread, &regs)
+    } else if offset >= USER_DEBUGREG_OFFSET && value == 0 {
+        Ok(())
+    } else {
+        Err(LinuxError::EIO)
+    }
+}
+
+/// Handles `PTRACE_GETREGSET`, for the register set of type `ty`.
+fn get_tracee_regset(thread: &Thread, ty: u32, iov: &mut iovec) -> LinuxResult {
+    match ty {
+        NT_PRSTATUS => get_regset(tracee_regs(thread)?, iov),
+        NT_PRFPREG => get_regset(tracee_fp_regs(thread)?, iov),
+        #[cfg(target_arch = "aarch64")]
+        linux_raw_sys::elf_uapi::NT_ARM_TLS => {
+            get_regset(with_tracee_trap_frame(thread, |tf| tf.tls() as u64)?, iov)
+        }
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Handles `PTRACE_SETREGSET`, for the register set of type `ty`.
+fn set_tracee_regset(thread: &Thread, ty: u32, iov: &mut iovec) -> LinuxResult {
+    match ty {
+        NT_PRSTATUS => {
+            let mut regs = tracee_regs(thread)?;
+            set_regset(&mut regs, iov)?;
+            set_tracee_regs(thread, &regs)
+        }
+        NT_PRFPREG => {
+            let mut regs = tracee_fp_regs(thread)?;
+            set_regset(&mut regs, iov)?;
+            set_tracee_fp_regs(thread, &regs)
+        }
+        #[cfg(target_arch = "aarch64")]
+        linux_raw_sys::elf_uapi::NT_ARM_TLS => {
+            let mut tls = with_tracee_trap_frame(thread, |tf| tf.tls() as u64)?;
+            set_regset(&mut tls, iov)?;
+            with_tracee_trap_frame(thread, |tf| tf.set_tls(tls as _))
+        }
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Parses the signal to deliver to a resumed tracee.
+fn parse_resume_signal(data: usize) -> LinuxResult<Option<Signo>> {
+    if data == 0 {
+        return Ok(None);
+    }
+    u8::try_from(data)
+        .ok()
+        .and_then(Signo::from_repr)
+        .map(Some)
+        .ok_or(LinuxError::EIO)
+}
+
+/// Handles a request on a stopped tracee.
+fn ptrace_stopped(thread: &Thread, request: u32, addr: usize, data: usize) -> LinuxResult<isize> {
+    let state = &thread.data::<ThreadData>().unwrap().ptrace;
+    if !state.lock().is_stopped() {
+        return Err(LinuxError::ESRCH);
+    }
+
+    match request {
+        PTRACE_PEEKTEXT | PTRACE_PEEKDATA => {
+            let value = access_tracee_memory(thread, addr, None)?;
+            *UserPtr::<usize>::from(data).get_as_mut()? = value;
+        }
+        PTRACE_POKETEXT | PTRACE_POKEDATA => {
+            access_tracee_memory(thread, addr, Some(data))?;
+        }
+        #[cfg(target_arch = "x86_64")]
+        PTRACE_PEEKUSR => {
+            let value = peek_user(thread, addr)?;
+            *UserPtr::<usize>::from(data).get_as_mut()? = value;
+        }
+        #[cfg(target_arch = "x86_64")]
+        PTRACE_POKEUSR => poke_user(thread, addr, data)?,
+        #[cfg(target_arch = "x86_64")]
+        PTRACE_GETREGS => {
+            let regs = tracee_regs(thread)?;
+            *UserPtr::<UserRegs>::from(data).get_as_mut()? = regs;
+        }
+        #[cfg(target_arch = "x86_64")]
+        PTRACE_SETREGS => {
+            let regs = *UserConstPtr::<UserRegs>::from(data).get_as_ref()?;
+            set_tracee_regs(thread, &regs)?;
+        }
+        #[cfg(target_arch = "x86_64")]
+        PTRACE_GETFPREGS => {
+            let regs = tracee_fp_regs(thread)?;
+            *UserPtr::<UserFpRegs>::from(data).get_as_mut()? = regs;
+        }
+        #[cfg(target_arch = "x86_64")]
+        PTRACE_SETFPREGS => {
+            let regs = UserConstPtr::<UserFpRegs>::from(data).get_as_ref()?.clone();
+            set_tracee_fp_regs(thread, &regs)?;
+        }
+        PTRACE_GETREGSET => {
+            let iov = UserPtr::<iovec>::from(data).get_as_mut()?;
+            get_tracee_regset(thread, addr as _, iov)?;
+        }
+        PTRACE_SETREGSET => {
+            let iov = UserPtr::<iovec>::from(data).get_as_mut()?;
+            set_tracee_regset(thread, addr as _, iov)?;
+        }
+        PTRACE_GETSIGINFO => {
+            let siginfo = state.lock().siginfo.clone().ok_or(LinuxError::EINVAL)?;
+            *UserPtr::<siginfo>::from(data).get_as_mut()? = siginfo.0;
+        }
+        PTRACE_SETSIGINFO => {
+            let siginfo = *UserConstPtr::<siginfo>::from(data).get_as_ref()?;
+            state.lock().siginfo = Some(SignalInfo(siginfo));
+        }
+        PTRACE_GETEVENTMSG => {
+            let msg = state.lock().event_msg;
+            *UserPtr::<usize>::from(data).get_as_mut()? = msg;
+        }
+        PTRACE_SETOPTIONS => {
+            if data & !(PTRACE_O_MASK as usize) != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            state.lock().options = data as _;
+        }
+        PTRACE_CONT | PTRACE_SYSCALL | PTRACE_SINGLESTEP => {
+            let mode = match request {
+                PTRACE_CONT => ResumeMode::Continue,
+                PTRACE_SYSCALL => ResumeMode::Syscall,
+                // See `set_single_step` for the architectures without it.
+                _ if cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) => {
+                    ResumeMode::SingleStep
+                }
+                _ => return Err(LinuxError::EIO),
+            };
+            let sig = parse_resume_signal(data)?;
+            if !state.resume(mode, sig) {
+                return Err(LinuxError::ESRCH);
+            }
+        }
+        PTRACE_DETACH => {
+            let sig = parse_resume_signal(data)?;
+            detach(thread, sig);
+        }
+        _ => return Err(LinuxError::EIO),
+    }
+    Ok(0)
+}
+
+pub fn sys_ptrace(request: u32, pid: Pid, addr: usize, data: usize) -> LinuxResult<isize> {
+    info!(
+        "sys_ptrace <= request: {}, pid: {}, addr: {:#x}, data: {:#x}",
+        request, pid, addr, data
+    );
+
+    match request {
+        PTRACE_TRACEME => {
+            let curr = current();
+            let thread = &curr.task_ext().thread;
+            let parent = thread.process().parent().ok_or(LinuxError::EPERM)?;
+            // The parent gets the same power over the process as by
+            // `PTRACE_ATTACH`, so it needs the same permission.
+            let parent_data = parent.data::<ProcessData>().ok_or(LinuxError::EPERM)?;
+            if !may_trace_by(parent_data, curr.task_ext().process_data()) {
+                return Err(LinuxError::EPERM);
+            }
+            if !attach(&parent, thread, false, 0) {
+                return Err(LinuxError::EPERM);
+            }
+            return Ok(0);
+        }
+        PTRACE_ATTACH | PTRACE_SEIZE => return ptrace_attach(pid, request == PTRACE_SEIZE, data),
+        _ => {}
+    }
+
+    let thread = get_tracee(pid)?;
+    let state = &thread.data::<ThreadData>().unwrap().ptrace;
+    match request {
+        PTRACE_KILL => {
+            send_signal_thread(&thread, SignalInfo::new(Signo::SIGKILL, SI_USER as _))?;
+            Ok(0)
+        }
+        PTRACE_INTERRUPT => {
+            let mut ptrace = state.lock();
+            if !ptrace.seized {
+                return Err(LinuxError::EIO);
+            }
+            ptrace.interrupt = true;
+            drop(ptrace);
+            interrupt_thread(&thread);
+            Ok(0)
+        }
+        _ => ptrace_stopped(&thread, request, addr, data),
+    }
+}
@@ -1,5 +1,6 @@
 use axerrno::{LinuxError, LinuxResult};
 use linux_raw_sys::general::timespec;
+use starry_core::signal::interruptible_sleep;
 
 use crate::{
     ptr::{UserConstPtr, UserPtr, nullable},
@@ -13,7 +14,8 @@ pub fn sys_sched_yield() -> LinuxResult<isize> {
 
 /// Sleep some nanoseconds
 ///
-/// TODO: should be woken by signals, and set errno
+/// Fails with `EINTR` if interrupted by a signal, storing the remaining time in
+/// `rem`.
 pub fn sys_nanosleep(req: UserConstPtr<timespec>, rem: UserPtr<timespec>) -> LinuxResult<isize> {
     let req = req.get_as_ref()?;
 
@@ -26,17 +28,15 @@ pub fn sys_nanosleep(req: UserConstPtr<timespec>, rem: UserPtr<timespec>) -> Lin
 
     let now = axhal::time::monotonic_time();
 
-    axtask::sleep(dur);
+    if interruptible_sleep(dur).is_ok() {
+        return Ok(0);
+    }
 
     let after = axhal::time::monotonic_time();
     let actual = after - now;
 
-    if let Some(diff) = dur.checked_sub(actual) {
-        if let Some(rem) = nullable!(rem.get_as_mut())? {
-            *rem = timespec::from_time_value(diff);
-        }
-        Err(LinuxError::EINTR)
-    } else {
-        Ok(0)
+    if let Some(rem) = nullable!(rem.get_as_mut())? {
+        *rem = timespec::from_time_value(dur.saturating_sub(actual));
     }
+    Err(LinuxError::EINTR)
 }
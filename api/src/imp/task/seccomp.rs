@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    general::CAP_SYS_ADMIN,
+    ptrace::{
+        SECCOMP_FILTER_FLAG_LOG, SECCOMP_FILTER_FLAG_SPEC_ALLOW, SECCOMP_FILTER_FLAG_TSYNC,
+        SECCOMP_GET_ACTION_AVAIL, SECCOMP_MODE_DISABLED, SECCOMP_MODE_FILTER, SECCOMP_MODE_STRICT,
+        SECCOMP_RET_ALLOW, SECCOMP_RET_ERRNO, SECCOMP_RET_KILL_PROCESS, SECCOMP_RET_KILL_THREAD,
+        SECCOMP_RET_LOG, SECCOMP_RET_TRAP, SECCOMP_SET_MODE_FILTER, SECCOMP_SET_MODE_STRICT,
+        sock_filter, sock_fprog,
+    },
+};
+use starry_core::{
+    seccomp::{SeccompFilter, SeccompMode},
+    task::ThreadData,
+};
+
+use crate::ptr::UserConstPtr;
+
+fn set_mode_strict() -> LinuxResult<isize> {
+    current()
+        .task_ext()
+        .thread_data()
+        .seccomp
+        .lock()
+        .set_strict()?;
+    Ok(0)
+}
+
+/// Installs the filter `prog` on the current thread, and on the other
+/// threads of its process with `SECCOMP_FILTER_FLAG_TSYNC`.
+///
+/// Installing a filter requires `no_new_privs` or `CAP_SYS_ADMIN`, so that
+/// it cannot mislead a program that gains privileges by `execve`.
+fn set_mode_filter(flags: u32, prog: UserConstPtr<sock_fprog>) -> LinuxResult<isize> {
+    const FLAG_MASK: u32 =
+        SECCOMP_FILTER_FLAG_TSYNC | SECCOMP_FILTER_FLAG_LOG | SECCOMP_FILTER_FLAG_SPEC_ALLOW;
+    if flags & !FLAG_MASK != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let curr = current();
+    let thr_data = curr.task_ext().thread_data();
+    let no_new_privs = thr_data.no_new_privs.load(Ordering::Relaxed);
+    if !no_new_privs && !curr.task_ext().process_data().cred().has_cap(CAP_SYS_ADMIN) {
+        return Err(LinuxError::EACCES);
+    }
+
+    let prog = prog.get_as_ref()?;
+    let insns: Vec<sock_filter> = UserConstPtr::<sock_filter>::from(prog.filter as usize)
+        .get_as_slice(prog.len as usize)?
+        .to_vec();
+    let filter = SeccompFilter::new(insns, flags & SECCOMP_FILTER_FLAG_LOG != 0)?;
+
+    let mut seccomp = thr_data.seccomp.lock().clone();
+    seccomp.add_filter(filter)?;
+
+    if flags & SECCOMP_FILTER_FLAG_TSYNC != 0 {
+        let threads = curr.task_ext().thread.process().threads();
+        // Every other thread must have a subset of the filters, or the tid
+        // of the first one that does not is returned.
+        for thread in &threads {
+            let data = thread.data::<ThreadData>().unwrap();
+            if !seccomp.extends(&data.seccomp.lock()) {
+                return Ok(thread.tid() as _);
+            }
+        }
+        for thread in &threads {
+            let data = thread.data::<ThreadData>().unwrap();
+            *data.seccomp.lock() = seccomp.clone();
+            if no_new_privs {
+                data.no_new_privs.store(true, Ordering::Relaxed);
+            }
+        }
+    } else {
+        *thr_data.seccomp.lock() = seccomp;
+    }
+    Ok(0)
+}
+
+/// Checks whether the action at `action` is supported.
+fn get_action_avail(action: UserConstPtr<u32>) -> LinuxResult<isize> {
+    match *action.get_as_ref()? {
+        SECCOMP_RET_KILL_PROCESS
+        | SECCOMP_RET_KILL_THREAD
+        | SECCOMP_RET_TRAP
+        | SECCOMP_RET_ERRNO
+        | SECCOMP_RET_LOG
+        | SECCOMP_RET_ALLOW => Ok(0),
+        _ => Err(LinuxError::EOPNOTSUPP),
+    }
+}
+
+pub fn sys_seccomp(op: u32, flags: u32, args: usize) -> LinuxResult<isize> {
+    info!(
+        "sys_seccomp <= op: {}, flags: {:#x}, args: {:#x}",
+        op, flags, args
+    );
+    match op {
+        SECCOMP_SET_MODE_STRICT => {
+            if flags != 0 || args != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            set_mode_strict()
+        }
+        SECCOMP_SET_MODE_FILTER => set_mode_filter(flags, args.into()),
+        SECCOMP_GET_ACTION_AVAIL => {
+            if flags != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            get_action_avail(args.into())
+        }
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Gets the seccomp mode of the current thread, see `PR_GET_SECCOMP`.
+pub(crate) fn get_seccomp() -> LinuxResult<isize> {
+    let mode = match current().task_ext().thread_data().seccomp.lock().mode() {
+        SeccompMode::Disabled => SECCOMP_MODE_DISABLED,
+        SeccompMode::Strict => SECCOMP_MODE_STRICT,
+        SeccompMode::Filter => SECCOMP_MODE_FILTER,
+    };
+    Ok(mode as _)
+}
+
+/// Sets the seccomp mode of the current thread, see `PR_SET_SECCOMP`.
+pub(crate) fn set_seccomp(mode: u32, filter: usize) -> LinuxResult<isize> {
+    match mode {
+        SECCOMP_MODE_STRICT => {
+            if filter != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            set_mode_strict()
+        }
+        SECCOMP_MODE_FILTER => set_mode_filter(0, filter.into()),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
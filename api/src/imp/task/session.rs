@@ -0,0 +1,97 @@
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axprocess::{Pid, Process};
+use axtask::{TaskExtRef, current};
+use starry_core::task::{ProcessData, add_process_group_to_table, get_process, get_process_group};
+
+/// Finds the process with the given PID, or the calling process if `pid` is
+/// 0.
+fn find_process(pid: i32) -> LinuxResult<Arc<Process>> {
+    match pid {
+        0 => Ok(current().task_ext().thread.process().clone()),
+        1.. => get_process(pid as Pid),
+        _ => Err(LinuxError::ESRCH),
+    }
+}
+
+pub fn sys_getpgid(pid: i32) -> LinuxResult<isize> {
+    Ok(find_process(pid)?.group().pgid() as _)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_getpgrp() -> LinuxResult<isize> {
+    sys_getpgid(0)
+}
+
+pub fn sys_getsid(pid: i32) -> LinuxResult<isize> {
+    Ok(find_process(pid)?.group().session().sid() as _)
+}
+
+/// Moves the process `pid` (or the calling process if 0) to the process group
+/// `pgid` (or a new group led by the process if 0).
+///
+/// The process must be the calling process or one of its children that has
+/// not called `execve` yet, and must stay in the session of the caller.
+pub fn sys_setpgid(pid: i32, pgid: i32) -> LinuxResult<isize> {
+    if pgid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let curr = current();
+    let caller = curr.task_ext().thread.process();
+    let target = if pid == 0 || pid as Pid == caller.pid() {
+        caller.clone()
+    } else {
+        let child = caller
+            .children()
+            .into_iter()
+            .find(|child| child.pid() as i32 == pid)
+            .ok_or(LinuxError::ESRCH)?;
+        if child
+            .data::<ProcessData>()
+            .is_some_and(|data| data.has_execved())
+        {
+            return Err(LinuxError::EACCES);
+        }
+        child
+    };
+
+    let session = caller.group().session();
+    let target_session = target.group().session();
+    if target_session.sid() == target.pid() || !Arc::ptr_eq(&target_session, &session) {
+        return Err(LinuxError::EPERM);
+    }
+
+    let pgid = if pgid == 0 { target.pid() } else { pgid as Pid };
+    match get_process_group(pgid) {
+        Ok(group) => {
+            if !target.move_to_group(&group) {
+                return Err(LinuxError::EPERM);
+            }
+        }
+        Err(_) if pgid == target.pid() => {
+            if let Some(group) = target.create_group() {
+                add_process_group_to_table(&group);
+            }
+        }
+        Err(_) => return Err(LinuxError::EPERM),
+    }
+    Ok(0)
+}
+
+/// Creates a new session led by the calling process, with a new process group
+/// led by it as well.
+///
+/// Fails with `EPERM` if a process group with the PID of the caller already
+/// exists, e.g. if the caller is a process group leader.
+pub fn sys_setsid() -> LinuxResult<isize> {
+    let curr = current();
+    let process = curr.task_ext().thread.process();
+    if get_process_group(process.pid()).is_ok() {
+        return Err(LinuxError::EPERM);
+    }
+
+    let (session, group) = process.create_session().ok_or(LinuxError::EPERM)?;
+    add_process_group_to_table(&group);
+    Ok(session.sid() as _)
+}
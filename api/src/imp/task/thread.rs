@@ -1,23 +1,39 @@
-use axerrno::LinuxResult;
+use core::{ffi::c_int, sync::atomic::Ordering};
+
+use alloc::string::String;
+
+use axerrno::{LinuxError, LinuxResult};
+use axsignal::Signo;
 use axtask::{TaskExtRef, current};
+use linux_raw_sys::prctl::{
+    PR_CAPBSET_DROP, PR_CAPBSET_READ, PR_GET_CHILD_SUBREAPER, PR_GET_DUMPABLE, PR_GET_NAME,
+    PR_GET_NO_NEW_PRIVS, PR_GET_PDEATHSIG, PR_GET_SECCOMP, PR_SET_CHILD_SUBREAPER, PR_SET_DUMPABLE,
+    PR_SET_NAME, PR_SET_NO_NEW_PRIVS, PR_SET_PDEATHSIG, PR_SET_SECCOMP,
+};
 use num_enum::TryFromPrimitive;
 
+use super::{get_seccomp, set_seccomp};
+use crate::{
+    imp::cred::{capbset_drop, capbset_read},
+    ptr::{UserConstPtr, UserPtr},
+};
+
 pub fn sys_getpid() -> LinuxResult<isize> {
     Ok(axtask::current().task_ext().thread.process().pid() as _)
 }
 
 pub fn sys_getppid() -> LinuxResult<isize> {
+    // The init process has no parent.
     Ok(axtask::current()
         .task_ext()
         .thread
         .process()
         .parent()
-        .unwrap()
-        .pid() as _)
+        .map_or(0, |parent| parent.pid()) as _)
 }
 
 pub fn sys_gettid() -> LinuxResult<isize> {
-    Ok(axtask::current().id().as_u64() as _)
+    Ok(axtask::current().task_ext().thread.tid() as _)
 }
 
 /// ARCH_PRCTL codes
@@ -49,7 +65,118 @@ pub fn sys_set_tid_address(clear_child_tid: usize) -> LinuxResult<isize> {
     curr.task_ext()
         .thread_data()
         .set_clear_child_tid(clear_child_tid);
-    Ok(curr.id().as_u64() as isize)
+    Ok(curr.task_ext().thread.tid() as _)
+}
+
+/// The size of the buffer holding a thread name for `PR_SET_NAME` and
+/// `PR_GET_NAME`, including the terminating NUL.
+const TASK_COMM_LEN: usize = 16;
+
+/// Performs the operation `option` on the calling thread or process.
+pub fn sys_prctl(
+    option: u32,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_prctl <= option: {}, args: {:#x}, {:#x}, {:#x}, {:#x}",
+        option, arg2, arg3, arg4, arg5
+    );
+    match option {
+        PR_CAPBSET_READ => capbset_read(arg2 as _),
+        PR_CAPBSET_DROP => capbset_drop(arg2 as _),
+        PR_GET_SECCOMP => get_seccomp(),
+        PR_SET_SECCOMP => set_seccomp(arg2 as _, arg3),
+        PR_SET_NO_NEW_PRIVS => {
+            // It cannot be unset.
+            if arg2 != 1 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            current()
+                .task_ext()
+                .thread_data()
+                .no_new_privs
+                .store(true, Ordering::Relaxed);
+            Ok(0)
+        }
+        PR_GET_NO_NEW_PRIVS => {
+            if arg2 != 0 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            let no_new_privs = current()
+                .task_ext()
+                .thread_data()
+                .no_new_privs
+                .load(Ordering::Relaxed);
+            Ok(no_new_privs as _)
+        }
+        PR_SET_NAME => {
+            let name = UserConstPtr::<u8>::from(arg2).get_as_null_terminated()?;
+            let name = &name[..name.len().min(TASK_COMM_LEN - 1)];
+            current().set_name(&String::from_utf8_lossy(name));
+            Ok(0)
+        }
+        PR_GET_NAME => {
+            let buf = UserPtr::<u8>::from(arg2).get_as_mut_slice(TASK_COMM_LEN)?;
+            let curr = current();
+            let name = curr.name().as_bytes();
+            let len = name.len().min(TASK_COMM_LEN - 1);
+            buf[..len].copy_from_slice(&name[..len]);
+            buf[len] = 0;
+            Ok(0)
+        }
+        PR_SET_PDEATHSIG => {
+            if arg2 != 0 {
+                u8::try_from(arg2)
+                    .ok()
+                    .and_then(Signo::from_repr)
+                    .ok_or(LinuxError::EINVAL)?;
+            }
+            current()
+                .task_ext()
+                .thread_data()
+                .pdeathsig
+                .store(arg2 as _, Ordering::Relaxed);
+            Ok(0)
+        }
+        PR_GET_PDEATHSIG => {
+            let pdeathsig = current()
+                .task_ext()
+                .thread_data()
+                .pdeathsig
+                .load(Ordering::Relaxed);
+            *UserPtr::<c_int>::from(arg2).get_as_mut()? = pdeathsig as _;
+            Ok(0)
+        }
+        PR_SET_DUMPABLE => {
+            // `SUID_DUMP_DISABLE` or `SUID_DUMP_USER`
+            if arg2 > 1 {
+                return Err(LinuxError::EINVAL);
+            }
+            current().task_ext().process_data().set_dumpable(arg2 != 0);
+            Ok(0)
+        }
+        PR_GET_DUMPABLE => Ok(current().task_ext().process_data().is_dumpable() as _),
+        PR_SET_CHILD_SUBREAPER => {
+            current()
+                .task_ext()
+                .thread
+                .process()
+                .set_child_subreaper(arg2 != 0);
+            Ok(0)
+        }
+        PR_GET_CHILD_SUBREAPER => {
+            let subreaper = current().task_ext().thread.process().is_child_subreaper();
+            *UserPtr::<c_int>::from(arg2).get_as_mut()? = subreaper as _;
+            Ok(0)
+        }
+        _ => {
+            warn!("sys_prctl: unsupported option {}", option);
+            Err(LinuxError::EINVAL)
+        }
+    }
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -58,8 +185,6 @@ pub fn sys_arch_prctl(
     code: i32,
     addr: usize,
 ) -> LinuxResult<isize> {
-    use crate::ptr::UserPtr;
-
     let code = ArchPrctlCode::try_from(code).map_err(|_| axerrno::LinuxError::EINVAL)?;
     debug!("sys_arch_prctl: code = {:?}, addr = {:#x}", code, addr);
 
@@ -1,14 +1,27 @@
 use alloc::{sync::Arc, vec::Vec};
 use axerrno::{LinuxError, LinuxResult};
-use axprocess::{Pid, Process};
+use axhal::time::TimeValue;
+use axprocess::{Pid, Process, Thread};
+use axsignal::{SignalInfo, Signo};
 use axtask::{TaskExtRef, current};
 use bitflags::bitflags;
 use linux_raw_sys::general::{
-    __WALL, __WCLONE, __WNOTHREAD, WCONTINUED, WEXITED, WNOHANG, WNOWAIT, WUNTRACED,
+    __WALL, __WCLONE, __WNOTHREAD, __kernel_old_timeval, __sifields__bindgen_ty_4, CLD_STOPPED,
+    CLD_TRAPPED, P_ALL, P_PGID, P_PID, P_PIDFD, WCONTINUED, WEXITED, WNOHANG, WNOWAIT, WSTOPPED,
+    WUNTRACED, rusage, siginfo_t,
+};
+use starry_core::{
+    ptrace::{PtraceState, release_traced, traced_threads},
+    signal::{JobEvent, interruptible_wait_until},
+    task::{ProcessData, ThreadData},
 };
-use starry_core::task::ProcessData;
 
-use crate::ptr::{UserPtr, nullable};
+use crate::{
+    file::{FileLike, PidFd},
+    ptr::{UserPtr, nullable},
+    signal::decode_wait_status,
+    time::TimeValueLike,
+};
 
 bitflags! {
     #[derive(Debug)]
@@ -18,6 +31,8 @@ bitflags! {
         /// Report the status of selected processes which are stopped due to a
         /// `SIGTTIN`, `SIGTTOU`, `SIGTSTP`, or `SIGSTOP` signal.
         const WUNTRACED = WUNTRACED;
+        /// Same as `WUNTRACED`, for `waitid`.
+        const WSTOPPED = WSTOPPED;
         /// Report the status of selected processes which have terminated.
         const WEXITED = WEXITED;
         /// Report the status of selected processes that have continued from a
@@ -53,16 +68,183 @@ impl WaitPid {
             WaitPid::Pgid(pgid) => child.group().pgid() == *pgid,
         }
     }
+
+    /// Whether a traced thread is selected, by its thread ID.
+    fn apply_tracee(&self, tracee: &Thread) -> bool {
+        match self {
+            WaitPid::Any => true,
+            WaitPid::Pid(pid) => tracee.tid() == *pid,
+            WaitPid::Pgid(pgid) => tracee.process().group().pgid() == *pgid,
+        }
+    }
 }
 
-pub fn sys_waitpid(pid: i32, exit_code_ptr: UserPtr<i32>, options: u32) -> LinuxResult<isize> {
-    let options = WaitOptions::from_bits_truncate(options);
-    info!("sys_waitpid <= pid: {:?}, options: {:?}", pid, options);
+fn ptrace_state(thread: &Thread) -> &PtraceState {
+    &thread.data::<ThreadData>().unwrap().ptrace
+}
+
+/// Whether a traced thread has a stop or exit to report, if `options` asks
+/// for it.
+fn has_tracee_report(tracee: &Thread, options: &WaitOptions) -> bool {
+    let state = ptrace_state(tracee).lock();
+    state.has_report() && (options.contains(WaitOptions::WEXITED) || !state.has_exit_report())
+}
+
+/// Gets the job-control state change of a child to report, if `options` asks
+/// for it.
+fn job_event(child: &Process, options: &WaitOptions) -> Option<JobEvent> {
+    let event = child.data::<ProcessData>()?.job_event()?;
+    let wanted = match event {
+        JobEvent::Stopped(_) => options.contains(WaitOptions::WUNTRACED),
+        JobEvent::Continued => options.contains(WaitOptions::WCONTINUED),
+    };
+    wanted.then_some(event)
+}
+
+/// A state change of a child or a traced thread reported by a wait.
+struct WaitResult {
+    /// The process or thread ID.
+    pid: Pid,
+    /// The wait status, as reported by `wait4`.
+    status: i32,
+    /// Whether the state change is reported to the tracer.
+    traced: bool,
+    /// The real user ID of the process.
+    uid: u32,
+    /// The user and system CPU time of the process, in nanoseconds.
+    cpu_time: (usize, usize),
+}
+
+impl WaitResult {
+    fn new(process: &Process, pid: Pid, status: i32, traced: bool) -> Self {
+        let data = process.data::<ProcessData>().unwrap();
+        Self {
+            pid,
+            status,
+            traced,
+            uid: data.cred().uid,
+            cpu_time: data.cpu_time(),
+        }
+    }
 
+    fn write_rusage(&self, rusage: &mut rusage) {
+        *rusage = unsafe { core::mem::zeroed() };
+        rusage.ru_utime =
+            __kernel_old_timeval::from_time_value(TimeValue::from_nanos(self.cpu_time.0 as _));
+        rusage.ru_stime =
+            __kernel_old_timeval::from_time_value(TimeValue::from_nanos(self.cpu_time.1 as _));
+    }
+}
+
+/// Waits for a state change of a child or a traced thread selected by
+/// `target` and `options`, and reports it.
+///
+/// Returns `None` if `WNOHANG` is set and there is nothing to report yet.
+fn do_wait(target: WaitPid, options: &WaitOptions) -> LinuxResult<Option<WaitResult>> {
     let curr = current();
     let proc_data = curr.task_ext().process_data();
     let process = curr.task_ext().thread.process();
+    let nowait = options.contains(WaitOptions::WNOWAIT);
 
+    loop {
+        // Children may be reaped by other threads or disappear meanwhile, so
+        // they are selected again after each wakeup.
+        let children = process
+            .children()
+            .into_iter()
+            .filter(|child| target.apply(child))
+            .filter(|child| {
+                options.contains(WaitOptions::WALL)
+                    || (options.contains(WaitOptions::WCLONE)
+                        == child.data::<ProcessData>().unwrap().is_clone_child())
+            })
+            .collect::<Vec<_>>();
+        // Stops and exits of traced threads are reported to the tracer,
+        // whether or not they are its children.
+        let tracees = traced_threads(process)
+            .into_iter()
+            .filter(|tracee| target.apply_tracee(tracee))
+            .collect::<Vec<_>>();
+        if children.is_empty() && tracees.is_empty() {
+            return Err(LinuxError::ECHILD);
+        }
+
+        if let Some((tracee, status, exited)) = tracees.iter().find_map(|tracee| {
+            let mut state = ptrace_state(tracee).lock();
+            if !options.contains(WaitOptions::WEXITED) && state.has_exit_report() {
+                // Stops are still reported
+                return None;
+            }
+            let (status, exited) = state.take_report(nowait)?;
+            Some((tracee, status, exited))
+        }) {
+            if exited && !nowait {
+                release_traced(process, tracee);
+            }
+            return Ok(Some(WaitResult::new(
+                tracee.process(),
+                tracee.tid(),
+                status,
+                true,
+            )));
+        }
+
+        if let Some(child) = children
+            .iter()
+            .find(|child| options.contains(WaitOptions::WEXITED) && child.is_zombie())
+        {
+            if !nowait {
+                child.free();
+            }
+            return Ok(Some(WaitResult::new(
+                child,
+                child.pid(),
+                child.exit_code(),
+                false,
+            )));
+        }
+
+        if let Some((child, event)) = children
+            .iter()
+            .find_map(|child| Some((child, job_event(child, options)?)))
+        {
+            let data = child.data::<ProcessData>().unwrap();
+            if !nowait && !data.clear_job_event(event) {
+                // Superseded by another event in the meantime
+                continue;
+            }
+            let status = match event {
+                JobEvent::Stopped(signo) => ((signo as i32) << 8) | 0x7f,
+                JobEvent::Continued => 0xffff,
+            };
+            return Ok(Some(WaitResult::new(child, child.pid(), status, false)));
+        }
+
+        if options.contains(WaitOptions::WNOHANG) {
+            return Ok(None);
+        }
+        interruptible_wait_until(&proc_data.child_exit_wq, None, || {
+            children.iter().any(|child| {
+                (options.contains(WaitOptions::WEXITED) && child.is_zombie())
+                    || job_event(child, options).is_some()
+            }) || tracees
+                .iter()
+                .any(|tracee| has_tracee_report(tracee, options))
+        })?;
+    }
+}
+
+pub fn sys_waitpid(
+    pid: i32,
+    exit_code_ptr: UserPtr<i32>,
+    options: u32,
+    rusage: UserPtr<rusage>,
+) -> LinuxResult<isize> {
+    // `wait4` always reports the termination of children.
+    let options = WaitOptions::from_bits_truncate(options) | WaitOptions::WEXITED;
+    info!("sys_waitpid <= pid: {:?}, options: {:?}", pid, options);
+
+    let process = current().task_ext().thread.process().clone();
     let pid = if pid == -1 {
         WaitPid::Any
     } else if pid == 0 {
@@ -73,34 +255,82 @@ pub fn sys_waitpid(pid: i32, exit_code_ptr: UserPtr<i32>, options: u32) -> Linux
         WaitPid::Pgid(-pid as _)
     };
 
-    let children = process
-        .children()
-        .into_iter()
-        .filter(|child| pid.apply(child))
-        .filter(|child| {
-            options.contains(WaitOptions::WALL)
-                || (options.contains(WaitOptions::WCLONE)
-                    == child.data::<ProcessData>().unwrap().is_clone_child())
-        })
-        .collect::<Vec<_>>();
-    if children.is_empty() {
-        return Err(LinuxError::ECHILD);
+    let exit_code = nullable!(exit_code_ptr.get_as_mut())?;
+    let rusage = nullable!(rusage.get_as_mut())?;
+    let Some(result) = do_wait(pid, &options)? else {
+        return Ok(0);
+    };
+    if let Some(exit_code) = exit_code {
+        *exit_code = result.status;
+    }
+    if let Some(rusage) = rusage {
+        result.write_rusage(rusage);
     }
+    Ok(result.pid as _)
+}
 
-    let exit_code = nullable!(exit_code_ptr.get_as_mut())?;
-    loop {
-        if let Some(child) = children.iter().find(|child| child.is_zombie()) {
-            if !options.contains(WaitOptions::WNOWAIT) {
-                child.free();
-            }
-            if let Some(exit_code) = exit_code {
-                *exit_code = child.exit_code();
+pub fn sys_waitid(
+    idtype: u32,
+    id: u32,
+    infop: UserPtr<siginfo_t>,
+    options: u32,
+    rusage: UserPtr<rusage>,
+) -> LinuxResult<isize> {
+    let mut options = WaitOptions::from_bits(options).ok_or(LinuxError::EINVAL)?;
+    info!(
+        "sys_waitid <= idtype: {}, id: {}, options: {:?}",
+        idtype, id, options
+    );
+    if !options.intersects(WaitOptions::WEXITED | WaitOptions::WSTOPPED | WaitOptions::WCONTINUED) {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let mut nonblocking_pidfd = false;
+    let target = match idtype {
+        P_ALL => WaitPid::Any,
+        P_PID if id as i32 > 0 => WaitPid::Pid(id as _),
+        P_PGID if id == 0 => WaitPid::Pgid(current().task_ext().thread.process().group().pgid()),
+        P_PGID if id as i32 > 0 => WaitPid::Pgid(id as _),
+        P_PIDFD => {
+            let pidfd = PidFd::from_fd(id as _).map_err(|_| LinuxError::EBADF)?;
+            // A nonblocking pidfd fails with `EAGAIN` instead of blocking.
+            if pidfd.is_nonblocking() {
+                nonblocking_pidfd = true;
+                options |= WaitOptions::WNOHANG;
             }
-            return Ok(child.pid() as _);
-        } else if options.contains(WaitOptions::WNOHANG) {
-            return Ok(0);
-        } else {
-            proc_data.child_exit_wq.wait();
+            WaitPid::Pid(pidfd.process().pid())
         }
+        _ => return Err(LinuxError::EINVAL),
+    };
+
+    let info = nullable!(infop.get_as_mut())?;
+    let rusage = nullable!(rusage.get_as_mut())?;
+    let Some(result) = do_wait(target, &options)? else {
+        if nonblocking_pidfd {
+            return Err(LinuxError::EAGAIN);
+        }
+        if let Some(info) = info {
+            *info = unsafe { core::mem::zeroed() };
+        }
+        return Ok(0);
+    };
+    if let Some(info) = info {
+        let (mut code, status) = decode_wait_status(result.status);
+        if result.traced && code == CLD_STOPPED {
+            code = CLD_TRAPPED;
+        }
+        let mut sig = SignalInfo::new(Signo::SIGCHLD, code as _);
+        sig.0.__bindgen_anon_1.__bindgen_anon_1._sifields._sigchld = __sifields__bindgen_ty_4 {
+            _pid: result.pid as _,
+            _uid: result.uid,
+            _status: status,
+            _utime: 0,
+            _stime: 0,
+        };
+        *info = sig.0;
+    }
+    if let Some(rusage) = rusage {
+        result.write_rusage(rusage);
     }
+    Ok(0)
 }
@@ -1,36 +1,202 @@
+use alloc::sync::Arc;
 use axerrno::{LinuxError, LinuxResult};
-use axhal::time::{monotonic_time, monotonic_time_nanos, nanos_to_ticks, wall_time};
+use axhal::time::{NANOS_PER_SEC, TimeValue, monotonic_time, monotonic_time_nanos, nanos_to_ticks};
+use axprocess::Pid;
+use axsignal::{SignalInfo, Signo};
+use axtask::{TaskExtRef, current};
 use linux_raw_sys::general::{
-    __kernel_clockid_t, CLOCK_MONOTONIC, CLOCK_REALTIME, timespec, timeval,
+    __kernel_clockid_t, __kernel_timer_t, __sifields__bindgen_ty_2, CAP_SYS_TIME, CLOCK_BOOTTIME,
+    CLOCK_MONOTONIC, CLOCK_MONOTONIC_COARSE, CLOCK_MONOTONIC_RAW, CLOCK_PROCESS_CPUTIME_ID,
+    CLOCK_REALTIME, CLOCK_REALTIME_COARSE, CLOCK_THREAD_CPUTIME_ID, SI_TIMER, SIGEV_NONE,
+    SIGEV_SIGNAL, SIGEV_THREAD_ID, TIMER_ABSTIME, itimerspec, itimerval, sigevent, sigval,
+    timespec, timeval,
 };
-use starry_core::task::time_stat_output;
+use starry_core::{
+    signal::interruptible_sleep,
+    task::{get_thread, time_stat_output},
+    time::{
+        PosixTimerClock, PosixTimerNotify, TimerType, create_posix_timer, delete_posix_timer,
+        get_itimer, get_posix_timer, posix_timer_clock, posix_timer_overrun, set_itimer,
+        set_posix_timer, set_wall_time, wall_time,
+    },
+};
+
+use super::sys_nanosleep;
+use crate::{
+    ptr::{UserConstPtr, UserPtr, nullable},
+    signal::{send_signal_process, send_signal_thread},
+    time::TimeValueLike,
+};
+
+fn process_cpu_time() -> TimeValue {
+    let (utime_ns, stime_ns) = current().task_ext().process_data().cpu_time();
+    TimeValue::from_nanos((utime_ns + stime_ns) as _)
+}
+
+fn thread_cpu_time() -> TimeValue {
+    let (utime_ns, stime_ns) = current().task_ext().time.borrow().output();
+    TimeValue::from_nanos((utime_ns + stime_ns) as _)
+}
 
-use crate::{ptr::UserPtr, time::TimeValueLike};
+fn clock_now(clock_id: __kernel_clockid_t) -> LinuxResult<TimeValue> {
+    Ok(match clock_id as u32 {
+        CLOCK_REALTIME | CLOCK_REALTIME_COARSE => wall_time(),
+        CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_MONOTONIC_COARSE | CLOCK_BOOTTIME => {
+            monotonic_time()
+        }
+        CLOCK_PROCESS_CPUTIME_ID => process_cpu_time(),
+        CLOCK_THREAD_CPUTIME_ID => thread_cpu_time(),
+        _ => {
+            warn!("Called clock_gettime for unsupported clock {}", clock_id);
+            return Err(LinuxError::EINVAL);
+        }
+    })
+}
 
 pub fn sys_clock_gettime(
     clock_id: __kernel_clockid_t,
     ts: UserPtr<timespec>,
 ) -> LinuxResult<isize> {
-    let now = match clock_id as u32 {
-        CLOCK_REALTIME => wall_time(),
-        CLOCK_MONOTONIC => monotonic_time(),
+    let now = clock_now(clock_id)?;
+    *ts.get_as_mut()? = timespec::from_time_value(now);
+    Ok(0)
+}
+
+pub fn sys_clock_getres(
+    clock_id: __kernel_clockid_t,
+    res: UserPtr<timespec>,
+) -> LinuxResult<isize> {
+    // Validate the clock
+    clock_now(clock_id)?;
+    let res_nanos = match clock_id as u32 {
+        CLOCK_REALTIME_COARSE | CLOCK_MONOTONIC_COARSE => {
+            NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64
+        }
+        _ => 1,
+    };
+    if let Some(res) = nullable!(res.get_as_mut())? {
+        *res = timespec::from_time_value(TimeValue::from_nanos(res_nanos));
+    }
+    Ok(0)
+}
+
+/// Fails with `EPERM` unless the calling process has `CAP_SYS_TIME`, which
+/// setting the wall clock needs.
+fn check_set_time() -> LinuxResult {
+    if current()
+        .task_ext()
+        .process_data()
+        .cred()
+        .has_cap(CAP_SYS_TIME)
+    {
+        Ok(())
+    } else {
+        Err(LinuxError::EPERM)
+    }
+}
+
+pub fn sys_clock_settime(
+    clock_id: __kernel_clockid_t,
+    ts: UserConstPtr<timespec>,
+) -> LinuxResult<isize> {
+    let time = timespec_to_time_value(*ts.get_as_ref()?)?;
+    debug!(
+        "sys_clock_settime <= clock_id: {}, time: {:?}",
+        clock_id, time
+    );
+    match clock_id as u32 {
+        CLOCK_REALTIME => {
+            check_set_time()?;
+            set_wall_time(time);
+        }
+        _ => return Err(LinuxError::EINVAL),
+    }
+    Ok(0)
+}
+
+pub fn sys_clock_nanosleep(
+    clock_id: __kernel_clockid_t,
+    flags: u32,
+    req: UserConstPtr<timespec>,
+    rem: UserPtr<timespec>,
+) -> LinuxResult<isize> {
+    match clock_id as u32 {
+        CLOCK_REALTIME | CLOCK_MONOTONIC | CLOCK_BOOTTIME => {}
+        CLOCK_PROCESS_CPUTIME_ID => return process_cpu_time_nanosleep(flags, req, rem),
         _ => {
-            warn!(
-                "Called sys_clock_gettime for unsupported clock {}",
-                clock_id
-            );
+            warn!("Called clock_nanosleep for unsupported clock {}", clock_id);
             return Err(LinuxError::EINVAL);
         }
-    };
-    *ts.get_as_mut()? = timespec::from_time_value(now);
+    }
+    if flags & TIMER_ABSTIME == 0 {
+        return sys_nanosleep(req, rem);
+    }
+
+    let deadline = timespec_to_time_value(*req.get_as_ref()?)?;
+    debug!(
+        "sys_clock_nanosleep <= clock_id: {}, deadline: {:?}",
+        clock_id, deadline
+    );
+    let now = clock_now(clock_id)?;
+    if deadline > now {
+        // `rem` is left untouched for absolute sleeps.
+        interruptible_sleep(deadline - now).map_err(|_| LinuxError::EINTR)?;
+    }
     Ok(0)
 }
 
+/// Sleeps until the CPU time of the calling process reaches the deadline of a
+/// `clock_nanosleep(2)` on `CLOCK_PROCESS_CPUTIME_ID`.
+fn process_cpu_time_nanosleep(
+    flags: u32,
+    req: UserConstPtr<timespec>,
+    rem: UserPtr<timespec>,
+) -> LinuxResult<isize> {
+    let req = timespec_to_time_value(*req.get_as_ref()?)?;
+    let deadline = if flags & TIMER_ABSTIME != 0 {
+        req
+    } else {
+        process_cpu_time() + req
+    };
+    debug!("sys_clock_nanosleep <= cpu time deadline: {:?}", deadline);
+
+    let curr = current();
+    let proc = curr.task_ext().thread.process();
+    loop {
+        let now = process_cpu_time();
+        if now >= deadline {
+            return Ok(0);
+        }
+        // The other threads each consume CPU time no faster than the
+        // monotonic clock advances, so this never oversleeps.
+        let others = proc.threads().len().saturating_sub(1).max(1);
+        if interruptible_sleep((deadline - now) / others as u32).is_err() {
+            if flags & TIMER_ABSTIME == 0 {
+                if let Some(rem) = nullable!(rem.get_as_mut())? {
+                    *rem = timespec::from_time_value(deadline.saturating_sub(process_cpu_time()));
+                }
+            }
+            return Err(LinuxError::EINTR);
+        }
+    }
+}
+
 pub fn sys_gettimeofday(ts: UserPtr<timeval>) -> LinuxResult<isize> {
     *ts.get_as_mut()? = timeval::from_time_value(wall_time());
     Ok(0)
 }
 
+pub fn sys_settimeofday(tv: UserConstPtr<timeval>, _tz: UserConstPtr<u8>) -> LinuxResult<isize> {
+    check_set_time()?;
+    // The timezone is obsolete and ignored, as on Linux.
+    if let Some(tv) = nullable!(tv.get_as_ref())? {
+        let time = timeval_to_time_value(*tv)?;
+        debug!("sys_settimeofday <= {:?}", time);
+        set_wall_time(time);
+    }
+    Ok(0)
+}
+
 #[repr(C)]
 pub struct Tms {
     /// user time
@@ -53,3 +219,246 @@ pub fn sys_times(tms: UserPtr<Tms>) -> LinuxResult<isize> {
     };
     Ok(nanos_to_ticks(monotonic_time_nanos()) as _)
 }
+
+fn timer_type(which: i32) -> LinuxResult<TimerType> {
+    match TimerType::try_from(which) {
+        Ok(TimerType::NONE) | Err(_) => Err(LinuxError::EINVAL),
+        Ok(ty) => Ok(ty),
+    }
+}
+
+fn itimerval_from_time_values((interval, value): (TimeValue, TimeValue)) -> itimerval {
+    itimerval {
+        it_interval: timeval::from_time_value(interval),
+        it_value: timeval::from_time_value(value),
+    }
+}
+
+fn timeval_to_time_value(tv: timeval) -> LinuxResult<TimeValue> {
+    if tv.tv_sec < 0 || !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(tv.to_time_value())
+}
+
+pub fn sys_getitimer(which: i32, curr_value: UserPtr<itimerval>) -> LinuxResult<isize> {
+    let ty = timer_type(which)?;
+    let curr = current();
+    *curr_value.get_as_mut()? =
+        itimerval_from_time_values(get_itimer(curr.task_ext().thread.process(), ty));
+    Ok(0)
+}
+
+pub fn sys_setitimer(
+    which: i32,
+    new_value: UserConstPtr<itimerval>,
+    old_value: UserPtr<itimerval>,
+) -> LinuxResult<isize> {
+    let ty = timer_type(which)?;
+    let (interval, value) = match nullable!(new_value.get_as_ref())? {
+        Some(new_value) => (
+            timeval_to_time_value(new_value.it_interval)?,
+            timeval_to_time_value(new_value.it_value)?,
+        ),
+        None => (TimeValue::ZERO, TimeValue::ZERO),
+    };
+    debug!(
+        "sys_setitimer <= which: {:?}, interval: {:?}, value: {:?}",
+        ty, interval, value
+    );
+
+    let curr = current();
+    let old = set_itimer(curr.task_ext().thread.process(), ty, interval, value);
+    if let Some(old_value) = nullable!(old_value.get_as_mut())? {
+        *old_value = itimerval_from_time_values(old);
+    }
+    Ok(0)
+}
+
+pub fn sys_alarm(seconds: u32) -> LinuxResult<isize> {
+    debug!("sys_alarm <= seconds: {}", seconds);
+    let curr = current();
+    let (_, old) = set_itimer(
+        curr.task_ext().thread.process(),
+        TimerType::REAL,
+        TimeValue::ZERO,
+        TimeValue::from_secs(seconds as _),
+    );
+    // Round to the nearest second, but never report an armed alarm as zero.
+    let mut remaining = old.as_secs();
+    if (remaining == 0 && !old.is_zero()) || old.subsec_micros() >= 500_000 {
+        remaining += 1;
+    }
+    Ok(remaining as _)
+}
+
+fn parse_timer_clock(clock_id: __kernel_clockid_t) -> LinuxResult<PosixTimerClock> {
+    Ok(match clock_id as u32 {
+        CLOCK_REALTIME => PosixTimerClock::Realtime,
+        CLOCK_MONOTONIC | CLOCK_BOOTTIME => PosixTimerClock::Monotonic,
+        CLOCK_PROCESS_CPUTIME_ID => PosixTimerClock::ProcessCpuTime,
+        CLOCK_THREAD_CPUTIME_ID => {
+            PosixTimerClock::ThreadCpuTime(current().task_ext().thread.tid())
+        }
+        _ => {
+            warn!("Unsupported clock {} for POSIX timers", clock_id);
+            return Err(LinuxError::EINVAL);
+        }
+    })
+}
+
+fn timespec_to_time_value(ts: timespec) -> LinuxResult<TimeValue> {
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(ts.to_time_value())
+}
+
+fn itimerspec_from_time_values((interval, value): (TimeValue, TimeValue)) -> itimerspec {
+    itimerspec {
+        it_interval: timespec::from_time_value(interval),
+        it_value: timespec::from_time_value(value),
+    }
+}
+
+fn timer_signal_info(signo: Signo, value: Option<usize>, id: i32, overrun: usize) -> SignalInfo {
+    let mut sig = SignalInfo::new(signo, SI_TIMER);
+    sig.0.__bindgen_anon_1.__bindgen_anon_1._sifields._timer = __sifields__bindgen_ty_2 {
+        _tid: id,
+        _overrun: overrun.min(i32::MAX as usize) as _,
+        _sigval: sigval {
+            sival_ptr: value.unwrap_or(id as usize) as _,
+        },
+        _sys_private: 0,
+    };
+    sig
+}
+
+fn make_timer_notify(sevp: UserConstPtr<sigevent>) -> LinuxResult<Option<PosixTimerNotify>> {
+    let curr = current();
+    let proc = curr.task_ext().thread.process();
+    let Some(sevp) = nullable!(sevp.get_as_ref())? else {
+        let proc = Arc::downgrade(proc);
+        return Ok(Some(Arc::new(move |id, overrun| {
+            if let Some(proc) = proc.upgrade() {
+                let sig = timer_signal_info(Signo::SIGALRM, None, id, overrun);
+                let _ = send_signal_process(&proc, sig);
+            }
+        })));
+    };
+
+    let notify = sevp.sigev_notify as u32;
+    if notify == SIGEV_NONE {
+        return Ok(None);
+    }
+    let signo = Signo::from_repr(sevp.sigev_signo as u8)
+        .filter(|_| (1..=64).contains(&sevp.sigev_signo))
+        .ok_or(LinuxError::EINVAL)?;
+    // SAFETY: `sival_ptr` covers the whole union.
+    let value = Some(unsafe { sevp.sigev_value.sival_ptr } as usize);
+    match notify {
+        SIGEV_SIGNAL => {
+            let proc = Arc::downgrade(proc);
+            Ok(Some(Arc::new(move |id, overrun| {
+                if let Some(proc) = proc.upgrade() {
+                    let sig = timer_signal_info(signo, value, id, overrun);
+                    let _ = send_signal_process(&proc, sig);
+                }
+            })))
+        }
+        SIGEV_THREAD_ID => {
+            // SAFETY: `_tid` is the active field for `SIGEV_THREAD_ID`.
+            let tid = unsafe { sevp._sigev_un._tid } as Pid;
+            let thr = get_thread(tid).map_err(|_| LinuxError::EINVAL)?;
+            if thr.process().pid() != proc.pid() {
+                return Err(LinuxError::EINVAL);
+            }
+            let thr = Arc::downgrade(&thr);
+            Ok(Some(Arc::new(move |id, overrun| {
+                if let Some(thr) = thr.upgrade() {
+                    let sig = timer_signal_info(signo, value, id, overrun);
+                    let _ = send_signal_thread(&thr, sig);
+                }
+            })))
+        }
+        _ => {
+            warn!("Unsupported sigev_notify {} for POSIX timers", notify);
+            Err(LinuxError::EINVAL)
+        }
+    }
+}
+
+pub fn sys_timer_create(
+    clock_id: __kernel_clockid_t,
+    sevp: UserConstPtr<sigevent>,
+    timer_id: UserPtr<__kernel_timer_t>,
+) -> LinuxResult<isize> {
+    debug!("sys_timer_create <= clock_id: {}", clock_id);
+    let clock = parse_timer_clock(clock_id)?;
+    let notify = make_timer_notify(sevp)?;
+    let timer_id = timer_id.get_as_mut()?;
+
+    let curr = current();
+    *timer_id = create_posix_timer(curr.task_ext().thread.process(), clock, notify);
+    Ok(0)
+}
+
+pub fn sys_timer_settime(
+    timer_id: __kernel_timer_t,
+    flags: u32,
+    new_value: UserConstPtr<itimerspec>,
+    old_value: UserPtr<itimerspec>,
+) -> LinuxResult<isize> {
+    let new_value = new_value.get_as_ref()?;
+    let interval = timespec_to_time_value(new_value.it_interval)?;
+    let mut value = timespec_to_time_value(new_value.it_value)?;
+    debug!(
+        "sys_timer_settime <= timer_id: {}, flags: {}, interval: {:?}, value: {:?}",
+        timer_id, flags, interval, value
+    );
+
+    let curr = current();
+    let proc = curr.task_ext().thread.process();
+    let mut absolute = flags & TIMER_ABSTIME != 0;
+    if absolute && !value.is_zero() {
+        let now = match posix_timer_clock(proc, timer_id)? {
+            PosixTimerClock::Realtime | PosixTimerClock::Monotonic => None,
+            PosixTimerClock::ProcessCpuTime => Some(process_cpu_time()),
+            PosixTimerClock::ThreadCpuTime(_) => Some(thread_cpu_time()),
+        };
+        // CPU-time timers take relative values, and an absolute time in the
+        // past expires immediately.
+        if let Some(now) = now {
+            value = value.saturating_sub(now).max(TimeValue::from_nanos(1));
+            absolute = false;
+        }
+    }
+    let old = set_posix_timer(proc, timer_id, interval, value, absolute)?;
+    if let Some(old_value) = nullable!(old_value.get_as_mut())? {
+        *old_value = itimerspec_from_time_values(old);
+    }
+    Ok(0)
+}
+
+pub fn sys_timer_gettime(
+    timer_id: __kernel_timer_t,
+    curr_value: UserPtr<itimerspec>,
+) -> LinuxResult<isize> {
+    let curr = current();
+    let value = get_posix_timer(curr.task_ext().thread.process(), timer_id)?;
+    *curr_value.get_as_mut()? = itimerspec_from_time_values(value);
+    Ok(0)
+}
+
+pub fn sys_timer_getoverrun(timer_id: __kernel_timer_t) -> LinuxResult<isize> {
+    let curr = current();
+    let overrun = posix_timer_overrun(curr.task_ext().thread.process(), timer_id)?;
+    Ok(overrun.min(i32::MAX as usize) as _)
+}
+
+pub fn sys_timer_delete(timer_id: __kernel_timer_t) -> LinuxResult<isize> {
+    debug!("sys_timer_delete <= timer_id: {}", timer_id);
+    let curr = current();
+    delete_posix_timer(curr.task_ext().thread.process(), timer_id)?;
+    Ok(0)
+}
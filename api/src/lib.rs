@@ -5,9 +5,14 @@
 extern crate axlog;
 extern crate alloc;
 
+pub mod coredump;
 pub mod file;
 pub mod path;
+pub mod perm;
 pub mod ptr;
+pub mod ptrace;
+pub mod regs;
+pub mod seccomp;
 pub mod signal;
 pub mod sockaddr;
 pub mod time;
@@ -6,10 +6,14 @@ use alloc::{
 };
 use axerrno::{AxError, AxResult, LinuxError, LinuxResult};
 use axfs::api::canonicalize;
+use axtask::{TaskExtRef, current};
 use linux_raw_sys::general::AT_FDCWD;
 use spin::RwLock;
 
-use crate::file::{Directory, File, FileLike};
+use crate::{
+    file::{Directory, File, FileLike, get_file_like},
+    perm::check_search,
+};
 
 /// 一个规范化的文件路径表示
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
@@ -290,11 +294,24 @@ impl HardlinkManager {
     }
 }
 
-pub fn handle_file_path(dirfd: c_int, path: &str) -> LinuxResult<FilePath> {
+/// Gets the path of the file or directory opened as `fd`.
+fn fd_path(fd: c_int) -> LinuxResult<String> {
+    let f = get_file_like(fd)?.into_any();
+    if let Some(file) = f.downcast_ref::<File>() {
+        Ok(file.path().into())
+    } else if let Some(dir) = f.downcast_ref::<Directory>() {
+        Ok(dir.path().into())
+    } else {
+        Err(LinuxError::EINVAL)
+    }
+}
+
+/// Resolves `path` like [`handle_file_path`], without checking permissions.
+pub fn join_file_path(dirfd: c_int, path: &str) -> LinuxResult<FilePath> {
     if path.starts_with('/') {
         Ok(FilePath::new(path)?)
     } else if path.is_empty() {
-        Ok(FilePath::new(File::from_fd(dirfd)?.path())?)
+        Ok(FilePath::new(fd_path(dirfd)?)?)
     } else {
         let base = if dirfd == AT_FDCWD {
             FilePath::new("")?
@@ -304,3 +321,17 @@ pub fn handle_file_path(dirfd: c_int, path: &str) -> LinuxResult<FilePath> {
         Ok(base.join(path)?)
     }
 }
+
+/// Resolves `path` relative to the directory opened as `dirfd`, or to the
+/// current directory if it is `AT_FDCWD`, or to the file `dirfd` itself if
+/// `path` is empty.
+///
+/// Fails with `EACCES` if the calling process may not search a directory on
+/// the way.
+pub fn handle_file_path(dirfd: c_int, path: &str) -> LinuxResult<FilePath> {
+    let file_path = join_file_path(dirfd, path)?;
+    if !path.is_empty() {
+        check_search(&file_path, &current().task_ext().process_data().cred())?;
+    }
+    Ok(file_path)
+}
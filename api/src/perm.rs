@@ -0,0 +1,206 @@
+//! File modes and ownership, and the permission checks of
+//! `path_resolution(7)`.
+//!
+//! The filesystems store no owners, and not all of them store modes, so the
+//! mode, owner and group of a file are kept here by its canonical path once
+//! set, like hard links in [`HARDLINK_MANAGER`]. The hard links of a file
+//! share the entry of their target. Other files have the mode reported by
+//! their filesystem, which is 0o755 on filesystems without modes such as FAT,
+//! and belong to root.
+
+use alloc::{collections::btree_map::BTreeMap, string::String};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::general::{
+    CAP_DAC_OVERRIDE, CAP_DAC_READ_SEARCH, CAP_FOWNER, R_OK, S_ISGID, S_ISVTX, W_OK, X_OK,
+};
+use spin::{Lazy, RwLock};
+use starry_core::cred::{Credentials, Gid, Uid};
+
+use crate::path::{FilePath, HARDLINK_MANAGER};
+
+/// The mode and ownership of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMeta {
+    /// The permission bits, with the set-user-ID, set-group-ID and sticky
+    /// bits
+    pub mode: u32,
+    /// The owner
+    pub uid: Uid,
+    /// The group
+    pub gid: Gid,
+    /// Whether the file is a directory
+    pub dir: bool,
+}
+
+impl FileMeta {
+    /// Checks whether a process with the credentials `cred` may access the
+    /// file as requested by `access`, a mask of `R_OK`, `W_OK` and `X_OK`.
+    ///
+    /// The permission bits of the owner apply to the owner, those of the
+    /// group to its members, and the others to everyone else.
+    /// `CAP_DAC_OVERRIDE` overrides them, except that a file must have an
+    /// execute bit to be executed, and `CAP_DAC_READ_SEARCH` allows reading
+    /// files and reading and searching directories.
+    pub fn check_access(&self, cred: &Credentials, access: u32) -> LinuxResult {
+        let granted = if cred.fsuid == self.uid {
+            self.mode >> 6
+        } else if cred.in_group(self.gid) {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        let overridden = if self.dir {
+            cred.has_cap(CAP_DAC_OVERRIDE)
+                || (access & W_OK == 0 && cred.has_cap(CAP_DAC_READ_SEARCH))
+        } else {
+            (cred.has_cap(CAP_DAC_OVERRIDE) && (access & X_OK == 0 || self.mode & 0o111 != 0))
+                || (access == R_OK && cred.has_cap(CAP_DAC_READ_SEARCH))
+        };
+        if granted & access == access || overridden {
+            Ok(())
+        } else {
+            Err(LinuxError::EACCES)
+        }
+    }
+}
+
+/// The mode, owner and group of files, by the canonical path of their
+/// target if they are hard links.
+type MetaTable = BTreeMap<String, (u32, Uid, Gid)>;
+
+static FILE_META: Lazy<RwLock<MetaTable>> = Lazy::new(|| {
+    let mut table = BTreeMap::new();
+    // Anyone may create files in /tmp.
+    table.insert("/tmp".into(), (0o777 | S_ISVTX, 0, 0));
+    RwLock::new(table)
+});
+
+fn trim(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+fn key(path: &str) -> String {
+    HARDLINK_MANAGER.real_path(trim(path))
+}
+
+/// Gets the mode and ownership of the file at the canonical `path`.
+pub fn file_meta(path: &str) -> LinuxResult<FileMeta> {
+    let metadata = axfs::api::metadata(path)?;
+    let dir = metadata.is_dir();
+    Ok(match FILE_META.read().get(&key(path)) {
+        Some(&(mode, uid, gid)) => FileMeta {
+            mode,
+            uid,
+            gid,
+            dir,
+        },
+        None => FileMeta {
+            mode: metadata.permissions().bits() as u32,
+            uid: 0,
+            gid: 0,
+            dir,
+        },
+    })
+}
+
+/// Sets the mode and ownership of the file at the canonical `path`.
+pub fn set_file_meta(path: &str, meta: &FileMeta) {
+    FILE_META
+        .write()
+        .insert(key(path), (meta.mode, meta.uid, meta.gid));
+}
+
+/// Forgets the mode and ownership of the removed file at `path`, so that they
+/// do not apply to another file created there.
+pub fn remove_file_meta(path: &str) {
+    FILE_META.write().remove(&key(path));
+}
+
+/// Checks whether a process with the credentials `cred` may search every
+/// directory on the way to `path`, which needs execute permission on them.
+pub fn check_search(path: &FilePath, cred: &Credentials) -> LinuxResult {
+    if cred.has_cap(CAP_DAC_OVERRIDE) || cred.has_cap(CAP_DAC_READ_SEARCH) {
+        return Ok(());
+    }
+    let path = trim(path.as_str());
+    for (end, _) in path.match_indices('/') {
+        // A missing directory is reported by the lookup itself.
+        let Ok(meta) = file_meta(&path[..=end]) else {
+            break;
+        };
+        if !meta.dir {
+            break;
+        }
+        meta.check_access(cred, X_OK)?;
+    }
+    Ok(())
+}
+
+/// Checks whether the calling process may create the file `path`, which
+/// needs write and search permission on its directory, and returns the
+/// metadata of the new file with `mode` restricted by the umask.
+///
+/// The file belongs to the filesystem user and group IDs of the process, or
+/// to the group of the directory if it has the set-group-ID bit, which new
+/// directories then inherit.
+pub fn check_create(path: &FilePath, mode: u32, dir: bool) -> LinuxResult<FileMeta> {
+    let curr = current();
+    let process_data = curr.task_ext().process_data();
+    let cred = process_data.cred();
+    let parent = check_parent(path, &cred)?;
+
+    let mut meta = FileMeta {
+        mode: mode & 0o7777 & !process_data.umask(),
+        uid: cred.fsuid,
+        gid: cred.fsgid,
+        dir,
+    };
+    if parent.mode & S_ISGID != 0 {
+        meta.gid = parent.gid;
+        if dir {
+            meta.mode |= S_ISGID;
+        }
+    }
+    Ok(meta)
+}
+
+/// Checks whether a process with the credentials `cred` may add or remove
+/// entries in the directory of `path`, which needs write and search
+/// permission on it, and returns the metadata of the directory.
+fn check_parent(path: &FilePath, cred: &Credentials) -> LinuxResult<FileMeta> {
+    let parent = file_meta(path.parent()?)?;
+    if !parent.dir {
+        return Err(LinuxError::ENOTDIR);
+    }
+    parent.check_access(cred, W_OK | X_OK)?;
+    Ok(parent)
+}
+
+/// Checks whether the calling process may create the hard link `path`, which
+/// needs write and search permission on its directory.
+pub fn check_link(path: &FilePath) -> LinuxResult {
+    check_parent(path, &current().task_ext().process_data().cred())?;
+    Ok(())
+}
+
+/// Checks whether the calling process may remove the file `path`, which
+/// needs write and search permission on its directory.
+///
+/// In a directory with the sticky bit, such as `/tmp`, only the owner of the
+/// file or of the directory, or a process with `CAP_FOWNER`, may remove it.
+pub fn check_remove(path: &FilePath) -> LinuxResult {
+    let cred = current().task_ext().process_data().cred();
+    let parent = check_parent(path, &cred)?;
+    if parent.mode & S_ISVTX != 0
+        && cred.fsuid != parent.uid
+        && cred.fsuid != file_meta(path)?.uid
+        && !cred.has_cap(CAP_FOWNER)
+    {
+        return Err(LinuxError::EPERM);
+    }
+    Ok(())
+}
@@ -0,0 +1,308 @@
+//! Stops of traced threads, see `ptrace(2)`.
+//!
+//! The tracing state lives in [`starry_core::ptrace`]. This module stops the
+//! current thread where its tracer asks it to, and tells the tracer about the
+//! stops, the threads it starts tracing by `clone` and their exits.
+
+use alloc::sync::Arc;
+
+use axhal::arch::TrapFrame;
+use axprocess::{Pid, Process, Thread};
+use axsignal::{SignalInfo, Signo};
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    general::{CAP_SYS_PTRACE, CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_TRAPPED, SI_USER},
+    ptrace::{PTRACE_EVENT_EXEC, PTRACE_EVENT_STOP, PTRACE_O_TRACEEXEC, PTRACE_O_TRACESYSGOOD},
+};
+use starry_core::{
+    ptrace::{Ptrace, ResumeMode, attach, detach_all, exit_traced},
+    task::{ProcessData, ThreadData},
+};
+
+use crate::signal::{send_signal_process, send_signal_thread, sigchld_info};
+
+/// The trap flag of `rflags`, which raises a debug exception after the next
+/// instruction.
+#[cfg(target_arch = "x86_64")]
+const RFLAGS_TF: u64 = 0x100;
+
+/// The software step bit of `SPSR_EL1`, which raises a software step
+/// exception after the next instruction, see `RESTORE_REGS` in axhal.
+#[cfg(target_arch = "aarch64")]
+const SPSR_SS: u64 = 1 << 21;
+
+/// Checks whether the calling process may trace the process with the data
+/// `target`, or access it as its tracer could, see [`may_trace_by`].
+pub(crate) fn may_trace(target: &ProcessData) -> bool {
+    may_trace_by(current().task_ext().process_data(), target)
+}
+
+/// Checks whether the process with the data `tracer` may trace the process
+/// with the data `target`: besides the credentials matching, the target must
+/// be dumpable unless the tracer has `CAP_SYS_PTRACE`.
+pub(crate) fn may_trace_by(tracer: &ProcessData, target: &ProcessData) -> bool {
+    let cred = tracer.cred();
+    cred.can_trace(&target.cred()) && (target.is_dumpable() || cred.has_cap(CAP_SYS_PTRACE))
+}
+
+/// Tells `tracer` about a state change of its tracee `thread` with `SIGCHLD`,
+/// and wakes it up if it is waiting for the change.
+fn notify_tracer(tracer: &Process, thread: &Thread, code: u32, status: i32) {
+    let sig = sigchld_info(thread.process(), thread.tid(), code, status);
+    let _ = send_signal_process(tracer, sig);
+    if let Some(data) = tracer.data::<ProcessData>() {
+        data.child_exit_wq.notify_all(false);
+    }
+}
+
+/// Enables or disables single-stepping of the current thread once it returns
+/// to user space.
+///
+/// riscv64 and loongarch64 have no single-stepping here, and
+/// `PTRACE_SINGLESTEP` fails with `EIO` on them. The RISC-V privileged
+/// architecture has no step bit for user mode, and its debug triggers are not
+/// available to the kernel, as on Linux. LoongArch has none either, and Linux
+/// emulates it with a hardware breakpoint at the next instruction, which
+/// axhal does not program.
+fn set_single_step(tf: &mut TrapFrame, enabled: bool) {
+    #[cfg(target_arch = "x86_64")]
+    if enabled {
+        tf.rflags |= RFLAGS_TF;
+    } else {
+        tf.rflags &= !RFLAGS_TF;
+    }
+    #[cfg(target_arch = "aarch64")]
+    if enabled {
+        tf.spsr |= SPSR_SS;
+    } else {
+        tf.spsr &= !SPSR_SS;
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = (tf, enabled);
+}
+
+/// Keeps single-stepping the current thread, if its tracer asked for it, when
+/// it returns to user space from a trap.
+///
+/// On aarch64, an exception taken from the stepped instruction, such as a
+/// syscall, clears the step bit of `SPSR_EL1`. The trap flag of x86_64 stays
+/// set in `rflags`.
+#[cfg(target_arch = "aarch64")]
+pub fn ptrace_keep_stepping(tf: &mut TrapFrame) {
+    let curr = current();
+    let stepping = curr.task_ext().thread_data().ptrace.lock().mode == ResumeMode::SingleStep;
+    set_single_step(tf, stepping);
+}
+
+/// Stops the current thread for its tracer, with the stop signal and event
+/// `code`.
+///
+/// Returns the signal the tracer delivers on resume, if any, or `None` if the
+/// thread is not traced or is killed while stopped.
+fn ptrace_stop(tf: &mut TrapFrame, code: i32, siginfo: SignalInfo) -> Option<Option<Signo>> {
+    let curr = current();
+    let thread = &curr.task_ext().thread;
+    let state = &curr.task_ext().thread_data().ptrace;
+    let sig = state.stop(tf, code, siginfo, |tracer| {
+        notify_tracer(tracer, thread, CLD_TRAPPED, code)
+    })?;
+    set_single_step(tf, state.lock().mode == ResumeMode::SingleStep);
+    Some(sig)
+}
+
+/// Stops the current thread for its tracer with `SIGTRAP`, on a syscall or an
+/// event. A signal the tracer delivers on resume is sent to the thread.
+fn ptrace_trap(tf: &mut TrapFrame, code: i32) {
+    let siginfo = SignalInfo::new(Signo::SIGTRAP, code);
+    if let Some(Some(signo)) = ptrace_stop(tf, code, siginfo) {
+        let curr = current();
+        let _ = send_signal_thread(
+            &curr.task_ext().thread,
+            SignalInfo::new(signo, SI_USER as _),
+        );
+    }
+}
+
+/// The stop signal of syscall stops, which tells them from other `SIGTRAP`s
+/// with `PTRACE_O_TRACESYSGOOD`.
+fn syscall_stop_code(ptrace: &Ptrace) -> i32 {
+    if ptrace.options & PTRACE_O_TRACESYSGOOD != 0 {
+        Signo::SIGTRAP as i32 | 0x80
+    } else {
+        Signo::SIGTRAP as i32
+    }
+}
+
+/// Stops the current thread in a signal-delivery-stop before `sig` is
+/// delivered, if it is traced.
+///
+/// Returns the signal to deliver instead, which the tracer chooses on resume,
+/// or `None` to discard it.
+pub fn ptrace_signal(tf: &mut TrapFrame, sig: SignalInfo) -> Option<SignalInfo> {
+    let curr = current();
+    let state = &curr.task_ext().thread_data().ptrace;
+    if state.tracer().is_none() {
+        return Some(sig);
+    }
+    let signo = ptrace_stop(tf, sig.signo() as _, sig)??;
+    // The signal information may have been changed by `PTRACE_SETSIGINFO`.
+    let siginfo = state.lock().siginfo.clone();
+    Some(
+        siginfo
+            .filter(|it| it.signo() == signo)
+            .unwrap_or_else(|| SignalInfo::new(signo, SI_USER as _)),
+    )
+}
+
+/// Stops the current thread in a `PTRACE_EVENT_STOP` if its tracer asked for
+/// it by `PTRACE_INTERRUPT`.
+pub fn ptrace_interrupt(tf: &mut TrapFrame) {
+    let curr = current();
+    let interrupt = core::mem::take(&mut curr.task_ext().thread_data().ptrace.lock().interrupt);
+    if interrupt {
+        ptrace_trap(tf, Signo::SIGTRAP as i32 | (PTRACE_EVENT_STOP << 8) as i32);
+    }
+}
+
+/// Stops the current thread at the entry of the syscall `sysno`, if its
+/// tracer traces syscalls.
+///
+/// Returns the number of the syscall to run, which the tracer may have
+/// changed, or `None` if the syscall is to be skipped, with the return value
+/// the tracer set in `tf`.
+pub fn ptrace_syscall_entry(tf: &mut TrapFrame, sysno: usize) -> Option<usize> {
+    let curr = current();
+    let thr_data = curr.task_ext().thread_data();
+    let mut ptrace = thr_data.ptrace.lock();
+    if ptrace.tracer().is_none() {
+        return Some(sysno);
+    }
+    ptrace.syscall_nr = Some(sysno);
+    if ptrace.mode != ResumeMode::Syscall {
+        return Some(sysno);
+    }
+    let code = syscall_stop_code(&ptrace);
+    drop(ptrace);
+
+    // The return value reads as `ENOSYS` until the syscall returns, and
+    // the tracer finds the number in `orig_rax`.
+    #[cfg(target_arch = "x86_64")]
+    {
+        tf.rax = -(axerrno::LinuxError::ENOSYS.code() as i64) as u64;
+    }
+    ptrace_trap(tf, code);
+    if thr_data.signal.pending().has(Signo::SIGKILL) {
+        return None;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    let sysno = thr_data.ptrace.lock().syscall_nr?;
+    #[cfg(target_arch = "aarch64")]
+    let sysno = tf.r[8] as usize;
+    #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+    let sysno = tf.regs.a7;
+    // A negative number such as -1 skips the syscall.
+    (sysno as isize >= 0).then_some(sysno)
+}
+
+/// Stops the current thread at the exit of a syscall: for an event of the
+/// syscall its tracer asked for, and if its tracer traces syscalls.
+///
+/// The return value of the syscall is in `tf`, where the tracer may change it.
+pub fn ptrace_syscall_exit(tf: &mut TrapFrame) {
+    let curr = current();
+    let state = &curr.task_ext().thread_data().ptrace;
+    let mut ptrace = state.lock();
+    if ptrace.tracer().is_none() {
+        return;
+    }
+    let event = ptrace.pending_event.take();
+    if let Some((_, msg)) = event {
+        ptrace.event_msg = msg;
+    }
+    drop(ptrace);
+
+    if let Some((event, _)) = event {
+        ptrace_trap(tf, Signo::SIGTRAP as i32 | (event << 8) as i32);
+    }
+    let ptrace = state.lock();
+    if ptrace.mode == ResumeMode::Syscall && ptrace.tracer().is_some() {
+        let code = syscall_stop_code(&ptrace);
+        drop(ptrace);
+        ptrace_trap(tf, code);
+    }
+    state.lock().syscall_nr = None;
+}
+
+/// Tells the tracer of the current thread that it executed a new program,
+/// which formerly ran as the thread `old_tid`.
+///
+/// With `PTRACE_O_TRACEEXEC`, the thread stops in a `PTRACE_EVENT_EXEC` at
+/// the exit of `execve`. Otherwise, unless attached by `PTRACE_SEIZE`, it is
+/// sent a `SIGTRAP`.
+pub fn ptrace_exec(old_tid: Pid) {
+    let curr = current();
+    let mut ptrace = curr.task_ext().thread_data().ptrace.lock();
+    if ptrace.tracer().is_none() {
+        return;
+    }
+    if ptrace.options & PTRACE_O_TRACEEXEC != 0 {
+        ptrace.pending_event = Some((PTRACE_EVENT_EXEC, old_tid as _));
+    } else if !ptrace.seized {
+        drop(ptrace);
+        let sig = SignalInfo::new(Signo::SIGTRAP, SI_USER as _);
+        let _ = send_signal_thread(&curr.task_ext().thread, sig);
+    }
+}
+
+/// Makes the tracer of the current thread trace `child` too, a thread it
+/// creates in a `clone` reported as `event` (`PTRACE_EVENT_FORK`, `VFORK` or
+/// `CLONE`).
+///
+/// The child is traced if the tracer set the option for the event, in which
+/// case the current thread stops for the event at the exit of `clone`, or if
+/// `forced` by `CLONE_PTRACE`. It starts with a `SIGSTOP`, or a
+/// `PTRACE_EVENT_STOP` if the tracer attached by `PTRACE_SEIZE`.
+pub fn ptrace_clone(child: &Arc<Thread>, event: u32, forced: bool) {
+    let curr = current();
+    let mut ptrace = curr.task_ext().thread_data().ptrace.lock();
+    let Some(tracer) = ptrace.tracer() else {
+        return;
+    };
+    let wanted = ptrace.options & (1 << event) != 0;
+    if !wanted && !forced {
+        return;
+    }
+    if wanted {
+        ptrace.pending_event = Some((event, child.tid() as _));
+    }
+    let (seized, options) = (ptrace.seized, ptrace.options);
+    drop(ptrace);
+
+    if !attach(&tracer, child, seized, options) {
+        return;
+    }
+    if seized {
+        child.data::<ThreadData>().unwrap().ptrace.lock().interrupt = true;
+    } else {
+        let _ = send_signal_thread(child, SignalInfo::new(Signo::SIGSTOP, SI_USER as _));
+    }
+}
+
+/// Tells the tracer of an exiting `thread` about its exit with the wait
+/// status `status`, and detaches the threads traced by its process if it is
+/// the `last` thread.
+pub fn ptrace_exit(thread: &Thread, status: i32, last: bool) {
+    if last {
+        detach_all(thread.process());
+    }
+    let Some(tracer) = exit_traced(thread, status, last) else {
+        return;
+    };
+    let (code, status) = match status & 0x7f {
+        0 => (CLD_EXITED, status >> 8),
+        signo if status & 0x80 != 0 => (CLD_DUMPED, signo),
+        signo => (CLD_KILLED, signo),
+    };
+    notify_tracer(&tracer, thread, code, status);
+}
@@ -0,0 +1,236 @@
+//! The user registers of a thread in the layouts of the Linux ABI, as found in
+//! core dumps and exchanged with tracers by `ptrace(2)`.
+
+use axerrno::LinuxResult;
+use axhal::arch::TrapFrame;
+use starry_core::ptrace::FpState;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        use axerrno::LinuxError;
+        use axhal::arch::FxsaveArea;
+
+        /// The general registers of a thread, `struct user_regs_struct`.
+        pub type UserRegs = [u64; 27];
+
+        /// The index of `orig_rax` in [`UserRegs`], the number of the
+        /// current syscall.
+        pub const ORIG_RAX: usize = 15;
+
+        /// The bits of `rflags` that user code may change.
+        const FLAG_MASK: u64 = 0x54dd5;
+
+        /// Gets the general registers of a thread from its trap frame.
+        pub fn user_regs(tf: &TrapFrame) -> UserRegs {
+            [
+                tf.r15, tf.r14, tf.r13, tf.r12, tf.rbp, tf.rbx, tf.r11, tf.r10, tf.r9, tf.r8,
+                tf.rax, tf.rcx, tf.rdx, tf.rsi, tf.rdi,
+                // orig_rax: not in a syscall
+                u64::MAX,
+                tf.rip, tf.cs, tf.rflags, tf.rsp, tf.ss, tf.fs_base,
+                // gs_base, ds, es, fs, gs
+                0, 0, 0, 0, 0,
+            ]
+        }
+
+        /// Sets the general registers of a thread in its trap frame.
+        ///
+        /// The segment registers cannot be changed, nor the system flags of
+        /// `rflags`.
+        pub fn set_user_regs(tf: &mut TrapFrame, regs: &UserRegs) -> LinuxResult {
+            let fs_base = regs[21];
+            if fs_base >= 1 << 47 {
+                return Err(LinuxError::EIO);
+            }
+            [
+                tf.r15, tf.r14, tf.r13, tf.r12, tf.rbp, tf.rbx, tf.r11, tf.r10, tf.r9, tf.r8,
+                tf.rax, tf.rcx, tf.rdx, tf.rsi, tf.rdi, _, tf.rip, _, _, tf.rsp, ..
+            ] = *regs;
+            tf.rflags = (tf.rflags & !FLAG_MASK) | (regs[18] & FLAG_MASK);
+            tf.fs_base = fs_base;
+            Ok(())
+        }
+
+        /// The FP/SIMD registers of a thread, `struct user_fpregs_struct`.
+        pub type UserFpRegs = FxsaveArea;
+
+        /// Gets the FP/SIMD registers of a thread.
+        pub fn fp_regs(fp: &FpState) -> UserFpRegs {
+            fp.fxsave_area.clone()
+        }
+
+        /// Sets the FP/SIMD registers of a thread.
+        pub fn set_fp_regs(fp: &mut FpState, regs: &UserFpRegs) {
+            fp.fxsave_area = regs.clone();
+            // The x87 and SSE states are no longer in their initial
+            // configuration.
+            fp.xsave_header[0] |= 0b11;
+            fp.sanitize();
+        }
+    } else if #[cfg(target_arch = "aarch64")] {
+        /// The general registers of a thread, `struct user_pt_regs`.
+        pub type UserRegs = [u64; 34];
+
+        /// The condition flags of `pstate`, the only bits user code may
+        /// change.
+        const PSTATE_NZCV: u64 = 0xf000_0000;
+
+        /// Gets the general registers of a thread from its trap frame.
+        pub fn user_regs(tf: &TrapFrame) -> UserRegs {
+            let mut regs = [0; 34];
+            regs[..31].copy_from_slice(&tf.r);
+            regs[31] = tf.usp;
+            regs[32] = tf.elr;
+            regs[33] = tf.spsr;
+            regs
+        }
+
+        /// Sets the general registers of a thread in its trap frame.
+        ///
+        /// Only the condition flags of `pstate` can be changed.
+        pub fn set_user_regs(tf: &mut TrapFrame, regs: &UserRegs) -> LinuxResult {
+            tf.r.copy_from_slice(&regs[..31]);
+            tf.usp = regs[31];
+            tf.elr = regs[32];
+            tf.spsr = (tf.spsr & !PSTATE_NZCV) | (regs[33] & PSTATE_NZCV);
+            Ok(())
+        }
+
+        /// The FP/SIMD registers of a thread, `struct user_fpsimd_state`.
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct UserFpRegs {
+            vregs: [u128; 32],
+            fpsr: u32,
+            fpcr: u32,
+            _reserved: [u32; 2],
+        }
+
+        /// Gets the FP/SIMD registers of a thread.
+        pub fn fp_regs(fp: &FpState) -> UserFpRegs {
+            UserFpRegs {
+                vregs: fp.regs,
+                fpsr: fp.fpsr,
+                fpcr: fp.fpcr,
+                _reserved: [0; 2],
+            }
+        }
+
+        /// Sets the FP/SIMD registers of a thread.
+        pub fn set_fp_regs(fp: &mut FpState, regs: &UserFpRegs) {
+            fp.regs = regs.vregs;
+            fp.fpsr = regs.fpsr;
+            fp.fpcr = regs.fpcr;
+        }
+    } else if #[cfg(target_arch = "riscv64")] {
+        /// The general registers of a thread, `struct user_regs_struct`.
+        pub type UserRegs = [usize; 32];
+
+        /// Gets the general registers of a thread from its trap frame.
+        pub fn user_regs(tf: &TrapFrame) -> UserRegs {
+            let r = &tf.regs;
+            [
+                tf.sepc, r.ra, r.sp, r.gp, r.tp, r.t0, r.t1, r.t2, r.s0, r.s1, r.a0, r.a1, r.a2,
+                r.a3, r.a4, r.a5, r.a6, r.a7, r.s2, r.s3, r.s4, r.s5, r.s6, r.s7, r.s8, r.s9,
+                r.s10, r.s11, r.t3, r.t4, r.t5, r.t6,
+            ]
+        }
+
+        /// Sets the general registers of a thread in its trap frame.
+        pub fn set_user_regs(tf: &mut TrapFrame, regs: &UserRegs) -> LinuxResult {
+            let r = &mut tf.regs;
+            [
+                tf.sepc, r.ra, r.sp, r.gp, r.tp, r.t0, r.t1, r.t2, r.s0, r.s1, r.a0, r.a1, r.a2,
+                r.a3, r.a4, r.a5, r.a6, r.a7, r.s2, r.s3, r.s4, r.s5, r.s6, r.s7, r.s8, r.s9,
+                r.s10, r.s11, r.t3, r.t4, r.t5, r.t6,
+            ] = *regs;
+            Ok(())
+        }
+
+        /// The floating-point registers of a thread, `struct
+        /// __riscv_d_ext_state`.
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct UserFpRegs {
+            f: [u64; 32],
+            fcsr: u32,
+            _pad: u32,
+        }
+
+        /// Gets the floating-point registers of a thread.
+        pub fn fp_regs(fp: &FpState) -> UserFpRegs {
+            UserFpRegs {
+                f: fp.fp,
+                fcsr: fp.fcsr as _,
+                _pad: 0,
+            }
+        }
+
+        /// Sets the floating-point registers of a thread.
+        pub fn set_fp_regs(fp: &mut FpState, regs: &UserFpRegs) {
+            fp.fp = regs.f;
+            fp.fcsr = regs.fcsr as _;
+        }
+    } else if #[cfg(target_arch = "loongarch64")] {
+        /// The general registers of a thread, `struct user_pt_regs`.
+        pub type UserRegs = [usize; 45];
+
+        /// Gets the general registers of a thread from its trap frame.
+        pub fn user_regs(tf: &TrapFrame) -> UserRegs {
+            let r = &tf.regs;
+            let mut regs = [0; 45];
+            regs[..32].copy_from_slice(&[
+                r.zero, r.ra, r.tp, r.sp, r.a0, r.a1, r.a2, r.a3, r.a4, r.a5, r.a6, r.a7, r.t0,
+                r.t1, r.t2, r.t3, r.t4, r.t5, r.t6, r.t7, r.t8, r.u0, r.fp, r.s0, r.s1, r.s2,
+                r.s3, r.s4, r.s5, r.s6, r.s7, r.s8,
+            ]);
+            // orig_a0, csr_era
+            regs[32] = r.a0;
+            regs[33] = tf.era;
+            regs
+        }
+
+        /// Sets the general registers of a thread in its trap frame.
+        ///
+        /// The zero register, `orig_a0` and the CSRs other than `era` cannot
+        /// be changed.
+        pub fn set_user_regs(tf: &mut TrapFrame, regs: &UserRegs) -> LinuxResult {
+            let r = &mut tf.regs;
+            let gprs: [usize; 32] = regs[..32].try_into().unwrap();
+            [
+                _, r.ra, r.tp, r.sp, r.a0, r.a1, r.a2, r.a3, r.a4, r.a5, r.a6, r.a7, r.t0, r.t1,
+                r.t2, r.t3, r.t4, r.t5, r.t6, r.t7, r.t8, r.u0, r.fp, r.s0, r.s1, r.s2, r.s3,
+                r.s4, r.s5, r.s6, r.s7, r.s8,
+            ] = gprs;
+            tf.era = regs[33];
+            Ok(())
+        }
+
+        /// The floating-point registers of a thread, `struct user_fp_state`.
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct UserFpRegs {
+            fpr: [u64; 32],
+            fcc: u64,
+            fcsr: u32,
+            _pad: u32,
+        }
+
+        /// Gets the floating-point registers of a thread.
+        pub fn fp_regs(fp: &FpState) -> UserFpRegs {
+            UserFpRegs {
+                fpr: fp.fp,
+                fcc: u64::from_le_bytes(fp.fcc),
+                fcsr: fp.fcsr as _,
+                _pad: 0,
+            }
+        }
+
+        /// Sets the floating-point registers of a thread.
+        pub fn set_fp_regs(fp: &mut FpState, regs: &UserFpRegs) {
+            fp.fp = regs.fpr;
+            fp.fcc = regs.fcc.to_le_bytes();
+            fp.fcsr = regs.fcsr as _;
+        }
+    }
+}
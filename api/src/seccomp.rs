@@ -0,0 +1,135 @@
+//! Checks of syscalls by seccomp, see `seccomp(2)`.
+//!
+//! The seccomp state lives in [`starry_core::seccomp`]. This module checks
+//! each syscall of the current thread against it before the syscall runs, and
+//! takes the action it decides on.
+
+use axhal::arch::TrapFrame;
+use axsignal::{SignalInfo, Signo};
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    general::{
+        __NR_exit, __NR_read, __NR_rt_sigreturn, __NR_write, __sifields__bindgen_ty_7, SYS_SECCOMP,
+    },
+    ptrace::{
+        SECCOMP_RET_ACTION_FULL, SECCOMP_RET_ALLOW, SECCOMP_RET_DATA, SECCOMP_RET_ERRNO,
+        SECCOMP_RET_KILL_THREAD, SECCOMP_RET_LOG, SECCOMP_RET_TRACE, SECCOMP_RET_TRAP,
+        SECCOMP_RET_USER_NOTIF, seccomp_data,
+    },
+};
+use starry_core::seccomp::SeccompMode;
+
+use crate::{
+    do_exit,
+    signal::{dump_core_and_exit, force_signal},
+};
+
+/// The syscalls allowed in strict mode.
+const STRICT_SYSCALLS: [u32; 4] = [__NR_read, __NR_write, __NR_exit, __NR_rt_sigreturn];
+
+/// The largest error number a syscall may return.
+const MAX_ERRNO: u32 = 4095;
+
+/// The architecture of syscalls, `AUDIT_ARCH_*`.
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = linux_raw_sys::ptrace::AUDIT_ARCH_X86_64;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = linux_raw_sys::ptrace::AUDIT_ARCH_AARCH64;
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH: u32 = linux_raw_sys::ptrace::AUDIT_ARCH_RISCV64;
+#[cfg(target_arch = "loongarch64")]
+const AUDIT_ARCH: u32 = linux_raw_sys::ptrace::AUDIT_ARCH_LOONGARCH64;
+
+/// Builds the `SIGSYS` sent for the syscall `data` by `SECCOMP_RET_TRAP`, or
+/// reported in the core dump of `SECCOMP_RET_KILL_*`, with the error number
+/// `errno`.
+fn sigsys_info(data: &seccomp_data, errno: u32) -> SignalInfo {
+    let mut sig = SignalInfo::new(Signo::SIGSYS, SYS_SECCOMP as _);
+    sig.0.__bindgen_anon_1.__bindgen_anon_1.si_errno = errno as _;
+    sig.0.__bindgen_anon_1.__bindgen_anon_1._sifields._sigsys = __sifields__bindgen_ty_7 {
+        _call_addr: data.instruction_pointer as _,
+        _syscall: data.nr,
+        _arch: data.arch,
+    };
+    sig
+}
+
+/// Checks the syscall `sysno` of the current thread against its seccomp mode,
+/// before it runs.
+///
+/// Returns whether the syscall is to run. If not, its return value is left in
+/// `tf`. The thread does not return if it is killed.
+pub fn seccomp_syscall_entry(tf: &mut TrapFrame, sysno: usize) -> bool {
+    let curr = current();
+    let seccomp = curr.task_ext().thread_data().seccomp.lock().clone();
+    let data = match seccomp.mode() {
+        SeccompMode::Disabled => return true,
+        SeccompMode::Strict => {
+            if STRICT_SYSCALLS.contains(&(sysno as u32)) {
+                return true;
+            }
+            warn!(
+                "seccomp: {:?} killed by syscall {} in strict mode",
+                curr.task_ext().thread,
+                sysno
+            );
+            do_exit(Signo::SIGKILL as i32, false);
+        }
+        SeccompMode::Filter => seccomp_data {
+            nr: sysno as _,
+            arch: AUDIT_ARCH,
+            instruction_pointer: tf.ip() as _,
+            args: [
+                tf.arg0() as _,
+                tf.arg1() as _,
+                tf.arg2() as _,
+                tf.arg3() as _,
+                tf.arg4() as _,
+                tf.arg5() as _,
+            ],
+        },
+    };
+
+    let (ret, log) = seccomp.run_filters(&data);
+    let action = ret & SECCOMP_RET_ACTION_FULL;
+    if action == SECCOMP_RET_ALLOW {
+        return true;
+    }
+    // Kills are always logged, and other actions if the filter asks to.
+    let logged = match action {
+        SECCOMP_RET_ERRNO | SECCOMP_RET_TRAP | SECCOMP_RET_TRACE | SECCOMP_RET_USER_NOTIF => log,
+        _ => true,
+    };
+    if logged {
+        warn!(
+            "seccomp: {:?} syscall {} returned {:#x}",
+            curr.task_ext().thread,
+            sysno,
+            ret
+        );
+    }
+
+    let errno = ret & SECCOMP_RET_DATA;
+    match action {
+        SECCOMP_RET_LOG => true,
+        SECCOMP_RET_ERRNO => {
+            tf.set_retval(-(errno.min(MAX_ERRNO) as isize) as usize);
+            false
+        }
+        SECCOMP_RET_TRAP => {
+            force_signal(sigsys_info(&data, errno));
+            false
+        }
+        // There is no supervisor to notify nor tracer to stop for.
+        SECCOMP_RET_TRACE | SECCOMP_RET_USER_NOTIF => {
+            tf.set_retval(-(axerrno::LinuxError::ENOSYS.code() as isize) as usize);
+            false
+        }
+        // Killing the last thread kills the process.
+        SECCOMP_RET_KILL_THREAD if curr.task_ext().thread.process().threads().len() > 1 => {
+            do_exit(Signo::SIGSYS as i32, false)
+        }
+        // `SECCOMP_RET_KILL_PROCESS`, and unknown actions
+        _ => dump_core_and_exit(&sigsys_info(&data, 0), tf),
+    }
+}
@@ -1,46 +1,214 @@
 use axerrno::{LinuxError, LinuxResult};
 use axhal::{
     arch::TrapFrame,
-    trap::{POST_TRAP, register_trap_handler},
+    mem::VirtAddr,
+    trap::{POST_TRAP, USER_EXCEPTION, UserException, register_trap_handler},
+};
+use axprocess::{Pid, Process, ProcessGroup, Thread};
+use axsignal::{
+    DefaultSignalAction, SignalAction, SignalActionFlags, SignalDisposition, SignalInfo,
+    SignalOSAction, SignalSet, Signo,
 };
-use axprocess::{Process, ProcessGroup, Thread};
-use axsignal::{SignalInfo, SignalOSAction, SignalSet};
 use axtask::{TaskExtRef, current};
-use starry_core::task::{ProcessData, ThreadData};
+use linux_raw_sys::general::{
+    __sifields__bindgen_ty_4, BUS_ADRALN, CLD_CONTINUED, CLD_DUMPED, CLD_EXITED, CLD_KILLED,
+    CLD_STOPPED, FPE_FLTINV, FPE_INTDIV, ILL_ILLOPC, SEGV_ACCERR, SI_KERNEL, TRAP_BRKPT,
+    TRAP_TRACE,
+};
+use starry_core::{
+    ptrace::save_fp_state,
+    signal::{
+        JobEvent, SendSignalIf, continue_process, interrupt_process, interrupt_thread,
+        should_restart_syscall, stop_process, wait_while_stopped,
+    },
+    task::{ProcessData, ThreadData},
+};
+
+use crate::{
+    coredump::dump_core,
+    do_exit,
+    ptrace::{ptrace_interrupt, ptrace_signal},
+};
 
-use crate::do_exit;
+/// The flag of the wait status of a process killed by a signal that tells it
+/// dumped core, see `WCOREDUMP`.
+const WCOREFLAG: i32 = 0x80;
 
 pub fn check_signals(tf: &mut TrapFrame, restore_blocked: Option<SignalSet>) -> bool {
-    let Some((sig, os_action)) = current()
-        .task_ext()
-        .thread_data()
-        .signal
-        .check_signals(tf, restore_blocked)
-    else {
-        return false;
-    };
+    let curr = current();
+    loop {
+        wait_while_stopped();
+        ptrace_interrupt(tf);
 
-    let signo = sig.signo();
-    match os_action {
-        SignalOSAction::Terminate => {
-            do_exit(128 + signo as i32, true);
-        }
-        SignalOSAction::CoreDump => {
-            // TODO: implement core dump
-            do_exit(128 + signo as i32, true);
-        }
-        SignalOSAction::Stop => {
-            // TODO: implement stop
-            do_exit(1, true);
+        let Some((sig, os_action)) = curr.task_ext().thread_data().signal.check_signals_with(
+            tf,
+            restore_blocked,
+            ptrace_signal,
+        ) else {
+            return false;
+        };
+
+        let signo = sig.signo();
+        let process_data = curr.task_ext().process_data();
+        match os_action {
+            SignalOSAction::Terminate if process_data.is_dumping_core() => {
+                // Killed by the thread dumping the core, which exits last and
+                // dumps the registers saved here
+                *curr.task_ext().thread_data().core_fp.lock() = Some(save_fp_state());
+                do_exit(signo as i32, false);
+            }
+            SignalOSAction::Terminate if process_data.is_group_exec() => {
+                // Killed by the thread executing a program, which carries on
+                // alone
+                do_exit(signo as i32, false);
+            }
+            SignalOSAction::Terminate => {
+                do_exit(signo as i32, true);
+            }
+            SignalOSAction::CoreDump => dump_core_and_exit(&sig, tf),
+            SignalOSAction::Stop => {
+                let process = curr.task_ext().thread.process();
+                if stop_process(process, signo) {
+                    notify_job_event(process, JobEvent::Stopped(signo));
+                }
+                // Parked by `wait_while_stopped` above until continued
+            }
+            SignalOSAction::Continue => {
+                // The process has been continued when the signal was sent
+            }
+            SignalOSAction::Handler => return true,
         }
-        SignalOSAction::Continue => {
-            // TODO: implement continue
+    }
+}
+
+/// Terminates the current process by the signal `sig` after dumping its core.
+pub(crate) fn dump_core_and_exit(sig: &SignalInfo, tf: &TrapFrame) -> ! {
+    let signo = sig.signo();
+    let curr = current();
+    if !curr.task_ext().process_data().start_core_dump() {
+        // Another thread is dumping the core, and kills this one
+        *curr.task_ext().thread_data().core_fp.lock() = Some(save_fp_state());
+        do_exit(signo as i32, false);
+    }
+    let status = if dump_core(sig, tf) {
+        signo as i32 | WCOREFLAG
+    } else {
+        signo as i32
+    };
+    do_exit(status, true)
+}
+
+/// Notifies the parent of a process of a job-control state change with
+/// `SIGCHLD`, unless it set `SA_NOCLDSTOP`, and wakes it up if it is waiting
+/// for the change.
+fn notify_job_event(proc: &Process, event: JobEvent) {
+    let Some(parent) = proc.parent() else {
+        return;
+    };
+    let Some(data) = parent.data::<ProcessData>() else {
+        return;
+    };
+    let (code, status) = match event {
+        JobEvent::Stopped(signo) => (CLD_STOPPED, signo),
+        JobEvent::Continued => (CLD_CONTINUED, Signo::SIGCONT),
+    };
+    let nocldstop = data.signal.actions().lock()[Signo::SIGCHLD]
+        .flags
+        .contains(SignalActionFlags::NOCLDSTOP);
+    if !nocldstop {
+        let _ = send_signal_process(&parent, sigchld_info(proc, proc.pid(), code, status as _));
+    }
+    data.child_exit_wq.notify_all(false);
+}
+
+/// Builds the `SIGCHLD` telling the parent or tracer of `pid`, a thread of
+/// `proc`, about a state change, with the code `code` (`CLD_*`) and the status
+/// `status`.
+pub(crate) fn sigchld_info(proc: &Process, pid: Pid, code: u32, status: i32) -> SignalInfo {
+    let uid = proc.data::<ProcessData>().map_or(0, |data| data.cred().uid);
+    let mut sig = SignalInfo::new(Signo::SIGCHLD, code as _);
+    sig.0.__bindgen_anon_1.__bindgen_anon_1._sifields._sigchld = __sifields__bindgen_ty_4 {
+        _pid: pid as _,
+        _uid: uid,
+        _status: status,
+        _utime: 0,
+        _stime: 0,
+    };
+    sig
+}
+
+/// Decodes a wait status into the `si_code` (`CLD_*`) and `si_status` of the
+/// `SIGCHLD` or `waitid` report of the state change.
+pub(crate) fn decode_wait_status(status: i32) -> (u32, i32) {
+    if status == 0xffff {
+        (CLD_CONTINUED, Signo::SIGCONT as _)
+    } else if status & 0xff == 0x7f {
+        (CLD_STOPPED, status >> 8)
+    } else if status & 0x7f == 0 {
+        (CLD_EXITED, (status >> 8) & 0xff)
+    } else if status & WCOREFLAG != 0 {
+        (CLD_DUMPED, status & 0x7f)
+    } else {
+        (CLD_KILLED, status & 0x7f)
+    }
+}
+
+/// Takes the actions of a signal that happen when it is sent rather than when
+/// it is delivered: `SIGCONT` continues a stopped process and discards the
+/// pending stop signals, and a stop signal discards a pending `SIGCONT`.
+fn prepare_signal(proc: &Process, signo: Signo) {
+    let mut flushed = SignalSet::default();
+    if signo == Signo::SIGCONT {
+        for stop in [
+            Signo::SIGSTOP,
+            Signo::SIGTSTP,
+            Signo::SIGTTIN,
+            Signo::SIGTTOU,
+        ] {
+            flushed.add(stop);
         }
-        SignalOSAction::Handler => {
-            // do nothing
+    } else if matches!(signo.default_action(), DefaultSignalAction::Stop) {
+        flushed.add(Signo::SIGCONT);
+    } else {
+        return;
+    }
+
+    if let Some(data) = proc.data::<ProcessData>() {
+        data.signal.flush_signals(&flushed);
+    }
+    for thr in proc.threads() {
+        if let Some(data) = thr.data::<ThreadData>() {
+            data.signal.flush_signals(&flushed);
         }
     }
-    true
+
+    if signo == Signo::SIGCONT && continue_process(proc) {
+        notify_job_event(proc, JobEvent::Continued);
+    }
+}
+
+/// Handles a syscall that failed with `ERESTART` after being interrupted by a
+/// signal, returning the value to put in the return value register.
+///
+/// The syscall fails with `EINTR` if a pending signal runs a handler installed
+/// without `SA_RESTART`. Otherwise the program counter is moved back to the
+/// syscall instruction, so that the syscall is restarted once the signals are
+/// handled.
+pub fn restart_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
+    if !should_restart_syscall() {
+        return -LinuxError::EINTR.code() as _;
+    }
+
+    // `syscall` on x86_64, `svc`/`ecall`/`syscall` on other architectures
+    const SYSCALL_INSN_LEN: usize = if cfg!(target_arch = "x86_64") { 2 } else { 4 };
+    tf.set_ip(tf.ip() - SYSCALL_INSN_LEN);
+    // The return value register holds the syscall number on x86_64, and the
+    // first argument on other architectures.
+    if cfg!(target_arch = "x86_64") {
+        syscall_num as _
+    } else {
+        tf.arg0() as _
+    }
 }
 
 #[register_trap_handler(POST_TRAP)]
@@ -50,26 +218,100 @@ fn post_trap_callback(tf: &mut TrapFrame, from_user: bool) {
     }
 
     check_signals(tf, None);
+    #[cfg(target_arch = "aarch64")]
+    crate::ptrace::ptrace_keep_stepping(tf);
+}
+
+/// Sends the signal `signo` with the code `code` to the current thread for a
+/// fault at `addr`, such as `SIGSEGV` for an invalid memory access.
+///
+/// The thread cannot run on past the fault, so the signal is not blocked or
+/// ignored: if it is, it is unblocked and its action is reset to the default
+/// one, which terminates the process.
+pub fn send_fault_signal(signo: Signo, code: u32, addr: VirtAddr) {
+    let curr = current();
+    info!(
+        "{} ({:?}): {:?} at {:#x}",
+        curr.id_name(),
+        curr.task_ext().thread,
+        signo,
+        addr
+    );
+    let mut sig = SignalInfo::new(signo, code as _);
+    sig.0
+        .__bindgen_anon_1
+        .__bindgen_anon_1
+        ._sifields
+        ._sigfault
+        ._addr = addr.as_mut_ptr() as _;
+    force_signal(sig);
+}
+
+/// Sends `sig` to the current thread, unblocking it and resetting its action
+/// to the default one if it is blocked or ignored.
+pub(crate) fn force_signal(sig: SignalInfo) {
+    let curr = current();
+    let signo = sig.signo();
+    let thr_data = curr.task_ext().thread_data();
+    let blocked = thr_data
+        .signal
+        .with_blocked_mut(|blocked| blocked.remove(signo));
+    let actions = curr.task_ext().process_data().signal.actions();
+    let mut actions = actions.lock();
+    if blocked || matches!(actions[signo].disposition, SignalDisposition::Ignore) {
+        actions[signo] = SignalAction::default();
+    }
+    drop(actions);
+    thr_data.signal.send_signal(sig);
+}
+
+#[register_trap_handler(USER_EXCEPTION)]
+fn user_exception_handler(kind: UserException, addr: VirtAddr) -> bool {
+    let (signo, code) = match kind {
+        UserException::IllegalInstruction => (Signo::SIGILL, ILL_ILLOPC),
+        UserException::Breakpoint => (Signo::SIGTRAP, TRAP_BRKPT),
+        UserException::MisalignedAccess => (Signo::SIGBUS, BUS_ADRALN),
+        UserException::AccessFault => (Signo::SIGSEGV, SEGV_ACCERR),
+        UserException::GeneralProtection => (Signo::SIGSEGV, SI_KERNEL),
+        UserException::DivideByZero => (Signo::SIGFPE, FPE_INTDIV),
+        UserException::FloatingPoint => (Signo::SIGFPE, FPE_FLTINV),
+        UserException::SingleStep => (Signo::SIGTRAP, TRAP_TRACE),
+    };
+    send_fault_signal(signo, code, addr);
+    true
 }
 
 pub fn send_signal_thread(thr: &Thread, sig: SignalInfo) -> LinuxResult<()> {
     info!("Send signal {:?} to thread {}", sig.signo(), thr.tid());
-    let Some(thr) = thr.data::<ThreadData>() else {
+    let Some(data) = thr.data::<ThreadData>() else {
         return Err(LinuxError::EPERM);
     };
-    thr.signal.send_signal(sig);
+    prepare_signal(thr.process(), sig.signo());
+    data.signal.send_signal(sig);
+    interrupt_thread(thr);
     Ok(())
 }
 
 pub fn send_signal_process(proc: &Process, sig: SignalInfo) -> LinuxResult<()> {
     info!("Send signal {:?} to process {}", sig.signo(), proc.pid());
-    let Some(proc) = proc.data::<ProcessData>() else {
+    let Some(data) = proc.data::<ProcessData>() else {
         return Err(LinuxError::EPERM);
     };
-    proc.signal.send_signal(sig);
+    prepare_signal(proc, sig.signo());
+    data.signal.send_signal(sig);
+    interrupt_process(proc);
     Ok(())
 }
 
+struct SendSignalImpl;
+
+#[crate_interface::impl_interface]
+impl SendSignalIf for SendSignalImpl {
+    fn send_signal_process(proc: &Process, sig: SignalInfo) {
+        let _ = send_signal_process(proc, sig);
+    }
+}
+
 pub fn send_signal_process_group(pg: &ProcessGroup, sig: SignalInfo) -> usize {
     info!(
         "Send signal {:?} to process group {}",
@@ -0,0 +1,125 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/capability.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_A "/tmp/caps_a"
+
+struct __user_cap_header_struct header = {_LINUX_CAPABILITY_VERSION_3, 0};
+struct __user_cap_data_struct data[2];
+
+int capget() { return syscall(SYS_capget, &header, data); }
+int capset() { return syscall(SYS_capset, &header, data); }
+
+void test_capget() {
+  if (capget() == 0 && (data[0].effective & (1 << CAP_KILL)) &&
+      (data[0].permitted & (1 << CAP_CHOWN))) {
+    puts("test_capget ok1");
+  }
+  if (fork() == 0) {
+    setuid(1000);
+    capget();
+    if (data[0].effective == 0 && data[0].permitted == 0) {
+      puts("test_capget ok2");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+void test_capset() {
+  close(open(FILE_A, O_CREAT | O_WRONLY, 0644));
+  if (fork() == 0) {
+    capget();
+    data[0].effective &= ~(1 << CAP_CHOWN);
+    if (capset() == 0 && chown(FILE_A, 1000, 1000) < 0 && errno == EPERM) {
+      puts("test_capset ok1");
+    }
+    // A permitted capability can be made effective again.
+    data[0].effective |= 1 << CAP_CHOWN;
+    if (capset() == 0 && chown(FILE_A, 1000, 1000) == 0) {
+      puts("test_capset ok2");
+    }
+    // A dropped one cannot.
+    data[0].effective &= ~(1 << CAP_CHOWN);
+    data[0].permitted &= ~(1 << CAP_CHOWN);
+    capset();
+    data[0].effective |= 1 << CAP_CHOWN;
+    data[0].permitted |= 1 << CAP_CHOWN;
+    if (capset() < 0 && errno == EPERM) {
+      puts("test_capset ok3");
+    }
+    exit(0);
+  }
+  wait(NULL);
+  unlink(FILE_A);
+}
+
+void test_kill() {
+  int fds[2];
+  pipe(fds);
+  int pid = fork();
+  if (pid == 0) {
+    int child = fork();
+    if (child == 0) {
+      setuid(1000);
+      write(fds[1], "x", 1);
+      while (1)
+        pause();
+    }
+    char c;
+    read(fds[0], &c, 1);
+    capget();
+    data[0].effective &= ~(1 << CAP_KILL);
+    capset();
+    if (kill(child, SIGTERM) < 0 && errno == EPERM) {
+      puts("test_kill ok1");
+    }
+    data[0].effective |= 1 << CAP_KILL;
+    capset();
+    if (kill(child, SIGTERM) == 0) {
+      puts("test_kill ok2");
+    }
+    waitpid(child, NULL, 0);
+    exit(0);
+  }
+  waitpid(pid, NULL, 0);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_bounding() {
+  if (fork() == 0) {
+    if (prctl(PR_CAPBSET_READ, CAP_CHOWN, 0, 0, 0) == 1 &&
+        prctl(PR_CAPBSET_DROP, CAP_CHOWN, 0, 0, 0) == 0 &&
+        prctl(PR_CAPBSET_READ, CAP_CHOWN, 0, 0, 0) == 0) {
+      puts("test_bounding ok1");
+    }
+    // The inheritable set cannot gain a capability outside the bounding set.
+    capget();
+    data[0].inheritable |= 1 << CAP_CHOWN;
+    if (capset() < 0 && errno == EPERM) {
+      puts("test_bounding ok2");
+    }
+    setuid(1000);
+    if (prctl(PR_CAPBSET_DROP, CAP_KILL, 0, 0, 0) < 0 && errno == EPERM) {
+      puts("test_bounding ok3");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+int main() {
+  test_capget();
+  test_capset();
+  test_kill();
+  test_bounding();
+  return 0;
+}
@@ -0,0 +1,128 @@
+#include <errno.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stdio.h>
+#include <time.h>
+#include <unistd.h>
+
+long elapsed_ms(struct timespec *start, clockid_t clock) {
+  struct timespec now;
+  clock_gettime(clock, &now);
+  return (now.tv_sec - start->tv_sec) * 1000 +
+         (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+void test_getres() {
+  struct timespec res;
+  if (clock_getres(CLOCK_MONOTONIC, &res) == 0 && res.tv_sec == 0 &&
+      res.tv_nsec > 0 && res.tv_nsec <= 1000000) {
+    puts("test_getres ok1");
+  }
+  if (clock_getres(CLOCK_PROCESS_CPUTIME_ID, &res) == 0) {
+    puts("test_getres ok2");
+  }
+  if (clock_getres(-100, &res) == -1 && errno == EINVAL) {
+    puts("test_getres ok3");
+  }
+}
+
+void test_settime() {
+  struct timespec old, now;
+  clock_gettime(CLOCK_REALTIME, &old);
+  struct timespec later = {old.tv_sec + 3600, old.tv_nsec};
+  if (clock_settime(CLOCK_REALTIME, &later) == 0) {
+    clock_gettime(CLOCK_REALTIME, &now);
+    if (now.tv_sec >= later.tv_sec && now.tv_sec < later.tv_sec + 2) {
+      puts("test_settime ok1");
+    }
+    old.tv_sec = now.tv_sec - 3600;
+    clock_settime(CLOCK_REALTIME, &old);
+  }
+  if (clock_settime(CLOCK_MONOTONIC, &old) == -1 && errno == EINVAL) {
+    puts("test_settime ok2");
+  }
+}
+
+void test_abs_sleep(clockid_t clock, const char *name) {
+  struct timespec start, deadline;
+  clock_gettime(clock, &start);
+  deadline = start;
+  deadline.tv_nsec += 100000000;
+  if (deadline.tv_nsec >= 1000000000) {
+    deadline.tv_sec++;
+    deadline.tv_nsec -= 1000000000;
+  }
+  if (clock_nanosleep(clock, TIMER_ABSTIME, &deadline, NULL) == 0 &&
+      elapsed_ms(&start, clock) >= 100) {
+    printf("%s ok1\n", name);
+  }
+  // A deadline in the past returns at once.
+  if (clock_nanosleep(clock, TIMER_ABSTIME, &start, NULL) == 0) {
+    printf("%s ok2\n", name);
+  }
+}
+
+void *spin(void *arg) {
+  while (!*(volatile int *)arg)
+    ;
+  return NULL;
+}
+
+void test_cpu_sleep() {
+  int stop = 0;
+  pthread_t thread;
+  pthread_create(&thread, NULL, spin, &stop);
+  struct timespec start;
+  clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &start);
+  struct timespec req = {0, 100000000};
+  // The spinning thread consumes the CPU time slept for.
+  if (clock_nanosleep(CLOCK_PROCESS_CPUTIME_ID, 0, &req, NULL) == 0 &&
+      elapsed_ms(&start, CLOCK_PROCESS_CPUTIME_ID) >= 100) {
+    puts("test_cpu_sleep ok");
+  }
+  stop = 1;
+  pthread_join(thread, NULL);
+}
+
+void test_abs_timer() {
+  sigset_t set;
+  sigemptyset(&set);
+  sigaddset(&set, SIGUSR1);
+  sigprocmask(SIG_BLOCK, &set, NULL);
+
+  struct sigevent sev = {0};
+  sev.sigev_notify = SIGEV_SIGNAL;
+  sev.sigev_signo = SIGUSR1;
+  timer_t timer;
+  timer_create(CLOCK_REALTIME, &sev, &timer);
+  struct timespec now;
+  clock_gettime(CLOCK_REALTIME, &now);
+  struct itimerspec its = {0};
+  its.it_value.tv_sec = now.tv_sec + 3600;
+  its.it_value.tv_nsec = now.tv_nsec;
+  timer_settime(timer, TIMER_ABSTIME, &its, NULL);
+
+  // Setting the wall clock past the deadline expires the timer.
+  now.tv_sec += 3600;
+  if (clock_settime(CLOCK_REALTIME, &now) == 0) {
+    struct timespec timeout = {2, 0};
+    if (sigtimedwait(&set, NULL, &timeout) == SIGUSR1) {
+      puts("test_abs_timer ok");
+    }
+    clock_gettime(CLOCK_REALTIME, &now);
+    now.tv_sec -= 3600;
+    clock_settime(CLOCK_REALTIME, &now);
+  }
+  timer_delete(timer);
+  sigprocmask(SIG_UNBLOCK, &set, NULL);
+}
+
+int main() {
+  test_getres();
+  test_settime();
+  test_abs_sleep(CLOCK_MONOTONIC, "test_abs_sleep_monotonic");
+  test_abs_sleep(CLOCK_REALTIME, "test_abs_sleep_realtime");
+  test_cpu_sleep();
+  test_abs_timer();
+  return 0;
+}
@@ -0,0 +1,77 @@
+#include <errno.h>
+#include <linux/sched.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static long clone3(struct clone_args *args, size_t size) {
+  return syscall(SYS_clone3, args, size);
+}
+
+void test_fork() {
+  struct clone_args args;
+  memset(&args, 0, sizeof(args));
+  args.exit_signal = SIGCHLD;
+  long pid = clone3(&args, sizeof(args));
+  if (pid == 0) {
+    _exit(9);
+  }
+  int status;
+  if (waitpid(pid, &status, 0) == pid && WIFEXITED(status) &&
+      WEXITSTATUS(status) == 9) {
+    puts("test_clone3_fork ok");
+  }
+}
+
+void test_set_tid() {
+  pid_t tid = 31337;
+  struct clone_args args;
+  memset(&args, 0, sizeof(args));
+  args.exit_signal = SIGCHLD;
+  args.set_tid = (uintptr_t)&tid;
+  args.set_tid_size = 1;
+  long pid = clone3(&args, sizeof(args));
+  if (pid == 0) {
+    _exit(getpid() == tid ? 0 : 1);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (pid == tid && WIFEXITED(status) && WEXITSTATUS(status) == 0) {
+    puts("test_clone3_set_tid ok1");
+  }
+
+  tid = 1 << 30;
+  if (clone3(&args, sizeof(args)) < 0 && errno == EINVAL) {
+    puts("test_clone3_set_tid ok2");
+  }
+}
+
+void test_invalid() {
+  struct clone_args args;
+  memset(&args, 0, sizeof(args));
+  args.exit_signal = SIGCHLD;
+  if (clone3(&args, 8) < 0 && errno == EINVAL) {
+    puts("test_clone3_invalid ok1");
+  }
+  args.exit_signal = 65;
+  if (clone3(&args, sizeof(args)) < 0 && errno == EINVAL) {
+    puts("test_clone3_invalid ok2");
+  }
+  // The exit signal is only sent to the parent of a new process.
+  args.exit_signal = SIGCHLD;
+  args.flags = CLONE_PARENT;
+  if (clone3(&args, sizeof(args)) < 0 && errno == EINVAL) {
+    puts("test_clone3_invalid ok3");
+  }
+}
+
+int main() {
+  test_fork();
+  test_set_tid();
+  test_invalid();
+  return 0;
+}
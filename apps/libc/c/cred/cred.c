@@ -0,0 +1,90 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <grp.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_root() {
+  if (getuid() == 0 && geteuid() == 0 && getgid() == 0 && getegid() == 0) {
+    puts("test_root ok");
+  }
+}
+
+void test_setresuid() {
+  if (fork() == 0) {
+    uid_t r, e, s;
+    setresuid(1000, 1000, 0);
+    getresuid(&r, &e, &s);
+    if (r == 1000 && e == 1000 && s == 0) {
+      puts("test_setresuid ok1");
+    }
+    // The saved set-user-ID allows switching back.
+    if (seteuid(0) == 0 && geteuid() == 0) {
+      puts("test_setresuid ok2");
+    }
+    // With the effective user ID 0, setuid sets all three.
+    setuid(2000);
+    getresuid(&r, &e, &s);
+    if (r == 2000 && e == 2000 && s == 2000) {
+      puts("test_setresuid ok3");
+    }
+    if (setuid(0) < 0 && errno == EPERM) {
+      puts("test_setresuid ok4");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+void test_groups() {
+  if (fork() == 0) {
+    gid_t groups[] = {5, 6, 7};
+    setgroups(3, groups);
+    gid_t got[8];
+    if (getgroups(8, got) == 3 && got[1] == 6) {
+      puts("test_groups ok1");
+    }
+    if (getgroups(1, got) < 0 && errno == EINVAL) {
+      puts("test_groups ok2");
+    }
+    setresgid(100, 100, 100);
+    setuid(1000);
+    if (getgid() == 100 && getegid() == 100) {
+      puts("test_groups ok3");
+    }
+    if (setgroups(0, NULL) < 0 && errno == EPERM) {
+      puts("test_groups ok4");
+    }
+    if (setgid(0) < 0 && errno == EPERM) {
+      puts("test_groups ok5");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+void test_inherit() {
+  if (fork() == 0) {
+    setgid(100);
+    setuid(1000);
+    if (fork() == 0) {
+      if (getuid() == 1000 && getgid() == 100) {
+        puts("test_inherit ok");
+      }
+      exit(0);
+    }
+    wait(NULL);
+    exit(0);
+  }
+  wait(NULL);
+}
+
+int main() {
+  test_root();
+  test_setresuid();
+  test_groups();
+  test_inherit();
+  return 0;
+}
@@ -0,0 +1,75 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define BAD_EXE "/tmp/execve_bad"
+
+static char *self;
+static char pid_arg[16];
+
+static void *exec_thread(void *arg) {
+  char *argv[] = {self, "exec", pid_arg, NULL};
+  execv(self, argv);
+  return NULL;
+}
+
+static void *bad_exec_thread(void *arg) {
+  char *argv[] = {BAD_EXE, NULL};
+  if (execv(BAD_EXE, argv) < 0 && errno == ENOEXEC) {
+    puts("test_exec_thread_fail ok1");
+  }
+  return NULL;
+}
+
+void test_exec_thread() {
+  int pid = fork();
+  if (pid == 0) {
+    snprintf(pid_arg, sizeof(pid_arg), "%d", getpid());
+    pthread_t thread;
+    pthread_create(&thread, NULL, exec_thread, NULL);
+    while (1)
+      pause();
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFEXITED(status) && WEXITSTATUS(status) == 0) {
+    puts("test_exec_thread ok3");
+  }
+}
+
+void test_exec_thread_fail() {
+  int fd = open(BAD_EXE, O_CREAT | O_WRONLY | O_TRUNC, 0755);
+  write(fd, "not an executable\n", 18);
+  close(fd);
+
+  // A failed execve leaves the other threads running.
+  pthread_t thread;
+  pthread_create(&thread, NULL, bad_exec_thread, NULL);
+  pthread_join(thread, NULL);
+  puts("test_exec_thread_fail ok2");
+  unlink(BAD_EXE);
+}
+
+int main(int argc, char *argv[]) {
+  if (argc == 3 && strcmp(argv[1], "exec") == 0) {
+    // The thread that called execve takes over the ID of the process.
+    if (atoi(argv[2]) == getpid()) {
+      puts("test_exec_thread ok1");
+    }
+    if (syscall(SYS_gettid) == getpid()) {
+      puts("test_exec_thread ok2");
+    }
+    return 0;
+  }
+  self = argv[0];
+  test_exec_thread();
+  test_exec_thread_fail();
+  return 0;
+}
@@ -0,0 +1,73 @@
+#include <signal.h>
+#include <stdio.h>
+#include <sys/time.h>
+#include <unistd.h>
+
+static volatile int alarms = 0;
+static void alarm_handler(int signum) { alarms++; }
+
+void test_setitimer() {
+  struct sigaction sa = {0};
+  sa.sa_handler = alarm_handler;
+  sigaction(SIGALRM, &sa, NULL);
+
+  struct itimerval it = {{0, 100000}, {0, 100000}};
+  setitimer(ITIMER_REAL, &it, NULL);
+  while (alarms < 3)
+    pause();
+  puts("test_setitimer ok1");
+
+  struct itimerval old;
+  getitimer(ITIMER_REAL, &old);
+  if (old.it_interval.tv_sec == 0 && old.it_interval.tv_usec == 100000) {
+    puts("test_setitimer ok2");
+  }
+
+  struct itimerval zero = {0};
+  setitimer(ITIMER_REAL, &zero, &old);
+  getitimer(ITIMER_REAL, &old);
+  if (old.it_value.tv_sec == 0 && old.it_value.tv_usec == 0) {
+    puts("test_setitimer ok3");
+  }
+}
+
+static volatile int vtalarms = 0;
+static void vtalarm_handler(int signum) { vtalarms++; }
+
+void test_virtual() {
+  struct sigaction sa = {0};
+  sa.sa_handler = vtalarm_handler;
+  sigaction(SIGVTALRM, &sa, NULL);
+
+  // Only time spent running counts, so sleeping does not fire the timer.
+  struct itimerval it = {{0, 0}, {0, 50000}};
+  setitimer(ITIMER_VIRTUAL, &it, NULL);
+  usleep(200000);
+  if (vtalarms == 0) {
+    puts("test_virtual ok1");
+  }
+  while (vtalarms == 0)
+    ;
+  puts("test_virtual ok2");
+}
+
+void test_alarm() {
+  alarms = 0;
+  if (alarm(5) == 0 && alarm(1) == 5) {
+    puts("test_alarm ok1");
+  }
+  pause();
+  if (alarms == 1) {
+    puts("test_alarm ok2");
+  }
+  if (alarm(0) == 0) {
+    puts("test_alarm ok3");
+  }
+}
+
+int main() {
+  test_setitimer();
+  test_virtual();
+  test_alarm();
+  return 0;
+}
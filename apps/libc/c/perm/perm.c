@@ -0,0 +1,126 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_A "/tmp/perm_a"
+#define FILE_B "/tmp/perm_b"
+#define DIR_D "/tmp/perm_d"
+
+void test_umask() {
+  umask(022);
+  close(open(FILE_A, O_CREAT | O_WRONLY, 0666));
+  struct stat st;
+  stat(FILE_A, &st);
+  if ((st.st_mode & 0777) == 0644) {
+    puts("test_umask ok1");
+  }
+  if (umask(077) == 022) {
+    puts("test_umask ok2");
+  }
+  umask(022);
+}
+
+void test_chmod() {
+  chmod(FILE_A, 0600);
+  struct stat st;
+  stat(FILE_A, &st);
+  if ((st.st_mode & 0777) == 0600 && st.st_uid == 0) {
+    puts("test_chmod ok");
+  }
+}
+
+void test_unprivileged() {
+  mkdir(DIR_D, 0755);
+  if (fork() == 0) {
+    setuid(1000);
+    if (open(FILE_A, O_RDONLY) < 0 && errno == EACCES) {
+      puts("test_unprivileged ok1");
+    }
+    if (access(FILE_A, R_OK) < 0 && errno == EACCES) {
+      puts("test_unprivileged ok2");
+    }
+    if (chmod(FILE_A, 0666) < 0 && errno == EPERM) {
+      puts("test_unprivileged ok3");
+    }
+    if (open(DIR_D "/f", O_CREAT | O_WRONLY, 0644) < 0 && errno == EACCES) {
+      puts("test_unprivileged ok4");
+    }
+    int fd = open(FILE_B, O_CREAT | O_WRONLY, 0644);
+    struct stat st;
+    fstat(fd, &st);
+    if (fd >= 0 && st.st_uid == 1000) {
+      puts("test_unprivileged ok5");
+    }
+    close(fd);
+    exit(0);
+  }
+  wait(NULL);
+  unlink(FILE_B);
+  rmdir(DIR_D);
+}
+
+void test_sticky() {
+  if (fork() == 0) {
+    setuid(1000);
+    // Only the owner of a file may remove it from the sticky /tmp.
+    if (unlink(FILE_A) < 0 && errno == EPERM) {
+      puts("test_sticky ok1");
+    }
+    close(open(FILE_B, O_CREAT | O_WRONLY, 0644));
+    if (unlink(FILE_B) == 0) {
+      puts("test_sticky ok2");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+void test_chown() {
+  chown(FILE_A, 1000, 1000);
+  if (fork() == 0) {
+    setgid(1000);
+    setuid(1000);
+    int fd = open(FILE_A, O_RDWR);
+    if (fd >= 0) {
+      puts("test_chown ok1");
+    }
+    close(fd);
+    if (chown(FILE_A, 0, 0) < 0 && errno == EPERM) {
+      puts("test_chown ok2");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+void test_faccessat() {
+  chown(FILE_A, 0, 0);
+  if (fork() == 0) {
+    // Access is checked with the real user ID unless asked otherwise.
+    setresuid(1000, 0, 0);
+    if (access(FILE_A, R_OK) < 0 && errno == EACCES) {
+      puts("test_faccessat ok1");
+    }
+    if (faccessat(AT_FDCWD, FILE_A, R_OK, AT_EACCESS) == 0) {
+      puts("test_faccessat ok2");
+    }
+    exit(0);
+  }
+  wait(NULL);
+  unlink(FILE_A);
+}
+
+int main() {
+  test_umask();
+  test_chmod();
+  test_unprivileged();
+  test_sticky();
+  test_chown();
+  test_faccessat();
+  return 0;
+}
@@ -0,0 +1,109 @@
+#include <errno.h>
+#include <linux/sched.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_pidfd_open() {
+  int pid = fork();
+  if (pid == 0) {
+    usleep(100000);
+    _exit(0);
+  }
+  int pidfd = syscall(SYS_pidfd_open, pid, 0);
+  if (pidfd >= 0) {
+    puts("test_pidfd_open ok1");
+  }
+  // The pidfd becomes readable once the process exits.
+  struct pollfd pfd = {pidfd, POLLIN, 0};
+  if (poll(&pfd, 1, 5000) == 1 && (pfd.revents & POLLIN)) {
+    puts("test_pidfd_open ok2");
+  }
+  waitpid(pid, NULL, 0);
+  close(pidfd);
+
+  if (syscall(SYS_pidfd_open, pid, 0) < 0 && errno == ESRCH) {
+    puts("test_pidfd_open ok3");
+  }
+}
+
+void test_send_signal() {
+  int pid = fork();
+  if (pid == 0) {
+    while (1)
+      pause();
+  }
+  int pidfd = syscall(SYS_pidfd_open, pid, 0);
+  if (syscall(SYS_pidfd_send_signal, pidfd, SIGTERM, NULL, 0) == 0) {
+    puts("test_pidfd_send_signal ok1");
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFSIGNALED(status) && WTERMSIG(status) == SIGTERM) {
+    puts("test_pidfd_send_signal ok2");
+  }
+  if (syscall(SYS_pidfd_send_signal, pidfd, SIGTERM, NULL, 0) < 0 &&
+      errno == ESRCH) {
+    puts("test_pidfd_send_signal ok3");
+  }
+  close(pidfd);
+}
+
+void test_getfd() {
+  int fds[2];
+  pipe(fds);
+  int pid = fork();
+  if (pid == 0) {
+    dup2(fds[1], 42);
+    while (1)
+      pause();
+  }
+  usleep(100000);
+  int pidfd = syscall(SYS_pidfd_open, pid, 0);
+  int fd = syscall(SYS_pidfd_getfd, pidfd, 42, 0);
+  struct stat st1, st2;
+  fstat(fd, &st1);
+  fstat(fds[1], &st2);
+  if (fd >= 0 && st1.st_ino == st2.st_ino) {
+    puts("test_pidfd_getfd ok");
+  }
+  close(fd);
+  kill(pid, SIGKILL);
+  waitpid(pid, NULL, 0);
+  close(pidfd);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_clone_pidfd() {
+  int pidfd = -1;
+  struct clone_args args;
+  memset(&args, 0, sizeof(args));
+  args.flags = CLONE_PIDFD;
+  args.pidfd = (uintptr_t)&pidfd;
+  args.exit_signal = SIGCHLD;
+  long pid = syscall(SYS_clone3, &args, sizeof(args));
+  if (pid == 0) {
+    _exit(0);
+  }
+  struct pollfd pfd = {pidfd, POLLIN, 0};
+  if (pidfd >= 0 && poll(&pfd, 1, 5000) == 1) {
+    puts("test_clone_pidfd ok");
+  }
+  waitpid(pid, NULL, 0);
+  close(pidfd);
+}
+
+int main() {
+  test_pidfd_open();
+  test_send_signal();
+  test_getfd();
+  test_clone_pidfd();
+  return 0;
+}
@@ -0,0 +1,90 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/time.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+volatile int alarms = 0;
+
+void handler(int sig) { alarms++; }
+
+void set_handler(int flags) {
+  struct sigaction sa;
+  memset(&sa, 0, sizeof(sa));
+  sa.sa_handler = handler;
+  sa.sa_flags = flags;
+  sigaction(SIGALRM, &sa, NULL);
+}
+
+void arm_alarm_ms(int ms) {
+  struct itimerval it = {0};
+  it.it_value.tv_sec = ms / 1000;
+  it.it_value.tv_usec = ms % 1000 * 1000;
+  setitimer(ITIMER_REAL, &it, NULL);
+}
+
+void test_eintr() {
+  int fds[2];
+  pipe(fds);
+  set_handler(0);
+  alarms = 0;
+  arm_alarm_ms(100);
+  char buf[8];
+  int n = read(fds[0], buf, sizeof(buf));
+  if (n == -1 && errno == EINTR && alarms == 1) {
+    puts("test_eintr ok");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_restart() {
+  int fds[2];
+  pipe(fds);
+  set_handler(SA_RESTART);
+  alarms = 0;
+  // The alarm interrupts the read before the child writes, and the read is
+  // restarted to wait for the data.
+  if (fork() == 0) {
+    usleep(300000);
+    write(fds[1], "abc", 3);
+    exit(0);
+  }
+  arm_alarm_ms(100);
+  char buf[8] = {0};
+  int n = read(fds[0], buf, sizeof(buf) - 1);
+  if (n == 3 && strcmp(buf, "abc") == 0 && alarms == 1) {
+    puts("test_restart ok");
+  }
+  wait(NULL);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_eof() {
+  int fds[2];
+  pipe(fds);
+  // A blocked read sees the end of file when the write end is closed.
+  if (fork() == 0) {
+    close(fds[0]);
+    usleep(100000);
+    exit(0);
+  }
+  close(fds[1]);
+  char buf[8];
+  if (read(fds[0], buf, sizeof(buf)) == 0) {
+    puts("test_eof ok");
+  }
+  wait(NULL);
+  close(fds[0]);
+}
+
+int main() {
+  test_eintr();
+  test_restart();
+  test_eof();
+  return 0;
+}
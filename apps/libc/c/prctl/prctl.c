@@ -0,0 +1,136 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/prctl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_name() {
+  char name[16] = {0};
+  prctl(PR_SET_NAME, "prctl_test", 0, 0, 0);
+  prctl(PR_GET_NAME, name, 0, 0, 0);
+  if (strcmp(name, "prctl_test") == 0) {
+    puts("test_name ok1");
+  }
+  // Names are truncated to 15 bytes.
+  prctl(PR_SET_NAME, "a_very_long_thread_name", 0, 0, 0);
+  prctl(PR_GET_NAME, name, 0, 0, 0);
+  if (strcmp(name, "a_very_long_thr") == 0) {
+    puts("test_name ok2");
+  }
+}
+
+void test_pdeathsig() {
+  prctl(PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0);
+  int fds[2];
+  pipe(fds);
+  int pid = fork();
+  if (pid == 0) {
+    if (fork() == 0) {
+      prctl(PR_SET_PDEATHSIG, SIGTERM, 0, 0, 0);
+      int sig;
+      prctl(PR_GET_PDEATHSIG, &sig, 0, 0, 0);
+      write(fds[1], &sig, sizeof(sig));
+      while (1)
+        pause();
+    }
+    int sig;
+    read(fds[0], &sig, sizeof(sig));
+    exit(sig == SIGTERM ? 0 : 1);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFEXITED(status) && WEXITSTATUS(status) == 0) {
+    puts("test_pdeathsig ok1");
+  }
+  // The orphan is reparented to this process, a subreaper.
+  wait(&status);
+  if (WIFSIGNALED(status) && WTERMSIG(status) == SIGTERM) {
+    puts("test_pdeathsig ok2");
+  }
+  prctl(PR_SET_CHILD_SUBREAPER, 0, 0, 0, 0);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_subreaper() {
+  prctl(PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0);
+  int subreaper = 0;
+  prctl(PR_GET_CHILD_SUBREAPER, &subreaper, 0, 0, 0);
+  if (subreaper == 1) {
+    puts("test_subreaper ok1");
+  }
+  int fds[2];
+  pipe(fds);
+  int pid = fork();
+  if (pid == 0) {
+    if (fork() == 0) {
+      // Wait to be orphaned by the exit of the parent
+      pid_t parent = getppid();
+      for (int i = 0; i < 500 && getppid() == parent; i++) {
+        usleep(10000);
+      }
+      pid_t ids[2] = {getpid(), getppid()};
+      write(fds[1], ids, sizeof(ids));
+      exit(7);
+    }
+    exit(0);
+  }
+  waitpid(pid, NULL, 0);
+  pid_t ids[2];
+  read(fds[0], ids, sizeof(ids));
+  if (ids[1] == getpid()) {
+    puts("test_subreaper ok2");
+  }
+  int status;
+  if (waitpid(-1, &status, 0) == ids[0] && WIFEXITED(status) &&
+      WEXITSTATUS(status) == 7) {
+    puts("test_subreaper ok3");
+  }
+  prctl(PR_SET_CHILD_SUBREAPER, 0, 0, 0, 0);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_dumpable() {
+  if (prctl(PR_GET_DUMPABLE, 0, 0, 0, 0) == 1) {
+    puts("test_dumpable ok1");
+  }
+  prctl(PR_SET_DUMPABLE, 0, 0, 0, 0);
+  if (prctl(PR_GET_DUMPABLE, 0, 0, 0, 0) == 0) {
+    puts("test_dumpable ok2");
+  }
+  if (prctl(PR_SET_DUMPABLE, 2, 0, 0, 0) < 0 && errno == EINVAL) {
+    puts("test_dumpable ok3");
+  }
+  prctl(PR_SET_DUMPABLE, 1, 0, 0, 0);
+}
+
+void test_no_new_privs() {
+  if (fork() == 0) {
+    if (prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0) == 0) {
+      puts("test_no_new_privs ok1");
+    }
+    prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0);
+    if (prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0) == 1) {
+      puts("test_no_new_privs ok2");
+    }
+    // It can not be unset.
+    if (prctl(PR_SET_NO_NEW_PRIVS, 0, 0, 0, 0) < 0 && errno == EINVAL) {
+      puts("test_no_new_privs ok3");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+int main() {
+  test_name();
+  test_pdeathsig();
+  test_subreaper();
+  test_dumpable();
+  test_no_new_privs();
+  return 0;
+}
@@ -0,0 +1,94 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/ptrace.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile long value = 1;
+
+void test_traceme() {
+  int pid = fork();
+  if (pid == 0) {
+    ptrace(PTRACE_TRACEME, 0, NULL, NULL);
+    raise(SIGSTOP);
+    exit(value);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFSTOPPED(status) && WSTOPSIG(status) == SIGSTOP) {
+    puts("test_traceme ok1");
+  }
+
+  errno = 0;
+  long data = ptrace(PTRACE_PEEKDATA, pid, (void *)&value, NULL);
+  if (data == 1 && errno == 0) {
+    puts("test_traceme ok2");
+  }
+  ptrace(PTRACE_POKEDATA, pid, (void *)&value, (void *)42);
+
+  ptrace(PTRACE_SETOPTIONS, pid, NULL, (void *)PTRACE_O_TRACESYSGOOD);
+  ptrace(PTRACE_SYSCALL, pid, NULL, NULL);
+  waitpid(pid, &status, 0);
+  if (WIFSTOPPED(status) && WSTOPSIG(status) == (SIGTRAP | 0x80)) {
+    puts("test_traceme ok3");
+  }
+
+  ptrace(PTRACE_CONT, pid, NULL, NULL);
+  waitpid(pid, &status, 0);
+  if (WIFEXITED(status) && WEXITSTATUS(status) == 42) {
+    puts("test_traceme ok4");
+  }
+}
+
+void test_attach() {
+  int pid = fork();
+  if (pid == 0) {
+    while (1)
+      pause();
+  }
+  if (ptrace(PTRACE_ATTACH, pid, NULL, NULL) == 0) {
+    puts("test_attach ok1");
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFSTOPPED(status) && WSTOPSIG(status) == SIGSTOP) {
+    puts("test_attach ok2");
+  }
+  // Only the tracer may operate on a tracee.
+  if (ptrace(PTRACE_ATTACH, pid, NULL, NULL) < 0 && errno == EPERM) {
+    puts("test_attach ok3");
+  }
+  ptrace(PTRACE_DETACH, pid, NULL, NULL);
+  kill(pid, SIGKILL);
+  waitpid(pid, &status, 0);
+  if (WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL) {
+    puts("test_attach ok4");
+  }
+}
+
+void test_permission() {
+  int target = fork();
+  if (target == 0) {
+    while (1)
+      pause();
+  }
+  if (fork() == 0) {
+    setuid(1000);
+    if (ptrace(PTRACE_ATTACH, target, NULL, NULL) < 0 && errno == EPERM) {
+      puts("test_ptrace_permission ok");
+    }
+    exit(0);
+  }
+  wait(NULL);
+  kill(target, SIGKILL);
+  wait(NULL);
+}
+
+int main() {
+  test_traceme();
+  test_attach();
+  test_permission();
+  return 0;
+}
@@ -0,0 +1,90 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <termios.h>
+#include <unistd.h>
+
+int master, slave;
+
+void test_open() {
+  master = posix_openpt(O_RDWR | O_NOCTTY);
+  if (master >= 0 && grantpt(master) == 0) {
+    puts("test_open ok1");
+  }
+  char *name = ptsname(master);
+  if (name && strncmp(name, "/dev/pts/", 9) == 0) {
+    puts("test_open ok2");
+  }
+  // The slave is locked until unlockpt.
+  if (open(name, O_RDWR | O_NOCTTY) == -1 && errno == EIO) {
+    puts("test_open ok3");
+  }
+  if (unlockpt(master) == 0) {
+    slave = open(name, O_RDWR | O_NOCTTY);
+    if (slave >= 0 && isatty(slave)) {
+      puts("test_open ok4");
+    }
+  }
+}
+
+void test_master_to_slave() {
+  char buf[16] = {0};
+  write(master, "hi\n", 3);
+  // Input is echoed back to the master, with the newline mapped to CR-LF.
+  if (read(master, buf, sizeof(buf) - 1) == 4 && strcmp(buf, "hi\r\n") == 0) {
+    puts("test_master_to_slave ok1");
+  }
+  memset(buf, 0, sizeof(buf));
+  if (read(slave, buf, sizeof(buf) - 1) == 3 && strcmp(buf, "hi\n") == 0) {
+    puts("test_master_to_slave ok2");
+  }
+}
+
+void test_slave_to_master() {
+  char buf[16] = {0};
+  write(slave, "out\n", 4);
+  if (read(master, buf, sizeof(buf) - 1) == 5 &&
+      strcmp(buf, "out\r\n") == 0) {
+    puts("test_slave_to_master ok");
+  }
+}
+
+void test_no_echo() {
+  struct termios t;
+  tcgetattr(slave, &t);
+  t.c_lflag &= ~ECHO;
+  tcsetattr(slave, TCSANOW, &t);
+  write(master, "quiet\n", 6);
+  char buf[16] = {0};
+  if (read(slave, buf, sizeof(buf) - 1) == 6 && strcmp(buf, "quiet\n") == 0) {
+    puts("test_no_echo ok1");
+  }
+  // Nothing was echoed, so the master has nothing to read.
+  fcntl(master, F_SETFL, O_NONBLOCK);
+  if (read(master, buf, sizeof(buf)) == -1 && errno == EAGAIN) {
+    puts("test_no_echo ok2");
+  }
+  fcntl(master, F_SETFL, 0);
+}
+
+void test_hangup() {
+  close(slave);
+  // With the slave closed, the master reads nothing more.
+  char buf[16];
+  if (read(master, buf, sizeof(buf)) == -1 && errno == EIO) {
+    puts("test_hangup ok");
+  }
+  close(master);
+}
+
+int main() {
+  test_open();
+  test_master_to_slave();
+  test_slave_to_master();
+  test_no_echo();
+  test_hangup();
+  return 0;
+}
@@ -0,0 +1,69 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_core_limit() {
+  struct rlimit rl;
+  getrlimit(RLIMIT_CORE, &rl);
+  // No core files are written unless asked for.
+  if (rl.rlim_cur == 0 && rl.rlim_max == RLIM_INFINITY) {
+    puts("test_core_limit ok1");
+  }
+
+  int pid = fork();
+  if (pid == 0) {
+    raise(SIGSEGV);
+    exit(0);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFSIGNALED(status) && WTERMSIG(status) == SIGSEGV &&
+      !WCOREDUMP(status)) {
+    puts("test_core_limit ok2");
+  }
+}
+
+void test_setrlimit() {
+  struct rlimit rl = {1024, 4096};
+  if (setrlimit(RLIMIT_CORE, &rl) == 0) {
+    puts("test_setrlimit ok1");
+  }
+  rl.rlim_cur = 8192;
+  if (setrlimit(RLIMIT_CORE, &rl) < 0 && errno == EINVAL) {
+    puts("test_setrlimit ok2");
+  }
+
+  if (fork() == 0) {
+    // Limits are inherited, and only privileged processes may raise the
+    // hard limit.
+    setuid(1000);
+    getrlimit(RLIMIT_CORE, &rl);
+    if (rl.rlim_cur == 1024 && rl.rlim_max == 4096) {
+      puts("test_setrlimit ok3");
+    }
+    rl.rlim_max = 8192;
+    if (setrlimit(RLIMIT_CORE, &rl) < 0 && errno == EPERM) {
+      puts("test_setrlimit ok4");
+    }
+    rl.rlim_max = 2048;
+    if (setrlimit(RLIMIT_CORE, &rl) == 0) {
+      puts("test_setrlimit ok5");
+    }
+    exit(0);
+  }
+  wait(NULL);
+
+  rl.rlim_cur = 0;
+  rl.rlim_max = RLIM_INFINITY;
+  setrlimit(RLIMIT_CORE, &rl);
+}
+
+int main() {
+  test_core_limit();
+  test_setrlimit();
+  return 0;
+}
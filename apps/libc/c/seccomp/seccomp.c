@@ -0,0 +1,93 @@
+#include <errno.h>
+#include <linux/filter.h>
+#include <linux/seccomp.h>
+#include <signal.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_strict() {
+  int pid = fork();
+  if (pid == 0) {
+    prctl(PR_SET_SECCOMP, SECCOMP_MODE_STRICT);
+    const char msg[] = "test_strict ok1\n";
+    write(STDOUT_FILENO, msg, sizeof(msg) - 1);
+    syscall(SYS_getpid);
+    syscall(SYS_exit, 0);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL) {
+    puts("test_strict ok2");
+  }
+}
+
+static void install_filter() {
+  struct sock_filter filter[] = {
+      BPF_STMT(BPF_LD | BPF_W | BPF_ABS, offsetof(struct seccomp_data, nr)),
+      BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getppid, 0, 1),
+      BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | EPERM),
+      BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getuid, 0, 1),
+      BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_TRAP),
+      BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getgid, 0, 1),
+      BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+      BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+  };
+  struct sock_fprog prog = {sizeof(filter) / sizeof(filter[0]), filter};
+  prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0);
+  if (prctl(PR_SET_SECCOMP, SECCOMP_MODE_FILTER, &prog) == 0) {
+    puts("test_filter ok1");
+  }
+}
+
+static volatile int trapped = 0;
+static void sigsys_handler(int signum, siginfo_t *info, void *ucontext) {
+  if (info->si_syscall == SYS_getuid) {
+    trapped = 1;
+  }
+}
+
+void test_filter() {
+  int pid = fork();
+  if (pid == 0) {
+    install_filter();
+    if (syscall(SYS_getppid) < 0 && errno == EPERM) {
+      puts("test_filter ok2");
+    }
+
+    struct sigaction sa = {0};
+    sa.sa_sigaction = sigsys_handler;
+    sa.sa_flags = SA_SIGINFO;
+    sigaction(SIGSYS, &sa, NULL);
+    syscall(SYS_getuid);
+    if (trapped) {
+      puts("test_filter ok3");
+    }
+
+    // The filter is inherited by children.
+    if (fork() == 0) {
+      if (syscall(SYS_getppid) < 0 && errno == EPERM) {
+        puts("test_filter ok4");
+      }
+      exit(0);
+    }
+    wait(NULL);
+    syscall(SYS_getgid);
+    exit(0);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFSIGNALED(status) && WTERMSIG(status) == SIGSYS) {
+    puts("test_filter ok5");
+  }
+}
+
+int main() {
+  test_strict();
+  test_filter();
+  return 0;
+}
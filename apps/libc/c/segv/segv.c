@@ -0,0 +1,88 @@
+#include <setjmp.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+sigjmp_buf env;
+volatile int faults = 0;
+void *fault_addr;
+int fault_code;
+
+void handler(int sig, siginfo_t *info, void *ucontext) {
+  faults++;
+  fault_addr = info->si_addr;
+  fault_code = info->si_code;
+  siglongjmp(env, 1);
+}
+
+void set_handler() {
+  struct sigaction sa;
+  memset(&sa, 0, sizeof(sa));
+  sa.sa_sigaction = handler;
+  sa.sa_flags = SA_SIGINFO;
+  sigaction(SIGSEGV, &sa, NULL);
+}
+
+void test_accerr() {
+  long page_size = sysconf(_SC_PAGESIZE);
+  char *page = mmap(NULL, page_size, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS,
+                    -1, 0);
+  volatile char *addr = page + 16;
+  faults = 0;
+  if (sigsetjmp(env, 1) == 0) {
+    *addr = 1;
+    puts("test_accerr unreachable");
+  }
+  if (faults == 1 && fault_addr == addr && fault_code == SEGV_ACCERR) {
+    puts("test_accerr ok1");
+  }
+  // The page stays readable, and becomes writable with mprotect.
+  mprotect(page, page_size, PROT_READ | PROT_WRITE);
+  *addr = 2;
+  if (*addr == 2 && faults == 1) {
+    puts("test_accerr ok2");
+  }
+  munmap(page, page_size);
+}
+
+void test_maperr() {
+  long page_size = sysconf(_SC_PAGESIZE);
+  char *page = mmap(NULL, page_size, PROT_READ | PROT_WRITE,
+                    MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  munmap(page, page_size);
+  volatile char *addr = page + 8;
+  faults = 0;
+  if (sigsetjmp(env, 1) == 0) {
+    *addr = 1;
+    puts("test_maperr unreachable");
+  }
+  if (faults == 1 && fault_addr == addr && fault_code == SEGV_MAPERR) {
+    puts("test_maperr ok");
+  }
+}
+
+void test_repeat() {
+  // Leaving with siglongjmp restores the signal mask, so SIGSEGV is not
+  // left blocked for the next fault.
+  faults = 0;
+  for (int i = 0; i < 3; i++) {
+    if (sigsetjmp(env, 1) == 0) {
+      *(volatile int *)8 = 1;
+    }
+  }
+  sigset_t mask;
+  sigprocmask(SIG_BLOCK, NULL, &mask);
+  if (faults == 3 && !sigismember(&mask, SIGSEGV)) {
+    puts("test_repeat ok");
+  }
+}
+
+int main() {
+  set_handler();
+  test_accerr();
+  test_maperr();
+  test_repeat();
+  return 0;
+}
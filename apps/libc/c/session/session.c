@@ -0,0 +1,87 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_setsid_leader() {
+  if (fork() == 0) {
+    // A group leader may not start a session, which would take its group
+    // away from the other members.
+    setpgid(0, 0);
+    if (setsid() == -1 && errno == EPERM) {
+      puts("test_setsid_leader ok1");
+    }
+    exit(0);
+  }
+  wait(NULL);
+  if (fork() == 0) {
+    pid_t sid = setsid();
+    if (sid == getpid() && getsid(0) == sid && getpgid(0) == sid) {
+      puts("test_setsid_leader ok2");
+    }
+    exit(0);
+  }
+  wait(NULL);
+}
+
+void test_setpgid_session() {
+  int fds[2];
+  pipe(fds);
+  pid_t pid = fork();
+  if (pid == 0) {
+    setsid();
+    write(fds[1], "x", 1);
+    pause();
+    exit(0);
+  }
+  char c;
+  read(fds[0], &c, 1);
+  // The child is in another session now.
+  if (setpgid(pid, pid) == -1 && errno == EPERM) {
+    puts("test_setpgid_session ok1");
+  }
+  if (setpgid(0, pid) == -1 && errno == EPERM) {
+    puts("test_setpgid_session ok2");
+  }
+  kill(pid, SIGKILL);
+  wait(NULL);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_child_ids() {
+  int fds[2];
+  pipe(fds);
+  pid_t pid = fork();
+  if (pid == 0) {
+    setpgid(0, 0);
+    write(fds[1], "x", 1);
+    pause();
+    exit(0);
+  }
+  char c;
+  read(fds[0], &c, 1);
+  if (getpgid(pid) == pid && getpgid(0) == getpgrp()) {
+    puts("test_child_ids ok1");
+  }
+  if (getsid(pid) == getsid(0)) {
+    puts("test_child_ids ok2");
+  }
+  kill(pid, SIGKILL);
+  wait(NULL);
+  if (getpgid(pid) == -1 && errno == ESRCH && getsid(pid) == -1 &&
+      errno == ESRCH) {
+    puts("test_child_ids ok3");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main() {
+  test_setsid_leader();
+  test_setpgid_session();
+  test_child_ids();
+  return 0;
+}
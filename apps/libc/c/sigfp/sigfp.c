@@ -0,0 +1,100 @@
+#define _GNU_SOURCE
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/time.h>
+#include <ucontext.h>
+
+volatile int flag;
+double fp_in = 1.25, fp_junk = -7.5;
+
+// Keeps `fp_in` in an FP/SIMD register while spinning until `flag` is set,
+// then stores the register to `*out`.
+static void spin_with_fp(double *out) {
+#if defined(__x86_64__)
+  asm volatile("movsd %1, %%xmm8\n"
+               "1: cmpl $0, %2\n"
+               "je 1b\n"
+               "movsd %%xmm8, %0"
+               : "=m"(*out)
+               : "m"(fp_in), "m"(flag)
+               : "xmm8");
+#elif defined(__riscv)
+  asm volatile("fld ft0, %1\n"
+               "1: lw t0, %2\n"
+               "beqz t0, 1b\n"
+               "fsd ft0, %0"
+               : "=m"(*out)
+               : "m"(fp_in), "m"(flag)
+               : "ft0", "t0");
+#elif defined(__aarch64__)
+  asm volatile("ldr d16, %1\n"
+               "1: ldr w9, %2\n"
+               "cbz w9, 1b\n"
+               "str d16, %0"
+               : "=m"(*out)
+               : "m"(fp_in), "m"(flag)
+               : "v16", "x9");
+#elif defined(__loongarch64)
+  asm volatile("fld.d $f0, %1\n"
+               "1: ld.w $t0, %2\n"
+               "beqz $t0, 1b\n"
+               "fst.d $f0, %0"
+               : "=m"(*out)
+               : "m"(fp_in), "m"(flag)
+               : "$f0", "$t0");
+#endif
+}
+
+// Overwrites the register `spin_with_fp` uses.
+static void clobber_fp() {
+#if defined(__x86_64__)
+  asm volatile("movsd %0, %%xmm8" ::"m"(fp_junk) : "xmm8");
+#elif defined(__riscv)
+  asm volatile("fld ft0, %0" ::"m"(fp_junk) : "ft0");
+#elif defined(__aarch64__)
+  asm volatile("ldr d16, %0" ::"m"(fp_junk) : "v16");
+#elif defined(__loongarch64)
+  asm volatile("fld.d $f0, %0" ::"m"(fp_junk) : "$f0");
+#endif
+}
+
+int saved_ok;
+
+static void handler(int sig, siginfo_t *info, void *ucontext) {
+#if defined(__x86_64__)
+  // The signal frame holds the interrupted FP state.
+  ucontext_t *uc = ucontext;
+  double saved;
+  if (uc->uc_mcontext.fpregs) {
+    memcpy(&saved, &uc->uc_mcontext.fpregs->_xmm[8], sizeof(saved));
+    saved_ok = saved == fp_in;
+  }
+#else
+  saved_ok = 1;
+#endif
+  clobber_fp();
+  flag = 1;
+}
+
+void test_clobber() {
+  struct sigaction sa = {0};
+  sa.sa_sigaction = handler;
+  sa.sa_flags = SA_SIGINFO;
+  sigaction(SIGALRM, &sa, NULL);
+  struct itimerval it = {{0, 0}, {0, 10000}};
+  setitimer(ITIMER_REAL, &it, NULL);
+  double out = 0;
+  spin_with_fp(&out);
+  if (saved_ok) {
+    puts("test_clobber ok1");
+  }
+  if (out == fp_in) {
+    puts("test_clobber ok2");
+  }
+}
+
+int main() {
+  test_clobber();
+  return 0;
+}
@@ -162,6 +162,30 @@ void test_sigsuspend() {
   kill(pid, SIGUSR1);
 }
 
+void test_sigchld() {
+  sigset_t set, old;
+  sigemptyset(&set);
+  sigaddset(&set, SIGCHLD);
+  sigprocmask(SIG_BLOCK, &set, &old);
+  pid_t pid = fork();
+  if (pid == 0) {
+    setuid(1234);
+    exit(3);
+  }
+  siginfo_t info;
+  struct timespec timeout = {5, 0};
+  if (sigtimedwait(&set, &info, &timeout) == SIGCHLD && info.si_pid == pid &&
+      info.si_code == CLD_EXITED && info.si_status == 3) {
+    puts("test_sigchld ok1");
+  }
+  // The real uid of the child when it exited
+  if (info.si_uid == 1234) {
+    puts("test_sigchld ok2");
+  }
+  waitpid(pid, NULL, 0);
+  sigprocmask(SIG_SETMASK, &old, NULL);
+}
+
 int main() {
   test_term();
   test_sigaction();
@@ -169,5 +193,6 @@ int main() {
   test_sigkill_stop();
   test_sigwait();
   test_sigsuspend();
+  test_sigchld();
   return 0;
 }
\ No newline at end of file
@@ -0,0 +1,105 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int fired = 0;
+static volatile int value = 0;
+
+static void timer_handler(int signum, siginfo_t *info, void *ucontext) {
+  if (info->si_code == SI_TIMER) {
+    fired++;
+    value = info->si_value.sival_int;
+  }
+}
+
+void test_signal() {
+  struct sigaction sa = {0};
+  sa.sa_sigaction = timer_handler;
+  sa.sa_flags = SA_SIGINFO;
+  sigaction(SIGUSR1, &sa, NULL);
+
+  struct sigevent sev = {0};
+  sev.sigev_notify = SIGEV_SIGNAL;
+  sev.sigev_signo = SIGUSR1;
+  sev.sigev_value.sival_int = 42;
+  timer_t timer;
+  if (timer_create(CLOCK_MONOTONIC, &sev, &timer) == 0) {
+    puts("test_timer_signal ok1");
+  }
+
+  struct itimerspec its = {{0, 50000000}, {0, 50000000}};
+  timer_settime(timer, 0, &its, NULL);
+  while (fired < 3)
+    pause();
+  if (value == 42) {
+    puts("test_timer_signal ok2");
+  }
+
+  struct itimerspec curr;
+  timer_gettime(timer, &curr);
+  if (curr.it_interval.tv_nsec == 50000000 &&
+      (curr.it_value.tv_sec > 0 || curr.it_value.tv_nsec > 0)) {
+    puts("test_timer_signal ok3");
+  }
+
+  timer_delete(timer);
+  if (timer_gettime(timer, &curr) < 0 && errno == EINVAL) {
+    puts("test_timer_signal ok4");
+  }
+}
+
+void test_abstime() {
+  struct sigevent sev = {0};
+  sev.sigev_notify = SIGEV_SIGNAL;
+  sev.sigev_signo = SIGUSR2;
+  timer_t timer;
+  timer_create(CLOCK_REALTIME, &sev, &timer);
+
+  sigset_t set;
+  sigemptyset(&set);
+  sigaddset(&set, SIGUSR2);
+  sigprocmask(SIG_BLOCK, &set, NULL);
+
+  struct itimerspec its = {0};
+  clock_gettime(CLOCK_REALTIME, &its.it_value);
+  its.it_value.tv_nsec += 100000000;
+  if (its.it_value.tv_nsec >= 1000000000) {
+    its.it_value.tv_sec++;
+    its.it_value.tv_nsec -= 1000000000;
+  }
+  timer_settime(timer, TIMER_ABSTIME, &its, NULL);
+
+  siginfo_t info;
+  struct timespec timeout = {2, 0};
+  if (sigtimedwait(&set, &info, &timeout) == SIGUSR2 &&
+      info.si_code == SI_TIMER) {
+    puts("test_timer_abstime ok");
+  }
+  sigprocmask(SIG_UNBLOCK, &set, NULL);
+  timer_delete(timer);
+}
+
+void test_sigev_none() {
+  struct sigevent sev = {0};
+  sev.sigev_notify = SIGEV_NONE;
+  timer_t timer;
+  timer_create(CLOCK_MONOTONIC, &sev, &timer);
+
+  struct itimerspec its = {{0, 0}, {10, 0}};
+  timer_settime(timer, 0, &its, NULL);
+  struct itimerspec curr;
+  timer_gettime(timer, &curr);
+  if (curr.it_value.tv_sec <= 10 && curr.it_value.tv_sec >= 9) {
+    puts("test_timer_sigev_none ok");
+  }
+  timer_delete(timer);
+}
+
+int main() {
+  test_signal();
+  test_abstime();
+  test_sigev_none();
+  return 0;
+}
@@ -0,0 +1,146 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <unistd.h>
+
+int master, slave;
+
+void open_pty() {
+  master = posix_openpt(O_RDWR | O_NOCTTY);
+  grantpt(master);
+  unlockpt(master);
+  slave = open(ptsname(master), O_RDWR | O_NOCTTY);
+  struct termios t;
+  tcgetattr(slave, &t);
+  t.c_lflag &= ~ECHO;
+  tcsetattr(slave, TCSANOW, &t);
+}
+
+void close_pty() {
+  close(slave);
+  close(master);
+}
+
+void test_canonical() {
+  open_pty();
+  char buf[16] = {0};
+  // Erase and kill edit the line, and a read returns one line at most.
+  write(master, "xy\025abd\177c\nde", 11);
+  if (read(slave, buf, sizeof(buf) - 1) == 4 && strcmp(buf, "abc\n") == 0) {
+    puts("test_canonical ok1");
+  }
+  // End of file completes the line without adding to it.
+  write(master, "f\004", 2);
+  memset(buf, 0, sizeof(buf));
+  if (read(slave, buf, sizeof(buf) - 1) == 3 && strcmp(buf, "def") == 0) {
+    puts("test_canonical ok2");
+  }
+  write(master, "\004", 1);
+  if (read(slave, buf, sizeof(buf) - 1) == 0) {
+    puts("test_canonical ok3");
+  }
+  close_pty();
+}
+
+void test_raw() {
+  open_pty();
+  struct termios t;
+  tcgetattr(slave, &t);
+  cfmakeraw(&t);
+  t.c_cc[VMIN] = 0;
+  t.c_cc[VTIME] = 1;
+  tcsetattr(slave, TCSANOW, &t);
+  char buf[16] = {0};
+  // Raw input is not edited, and needs no line end.
+  write(master, "ab\177\025c", 5);
+  if (read(slave, buf, sizeof(buf) - 1) == 5 &&
+      memcmp(buf, "ab\177\025c", 5) == 0) {
+    puts("test_raw ok1");
+  }
+  // With no input, the read times out after VTIME.
+  if (read(slave, buf, sizeof(buf) - 1) == 0) {
+    puts("test_raw ok2");
+  }
+  close_pty();
+}
+
+// Runs `op` on the terminal in a background process group of its session,
+// and checks that it is stopped by `signo`.
+void check_stopped(const char *name, void (*op)(void), int signo) {
+  pid_t pid = fork();
+  if (pid == 0) {
+    setpgid(0, 0);
+    op();
+    exit(0);
+  }
+  int status;
+  waitpid(pid, &status, WUNTRACED);
+  if (WIFSTOPPED(status) && WSTOPSIG(status) == signo) {
+    printf("%s ok\n", name);
+  }
+  kill(pid, SIGKILL);
+  waitpid(pid, NULL, 0);
+}
+
+void bg_read() {
+  char c;
+  read(slave, &c, 1);
+}
+
+void bg_write() { write(slave, "x", 1); }
+
+void bg_tcsetattr() {
+  struct termios t;
+  tcgetattr(slave, &t);
+  tcsetattr(slave, TCSANOW, &t);
+}
+
+void test_background() {
+  open_pty();
+  if (fork() == 0) {
+    setsid();
+    ioctl(slave, TIOCSCTTY, 0);
+    check_stopped("test_background_read", bg_read, SIGTTIN);
+    check_stopped("test_background_tcsetattr", bg_tcsetattr, SIGTTOU);
+
+    pid_t pid = fork();
+    if (pid == 0) {
+      setpgid(0, 0);
+      // Without TOSTOP, writes from the background go through.
+      if (write(slave, "x", 1) == 1) {
+        puts("test_background_write ok1");
+      }
+      // Reads fail instead if SIGTTIN is ignored.
+      signal(SIGTTIN, SIG_IGN);
+      char c;
+      if (read(slave, &c, 1) == -1 && errno == EIO) {
+        puts("test_background_read_ignored ok");
+      }
+      exit(0);
+    }
+    waitpid(pid, NULL, 0);
+
+    struct termios t;
+    tcgetattr(slave, &t);
+    t.c_lflag |= TOSTOP;
+    tcsetattr(slave, TCSANOW, &t);
+    check_stopped("test_background_write", bg_write, SIGTTOU);
+    exit(0);
+  }
+  wait(NULL);
+  close_pty();
+}
+
+int main() {
+  test_canonical();
+  test_raw();
+  test_background();
+  return 0;
+}
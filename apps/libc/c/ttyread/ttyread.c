@@ -0,0 +1,89 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <time.h>
+#include <unistd.h>
+
+int master, slave;
+
+void open_pty() {
+  master = posix_openpt(O_RDWR | O_NOCTTY);
+  grantpt(master);
+  unlockpt(master);
+  slave = open(ptsname(master), O_RDWR | O_NOCTTY);
+}
+
+void close_pty() {
+  close(slave);
+  close(master);
+}
+
+long elapsed_ms(struct timespec *start) {
+  struct timespec now;
+  clock_gettime(CLOCK_MONOTONIC, &now);
+  return (now.tv_sec - start->tv_sec) * 1000 +
+         (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+// Reads from `fd`, which blocks until the child writes to `peer` after a
+// delay.
+void read_blocked(const char *name, int fd, int peer, const char *data) {
+  if (fork() == 0) {
+    usleep(100000);
+    write(peer, data, strlen(data));
+    exit(0);
+  }
+  struct timespec start;
+  clock_gettime(CLOCK_MONOTONIC, &start);
+  char buf[16] = {0};
+  int n = read(fd, buf, sizeof(buf) - 1);
+  if (n == strlen(data) && strcmp(buf, data) == 0) {
+    printf("%s ok1\n", name);
+  }
+  if (elapsed_ms(&start) >= 50) {
+    printf("%s ok2\n", name);
+  }
+  wait(NULL);
+}
+
+void test_raw() {
+  open_pty();
+  struct termios t;
+  tcgetattr(slave, &t);
+  cfmakeraw(&t);
+  tcsetattr(slave, TCSANOW, &t);
+  read_blocked("test_raw_slave", slave, master, "abc");
+  read_blocked("test_raw_master", master, slave, "xyz");
+  close_pty();
+}
+
+void test_canonical() {
+  open_pty();
+  struct termios t;
+  tcgetattr(slave, &t);
+  t.c_lflag &= ~ECHO;
+  tcsetattr(slave, TCSANOW, &t);
+  // A canonical read waits for the whole line.
+  if (fork() == 0) {
+    write(master, "ab", 2);
+    usleep(100000);
+    write(master, "c\n", 2);
+    exit(0);
+  }
+  char buf[16] = {0};
+  if (read(slave, buf, sizeof(buf) - 1) == 4 && strcmp(buf, "abc\n") == 0) {
+    puts("test_canonical ok");
+  }
+  wait(NULL);
+  close_pty();
+}
+
+int main() {
+  test_raw();
+  test_canonical();
+  return 0;
+}
@@ -0,0 +1,127 @@
+#include <elf.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+#include <sys/syscall.h>
+#include <sys/time.h>
+#include <time.h>
+#include <unistd.h>
+
+// Finds the vDSO function whose name ends with `name`, whatever the prefix
+// of the architecture.
+void *vdso_sym(const char *name) {
+  char *base = (char *)getauxval(AT_SYSINFO_EHDR);
+  if (!base) {
+    return NULL;
+  }
+  Elf64_Ehdr *eh = (Elf64_Ehdr *)base;
+  Elf64_Phdr *ph = (Elf64_Phdr *)(base + eh->e_phoff);
+  long bias = 0;
+  Elf64_Dyn *dyn = NULL;
+  for (int i = 0; i < eh->e_phnum; i++) {
+    if (ph[i].p_type == PT_LOAD) {
+      bias = (long)base + ph[i].p_offset - ph[i].p_vaddr;
+    } else if (ph[i].p_type == PT_DYNAMIC) {
+      dyn = (Elf64_Dyn *)(base + ph[i].p_offset);
+    }
+  }
+  Elf64_Sym *syms = NULL;
+  char *strs = NULL;
+  Elf32_Word *hash = NULL;
+  for (; dyn && dyn->d_tag != DT_NULL; dyn++) {
+    if (dyn->d_tag == DT_SYMTAB) {
+      syms = (Elf64_Sym *)(bias + dyn->d_un.d_ptr);
+    } else if (dyn->d_tag == DT_STRTAB) {
+      strs = (char *)(bias + dyn->d_un.d_ptr);
+    } else if (dyn->d_tag == DT_HASH) {
+      hash = (Elf32_Word *)(bias + dyn->d_un.d_ptr);
+    }
+  }
+  if (!syms || !strs || !hash) {
+    return NULL;
+  }
+  size_t len = strlen(name);
+  for (Elf32_Word i = 0; i < hash[1]; i++) {
+    char *sym = strs + syms[i].st_name;
+    size_t sym_len = strlen(sym);
+    if (syms[i].st_shndx != SHN_UNDEF && sym_len >= len &&
+        strcmp(sym + sym_len - len, name) == 0) {
+      return (void *)(bias + syms[i].st_value);
+    }
+  }
+  return NULL;
+}
+
+long ts_ns(struct timespec *ts) { return ts->tv_sec * 1000000000L + ts->tv_nsec; }
+
+long tv_us(struct timeval *tv) { return tv->tv_sec * 1000000L + tv->tv_usec; }
+
+void test_clock_gettime(clockid_t clock, const char *name) {
+  int (*vdso_clock_gettime)(clockid_t, struct timespec *) =
+      vdso_sym("clock_gettime");
+  if (!vdso_clock_gettime) {
+    return;
+  }
+  // The vDSO reads the same clock as the syscall, in between two calls.
+  struct timespec before, ts, after;
+  syscall(SYS_clock_gettime, clock, &before);
+  int ret = vdso_clock_gettime(clock, &ts);
+  syscall(SYS_clock_gettime, clock, &after);
+  if (ret == 0 && ts_ns(&before) <= ts_ns(&ts) && ts_ns(&ts) <= ts_ns(&after)) {
+    printf("%s ok\n", name);
+  }
+}
+
+void test_gettimeofday() {
+  int (*vdso_gettimeofday)(struct timeval *, void *) = vdso_sym("gettimeofday");
+  if (!vdso_gettimeofday) {
+    return;
+  }
+  struct timeval before, tv, after;
+  syscall(SYS_gettimeofday, &before, NULL);
+  int ret = vdso_gettimeofday(&tv, NULL);
+  syscall(SYS_gettimeofday, &after, NULL);
+  if (ret == 0 && tv_us(&before) <= tv_us(&tv) && tv_us(&tv) <= tv_us(&after)) {
+    puts("test_gettimeofday ok");
+  }
+}
+
+// The sigaction of the kernel, without the restorer libc would set.
+struct ksigaction {
+  void (*handler)(int);
+  unsigned long flags;
+#if defined(__x86_64__) || defined(__aarch64__)
+  void (*restorer)(void);
+#endif
+  unsigned long mask;
+};
+
+void *handler_ret;
+
+void handler(int sig) { handler_ret = __builtin_return_address(0); }
+
+void test_sigreturn() {
+  void *sigreturn = vdso_sym("rt_sigreturn");
+  if (!sigreturn) {
+    return;
+  }
+  struct ksigaction ksa = {0};
+  ksa.handler = handler;
+  syscall(SYS_rt_sigaction, SIGUSR1, &ksa, NULL, 8);
+  kill(getpid(), SIGUSR1);
+  // The handler returns into the trampoline of the vDSO, and from there
+  // back here.
+  if (handler_ret == sigreturn) {
+    puts("test_sigreturn ok");
+  }
+  signal(SIGUSR1, SIG_DFL);
+}
+
+int main() {
+  test_clock_gettime(CLOCK_REALTIME, "test_clock_gettime_realtime");
+  test_clock_gettime(CLOCK_MONOTONIC, "test_clock_gettime_monotonic");
+  test_gettimeofday();
+  test_sigreturn();
+  return 0;
+}
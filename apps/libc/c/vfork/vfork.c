@@ -0,0 +1,62 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int shared = 0;
+
+void test_vfork() {
+  int pid = vfork();
+  if (pid == 0) {
+    // The parent is suspended until the child exits, and they share memory.
+    usleep(100000);
+    shared = 1;
+    _exit(7);
+  }
+  if (shared == 1) {
+    puts("test_vfork ok1");
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (WIFEXITED(status) && WEXITSTATUS(status) == 7) {
+    puts("test_vfork ok2");
+  }
+}
+
+void test_vfork_exec(char *self) {
+  // A failed execve leaves the parent suspended.
+  int pid = vfork();
+  if (pid == 0) {
+    char *argv[] = {"/nonexistent", NULL};
+    execv(argv[0], argv);
+    shared = 2;
+    _exit(3);
+  }
+  int status;
+  waitpid(pid, &status, 0);
+  if (shared == 2 && WIFEXITED(status) && WEXITSTATUS(status) == 3) {
+    puts("test_vfork_exec ok1");
+  }
+
+  // A successful one resumes it.
+  pid = vfork();
+  if (pid == 0) {
+    char *argv[] = {self, "exit", NULL};
+    execv(self, argv);
+    _exit(1);
+  }
+  waitpid(pid, &status, 0);
+  if (WIFEXITED(status) && WEXITSTATUS(status) == 5) {
+    puts("test_vfork_exec ok2");
+  }
+}
+
+int main(int argc, char *argv[]) {
+  if (argc == 2 && strcmp(argv[1], "exit") == 0) {
+    return 5;
+  }
+  test_vfork();
+  test_vfork_exec(argv[0]);
+  return 0;
+}
@@ -0,0 +1,112 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_exited() {
+  int pid = fork();
+  if (pid == 0) {
+    exit(3);
+  }
+  siginfo_t info = {0};
+  // The child stays waitable with WNOWAIT.
+  waitid(P_PID, pid, &info, WEXITED | WNOWAIT);
+  if (info.si_pid == pid && info.si_code == CLD_EXITED && info.si_status == 3) {
+    puts("test_waitid_exited ok1");
+  }
+  info.si_pid = 0;
+  waitid(P_ALL, 0, &info, WEXITED);
+  if (info.si_pid == pid && info.si_signo == SIGCHLD && info.si_uid == 0) {
+    puts("test_waitid_exited ok2");
+  }
+  if (waitid(P_ALL, 0, &info, WEXITED) < 0 && errno == ECHILD) {
+    puts("test_waitid_exited ok3");
+  }
+}
+
+void test_nohang() {
+  int pid = fork();
+  if (pid == 0) {
+    while (1)
+      pause();
+  }
+  siginfo_t info;
+  info.si_pid = 1;
+  if (waitid(P_PID, pid, &info, WEXITED | WNOHANG) == 0 && info.si_pid == 0) {
+    puts("test_waitid_nohang ok");
+  }
+  kill(pid, SIGKILL);
+  waitid(P_PID, pid, &info, WEXITED);
+  if (info.si_code == CLD_KILLED && info.si_status == SIGKILL) {
+    puts("test_waitid_killed ok");
+  }
+}
+
+void test_stopped() {
+  int pid = fork();
+  if (pid == 0) {
+    while (1)
+      pause();
+  }
+  siginfo_t info;
+  kill(pid, SIGSTOP);
+  waitid(P_PID, pid, &info, WSTOPPED);
+  if (info.si_code == CLD_STOPPED && info.si_status == SIGSTOP) {
+    puts("test_waitid_stopped ok");
+  }
+  kill(pid, SIGCONT);
+  waitid(P_PID, pid, &info, WCONTINUED);
+  if (info.si_code == CLD_CONTINUED && info.si_status == SIGCONT) {
+    puts("test_waitid_continued ok");
+  }
+  kill(pid, SIGKILL);
+  waitid(P_PID, pid, &info, WEXITED);
+}
+
+void test_pgid() {
+  int pid = fork();
+  if (pid == 0) {
+    setpgid(0, 0);
+    usleep(100000);
+    exit(0);
+  }
+  setpgid(pid, pid);
+  siginfo_t info;
+  if (waitid(P_PGID, pid, &info, WEXITED) == 0 && info.si_pid == pid) {
+    puts("test_waitid_pgid ok");
+  }
+}
+
+void test_pidfd() {
+  int pid = fork();
+  if (pid == 0) {
+    exit(4);
+  }
+  int pidfd = syscall(SYS_pidfd_open, pid, 0);
+  siginfo_t info;
+  if (waitid(P_PIDFD, pidfd, &info, WEXITED) == 0 && info.si_pid == pid &&
+      info.si_status == 4) {
+    puts("test_waitid_pidfd ok");
+  }
+  close(pidfd);
+}
+
+void test_invalid() {
+  siginfo_t info;
+  if (waitid(P_ALL, 0, &info, 0) < 0 && errno == EINVAL) {
+    puts("test_waitid_invalid ok");
+  }
+}
+
+int main() {
+  test_exited();
+  test_nohang();
+  test_stopped();
+  test_pgid();
+  test_pidfd();
+  test_invalid();
+  return 0;
+}
@@ -6,7 +6,8 @@ mod file;
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
 
-use alloc::{string::String, vec::Vec};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use axfs_vfs::VfsOps;
 use axio::{self as io, prelude::*};
 
 /// Returns an iterator over the entries within a directory.
@@ -92,3 +93,8 @@ pub fn rename(old: &str, new: &str) -> io::Result<()> {
 pub fn absolute_path_exists(path: &str) -> bool {
     crate::root::lookup(None, path).is_ok()
 }
+
+/// Mounts a filesystem at the specified absolute path.
+pub fn mount(path: &'static str, fs: Arc<dyn VfsOps>) -> io::Result<()> {
+    crate::root::mount(path, fs)
+}
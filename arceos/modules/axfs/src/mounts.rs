@@ -10,6 +10,8 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let bar = fs::devfs::ZeroDev;
     let devfs = fs::devfs::DeviceFileSystem::new();
     let foo_dir = devfs.mkdir("foo");
+    // Mount point of devpts
+    devfs.mkdir("pts");
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
     foo_dir.add("bar", Arc::new(bar));
@@ -34,6 +36,12 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
     file_somaxconn.write_at(0, b"4096\n")?;
 
+    // Create /proc/sys/kernel/core_pattern
+    proc_root.create("sys/kernel", VfsNodeType::Dir)?;
+    proc_root.create("sys/kernel/core_pattern", VfsNodeType::File)?;
+    let file_core_pattern = proc_root.clone().lookup("./sys/kernel/core_pattern")?;
+    file_core_pattern.write_at(0, b"core\n")?;
+
     // Create /proc/sys/vm/overcommit_memory
     proc_root.create("sys/vm", VfsNodeType::Dir)?;
     proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
@@ -301,6 +301,10 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     }
 }
 
+pub(crate) fn mount(path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    ROOT_DIR.mount(path, fs)
+}
+
 pub(crate) fn current_dir() -> AxResult<String> {
     Ok(CURRENT_DIR_PATH.lock().clone())
 }
@@ -193,6 +193,11 @@ impl UspaceContext {
                 msr     elr_el1, x11
                 msr     spsr_el1, x12
 
+                // software step, see `RESTORE_REGS` in trap.S
+                mrs     x9, mdscr_el1
+                bfxil   x9, x12, #21, #1
+                msr     mdscr_el1, x9
+
                 ldr     x30, [x0, 30 * 8]
                 ldp     x28, x29, [x0, 28 * 8]
                 ldp     x26, x27, [x0, 26 * 8]
@@ -246,10 +251,25 @@ pub struct FpState {
     pub fpsr: u32,
 }
 
-#[cfg(feature = "fp_simd")]
 impl FpState {
-    fn switch_to(&mut self, next_fpstate: &FpState) {
-        unsafe { fpstate_switch(self, next_fpstate) }
+    /// Saves the FP/SIMD states of the current CPU.
+    ///
+    /// It does nothing if the `fp_simd` feature is disabled.
+    pub fn save(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            fpstate_save(self)
+        }
+    }
+
+    /// Restores the FP/SIMD states of the current CPU.
+    ///
+    /// It does nothing if the `fp_simd` feature is disabled.
+    pub fn restore(&self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            fpstate_restore(self)
+        }
     }
 }
 
@@ -330,7 +350,10 @@ impl TaskContext {
             unsafe { super::write_thread_pointer(next_ctx.tpidr_el0 as _) };
         }
         #[cfg(feature = "fp_simd")]
-        self.fp_state.switch_to(&next_ctx.fp_state);
+        {
+            self.fp_state.save();
+            next_ctx.fp_state.restore();
+        }
         #[cfg(feature = "uspace")]
         {
             if self.ttbr0_el1 != next_ctx.ttbr0_el1 {
@@ -373,10 +396,9 @@ unsafe extern "C" fn context_switch(_current_task: &mut TaskContext, _next_task:
 
 #[naked]
 #[cfg(feature = "fp_simd")]
-unsafe extern "C" fn fpstate_switch(_current_fpstate: &mut FpState, _next_fpstate: &FpState) {
+unsafe extern "C" fn fpstate_save(_fpstate: &mut FpState) {
     naked_asm!(
         "
-        // save fp/neon context
         mrs     x9, fpcr
         mrs     x10, fpsr
         stp     q0, q1, [x0, 0 * 16]
@@ -395,31 +417,37 @@ unsafe extern "C" fn fpstate_switch(_current_fpstate: &mut FpState, _next_fpstat
         stp     q26, q27, [x0, 26 * 16]
         stp     q28, q29, [x0, 28 * 16]
         stp     q30, q31, [x0, 30 * 16]
-        str     x9, [x0, 64 *  8]
-        str     x10, [x0, 65 * 8]
-
-        // restore fp/neon context
-        ldp     q0, q1, [x1, 0 * 16]
-        ldp     q2, q3, [x1, 2 * 16]
-        ldp     q4, q5, [x1, 4 * 16]
-        ldp     q6, q7, [x1, 6 * 16]
-        ldp     q8, q9, [x1, 8 * 16]
-        ldp     q10, q11, [x1, 10 * 16]
-        ldp     q12, q13, [x1, 12 * 16]
-        ldp     q14, q15, [x1, 14 * 16]
-        ldp     q16, q17, [x1, 16 * 16]
-        ldp     q18, q19, [x1, 18 * 16]
-        ldp     q20, q21, [x1, 20 * 16]
-        ldp     q22, q23, [x1, 22 * 16]
-        ldp     q24, q25, [x1, 24 * 16]
-        ldp     q26, q27, [x1, 26 * 16]
-        ldp     q28, q29, [x1, 28 * 16]
-        ldp     q30, q31, [x1, 30 * 16]
-        ldr     x9, [x1, 64 * 8]
-        ldr     x10, [x1, 65 * 8]
+        str     w9, [x0, 32 * 16]
+        str     w10, [x0, 32 * 16 + 4]
+        ret",
+    )
+}
+
+#[naked]
+#[cfg(feature = "fp_simd")]
+unsafe extern "C" fn fpstate_restore(_fpstate: &FpState) {
+    naked_asm!(
+        "
+        ldp     q0, q1, [x0, 0 * 16]
+        ldp     q2, q3, [x0, 2 * 16]
+        ldp     q4, q5, [x0, 4 * 16]
+        ldp     q6, q7, [x0, 6 * 16]
+        ldp     q8, q9, [x0, 8 * 16]
+        ldp     q10, q11, [x0, 10 * 16]
+        ldp     q12, q13, [x0, 12 * 16]
+        ldp     q14, q15, [x0, 14 * 16]
+        ldp     q16, q17, [x0, 16 * 16]
+        ldp     q18, q19, [x0, 18 * 16]
+        ldp     q20, q21, [x0, 20 * 16]
+        ldp     q22, q23, [x0, 22 * 16]
+        ldp     q24, q25, [x0, 24 * 16]
+        ldp     q26, q27, [x0, 26 * 16]
+        ldp     q28, q29, [x0, 28 * 16]
+        ldp     q30, q31, [x0, 30 * 16]
+        ldr     w9, [x0, 32 * 16]
+        ldr     w10, [x0, 32 * 16 + 4]
         msr     fpcr, x9
         msr     fpsr, x10
-
         isb
         ret",
     )
@@ -142,11 +142,13 @@ pub unsafe fn write_thread_pointer(tpidr_el0: usize) {
 
 /// Initializes CPU states on the current CPU.
 ///
-/// On AArch64, it sets the exception vector base address (`VBAR_EL1`) and `TTBR0_EL1`.
+/// On AArch64, it sets the exception vector base address (`VBAR_EL1`) and `TTBR0_EL1`,
+/// and clears the OS lock, which disables debug exceptions such as software steps.
 pub fn cpu_init() {
     unsafe extern "C" {
         fn exception_vector_base();
     }
     set_exception_vector_base(exception_vector_base as usize);
     unsafe { write_page_table_root0(0.into()) }; // disable low address access in EL1
+    unsafe { asm!("msr oslar_el1, xzr; isb") };
 }
@@ -1,10 +1,13 @@
 use core::arch::global_asm;
 
 use aarch64_cpu::registers::{ESR_EL1, FAR_EL1};
+use memory_addr::VirtAddr;
 use page_table_entry::MappingFlags;
 use tock_registers::interfaces::Readable;
 
 use super::TrapFrame;
+#[cfg(feature = "uspace")]
+use crate::trap::UserException;
 
 global_asm!(
     include_str!("trap.S"),
@@ -51,12 +54,33 @@ fn handle_irq_exception(tf: &mut TrapFrame, source: TrapSource) {
     crate::trap::post_trap_callback(tf, source.is_from_user());
 }
 
+/// Reports an abort by user code other than a translation or permission
+/// fault, returning whether the abort was one.
+#[cfg(feature = "uspace")]
+fn handle_user_abort(tf: &TrapFrame, iss: u64, vaddr: VirtAddr) -> bool {
+    if matches!(iss & 0b111100, 0b0100 | 0b1100) {
+        return false;
+    }
+    // IFSC or DFSC 0b100001 is an alignment fault.
+    let kind = if iss & 0b111111 == 0b100001 {
+        UserException::MisalignedAccess
+    } else {
+        UserException::AccessFault
+    };
+    crate::trap::handle_user_exception(tf, kind, vaddr);
+    true
+}
+
 fn handle_instruction_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
     let mut access_flags = MappingFlags::EXECUTE;
     if is_user {
         access_flags |= MappingFlags::USER;
     }
     let vaddr = va!(FAR_EL1.get() as usize);
+    #[cfg(feature = "uspace")]
+    if is_user && handle_user_abort(tf, iss, vaddr) {
+        return;
+    }
 
     // Only handle Translation fault and Permission fault
     if !matches!(iss & 0b111100, 0b0100 | 0b1100) // IFSC or DFSC bits
@@ -86,6 +110,10 @@ fn handle_data_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
         access_flags |= MappingFlags::USER;
     }
     let vaddr = va!(FAR_EL1.get() as usize);
+    #[cfg(feature = "uspace")]
+    if is_user && handle_user_abort(tf, iss, vaddr) {
+        return;
+    }
 
     // Only handle Translation fault and Permission fault
     if !matches!(iss & 0b111100, 0b0100 | 0b1100) // IFSC or DFSC bits
@@ -103,6 +131,21 @@ fn handle_data_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
     }
 }
 
+/// The kind of an exception raised by user code with the exception class
+/// `ec`, if it is reported to the user process.
+#[cfg(feature = "uspace")]
+fn user_exception(ec: ESR_EL1::EC::Value) -> Option<UserException> {
+    use ESR_EL1::EC::Value;
+    Some(match ec {
+        Value::Unknown | Value::IllegalExecutionState => UserException::IllegalInstruction,
+        Value::Brk64 => UserException::Breakpoint,
+        Value::SoftwareStepLowerEL => UserException::SingleStep,
+        Value::PCAlignmentFault | Value::SPAlignmentFault => UserException::MisalignedAccess,
+        Value::TrappedFP64 => UserException::FloatingPoint,
+        _ => return None,
+    })
+}
+
 #[unsafe(no_mangle)]
 fn handle_sync_exception(tf: &mut TrapFrame, source: TrapSource) {
     let esr = ESR_EL1.extract();
@@ -119,6 +162,16 @@ fn handle_sync_exception(tf: &mut TrapFrame, source: TrapSource) {
         Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => handle_instruction_abort(tf, iss, false),
         Some(ESR_EL1::EC::Value::DataAbortLowerEL) => handle_data_abort(tf, iss, true),
         Some(ESR_EL1::EC::Value::DataAbortCurrentEL) => handle_data_abort(tf, iss, false),
+        #[cfg(feature = "uspace")]
+        Some(ec) if source.is_from_user() && user_exception(ec).is_some() => {
+            // FAR_EL1 holds a misaligned PC, while a misaligned SP is reported at
+            // the instruction using it.
+            let addr = match ec {
+                ESR_EL1::EC::Value::PCAlignmentFault => FAR_EL1.get(),
+                _ => tf.elr,
+            };
+            crate::trap::handle_user_exception(tf, user_exception(ec).unwrap(), va!(addr as usize));
+        }
         Some(ESR_EL1::EC::Value::Brk64) => {
             debug!("BRK #{:#x} @ {:#x} ", iss, tf.elr);
             tf.elr += 4;
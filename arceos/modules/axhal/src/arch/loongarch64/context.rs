@@ -43,7 +43,6 @@ pub struct GeneralRegisters {
 }
 
 /// Floating-point registers of LoongArch64.
-#[cfg(feature = "fp_simd")]
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
 pub struct FpStatus {
@@ -55,6 +54,28 @@ pub struct FpStatus {
     pub fcsr: usize,
 }
 
+impl FpStatus {
+    /// Saves the floating-point registers of the current CPU.
+    ///
+    /// It does nothing if the `fp_simd` feature is disabled.
+    pub fn save(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            save_fp_registers(self)
+        }
+    }
+
+    /// Restores the floating-point registers of the current CPU.
+    ///
+    /// It does nothing if the `fp_simd` feature is disabled.
+    pub fn restore(&self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            restore_fp_registers(self)
+        }
+    }
+}
+
 /// Saved registers when a trap (interrupt or exception) occurs.
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
@@ -331,9 +352,9 @@ impl TaskContext {
             }
         }
         #[cfg(feature = "fp_simd")]
-        unsafe {
-            save_fp_registers(&mut self.fp_status);
-            restore_fp_registers(&next_ctx.fp_status);
+        {
+            self.fp_status.save();
+            next_ctx.fp_status.restore();
         }
 
         unsafe { context_switch(self, next_ctx) }
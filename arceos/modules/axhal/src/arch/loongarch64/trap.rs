@@ -1,4 +1,6 @@
 use super::context::TrapFrame;
+#[cfg(feature = "uspace")]
+use crate::trap::UserException;
 use loongArch64::register::{
     badv,
     estat::{self, Exception, Trap},
@@ -16,6 +18,23 @@ fn handle_breakpoint(era: &mut usize) {
     *era += 4;
 }
 
+/// The kind of an exception raised by user code, if it is reported to the
+/// user process.
+#[cfg(feature = "uspace")]
+fn user_exception(e: Exception) -> Option<UserException> {
+    Some(match e {
+        Exception::InstructionNotExist | Exception::InstructionPrivilegeIllegal => {
+            UserException::IllegalInstruction
+        }
+        Exception::Breakpoint => UserException::Breakpoint,
+        Exception::AddressNotAligned => UserException::MisalignedAccess,
+        Exception::FetchInstructionAddressError
+        | Exception::MemoryAccessAddressError
+        | Exception::PagePrivilegeIllegal => UserException::AccessFault,
+        _ => return None,
+    })
+}
+
 fn handle_page_fault(tf: &TrapFrame, mut access_flags: MappingFlags, is_user: bool) {
     if is_user {
         access_flags |= MappingFlags::USER;
@@ -60,6 +79,18 @@ fn loongarch64_trap_handler(tf: &mut TrapFrame, from_user: bool) {
         | Trap::Exception(Exception::PageNonExecutableFault) => {
             handle_page_fault(tf, MappingFlags::EXECUTE, from_user);
         }
+        #[cfg(feature = "uspace")]
+        Trap::Exception(e) if from_user && user_exception(e).is_some() => {
+            // An illegal instruction or breakpoint is reported at the
+            // instruction itself.
+            let addr = match e {
+                Exception::InstructionNotExist
+                | Exception::InstructionPrivilegeIllegal
+                | Exception::Breakpoint => tf.era,
+                _ => badv::read().raw(),
+            };
+            crate::trap::handle_user_exception(tf, user_exception(e).unwrap(), va!(addr));
+        }
         Trap::Exception(Exception::Breakpoint) => handle_breakpoint(&mut tf.era),
         Trap::Interrupt(_) => {
             let irq_num: usize = estat.is().trailing_zeros() as usize;
@@ -1,6 +1,5 @@
 use core::arch::naked_asm;
 use memory_addr::VirtAddr;
-#[cfg(feature = "fp_simd")]
 use riscv::register::sstatus::FS;
 
 /// General registers of RISC-V.
@@ -42,7 +41,6 @@ pub struct GeneralRegisters {
 }
 
 /// Floating-point registers of RISC-V.
-#[cfg(feature = "fp_simd")]
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct FpStatus {
@@ -52,7 +50,6 @@ pub struct FpStatus {
     pub fs: FS,
 }
 
-#[cfg(feature = "fp_simd")]
 impl Default for FpStatus {
     fn default() -> Self {
         Self {
@@ -63,6 +60,30 @@ impl Default for FpStatus {
     }
 }
 
+impl FpStatus {
+    /// Saves the floating-point registers and `fcsr` of the current CPU.
+    ///
+    /// It does nothing if the `fp_simd` feature is disabled.
+    pub fn save(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            save_fp_registers(&mut self.fp)
+        }
+    }
+
+    /// Restores the floating-point registers and `fcsr` of the current CPU.
+    ///
+    /// The caller must mark the FP state as dirty in `sstatus`, so that it is
+    /// saved on the next context switch. It does nothing if the `fp_simd`
+    /// feature is disabled.
+    pub fn restore(&self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            restore_fp_registers(&self.fp)
+        }
+    }
+}
+
 /// Saved registers when a trap (interrupt or exception) occurs.
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
@@ -208,6 +229,8 @@ impl UspaceContext {
             const BIT_FS: usize = 13;
             sstatus |= (FS::Initial as usize) << BIT_FS;
         }
+        // `sstatus.VS` is left off: vector registers are not switched, so
+        // vector instructions raise illegal instruction exceptions.
 
         Self(TrapFrame {
             regs: GeneralRegisters {
@@ -385,7 +408,6 @@ impl TaskContext {
         #[cfg(feature = "fp_simd")]
         {
             use riscv::register::sstatus;
-            use riscv::register::sstatus::FS;
             // get the real FP state of the current task
             let current_fs = sstatus::read().fs();
             // save the current task's FP state
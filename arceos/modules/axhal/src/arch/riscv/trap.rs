@@ -5,6 +5,8 @@ use riscv::interrupt::supervisor::{Exception as E, Interrupt as I};
 use riscv::register::{scause, stval};
 
 use super::TrapFrame;
+#[cfg(feature = "uspace")]
+use crate::trap::UserException;
 
 core::arch::global_asm!(
     include_asm_macros!(),
@@ -17,6 +19,21 @@ fn handle_breakpoint(sepc: &mut usize) {
     *sepc += 2
 }
 
+/// The kind of an exception raised by user code, if it is reported to the
+/// user process.
+#[cfg(feature = "uspace")]
+fn user_exception(e: E) -> Option<UserException> {
+    Some(match e {
+        E::IllegalInstruction => UserException::IllegalInstruction,
+        E::Breakpoint => UserException::Breakpoint,
+        E::InstructionMisaligned | E::LoadMisaligned | E::StoreMisaligned => {
+            UserException::MisalignedAccess
+        }
+        E::InstructionFault | E::LoadFault | E::StoreFault => UserException::AccessFault,
+        _ => return None,
+    })
+}
+
 fn handle_page_fault(
     tf: &TrapFrame,
     vaddr: VirtAddr,
@@ -63,6 +80,16 @@ fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
             Trap::Exception(E::InstructionPageFault) => {
                 handle_page_fault(tf, vaddr, MappingFlags::EXECUTE, from_user)
             }
+            #[cfg(feature = "uspace")]
+            Trap::Exception(e) if from_user && user_exception(e).is_some() => {
+                // An illegal instruction or breakpoint is reported at the
+                // instruction itself, where `stval` may hold its encoding.
+                let addr = match e {
+                    E::IllegalInstruction | E::Breakpoint => va!(tf.sepc),
+                    _ => vaddr,
+                };
+                crate::trap::handle_user_exception(tf, user_exception(e).unwrap(), addr);
+            }
             Trap::Exception(E::Breakpoint) => handle_breakpoint(&mut tf.sepc),
             Trap::Interrupt(_) => {
                 handle_trap!(IRQ, scause.bits());
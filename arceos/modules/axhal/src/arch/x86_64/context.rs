@@ -1,4 +1,8 @@
-use core::{arch::naked_asm, fmt};
+use core::{
+    arch::naked_asm,
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
 use memory_addr::VirtAddr;
 /// Saved registers when a trap (interrupt or exception) occurs.
 #[allow(missing_docs)]
@@ -272,7 +276,7 @@ struct ContextSwitchFrame {
 /// See <https://www.felixcloutier.com/x86/fxsave> for more details.
 #[allow(missing_docs)]
 #[repr(C, align(16))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FxsaveArea {
     pub fcw: u16,
     pub fsw: u16,
@@ -284,35 +288,121 @@ pub struct FxsaveArea {
     pub mxcsr_mask: u32,
     pub st: [u64; 16],
     pub xmm: [u64; 32],
-    _padding: [u64; 12],
+    _reserved: [u64; 6],
+    /// Bytes left to software by the processor, which describe the extended
+    /// state in a Linux signal frame.
+    pub sw_reserved: [u32; 12],
 }
 
 static_assertions::const_assert_eq!(core::mem::size_of::<FxsaveArea>(), 512);
 
+/// The state components saved in an [`ExtendedState`] by XSAVE, as a value of
+/// `XCR0`, or 0 if XSAVE is not supported and FXSAVE is used instead.
+static XSAVE_MASK: AtomicU64 = AtomicU64::new(0);
+
+/// Enables XSAVE for the x87, SSE and AVX state components on the current
+/// CPU, if it is supported.
+#[cfg(feature = "fp_simd")]
+pub(super) fn init_xsave() {
+    use x86_64::registers::control::{Cr4, Cr4Flags};
+    use x86_64::registers::xcontrol::{XCr0, XCr0Flags};
+
+    let Some(features) = raw_cpuid::CpuId::new().get_feature_info() else {
+        return;
+    };
+    if !features.has_xsave() {
+        return;
+    }
+    let mut mask = XCr0Flags::X87 | XCr0Flags::SSE;
+    if features.has_avx() {
+        mask |= XCr0Flags::AVX;
+    }
+    unsafe {
+        Cr4::update(|cr4| cr4.insert(Cr4Flags::OSXSAVE));
+        XCr0::write(mask);
+    }
+    XSAVE_MASK.store(mask.bits(), Ordering::Relaxed);
+}
+
 /// Extended state of a task, such as FP/SIMD states.
+///
+/// It is in the standard format of the XSAVE instruction: the legacy region
+/// of FXSAVE, the XSAVE header and the AVX state. Without XSAVE, only the
+/// legacy region is used.
+///
+/// See Intel SDM Vol. 1, Chapter 13 for more details.
+#[repr(C, align(64))]
+#[derive(Clone)]
 pub struct ExtendedState {
     /// Memory region for the FXSAVE/FXRSTOR instruction.
     pub fxsave_area: FxsaveArea,
+    /// The XSAVE header, whose first word is the bitmap of the state
+    /// components that are not in their initial configuration.
+    pub xsave_header: [u64; 8],
+    /// The upper 128 bits of the YMM registers.
+    pub ymmh: [u128; 16],
 }
 
-#[cfg(feature = "fp_simd")]
+static_assertions::const_assert_eq!(core::mem::size_of::<ExtendedState>(), 832);
+
 impl ExtendedState {
+    /// Returns the state components saved by [`save`](Self::save), as a value
+    /// of `XCR0`, or 0 if only the legacy region is saved with FXSAVE.
+    pub fn xsave_mask() -> u64 {
+        XSAVE_MASK.load(Ordering::Relaxed)
+    }
+
+    /// Saves the FP/SIMD states of the current CPU.
+    ///
+    /// It does nothing if the `fp_simd` feature is disabled.
     #[inline]
-    fn save(&mut self) {
-        unsafe { core::arch::x86_64::_fxsave64(&mut self.fxsave_area as *mut _ as *mut u8) }
+    pub fn save(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            let ptr = self as *mut _ as *mut u8;
+            match Self::xsave_mask() {
+                0 => core::arch::x86_64::_fxsave64(ptr),
+                mask => core::arch::x86_64::_xsave64(ptr, mask),
+            }
+        }
     }
 
+    /// Restores the FP/SIMD states of the current CPU.
+    ///
+    /// The XSAVE header and MXCSR must be valid, or a general protection
+    /// fault occurs. It does nothing if the `fp_simd` feature is disabled.
     #[inline]
-    fn restore(&self) {
-        unsafe { core::arch::x86_64::_fxrstor64(&self.fxsave_area as *const _ as *const u8) }
+    pub fn restore(&self) {
+        #[cfg(feature = "fp_simd")]
+        unsafe {
+            let ptr = self as *const _ as *const u8;
+            match Self::xsave_mask() {
+                0 => core::arch::x86_64::_fxrstor64(ptr),
+                mask => core::arch::x86_64::_xrstor64(ptr, mask),
+            }
+        }
+    }
+
+    /// Makes the states valid to restore: clears the state components and
+    /// MXCSR bits that are not supported.
+    pub fn sanitize(&mut self) {
+        let mxcsr_mask = match self.fxsave_area.mxcsr_mask {
+            0 => 0xffbf,
+            mask => mask,
+        };
+        self.fxsave_area.mxcsr &= mxcsr_mask;
+        self.xsave_header[0] &= Self::xsave_mask();
+        self.xsave_header[1..].fill(0);
     }
 
-    const fn default() -> Self {
-        let mut area: FxsaveArea = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
-        area.fcw = 0x37f;
-        area.ftw = 0xffff;
-        area.mxcsr = 0x1f80;
-        Self { fxsave_area: area }
+    /// Returns the initial states, which new tasks start with.
+    pub const fn new() -> Self {
+        let mut state: Self = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
+        state.fxsave_area.fcw = 0x37f;
+        // The abridged tag word of FXSAVE is 0 with all x87 registers empty.
+        state.fxsave_area.ftw = 0;
+        state.fxsave_area.mxcsr = 0x1f80;
+        state
     }
 }
 
@@ -320,6 +410,7 @@ impl fmt::Debug for ExtendedState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("ExtendedState")
             .field("fxsave_area", &self.fxsave_area)
+            .field("xsave_header", &self.xsave_header)
             .finish()
     }
 }
@@ -380,7 +471,7 @@ impl TaskContext {
             #[cfg(feature = "uspace")]
             cr3: crate::paging::kernel_page_table_root(),
             #[cfg(feature = "fp_simd")]
-            ext_state: ExtendedState::default(),
+            ext_state: ExtendedState::new(),
             #[cfg(feature = "uspace")]
             gs_base: 0,
         }
@@ -395,10 +486,13 @@ impl TaskContext {
             // is executed), (stack pointer + 8) should be 16-byte aligned.
             let frame_ptr = (kstack_top.as_mut_ptr() as *mut u64).sub(1);
             let frame_ptr = (frame_ptr as *mut ContextSwitchFrame).sub(1);
-            core::ptr::write(frame_ptr, ContextSwitchFrame {
-                rip: entry as _,
-                ..Default::default()
-            });
+            core::ptr::write(
+                frame_ptr,
+                ContextSwitchFrame {
+                    rip: entry as _,
+                    ..Default::default()
+                },
+            );
             self.rsp = frame_ptr as u64;
         }
         self.kstack_top = kstack_top;
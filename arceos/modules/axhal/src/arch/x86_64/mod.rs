@@ -129,10 +129,13 @@ pub unsafe fn write_thread_pointer(fs_base: usize) {
 ///
 /// In detail, it initializes the GDT, IDT on x86_64 platforms. If the `uspace`
 /// feature is enabled, it also initializes relevant model-specific registers
-/// to enable the `syscall` instruction.
+/// to enable the `syscall` instruction, and if the `fp_simd` feature is
+/// enabled, it enables XSAVE for the FP/SIMD states if supported.
 pub fn cpu_init() {
     init_gdt();
     init_idt();
+    #[cfg(feature = "fp_simd")]
+    context::init_xsave();
     #[cfg(feature = "uspace")]
     init_syscall();
 }
@@ -3,6 +3,8 @@ use x86::{controlregs::cr2, irq::*};
 use x86_64::structures::idt::PageFaultErrorCode;
 
 use super::context::TrapFrame;
+#[cfg(feature = "uspace")]
+use crate::trap::UserException;
 
 core::arch::global_asm!(include_str!("trap.S"));
 
@@ -38,6 +40,16 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
     }
     match tf.vector as u8 {
         PAGE_FAULT_VECTOR => handle_page_fault(tf),
+        #[cfg(feature = "uspace")]
+        vector if tf.is_user() && user_exception(vector).is_some() => {
+            let kind = user_exception(vector).unwrap();
+            // A general protection fault has no faulting address to report.
+            let addr = match kind {
+                UserException::GeneralProtection => 0,
+                _ => tf.rip as usize,
+            };
+            crate::trap::handle_user_exception(tf, kind, va!(addr));
+        }
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
         GENERAL_PROTECTION_FAULT_VECTOR => {
             panic!(
@@ -66,6 +78,24 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
     super::tls::switch_to_user_fs_base(tf);
 }
 
+/// The kind of an exception raised by user code with the given vector, if it
+/// is reported to the user process.
+#[cfg(feature = "uspace")]
+fn user_exception(vector: u8) -> Option<UserException> {
+    Some(match vector {
+        DIVIDE_ERROR_VECTOR => UserException::DivideByZero,
+        DEBUG_VECTOR => UserException::SingleStep,
+        BREAKPOINT_VECTOR => UserException::Breakpoint,
+        INVALID_OPCODE_VECTOR => UserException::IllegalInstruction,
+        GENERAL_PROTECTION_FAULT_VECTOR | STACK_SEGEMENT_FAULT_VECTOR => {
+            UserException::GeneralProtection
+        }
+        ALIGNMENT_CHECK_VECTOR => UserException::MisalignedAccess,
+        X87_FPU_VECTOR | SIMD_FLOATING_POINT_VECTOR => UserException::FloatingPoint,
+        _ => return None,
+    })
+}
+
 fn vec_to_str(vec: u64) -> &'static str {
     if vec < 32 {
         EXCEPTIONS[vec as usize].mnemonic
@@ -0,0 +1,98 @@
+//! Console input and output.
+//!
+//! Input is moved from the console device into a ring buffer by
+//! [`handle_input`], called from the receive interrupt handler of the device.
+//! [`read_bytes`] then takes it from the buffer, and a handler set by
+//! [`set_input_handler`] is notified of new input, so that readers can block
+//! instead of polling the device.
+
+use kspin::SpinNoIrq;
+use lazyinit::LazyInit;
+
+use crate::platform::console as device;
+
+pub use crate::platform::console::write_bytes;
+
+/// The capacity of the input buffer. Input received while it is full is
+/// dropped.
+const INPUT_BUF_SIZE: usize = 1024;
+
+struct InputBuffer {
+    buf: [u8; INPUT_BUF_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl InputBuffer {
+    const fn new() -> Self {
+        Self {
+            buf: [0; INPUT_BUF_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Moves the input pending in the device into the buffer, returning
+    /// whether there was any.
+    fn fill(&mut self) -> bool {
+        let mut chunk = [0; 32];
+        let mut received = false;
+        loop {
+            let len = device::read_bytes(&mut chunk);
+            if len == 0 {
+                return received;
+            }
+            received = true;
+            for &c in &chunk[..len] {
+                if self.len < INPUT_BUF_SIZE {
+                    self.buf[(self.head + self.len) % INPUT_BUF_SIZE] = c;
+                    self.len += 1;
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self, bytes: &mut [u8]) -> usize {
+        let len = bytes.len().min(self.len);
+        for byte in &mut bytes[..len] {
+            *byte = self.buf[self.head];
+            self.head = (self.head + 1) % INPUT_BUF_SIZE;
+        }
+        self.len -= len;
+        len
+    }
+}
+
+static INPUT: SpinNoIrq<InputBuffer> = SpinNoIrq::new(InputBuffer::new());
+static INPUT_HANDLER: LazyInit<fn()> = LazyInit::new();
+
+/// Sets the function called when console input is received. It is called in
+/// interrupt context, so it must not block.
+///
+/// The handler can only be set once.
+pub fn set_input_handler(handler: fn()) {
+    INPUT_HANDLER.init_once(handler);
+}
+
+/// Moves the input pending in the console device into the input buffer, and
+/// calls the input handler if there was any.
+#[allow(dead_code)]
+pub(crate) fn handle_input() {
+    // The device is drained with the buffer locked, so that input is kept in
+    // order when read concurrently by `read_bytes`.
+    let received = INPUT.lock().fill();
+    if received {
+        if let Some(handler) = INPUT_HANDLER.get() {
+            handler();
+        }
+    }
+}
+
+/// Reads bytes from the console into the given mutable slice, without
+/// blocking.
+/// Returns the number of bytes read.
+pub fn read_bytes(bytes: &mut [u8]) -> usize {
+    let mut input = INPUT.lock();
+    input.fill();
+    input.pop(bytes)
+}
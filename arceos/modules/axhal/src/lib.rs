@@ -56,10 +56,7 @@ pub mod irq;
 #[cfg(feature = "paging")]
 pub mod paging;
 
-/// Console input and output.
-pub mod console {
-    pub use super::platform::console::*;
-}
+pub mod console;
 
 /// Miscellaneous operation, e.g. terminate the system.
 pub mod misc {
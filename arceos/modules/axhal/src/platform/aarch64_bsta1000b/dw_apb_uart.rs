@@ -63,4 +63,5 @@ pub fn init_irq() {
 /// UART IRQ Handler
 pub fn handle() {
     trace!("Uart IRQ Handler");
+    crate::console::handle_input();
 }
@@ -1,8 +1,8 @@
 #![allow(unused_imports)]
 
-use aarch64_cpu::registers::{CNTFRQ_EL0, CNTP_CTL_EL0, CNTP_TVAL_EL0, CNTPCT_EL0};
+use aarch64_cpu::registers::{CNTFRQ_EL0, CNTKCTL_EL1, CNTP_CTL_EL0, CNTP_TVAL_EL0, CNTPCT_EL0};
 use int_ratio::Ratio;
-use tock_registers::interfaces::{Readable, Writeable};
+use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 
 static mut CNTPCT_TO_NANOS_RATIO: Ratio = Ratio::zero();
 static mut NANOS_TO_CNTPCT_RATIO: Ratio = Ratio::zero();
@@ -77,6 +77,9 @@ pub(crate) fn init_early() {
 }
 
 pub(crate) fn init_percpu() {
+    // Allow user space to read the physical counter, for the vDSO.
+    #[cfg(feature = "uspace")]
+    CNTKCTL_EL1.modify(CNTKCTL_EL1::EL0PCTEN::SET);
     #[cfg(feature = "irq")]
     {
         CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::SET);
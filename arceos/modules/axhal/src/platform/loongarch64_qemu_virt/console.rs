@@ -41,3 +41,15 @@ pub(super) fn init_early() {
     let vaddr = phys_to_virt(UART_BASE);
     UART.init_once(SpinNoIrq::new(Uart::new(vaddr.as_usize())));
 }
+
+/// Enables the receive interrupt of the UART and registers its handler.
+#[cfg(feature = "irq")]
+pub(super) fn init_irq() {
+    /// The interrupt enable register, and its received data available bit.
+    const IER: usize = 1;
+    const IER_RDA: u8 = 1;
+
+    crate::irq::register_handler(super::irq::UART_IRQ_NUM, crate::console::handle_input);
+    let ier = (UART.lock().base_address() + IER) as *mut u8;
+    unsafe { ier.write_volatile(IER_RDA) };
+}
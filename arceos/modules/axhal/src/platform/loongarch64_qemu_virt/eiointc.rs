@@ -0,0 +1,54 @@
+//! Extended I/O Interrupt Controller (EIOINTC), accessed through IOCSRs, as
+//! much as needed to route all its vectors to `HWI0` of CPU 0.
+
+use loongArch64::iocsr::{iocsr_read_d, iocsr_read_w, iocsr_write_d, iocsr_write_w};
+
+/// The number of interrupt vectors.
+pub(super) const VECTOR_COUNT: usize = 256;
+
+const MISC_FUNC: usize = 0x420;
+const MISC_FUNC_EXT_IOI_EN: u64 = 1 << 48;
+
+const IPMAP: usize = 0x14c0;
+const ENABLE: usize = 0x1600;
+const ISR: usize = 0x1800;
+const ROUTE: usize = 0x1c00;
+
+/// Enables the controller and routes all vectors to `HWI0` of CPU 0, each
+/// disabled until [`set_enable`] is called.
+pub(super) fn init() {
+    iocsr_write_d(MISC_FUNC, iocsr_read_d(MISC_FUNC) | MISC_FUNC_EXT_IOI_EN);
+    // One byte for each group of 32 vectors, with a bit for the CPU
+    // interrupt pin starting from `HWI0`.
+    for i in (0..VECTOR_COUNT / 32).step_by(4) {
+        iocsr_write_w(IPMAP + i, 0x0101_0101);
+    }
+    // One byte for each vector, with a bit for the CPU in its node.
+    for i in (0..VECTOR_COUNT).step_by(4) {
+        iocsr_write_w(ROUTE + i, 0x0101_0101);
+    }
+    for i in (0..VECTOR_COUNT / 8).step_by(4) {
+        iocsr_write_w(ENABLE + i, 0);
+    }
+}
+
+/// Enables or disables the interrupt vector `vector`.
+pub(super) fn set_enable(vector: usize, enabled: bool) {
+    let reg = ENABLE + vector / 32 * 4;
+    let bit = 1 << (vector % 32);
+    let old = iocsr_read_w(reg);
+    iocsr_write_w(reg, if enabled { old | bit } else { old & !bit });
+}
+
+/// Takes the lowest pending interrupt vector, if any, clearing its status.
+pub(super) fn claim() -> Option<usize> {
+    (0..VECTOR_COUNT / 64).find_map(|i| {
+        let pending = iocsr_read_d(ISR + i * 8);
+        if pending == 0 {
+            return None;
+        }
+        let bit = pending.trailing_zeros() as usize;
+        iocsr_write_d(ISR + i * 8, 1 << bit);
+        Some(i * 64 + bit)
+    })
+}
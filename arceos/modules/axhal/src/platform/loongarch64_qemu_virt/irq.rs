@@ -3,12 +3,24 @@ use loongArch64::register::{
     estat, ticlr,
 };
 
-/// The maximum number of IRQs.
-pub const MAX_IRQ_COUNT: usize = 12;
+use super::{eiointc, pch_pic};
+
+/// The number of IRQs of the CPU, numbered by their bits in `estat.is`.
+const CPU_IRQ_COUNT: usize = 16;
+
+/// The IRQ of the EIOINTC, through which device interrupts arrive.
+const EIOINTC_IRQ_NUM: usize = estat::Interrupt::HWI0 as usize;
+
+/// The maximum number of IRQs: those of the CPU, followed by the vectors of
+/// the EIOINTC, which are the interrupt inputs of the PCH-PIC.
+pub const MAX_IRQ_COUNT: usize = CPU_IRQ_COUNT + eiointc::VECTOR_COUNT;
 
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = estat::Interrupt::Timer as usize;
 
+/// The UART IRQ number.
+pub const UART_IRQ_NUM: usize = CPU_IRQ_COUNT + axconfig::devices::UART_IRQ;
+
 /// Enables or disables the given IRQ.
 pub fn set_enable(irq_num: usize, enabled: bool) {
     if irq_num == TIMER_IRQ_NUM {
@@ -18,6 +30,12 @@ pub fn set_enable(irq_num: usize, enabled: bool) {
             false => old_value & !LineBasedInterrupt::TIMER,
         };
         ecfg::set_lie(new_value);
+    } else if irq_num >= CPU_IRQ_COUNT {
+        let vector = irq_num - CPU_IRQ_COUNT;
+        if vector < pch_pic::IRQ_COUNT {
+            pch_pic::set_enable(vector, enabled);
+        }
+        eiointc::set_enable(vector, enabled);
     }
 }
 
@@ -35,5 +53,19 @@ pub fn dispatch_irq(irq_num: usize) {
     if irq_num == TIMER_IRQ_NUM {
         ticlr::clear_timer_interrupt();
     }
-    crate::irq::dispatch_irq_common(irq_num)
+    if irq_num == EIOINTC_IRQ_NUM {
+        if let Some(vector) = eiointc::claim() {
+            crate::irq::dispatch_irq_common(CPU_IRQ_COUNT + vector);
+        }
+        return;
+    }
+    crate::irq::dispatch_irq_common(irq_num);
+}
+
+/// Sets up the interrupt controllers, and lets the CPU take interrupts from
+/// the EIOINTC.
+pub(super) fn init() {
+    eiointc::init();
+    pch_pic::init();
+    ecfg::set_lie(ecfg::read().lie() | LineBasedInterrupt::HWI0);
 }
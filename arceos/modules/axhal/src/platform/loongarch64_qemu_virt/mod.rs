@@ -2,15 +2,25 @@ mod boot;
 
 pub mod console;
 #[cfg(feature = "irq")]
+mod eiointc;
+#[cfg(feature = "irq")]
 pub mod irq;
 pub mod mem;
 pub mod misc;
 #[cfg(feature = "smp")]
 pub mod mp;
+#[cfg(feature = "irq")]
+mod pch_pic;
 pub mod time;
 
 /// Initializes the platform devices for the primary CPU.
-pub fn platform_init() {}
+pub fn platform_init() {
+    #[cfg(feature = "irq")]
+    {
+        self::irq::init();
+        self::console::init_irq();
+    }
+}
 
 /// Initializes the platform devices for secondary CPUs.
 #[cfg(feature = "smp")]
@@ -0,0 +1,50 @@
+//! Platform Controller Hub Programmable Interrupt Controller (PCH-PIC), as
+//! much as needed to forward level-triggered device interrupts to the
+//! EIOINTC.
+
+use core::ptr::NonNull;
+
+use memory_addr::PhysAddr;
+
+use crate::mem::phys_to_virt;
+
+const PCH_PIC_BASE: PhysAddr = pa!(axconfig::devices::PCH_PIC_PADDR);
+
+/// The number of interrupt inputs.
+pub(super) const IRQ_COUNT: usize = 64;
+
+const INT_MASK: usize = 0x20;
+const INT_EDGE: usize = 0x60;
+const ROUTE_ENTRY: usize = 0x100;
+const HTMSI_VEC: usize = 0x200;
+const INT_POL: usize = 0x3e0;
+
+fn reg<T>(offset: usize) -> NonNull<T> {
+    NonNull::new((phys_to_virt(PCH_PIC_BASE).as_usize() + offset) as *mut T).unwrap()
+}
+
+/// Masks all inputs, and makes them level-triggered and active high, each
+/// sending the EIOINTC vector of its own number.
+pub(super) fn init() {
+    unsafe {
+        for i in (0..IRQ_COUNT / 8).step_by(4) {
+            reg::<u32>(INT_MASK + i).write_volatile(u32::MAX);
+            reg::<u32>(INT_EDGE + i).write_volatile(0);
+            reg::<u32>(INT_POL + i).write_volatile(0);
+        }
+        for irq in 0..IRQ_COUNT {
+            reg::<u8>(ROUTE_ENTRY + irq).write_volatile(1);
+            reg::<u8>(HTMSI_VEC + irq).write_volatile(irq as u8);
+        }
+    }
+}
+
+/// Unmasks or masks the interrupt input `irq`.
+pub(super) fn set_enable(irq: usize, enabled: bool) {
+    let mask = reg::<u32>(INT_MASK + irq / 32 * 4);
+    let bit = 1 << (irq % 32);
+    unsafe {
+        let old = mask.read_volatile();
+        mask.write_volatile(if enabled { old & !bit } else { old | bit });
+    }
+}
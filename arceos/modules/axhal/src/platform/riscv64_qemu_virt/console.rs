@@ -1,6 +1,16 @@
-use memory_addr::VirtAddr;
+//! Output goes through the SBI console, and input is read from the UART
+//! directly so that it can raise receive interrupts.
 
-use crate::mem::virt_to_phys;
+use kspin::SpinNoIrq;
+use lazyinit::LazyInit;
+use memory_addr::{PhysAddr, VirtAddr};
+use ns16550a::Uart;
+
+use crate::mem::{phys_to_virt, virt_to_phys};
+
+const UART_BASE: PhysAddr = pa!(axconfig::devices::UART_PADDR);
+
+static UART: LazyInit<SpinNoIrq<Uart>> = LazyInit::new();
 
 /// The maximum number of bytes that can be read at once.
 const MAX_RW_SIZE: usize = 256;
@@ -53,10 +63,30 @@ pub fn write_bytes(bytes: &[u8]) {
 /// Reads bytes from the console into the given mutable slice.
 /// Returns the number of bytes read.
 pub fn read_bytes(bytes: &mut [u8]) -> usize {
-    sbi_rt::console_read(sbi_rt::Physical::new(
-        bytes.len().min(MAX_RW_SIZE),
-        virt_to_phys(VirtAddr::from_mut_ptr_of(bytes.as_mut_ptr())).as_usize(),
-        0,
-    ))
-    .value
+    let uart = UART.lock();
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        match uart.get() {
+            Some(c) => *byte = c,
+            None => return i,
+        }
+    }
+    bytes.len()
+}
+
+/// Early stage initialization for ns16550a
+pub(super) fn init_early() {
+    let vaddr = phys_to_virt(UART_BASE);
+    UART.init_once(SpinNoIrq::new(Uart::new(vaddr.as_usize())));
+}
+
+/// Enables the receive interrupt of the UART and registers its handler.
+#[cfg(feature = "irq")]
+pub(super) fn init_irq() {
+    /// The interrupt enable register, and its received data available bit.
+    const IER: usize = 1;
+    const IER_RDA: u8 = 1;
+
+    crate::irq::register_handler(super::irq::UART_IRQ_NUM, crate::console::handle_input);
+    let ier = (UART.lock().base_address() + IER) as *mut u8;
+    unsafe { ier.write_volatile(IER_RDA) };
 }
@@ -1,5 +1,3 @@
-//! TODO: PLIC
-
 use crate::irq::IrqHandler;
 use lazyinit::LazyInit;
 use riscv::register::sie;
@@ -25,20 +23,15 @@ pub const MAX_IRQ_COUNT: usize = 1024;
 /// The timer IRQ number (supervisor timer interrupt in `scause`).
 pub const TIMER_IRQ_NUM: usize = S_TIMER;
 
-macro_rules! with_cause {
-    ($cause: expr, @TIMER => $timer_op: expr, @EXT => $ext_op: expr $(,)?) => {
-        match $cause {
-            S_TIMER => $timer_op,
-            S_EXT => $ext_op,
-            _ => panic!("invalid trap cause: {:#x}", $cause),
-        }
-    };
-}
+/// The UART IRQ number (its interrupt source in the PLIC).
+pub const UART_IRQ_NUM: usize = axconfig::devices::UART_IRQ;
 
 /// Enables or disables the given IRQ.
-pub fn set_enable(scause: usize, _enabled: bool) {
-    if scause == S_EXT {
-        // TODO: set enable in PLIC
+///
+/// IRQs of devices are numbered by their interrupt sources in the PLIC.
+pub fn set_enable(irq: usize, enabled: bool) {
+    if irq & INTC_IRQ_BASE == 0 {
+        super::plic::set_enable(irq, enabled);
     }
 }
 
@@ -46,17 +39,19 @@ pub fn set_enable(scause: usize, _enabled: bool) {
 ///
 /// It also enables the IRQ if the registration succeeds. It returns `false` if
 /// the registration failed.
-pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
-    with_cause!(
-        scause,
-        @TIMER => if !TIMER_HANDLER.is_inited() {
-            TIMER_HANDLER.init_once(handler);
-            true
-        } else {
-            false
-        },
-        @EXT => crate::irq::register_handler_common(scause & !INTC_IRQ_BASE, handler),
-    )
+pub fn register_handler(irq: usize, handler: IrqHandler) -> bool {
+    match irq {
+        S_TIMER => {
+            if !TIMER_HANDLER.is_inited() {
+                TIMER_HANDLER.init_once(handler);
+                true
+            } else {
+                false
+            }
+        }
+        _ if irq & INTC_IRQ_BASE == 0 => crate::irq::register_handler_common(irq, handler),
+        _ => panic!("invalid IRQ: {:#x}", irq),
+    }
 }
 
 /// Dispatches the IRQ.
@@ -65,17 +60,23 @@ pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
 /// up in the IRQ handler table and calls the corresponding handler. If
 /// necessary, it also acknowledges the interrupt controller after handling.
 pub fn dispatch_irq(scause: usize) {
-    with_cause!(
-        scause,
-        @TIMER => {
+    match scause {
+        S_TIMER => {
             trace!("IRQ: timer");
             TIMER_HANDLER();
-        },
-        @EXT => crate::irq::dispatch_irq_common(0), // TODO: get IRQ number from PLIC
-    );
+        }
+        S_EXT => {
+            if let Some(irq) = super::plic::claim() {
+                crate::irq::dispatch_irq_common(irq);
+                super::plic::complete(irq);
+            }
+        }
+        _ => panic!("invalid trap cause: {:#x}", scause),
+    }
 }
 
 pub(super) fn init_percpu() {
+    super::plic::init_percpu();
     // enable soft interrupts, timer interrupts, and external interrupts
     unsafe {
         sie::set_ssoft();
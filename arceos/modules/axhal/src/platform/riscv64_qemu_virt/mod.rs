@@ -7,6 +7,8 @@ pub mod time;
 
 #[cfg(feature = "irq")]
 pub mod irq;
+#[cfg(feature = "irq")]
+mod plic;
 
 #[cfg(feature = "smp")]
 pub mod mp;
@@ -22,6 +24,7 @@ unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     crate::cpu::init_primary(cpu_id);
     #[cfg(feature = "uspace")]
     riscv::register::sstatus::set_sum();
+    self::console::init_early();
     self::time::init_early();
     rust_main(cpu_id, dtb);
 }
@@ -41,6 +44,8 @@ pub fn platform_init() {
     #[cfg(feature = "irq")]
     self::irq::init_percpu();
     self::time::init_percpu();
+    #[cfg(feature = "irq")]
+    self::console::init_irq();
 }
 
 /// Initializes the platform devices for secondary CPUs.
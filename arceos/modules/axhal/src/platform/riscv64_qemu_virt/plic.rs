@@ -0,0 +1,63 @@
+//! Platform-Level Interrupt Controller (PLIC), as much as needed to route
+//! device interrupts to the supervisor mode of each hart.
+
+use core::ptr::NonNull;
+
+use memory_addr::PhysAddr;
+
+use crate::mem::phys_to_virt;
+
+const PLIC_BASE: PhysAddr = pa!(axconfig::devices::PLIC_PADDR);
+
+const PRIORITY_OFFSET: usize = 0;
+const ENABLE_OFFSET: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_OFFSET: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const THRESHOLD: usize = 0;
+const CLAIM_COMPLETE: usize = 4;
+
+fn reg(offset: usize) -> NonNull<u32> {
+    NonNull::new((phys_to_virt(PLIC_BASE).as_usize() + offset) as *mut u32).unwrap()
+}
+
+/// The PLIC context of the supervisor mode of the current hart, which
+/// follows that of its machine mode.
+fn context() -> usize {
+    crate::cpu::this_cpu_id() * 2 + 1
+}
+
+/// Enables or disables the interrupt source `irq` for the current hart.
+pub(super) fn set_enable(irq: usize, enabled: bool) {
+    let enable = reg(ENABLE_OFFSET + context() * ENABLE_STRIDE + irq / 32 * 4);
+    let bit = 1 << (irq % 32);
+    unsafe {
+        // A priority of 0 means never interrupt.
+        reg(PRIORITY_OFFSET + irq * 4).write_volatile(enabled as u32);
+        let old = enable.read_volatile();
+        enable.write_volatile(if enabled { old | bit } else { old & !bit });
+    }
+}
+
+/// Claims the highest priority pending interrupt of the current hart, if any.
+pub(super) fn claim() -> Option<usize> {
+    let irq = unsafe {
+        reg(CONTEXT_OFFSET + context() * CONTEXT_STRIDE + CLAIM_COMPLETE).read_volatile()
+    };
+    (irq != 0).then_some(irq as usize)
+}
+
+/// Signals the completion of the handling of the claimed interrupt `irq`.
+pub(super) fn complete(irq: usize) {
+    unsafe {
+        reg(CONTEXT_OFFSET + context() * CONTEXT_STRIDE + CLAIM_COMPLETE)
+            .write_volatile(irq as u32);
+    }
+}
+
+/// Lets all interrupts with a nonzero priority through to the current hart.
+pub(super) fn init_percpu() {
+    unsafe {
+        reg(CONTEXT_OFFSET + context() * CONTEXT_STRIDE + THRESHOLD).write_volatile(0);
+    }
+}
@@ -55,6 +55,11 @@ pub(super) fn init_early() {
 }
 
 pub(super) fn init_percpu() {
+    // Allow user space to read the `time` CSR, for the vDSO.
+    #[cfg(feature = "uspace")]
+    unsafe {
+        riscv::register::scounteren::set_tm()
+    };
     #[cfg(feature = "irq")]
     sbi_rt::set_timer(0);
 }
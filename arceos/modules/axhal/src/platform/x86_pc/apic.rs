@@ -13,6 +13,8 @@ use self::vectors::*;
 use crate::mem::phys_to_virt;
 
 pub(super) mod vectors {
+    /// The vector of IO APIC interrupt 0, followed by the others.
+    pub const IO_APIC_VECTOR_BASE: u8 = 0x20;
     pub const APIC_TIMER_VECTOR: u8 = 0xf0;
     pub const APIC_SPURIOUS_VECTOR: u8 = 0xf1;
     pub const APIC_ERROR_VECTOR: u8 = 0xf2;
@@ -35,12 +37,13 @@ static IO_APIC: LazyInit<SpinNoIrq<IoApic>> = LazyInit::new();
 #[cfg(feature = "irq")]
 pub fn set_enable(vector: usize, enabled: bool) {
     // should not affect LAPIC interrupts
-    if vector < APIC_TIMER_VECTOR as _ {
+    if (IO_APIC_VECTOR_BASE as usize..APIC_TIMER_VECTOR as usize).contains(&vector) {
+        let irq = vector as u8 - IO_APIC_VECTOR_BASE;
         unsafe {
             if enabled {
-                IO_APIC.lock().enable_irq(vector as u8);
+                IO_APIC.lock().enable_irq(irq);
             } else {
-                IO_APIC.lock().disable_irq(vector as u8);
+                IO_APIC.lock().disable_irq(irq);
             }
         }
     }
@@ -117,7 +120,15 @@ pub(super) fn init_primary() {
     }
 
     info!("Initialize IO APIC...");
-    let io_apic = unsafe { IoApic::new(phys_to_virt(IO_APIC_BASE).as_usize() as u64) };
+    let mut io_apic = unsafe { IoApic::new(phys_to_virt(IO_APIC_BASE).as_usize() as u64) };
+    unsafe {
+        // Route each interrupt to its vector, masked until a handler is
+        // registered.
+        io_apic.init(IO_APIC_VECTOR_BASE);
+        for irq in 0..=io_apic.max_table_entry() {
+            io_apic.disable_irq(irq);
+        }
+    }
     IO_APIC.init_once(SpinNoIrq::new(io_apic));
 }
 
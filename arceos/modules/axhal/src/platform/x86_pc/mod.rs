@@ -55,6 +55,8 @@ unsafe extern "C" fn rust_entry_secondary(magic: usize) {
 pub fn platform_init() {
     self::apic::init_primary();
     self::time::init_primary();
+    #[cfg(feature = "irq")]
+    self::uart16550::init_irq();
 }
 
 /// Initializes the platform devices for secondary CPUs.
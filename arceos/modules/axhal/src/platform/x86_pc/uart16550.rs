@@ -8,6 +8,10 @@ const OSC_FREQ: usize = 1_843_200;
 
 static COM1: SpinNoIrq<Uart16550> = SpinNoIrq::new(Uart16550::new(0x3f8));
 
+/// The ISA IRQ of COM1.
+#[cfg(feature = "irq")]
+const COM1_IRQ: u8 = 4;
+
 bitflags::bitflags! {
     /// Line status flags
     struct LineStsFlags: u8 {
@@ -55,6 +59,9 @@ impl Uart16550 {
             // Disable DLAB and set data word length to 8 bits
             self.line_ctrl.write(0x03);
 
+            // Enable received data available interrupts
+            self.int_en.write(0x01);
+
             // Enable FIFO, clear TX/RX queues and
             // set interrupt watermark at 14 bytes
             self.fifo_ctrl.write(0xC7);
@@ -125,3 +132,16 @@ pub fn read_bytes(bytes: &mut [u8]) -> usize {
 pub(super) fn init() {
     COM1.lock().init(115200);
 }
+
+/// Registers the interrupt handler of received input.
+#[cfg(feature = "irq")]
+pub(super) fn init_irq() {
+    use super::apic::vectors::IO_APIC_VECTOR_BASE;
+    crate::irq::register_handler(
+        (IO_APIC_VECTOR_BASE + COM1_IRQ) as _,
+        crate::console::handle_input,
+    );
+    // The interrupt is edge-triggered, so input received before it was
+    // enabled has to be drained for the next one to be raised.
+    crate::console::handle_input();
+}
@@ -16,6 +16,35 @@ pub static IRQ: [fn(usize) -> bool];
 #[def_trap_handler]
 pub static PAGE_FAULT: [fn(VirtAddr, MappingFlags, bool) -> bool];
 
+/// The kinds of exceptions raised by user code, other than page faults.
+#[cfg(feature = "uspace")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserException {
+    /// An undefined or privileged instruction.
+    IllegalInstruction,
+    /// A breakpoint instruction.
+    Breakpoint,
+    /// A misaligned instruction or memory access.
+    MisalignedAccess,
+    /// An access to memory that faulted other than on translation, such as
+    /// a bus error.
+    AccessFault,
+    /// A general protection fault, which reports no address.
+    GeneralProtection,
+    /// An integer division by zero.
+    DivideByZero,
+    /// A floating-point exception.
+    FloatingPoint,
+    /// A trap after single-stepping an instruction.
+    SingleStep,
+}
+
+/// A slice of handler functions for exceptions raised by user code, which
+/// get the kind of exception and the faulting address.
+#[cfg(feature = "uspace")]
+#[def_trap_handler]
+pub static USER_EXCEPTION: [fn(UserException, VirtAddr) -> bool];
+
 /// A slice of syscall handler functions.
 #[cfg(feature = "uspace")]
 #[def_trap_handler]
@@ -48,6 +77,16 @@ pub(crate) fn post_trap_callback(tf: &mut TrapFrame, from_user: bool) {
     }
 }
 
+/// Calls the handler of an exception raised by user code, panicking if it is
+/// not handled.
+#[cfg(feature = "uspace")]
+#[allow(dead_code)]
+pub(crate) fn handle_user_exception(tf: &TrapFrame, kind: UserException, addr: VirtAddr) {
+    if !handle_trap!(USER_EXCEPTION, kind, addr) {
+        panic!("Unhandled user {:?} @ {:#x}:\n{:#x?}", kind, addr, tf);
+    }
+}
+
 /// Call the external syscall handler.
 #[cfg(feature = "uspace")]
 pub(crate) fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
@@ -44,6 +44,14 @@ impl AddrSpace {
         self.pt.root_paddr()
     }
 
+    /// Returns an iterator over the address ranges and mapping flags of the
+    /// memory areas, sorted by address.
+    pub fn areas(&self) -> impl Iterator<Item = (VirtAddrRange, MappingFlags)> + '_ {
+        self.areas
+            .iter()
+            .map(|area| (area.va_range(), area.flags()))
+    }
+
     /// Checks if the address space contains the given address range.
     pub fn contains_range(&self, start: VirtAddr, size: usize) -> bool {
         self.va_range
@@ -77,12 +77,21 @@ pub struct TaskInner {
     tls: TlsArea,
 }
 
+static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
 impl TaskId {
     fn new() -> Self {
-        static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
         Self(ID_COUNTER.fetch_add(1, Ordering::Relaxed))
     }
 
+    /// Makes sure that `id` is never given to a new task, so that it can be
+    /// used as an ID chosen by other means.
+    ///
+    /// IDs that have been given to tasks before are not affected.
+    pub fn reserve(id: u64) {
+        ID_COUNTER.fetch_max(id + 1, Ordering::Relaxed);
+    }
+
     /// Convert the task ID to a `u64`.
     pub const fn as_u64(&self) -> u64 {
         self.0
@@ -416,8 +425,14 @@ impl TaskInner {
         self.wait_for_exit.notify_all(false);
     }
 
+    /// Returns a raw pointer to the task context.
+    ///
+    /// # Safety
+    ///
+    /// The context is used by context switches, so it may only be changed by
+    /// the task itself, such as to change its page table root.
     #[inline]
-    pub(crate) const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
+    pub const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
         self.ctx.get()
     }
 
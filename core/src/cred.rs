@@ -0,0 +1,245 @@
+//! Process credentials: the user and group IDs of `credentials(7)`, and the
+//! capabilities of `capabilities(7)`.
+
+use alloc::vec::Vec;
+use linux_raw_sys::general::{
+    CAP_CHOWN, CAP_DAC_OVERRIDE, CAP_DAC_READ_SEARCH, CAP_FOWNER, CAP_FSETID, CAP_KILL,
+    CAP_LAST_CAP, CAP_LINUX_IMMUTABLE, CAP_MAC_OVERRIDE, CAP_MKNOD, CAP_SYS_PTRACE,
+};
+
+/// A user ID.
+pub type Uid = u32;
+/// A group ID.
+pub type Gid = u32;
+
+/// The maximum number of supplementary groups.
+pub const NGROUPS_MAX: usize = 65536;
+
+/// A set of capabilities, by their numbers such as `CAP_KILL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CapSet(pub u64);
+
+impl CapSet {
+    /// No capabilities.
+    pub const EMPTY: Self = Self(0);
+    /// All capabilities.
+    pub const FULL: Self = Self((1 << (CAP_LAST_CAP + 1)) - 1);
+    /// The capabilities for file operations, which are dropped from the
+    /// effective set when the filesystem user ID changes from 0, and restored
+    /// when it changes back.
+    pub const FS: Self = Self(
+        (1 << CAP_CHOWN)
+            | (1 << CAP_DAC_OVERRIDE)
+            | (1 << CAP_DAC_READ_SEARCH)
+            | (1 << CAP_FOWNER)
+            | (1 << CAP_FSETID)
+            | (1 << CAP_LINUX_IMMUTABLE)
+            | (1 << CAP_MAC_OVERRIDE)
+            | (1 << CAP_MKNOD),
+    );
+
+    /// Whether the set contains the capability `cap`.
+    pub const fn contains(self, cap: u32) -> bool {
+        cap <= CAP_LAST_CAP && self.0 & (1 << cap) != 0
+    }
+
+    /// Whether all capabilities in the set are in `other`.
+    pub const fn is_subset(self, other: Self) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    /// The capabilities in either set.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// The capabilities in both sets.
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// The capabilities in the set but not in `other`.
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+/// The credentials of a process.
+///
+/// Credentials are replaced as a whole rather than modified in place, see
+/// [`ProcessData::set_cred`](crate::task::ProcessData::set_cred).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// The real user ID
+    pub uid: Uid,
+    /// The effective user ID, used for most permission checks
+    pub euid: Uid,
+    /// The saved set-user-ID
+    pub suid: Uid,
+    /// The filesystem user ID, used for file permission checks
+    pub fsuid: Uid,
+    /// The real group ID
+    pub gid: Gid,
+    /// The effective group ID
+    pub egid: Gid,
+    /// The saved set-group-ID
+    pub sgid: Gid,
+    /// The filesystem group ID
+    pub fsgid: Gid,
+    /// The supplementary group IDs, sorted
+    pub groups: Vec<Gid>,
+    /// The capabilities the process may make effective
+    pub cap_permitted: CapSet,
+    /// The capabilities used for permission checks
+    pub cap_effective: CapSet,
+    /// The capabilities that may be kept across `execve`
+    pub cap_inheritable: CapSet,
+    /// The limit on the capabilities gained by `execve`
+    pub cap_bounding: CapSet,
+}
+
+impl Credentials {
+    /// The credentials of the superuser, which the first process starts
+    /// with.
+    pub const fn root() -> Self {
+        Self {
+            uid: 0,
+            euid: 0,
+            suid: 0,
+            fsuid: 0,
+            gid: 0,
+            egid: 0,
+            sgid: 0,
+            fsgid: 0,
+            groups: Vec::new(),
+            cap_permitted: CapSet::FULL,
+            cap_effective: CapSet::FULL,
+            cap_inheritable: CapSet::EMPTY,
+            cap_bounding: CapSet::FULL,
+        }
+    }
+
+    /// Whether the capability `cap` is effective, which exempts the process
+    /// from the permission checks it covers.
+    pub fn has_cap(&self, cap: u32) -> bool {
+        self.cap_effective.contains(cap)
+    }
+
+    /// Whether `uid` is the real, effective or saved user ID, which an
+    /// unprivileged process may switch between.
+    pub fn has_uid(&self, uid: Uid) -> bool {
+        uid == self.uid || uid == self.euid || uid == self.suid
+    }
+
+    /// Whether `gid` is the real, effective or saved group ID.
+    pub fn has_gid(&self, gid: Gid) -> bool {
+        gid == self.gid || gid == self.egid || gid == self.sgid
+    }
+
+    /// Whether the process is a member of the group `gid` for file access,
+    /// either as its filesystem group or a supplementary one.
+    pub fn in_group(&self, gid: Gid) -> bool {
+        gid == self.fsgid || self.groups.binary_search(&gid).is_ok()
+    }
+
+    /// Sets the effective user ID, which the filesystem user ID follows.
+    pub fn set_euid(&mut self, euid: Uid) {
+        self.euid = euid;
+        self.fsuid = euid;
+    }
+
+    /// Sets the effective group ID, which the filesystem group ID follows.
+    pub fn set_egid(&mut self, egid: Gid) {
+        self.egid = egid;
+        self.fsgid = egid;
+    }
+
+    /// Checks whether a process with these credentials may send a signal to
+    /// a process with the credentials `target`, as in `kill(2)`: the real or
+    /// effective user ID of the sender must be the real or saved user ID of
+    /// the target.
+    pub fn can_signal(&self, target: &Credentials) -> bool {
+        self.has_cap(CAP_KILL)
+            || self.uid == target.uid
+            || self.uid == target.suid
+            || self.euid == target.uid
+            || self.euid == target.suid
+    }
+
+    /// Whether the effective or filesystem user or group IDs differ from those
+    /// in `old`, after which a process is no longer dumpable and its
+    /// parent-death signal is cleared.
+    pub fn ids_changed_from(&self, old: &Credentials) -> bool {
+        self.euid != old.euid
+            || self.egid != old.egid
+            || self.fsuid != old.fsuid
+            || self.fsgid != old.fsgid
+    }
+
+    /// Whether the real user and group IDs of these credentials are the real,
+    /// effective and saved ones of `target`.
+    pub fn matches_ids_of(&self, target: &Credentials) -> bool {
+        [target.uid, target.euid, target.suid] == [self.uid; 3]
+            && [target.gid, target.egid, target.sgid] == [self.gid; 3]
+    }
+
+    /// Checks whether a process with these credentials may trace a process
+    /// with the credentials `target`, as in `ptrace(2)`: the real user and
+    /// group IDs of the tracer must be the real, effective and saved ones of
+    /// the target.
+    pub fn can_trace(&self, target: &Credentials) -> bool {
+        self.has_cap(CAP_SYS_PTRACE) || self.matches_ids_of(target)
+    }
+
+    /// Adjusts the capabilities after the user IDs changed from those in
+    /// `old`, so that a process that gives up being the superuser gives up
+    /// its capabilities too:
+    ///
+    /// - If the real, effective or saved user ID was 0 and none is anymore,
+    ///   the permitted and effective capabilities are cleared.
+    /// - If the effective user ID changes from 0, the effective capabilities
+    ///   are cleared, and if it changes to 0, they are set to the permitted
+    ///   ones.
+    /// - If the filesystem user ID changes from 0, the [file
+    ///   capabilities](CapSet::FS) are cleared from the effective ones, and
+    ///   if it changes to 0, the permitted ones among them are added back.
+    pub fn update_caps_for_uid_change(&mut self, old: &Credentials) {
+        let had_root = old.uid == 0 || old.euid == 0 || old.suid == 0;
+        if had_root && self.uid != 0 && self.euid != 0 && self.suid != 0 {
+            self.cap_permitted = CapSet::EMPTY;
+            self.cap_effective = CapSet::EMPTY;
+        }
+        if old.euid == 0 && self.euid != 0 {
+            self.cap_effective = CapSet::EMPTY;
+        } else if old.euid != 0 && self.euid == 0 {
+            self.cap_effective = self.cap_permitted;
+        }
+        if old.fsuid == 0 && self.fsuid != 0 {
+            self.cap_effective = self.cap_effective.difference(CapSet::FS);
+        } else if old.fsuid != 0 && self.fsuid == 0 {
+            self.cap_effective = self
+                .cap_effective
+                .union(self.cap_permitted.intersection(CapSet::FS));
+        }
+    }
+
+    /// Computes the capabilities after `execve`, once the user IDs are set
+    /// for the executed file.
+    ///
+    /// Files have no capabilities of their own, so only the superuser gains
+    /// any: a process with a real or effective user ID of 0 is permitted all
+    /// capabilities within the bounding set, which are effective if the
+    /// effective user ID is 0. Other processes lose all their capabilities.
+    pub fn update_caps_for_exec(&mut self) {
+        if self.uid == 0 || self.euid == 0 {
+            self.cap_permitted = self.cap_inheritable.union(self.cap_bounding);
+        } else {
+            self.cap_permitted = CapSet::EMPTY;
+        }
+        self.cap_effective = if self.euid == 0 {
+            self.cap_permitted
+        } else {
+            CapSet::EMPTY
+        };
+    }
+}
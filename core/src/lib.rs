@@ -8,7 +8,14 @@
 extern crate axlog;
 extern crate alloc;
 
+pub mod cred;
 pub mod futex;
 pub mod mm;
+pub mod ptrace;
+pub mod resource;
+pub mod seccomp;
+pub mod signal;
 pub mod task;
-mod time;
+pub mod time;
+pub mod timer;
+pub mod vdso;
@@ -2,12 +2,12 @@
 
 use core::ffi::CStr;
 
-use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
 use axerrno::{AxError, AxResult};
 use axhal::{mem::virt_to_phys, paging::MappingFlags};
 use axmm::{AddrSpace, kernel_aspace};
-use kernel_elf_parser::{AuxvEntry, ELFParser, app_stack_region};
-use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddr};
+use kernel_elf_parser::{AuxvEntry, AuxvType, ELFParser, app_stack_region};
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddr, VirtAddrRange};
 use xmas_elf::{ElfFile, program::SegmentData};
 
 /// Creates a new empty user address space.
@@ -30,6 +30,22 @@ pub fn copy_from_kernel(aspace: &mut AddrSpace) -> AxResult {
     Ok(())
 }
 
+/// Switches the current task to the user address space `aspace`, which it
+/// uses from now on.
+pub fn switch_user_aspace(aspace: &AddrSpace) {
+    let root = aspace.page_table_root();
+    // SAFETY: the task changes its own context. If it is switched out before
+    // the page table root register is written, it gets the new root when it
+    // is switched back in.
+    unsafe {
+        (*axtask::current().ctx_mut_ptr()).set_page_table_root(root);
+        #[cfg(any(target_arch = "aarch64", target_arch = "loongarch64"))]
+        axhal::arch::write_page_table_root0(root);
+        #[cfg(not(any(target_arch = "aarch64", target_arch = "loongarch64")))]
+        axhal::arch::write_page_table_root(root);
+    }
+}
+
 /// Map the signal trampoline to the user address space.
 pub fn map_trampoline(aspace: &mut AddrSpace) -> AxResult {
     let signal_trampoline_paddr = virt_to_phys(axsignal::arch::signal_trampoline_address().into());
@@ -42,15 +58,95 @@ pub fn map_trampoline(aspace: &mut AddrSpace) -> AxResult {
     Ok(())
 }
 
+/// A user memory range mapped from a file.
+#[derive(Debug, Clone)]
+pub struct FileMapping {
+    /// The mapped address range
+    pub range: VirtAddrRange,
+    /// The offset in the file of the start of the range
+    pub offset: usize,
+    /// The path of the file
+    pub path: String,
+}
+
+/// The file mappings of a user address space, sorted by address.
+///
+/// The address space itself does not remember where the data of its areas
+/// came from, so this is recorded for `/proc/[pid]/maps`-like reports such as
+/// the `NT_FILE` note of core dumps.
+#[derive(Debug, Default, Clone)]
+pub struct FileMappings(Vec<FileMapping>);
+
+impl FileMappings {
+    /// Records a file mapping, replacing any overlapping ones.
+    pub fn insert(&mut self, mapping: FileMapping) {
+        self.remove(mapping.range);
+        let pos = self
+            .0
+            .partition_point(|it| it.range.start < mapping.range.start);
+        self.0.insert(pos, mapping);
+    }
+
+    /// Forgets the file mappings in `range`, trimming or splitting the ones
+    /// that overlap it partially.
+    pub fn remove(&mut self, range: VirtAddrRange) {
+        let mut mappings = Vec::with_capacity(self.0.len() + 1);
+        for mapping in self.0.drain(..) {
+            if !mapping.range.overlaps(range) {
+                mappings.push(mapping);
+                continue;
+            }
+            if mapping.range.start < range.start {
+                mappings.push(FileMapping {
+                    range: VirtAddrRange::new(mapping.range.start, range.start),
+                    offset: mapping.offset,
+                    path: mapping.path.clone(),
+                });
+            }
+            if mapping.range.end > range.end {
+                mappings.push(FileMapping {
+                    range: VirtAddrRange::new(range.end, mapping.range.end),
+                    offset: mapping.offset + (range.end - mapping.range.start),
+                    path: mapping.path,
+                });
+            }
+        }
+        self.0 = mappings;
+    }
+
+    /// Returns an iterator over the file mappings, sorted by address.
+    pub fn iter(&self) -> impl Iterator<Item = &FileMapping> {
+        self.0.iter()
+    }
+}
+
+/// A user app loaded by [`load_user_app`].
+pub struct LoadedApp {
+    /// The entry point of the user app.
+    pub entry: VirtAddr,
+    /// The stack pointer of the user app.
+    pub ustack_top: VirtAddr,
+    /// The auxiliary vector passed to the user app.
+    pub auxv: Vec<AuxvEntry>,
+    /// The file mappings of the user app.
+    pub file_mappings: FileMappings,
+}
+
 /// Map the elf file to the user address space.
 ///
 /// # Arguments
 /// - `uspace`: The address space of the user app.
 /// - `elf`: The elf file.
+/// - `path`: The path of the elf file, recorded in `file_mappings`.
 ///
 /// # Returns
 /// - The entry point of the user app.
-fn map_elf(uspace: &mut AddrSpace, elf: &ElfFile) -> AxResult<(VirtAddr, [AuxvEntry; 16])> {
+fn map_elf(
+    uspace: &mut AddrSpace,
+    elf: &ElfFile,
+    path: &str,
+    file_mappings: &mut FileMappings,
+) -> AxResult<(VirtAddr, [AuxvEntry; 16])> {
     let uspace_base = uspace.base().as_usize();
     let elf_parser = ELFParser::new(
         elf,
@@ -78,6 +174,11 @@ fn map_elf(uspace: &mut AddrSpace, elf: &ElfFile) -> AxResult<(VirtAddr, [AuxvEn
             segement.flags,
             true,
         )?;
+        file_mappings.insert(FileMapping {
+            range: VirtAddrRange::from_start_size(segement.vaddr.align_down_4k(), seg_align_size),
+            offset: segement.offset - seg_pad,
+            path: path.into(),
+        });
         let seg_data = elf
             .input
             .get(segement.offset..segement.offset + segement.filesz as usize)
@@ -92,71 +193,162 @@ fn map_elf(uspace: &mut AddrSpace, elf: &ElfFile) -> AxResult<(VirtAddr, [AuxvEn
     ))
 }
 
-/// Load the user app to the user address space.
+/// The number of interpreters of scripts and dynamic executables followed at
+/// most when executing a file.
+const MAX_INTERP_DEPTH: usize = 4;
+
+/// A user app checked by [`prepare_user_app`], to be loaded by
+/// [`load_user_app`].
+pub struct UserApp {
+    /// The arguments of the user app, with the interpreters of scripts and
+    /// dynamic executables prepended.
+    pub args: Vec<String>,
+    /// The canonical path of the ELF file to be loaded.
+    path: String,
+    /// The content of the ELF file to be loaded.
+    data: Vec<u8>,
+}
+
+/// Reads the user app and checks that it can be loaded, following the
+/// interpreters of scripts and dynamic executables.
 ///
 /// # Arguments
-/// - `uspace`: The address space of the user app.
-/// - `args`: The arguments of the user app. The first argument is the path of the user app.
-/// - `envs`: The environment variables of the user app.
+/// - `path`: The path of the user app.
+/// - `args`: The arguments of the user app.
 ///
-/// # Returns
-/// - The entry point of the user app.
-/// - The stack pointer of the user app.
-pub fn load_user_app(
-    uspace: &mut AddrSpace,
-    args: &[String],
-    envs: &[String],
-) -> AxResult<(VirtAddr, VirtAddr)> {
-    if args.is_empty() {
-        return Err(AxError::InvalidInput);
-    }
-    let file_data = axfs::api::read(args[0].as_str())?;
-    if file_data.starts_with(b"#!") {
-        let head = &file_data[2..file_data.len().min(256)];
-        let pos = head.iter().position(|c| *c == b'\n').unwrap_or(head.len());
-        let line = core::str::from_utf8(&head[..pos]).map_err(|_| AxError::InvalidData)?;
-
-        let new_args: Vec<String> = line
-            .splitn(2, |c: char| c.is_ascii_whitespace())
-            .map(|s| s.trim_ascii().to_owned())
-            .chain(args.iter().cloned())
+/// # Errors
+/// - [`AxError::InvalidData`] if the app or an interpreter is neither a
+///   script nor a valid ELF file, or there are too many interpreters.
+/// - The error of reading the app or an interpreter otherwise.
+pub fn prepare_user_app(path: &str, args: &[String]) -> AxResult<UserApp> {
+    let mut path = String::from(path);
+    let mut args = args.to_vec();
+    for _ in 0..=MAX_INTERP_DEPTH {
+        let data = axfs::api::read(path.as_str())?;
+        let (interp_path, interp_arg) = if data.starts_with(b"#!") {
+            script_interp(&data)?
+        } else {
+            let elf = ElfFile::new(&data).map_err(|_| AxError::InvalidData)?;
+            check_elf(&elf)?;
+            match elf_interp(&elf)? {
+                Some(interp_path) => (interp_path, None),
+                None => {
+                    return Ok(UserApp {
+                        args,
+                        path: axfs::api::canonicalize(path.as_str())?,
+                        data,
+                    });
+                }
+            }
+        };
+        // The interpreter gets the path of the app in place of its first
+        // argument.
+        args = [interp_path.clone()]
+            .into_iter()
+            .chain(interp_arg)
+            .chain([path])
+            .chain(args.into_iter().skip(1))
             .collect();
-        return load_user_app(uspace, &new_args, envs);
+        path = interp_path;
     }
-    let elf = ElfFile::new(&file_data).map_err(|_| AxError::InvalidData)?;
-
-    if let Some(interp) = elf
-        .program_iter()
-        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))
-    {
-        let interp = match interp.get_data(&elf) {
-            Ok(SegmentData::Undefined(data)) => data,
-            _ => panic!("Invalid data in Interp Elf Program Header"),
-        };
+    Err(AxError::InvalidData)
+}
 
-        let mut interp_path = axfs::api::canonicalize(
-            CStr::from_bytes_with_nul(interp)
-                .map_err(|_| AxError::InvalidData)?
-                .to_str()
-                .map_err(|_| AxError::InvalidData)?,
-        )?;
+/// Returns the interpreter of the script `data` and its optional argument,
+/// from the `#!` line.
+fn script_interp(data: &[u8]) -> AxResult<(String, Option<String>)> {
+    let head = &data[2..data.len().min(256)];
+    let pos = head.iter().position(|c| *c == b'\n').unwrap_or(head.len());
+    let line = core::str::from_utf8(&head[..pos]).map_err(|_| AxError::InvalidData)?;
+    let mut line = line
+        .trim_ascii()
+        .splitn(2, |c: char| c.is_ascii_whitespace())
+        .map(|s| s.trim_ascii().to_owned());
+    let interp_path = line.next().filter(|s| !s.is_empty());
+    Ok((interp_path.ok_or(AxError::InvalidData)?, line.next()))
+}
 
-        if interp_path == "/lib/ld-linux-riscv64-lp64.so.1"
-            || interp_path == "/lib64/ld-linux-loongarch-lp64d.so.1"
-            || interp_path == "/lib64/ld-linux-x86-64.so.2"
-            || interp_path == "/lib/ld-linux-aarch64.so.1"
+/// Checks that the loadable segments of `elf` lie in the file and can be
+/// mapped.
+fn check_elf(elf: &ElfFile) -> AxResult {
+    let uspace_base = axconfig::plat::USER_SPACE_BASE;
+    let elf_parser = ELFParser::new(
+        elf,
+        axconfig::plat::USER_INTERP_BASE,
+        Some(uspace_base as isize),
+        uspace_base,
+    )
+    .map_err(|_| AxError::InvalidData)?;
+    for segment in elf_parser.ph_load() {
+        let in_file = segment
+            .offset
+            .checked_add(segment.filesz as usize)
+            .is_some_and(|end| end <= elf.input.len());
+        if !in_file
+            || segment.filesz > segment.memsz
+            || segment.vaddr.align_offset_4k() != segment.offset % PAGE_SIZE_4K
         {
-            // TODO: Use soft link
-            interp_path = String::from("/musl/lib/libc.so");
+            return Err(AxError::InvalidData);
         }
+    }
+    Ok(())
+}
 
-        // Set the first argument to the path of the user app.
-        let mut new_args = vec![interp_path];
-        new_args.extend_from_slice(args);
-        return load_user_app(uspace, &new_args, envs);
+/// Returns the path of the interpreter of the dynamic executable `elf`, or
+/// `None` if it is static.
+fn elf_interp(elf: &ElfFile) -> AxResult<Option<String>> {
+    let Some(interp) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))
+    else {
+        return Ok(None);
+    };
+    let interp = match interp.get_data(elf) {
+        Ok(SegmentData::Undefined(data)) => data,
+        _ => return Err(AxError::InvalidData),
+    };
+
+    let interp_path = axfs::api::canonicalize(
+        CStr::from_bytes_until_nul(interp)
+            .map_err(|_| AxError::InvalidData)?
+            .to_str()
+            .map_err(|_| AxError::InvalidData)?,
+    )?;
+
+    if interp_path == "/lib/ld-linux-riscv64-lp64.so.1"
+        || interp_path == "/lib64/ld-linux-loongarch-lp64d.so.1"
+        || interp_path == "/lib64/ld-linux-x86-64.so.2"
+        || interp_path == "/lib/ld-linux-aarch64.so.1"
+    {
+        // TODO: Use soft link
+        return Ok(Some(String::from("/musl/lib/libc.so")));
     }
+    Ok(Some(interp_path))
+}
 
-    let (entry, mut auxv) = map_elf(uspace, &elf)?;
+/// Load the user app to the user address space.
+///
+/// # Arguments
+/// - `uspace`: The address space of the user app.
+/// - `app`: The user app, checked by [`prepare_user_app`].
+/// - `envs`: The environment variables of the user app.
+///
+/// # Returns
+/// - The entry point, stack pointer, auxiliary vector and file mappings of
+///   the user app.
+pub fn load_user_app(
+    uspace: &mut AddrSpace,
+    app: &UserApp,
+    envs: &[String],
+) -> AxResult<LoadedApp> {
+    let elf = ElfFile::new(&app.data).map_err(|_| AxError::InvalidData)?;
+    let mut file_mappings = FileMappings::default();
+    let (entry, auxv) = map_elf(uspace, &elf, &app.path, &mut file_mappings)?;
+    let mut auxv = auxv.to_vec();
+    auxv.push(AuxvEntry::new(
+        AuxvType::SYSINFO_EHDR,
+        crate::vdso::vdso_base().as_usize(),
+    ));
     // The user stack is divided into two parts:
     // `ustack_start` -> `ustack_pointer`: It is the stack space that users actually read and write.
     // `ustack_pointer` -> `ustack_end`: It is the space that contains the arguments, environment variables and auxv passed to the app.
@@ -169,7 +361,7 @@ pub fn load_user_app(
         ustack_start, ustack_end
     );
 
-    let stack_data = app_stack_region(args, envs, &mut auxv, ustack_start, ustack_size);
+    let stack_data = app_stack_region(&app.args, envs, &mut auxv, ustack_start, ustack_size);
     uspace.map_alloc(
         ustack_start,
         ustack_size,
@@ -190,7 +382,12 @@ pub fn load_user_app(
 
     uspace.write(user_sp, stack_data.as_slice())?;
 
-    Ok((entry, user_sp))
+    Ok(LoadedApp {
+        entry,
+        ustack_top: user_sp,
+        auxv,
+        file_mappings,
+    })
 }
 
 #[percpu::def_percpu]
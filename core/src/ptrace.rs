@@ -0,0 +1,358 @@
+//! Process tracing, see `ptrace(2)`.
+//!
+//! A traced thread stops where its tracer asks it to: before a signal is
+//! delivered, at the entry and exit of syscalls, and on events such as
+//! `execve` and `clone`. The stop is reported to the tracer by `wait4(2)`, and
+//! the tracer may then inspect and change the thread before resuming it.
+//!
+//! A stopped thread sleeps in [`PtraceState::stop`]. Its user registers stay in
+//! the trap frame at the top of its kernel stack, see
+//! [`user_trap_frame`](crate::task::user_trap_frame), and its FP/SIMD registers
+//! are saved in the stop.
+
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+
+use axhal::arch::TrapFrame;
+use axprocess::{Process, Thread};
+use axsignal::{SignalInfo, Signo};
+use axtask::WaitQueue;
+use spin::{Mutex, MutexGuard};
+
+use crate::{
+    signal::killable_wait_until,
+    task::{ProcessData, ThreadData},
+};
+
+/// The FP/SIMD registers of a thread.
+#[cfg(target_arch = "x86_64")]
+pub type FpState = axhal::arch::ExtendedState;
+/// The FP/SIMD registers of a thread.
+#[cfg(target_arch = "aarch64")]
+pub type FpState = axhal::arch::FpState;
+/// The FP/SIMD registers of a thread.
+#[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+pub type FpState = axhal::arch::FpStatus;
+
+fn new_fp_state() -> FpState {
+    #[cfg(target_arch = "x86_64")]
+    return FpState::new();
+    #[cfg(not(target_arch = "x86_64"))]
+    return FpState::default();
+}
+
+/// Saves the FP/SIMD registers of the current thread, which are those of its
+/// user program while it runs in the kernel.
+pub fn save_fp_state() -> Box<FpState> {
+    let mut fp = Box::new(new_fp_state());
+    fp.save();
+    fp
+}
+
+/// How the tracer last resumed a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResumeMode {
+    /// Runs until the next signal or event.
+    #[default]
+    Continue,
+    /// Also stops at the next entry or exit of a syscall.
+    Syscall,
+    /// Also stops after the next instruction.
+    SingleStep,
+}
+
+/// A ptrace stop of a thread.
+pub struct PtraceStop {
+    /// The bits 8 to 23 of the wait status: the stop signal and the event
+    code: i32,
+    /// Whether the stop has been reported by `wait4(2)`
+    reported: bool,
+    /// The signal to deliver on resume, set once the tracer resumes the thread
+    resume: Option<Option<Signo>>,
+    /// The FP/SIMD registers of the thread
+    pub fp: Box<FpState>,
+    /// Whether the tracer has changed the FP/SIMD registers
+    pub fp_dirty: bool,
+}
+
+/// The tracing state of a thread.
+#[derive(Default)]
+pub struct Ptrace {
+    /// The tracing process
+    tracer: Option<Weak<Process>>,
+    /// The current stop, if any
+    stop: Option<PtraceStop>,
+    /// Whether attached by `PTRACE_SEIZE`
+    pub seized: bool,
+    /// The `PTRACE_O_*` options
+    pub options: u32,
+    /// How the thread was last resumed
+    pub mode: ResumeMode,
+    /// The signal that caused the last stop, see `PTRACE_GETSIGINFO`
+    pub siginfo: Option<SignalInfo>,
+    /// The message of the last event, see `PTRACE_GETEVENTMSG`
+    pub event_msg: usize,
+    /// The number of the current syscall, reported as `orig_rax` on x86_64
+    pub syscall_nr: Option<usize>,
+    /// An event to stop for at the exit of the current syscall, and its
+    /// message
+    pub pending_event: Option<(u32, usize)>,
+    /// Whether `PTRACE_INTERRUPT` asks the thread to stop
+    pub interrupt: bool,
+    /// The wait status of the thread if it exited before the tracer was told
+    exit_status: Option<i32>,
+}
+
+impl Ptrace {
+    /// Gets the tracer, if the thread is traced.
+    pub fn tracer(&self) -> Option<Arc<Process>> {
+        self.tracer.as_ref()?.upgrade()
+    }
+
+    /// Whether the thread is in a stop that it has not been resumed from, in
+    /// which the tracer may operate on it.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.as_ref().is_some_and(|stop| stop.resume.is_none())
+    }
+
+    /// Gets the current stop, if the thread is stopped.
+    pub fn stop_mut(&mut self) -> Option<&mut PtraceStop> {
+        self.stop.as_mut().filter(|stop| stop.resume.is_none())
+    }
+
+    /// Resumes the stopped thread in `mode`, delivering `sig` if any.
+    ///
+    /// Returns `false` if the thread is not stopped.
+    fn resume(&mut self, mode: ResumeMode, sig: Option<Signo>) -> bool {
+        let Some(stop) = self.stop_mut() else {
+            return false;
+        };
+        stop.resume = Some(sig);
+        self.mode = mode;
+        true
+    }
+
+    /// Whether there is a stop or exit to report to the tracer.
+    pub fn has_report(&self) -> bool {
+        self.exit_status.is_some()
+            || self
+                .stop
+                .as_ref()
+                .is_some_and(|stop| !stop.reported && stop.resume.is_none())
+    }
+
+    /// Whether the thread has exited, and the exit is yet to be reported to
+    /// the tracer.
+    pub fn has_exit_report(&self) -> bool {
+        self.exit_status.is_some()
+    }
+
+    /// Gets the wait status of a stop or exit to report to the tracer, and
+    /// marks it reported unless `peek` is set.
+    ///
+    /// The second value is whether the thread has exited, in which case it is
+    /// no longer traced once reported.
+    pub fn take_report(&mut self, peek: bool) -> Option<(i32, bool)> {
+        if let Some(status) = self.exit_status {
+            if !peek {
+                self.exit_status = None;
+            }
+            return Some((status, true));
+        }
+        let stop = self.stop.as_mut().filter(|stop| stop.resume.is_none())?;
+        if stop.reported {
+            return None;
+        }
+        stop.reported = !peek;
+        Some(((stop.code << 8) | 0x7f, false))
+    }
+}
+
+/// Per-thread tracing state.
+pub struct PtraceState {
+    inner: Mutex<Ptrace>,
+    /// The wait queue of the thread while stopped
+    wq: WaitQueue,
+}
+
+impl PtraceState {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Mutex::new(Ptrace::default()),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Locks the tracing state.
+    ///
+    /// The lock is a spin lock: it must not be held across blocking
+    /// operations.
+    pub fn lock(&self) -> MutexGuard<'_, Ptrace> {
+        self.inner.lock()
+    }
+
+    /// Gets the tracer, if the thread is traced.
+    pub fn tracer(&self) -> Option<Arc<Process>> {
+        self.lock().tracer()
+    }
+
+    /// Stops the current thread, which the state belongs to, until its tracer
+    /// resumes it.
+    ///
+    /// `code` holds the stop signal and the event, `siginfo` is reported by
+    /// `PTRACE_GETSIGINFO`, and `notify` tells the tracer about the stop once
+    /// it is recorded.
+    ///
+    /// Returns the signal the tracer delivers on resume, if any, or `None` if
+    /// the thread is not traced or is killed while stopped.
+    pub fn stop(
+        &self,
+        tf: &mut TrapFrame,
+        code: i32,
+        siginfo: SignalInfo,
+        notify: impl FnOnce(&Process),
+    ) -> Option<Option<Signo>> {
+        let mut inner = self.lock();
+        let tracer = inner.tracer()?;
+        inner.stop = Some(PtraceStop {
+            code,
+            reported: false,
+            resume: None,
+            fp: save_fp_state(),
+            fp_dirty: false,
+        });
+        inner.siginfo = Some(siginfo);
+        drop(inner);
+
+        notify(&tracer);
+        killable_wait_until(&self.wq, || {
+            self.lock()
+                .stop
+                .as_ref()
+                .is_none_or(|stop| stop.resume.is_some())
+        });
+
+        let stop = self.lock().stop.take()?;
+        if stop.fp_dirty {
+            stop.fp.restore();
+            #[cfg(target_arch = "riscv64")]
+            {
+                // Marks the FP state dirty, so that it is saved on context
+                // switches.
+                const SSTATUS_FS: usize = 0b11 << 13;
+                tf.sstatus |= SSTATUS_FS;
+            }
+        }
+        #[cfg(not(target_arch = "riscv64"))]
+        let _ = tf;
+        stop.resume
+    }
+
+    /// Resumes the stopped thread in `mode`, delivering `sig` if any.
+    ///
+    /// Returns `false` if the thread is not stopped.
+    pub fn resume(&self, mode: ResumeMode, sig: Option<Signo>) -> bool {
+        if !self.lock().resume(mode, sig) {
+            return false;
+        }
+        self.wq.notify_all(false);
+        true
+    }
+}
+
+fn ptrace_state(thread: &Thread) -> &PtraceState {
+    &thread.data::<ThreadData>().unwrap().ptrace
+}
+
+fn tracees(tracer: &Process) -> &Mutex<Vec<Arc<Thread>>> {
+    &tracer.data::<ProcessData>().unwrap().tracees
+}
+
+/// Makes `tracer` trace `thread`, with the `PTRACE_O_*` `options`.
+///
+/// Returns `false` if the thread is already traced.
+pub fn attach(tracer: &Arc<Process>, thread: &Arc<Thread>, seized: bool, options: u32) -> bool {
+    let mut inner = ptrace_state(thread).lock();
+    if inner.tracer().is_some() {
+        return false;
+    }
+    *inner = Ptrace {
+        tracer: Some(Arc::downgrade(tracer)),
+        seized,
+        options,
+        ..Default::default()
+    };
+    drop(inner);
+    tracees(tracer).lock().push(thread.clone());
+    true
+}
+
+/// Stops tracing `thread`, resuming it with `sig` if it is stopped.
+pub fn detach(thread: &Thread, sig: Option<Signo>) {
+    let state = ptrace_state(thread);
+    let mut inner = state.lock();
+    let tracer = inner.tracer.take();
+    inner.resume(ResumeMode::Continue, sig);
+    // Also when the thread is running, and not resumed above.
+    inner.mode = ResumeMode::Continue;
+    inner.seized = false;
+    inner.options = 0;
+    inner.pending_event = None;
+    inner.interrupt = false;
+    drop(inner);
+    state.wq.notify_all(false);
+
+    if let Some(tracer) = tracer.and_then(|it| it.upgrade()) {
+        tracees(&tracer)
+            .lock()
+            .retain(|it| !core::ptr::eq(it.as_ref(), thread));
+    }
+}
+
+/// Lists the threads traced by `tracer`, including those that exited and
+/// have yet to be reported.
+pub fn traced_threads(tracer: &Process) -> Vec<Arc<Thread>> {
+    tracees(tracer).lock().clone()
+}
+
+/// Records the exit of the traced `thread` with the wait status `status`.
+///
+/// The exit is reported to the tracer by `wait4(2)`, unless the thread is
+/// the last one of a child of the tracer, which its parent waits for anyway.
+/// Returns the tracer to notify in the former case.
+pub fn exit_traced(thread: &Thread, status: i32, last: bool) -> Option<Arc<Process>> {
+    let state = ptrace_state(thread);
+    let tracer = state.tracer()?;
+    let is_child = thread
+        .process()
+        .parent()
+        .is_some_and(|parent| Arc::ptr_eq(&parent, &tracer));
+    if last && is_child {
+        detach(thread, None);
+        return None;
+    }
+    let mut inner = state.lock();
+    inner.stop = None;
+    inner.exit_status = Some(status);
+    Some(tracer)
+}
+
+/// Removes an exited thread from the threads traced by `tracer`, once its
+/// exit is reported.
+pub fn release_traced(tracer: &Process, thread: &Thread) {
+    ptrace_state(thread).lock().tracer = None;
+    tracees(tracer)
+        .lock()
+        .retain(|it| !core::ptr::eq(it.as_ref(), thread));
+}
+
+/// Detaches the threads traced by `tracer`, as it exits.
+pub fn detach_all(tracer: &Process) {
+    let threads = core::mem::take(&mut *tracees(tracer).lock());
+    for thread in threads {
+        detach(&thread, None);
+    }
+}
@@ -0,0 +1,47 @@
+//! Resource limits of processes, see `getrlimit(2)`.
+
+use linux_raw_sys::general::{RLIM_NLIMITS, RLIMIT_CORE, RLIMIT_NOFILE, RLIMIT_STACK, rlimit64};
+
+/// The value of a resource limit meaning that the resource is not limited.
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// The soft and hard limits of the resources of a process, inherited by its
+/// children and kept across `execve`.
+///
+/// Only `RLIMIT_CORE` is enforced, by core dumps. The others are reported
+/// and may be changed, but do not restrict anything.
+#[derive(Debug, Clone)]
+pub struct ResourceLimits([rlimit64; RLIM_NLIMITS as usize]);
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        let mut limits = Self(
+            [rlimit64 {
+                rlim_cur: RLIM_INFINITY,
+                rlim_max: RLIM_INFINITY,
+            }; RLIM_NLIMITS as usize],
+        );
+        // No core files unless asked for, as on most Linux distributions.
+        limits.0[RLIMIT_CORE as usize].rlim_cur = 0;
+        limits.0[RLIMIT_STACK as usize].rlim_cur = axconfig::plat::USER_STACK_SIZE as u64;
+        // The size of the file descriptor table.
+        limits.0[RLIMIT_NOFILE as usize] = rlimit64 {
+            rlim_cur: 1024,
+            rlim_max: 1024,
+        };
+        limits
+    }
+}
+
+impl ResourceLimits {
+    /// Returns the limits of `resource`, or `None` if there is no such
+    /// resource.
+    pub fn get(&self, resource: u32) -> Option<rlimit64> {
+        self.0.get(resource as usize).copied()
+    }
+
+    /// Sets the limits of `resource`, which must be a valid resource.
+    pub fn set(&mut self, resource: u32, limit: rlimit64) {
+        self.0[resource as usize] = limit;
+    }
+}
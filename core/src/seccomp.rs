@@ -0,0 +1,300 @@
+//! Secure computing mode, see `seccomp(2)`.
+//!
+//! A thread in strict mode may only use `read`, `write`, `exit` and
+//! `rt_sigreturn`. In filter mode, every syscall of the thread is checked by
+//! the classic BPF programs installed by the thread and the threads it was
+//! cloned from, which decide whether it runs, fails or kills the thread.
+//!
+//! The mode and the filters are inherited by `clone` and kept across
+//! `execve`, and can only be made stricter.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::mem::size_of;
+
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::ptrace::{
+    BPF_A, BPF_ABS, BPF_ADD, BPF_ALU, BPF_AND, BPF_DIV, BPF_IMM, BPF_JA, BPF_JEQ, BPF_JGE, BPF_JGT,
+    BPF_JMP, BPF_JSET, BPF_K, BPF_LD, BPF_LDX, BPF_LEN, BPF_LSH, BPF_MAXINSNS, BPF_MEM, BPF_MISC,
+    BPF_MUL, BPF_NEG, BPF_OR, BPF_RET, BPF_RSH, BPF_ST, BPF_STX, BPF_SUB, BPF_TAX, BPF_TXA, BPF_W,
+    BPF_X, BPF_XOR, SECCOMP_RET_ACTION_FULL, SECCOMP_RET_ALLOW, seccomp_data, sock_filter,
+};
+
+/// The number of words of the scratch memory of a BPF program.
+const BPF_MEMWORDS: u32 = 16;
+
+/// The maximum number of instructions of the filters of a thread, where each
+/// filter counts 4 more.
+const MAX_INSNS_PER_PATH: usize = 32768;
+
+const fn bpf_class(code: u32) -> u32 {
+    code & 0x07
+}
+
+/// The seccomp mode of a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeccompMode {
+    /// Syscalls are not checked.
+    #[default]
+    Disabled,
+    /// Only `read`, `write`, `exit` and `rt_sigreturn` are allowed.
+    Strict,
+    /// Syscalls are checked by BPF filters.
+    Filter,
+}
+
+/// A classic BPF program that checks syscalls, linked to the filters
+/// installed before it.
+pub struct SeccompFilter {
+    prog: Vec<sock_filter>,
+    /// Whether the actions it returns, other than `SECCOMP_RET_ALLOW`, are
+    /// logged
+    pub log: bool,
+    prev: Option<Arc<SeccompFilter>>,
+}
+
+impl SeccompFilter {
+    /// Creates a filter running `prog`, after checking that it is a valid
+    /// program which only loads from `struct seccomp_data`.
+    pub fn new(mut prog: Vec<sock_filter>, log: bool) -> LinuxResult<Self> {
+        if prog.is_empty() || prog.len() > BPF_MAXINSNS as usize {
+            return Err(LinuxError::EINVAL);
+        }
+        let len = prog.len();
+        for (pc, insn) in prog.iter_mut().enumerate() {
+            let code = insn.code as u32;
+            let k = insn.k;
+            let valid = match code {
+                c if c > 0xff => false,
+                c if c == BPF_LD | BPF_W | BPF_ABS => {
+                    k % 4 == 0 && (k as usize) < size_of::<seccomp_data>()
+                }
+                c if c == BPF_LD | BPF_W | BPF_LEN || c == BPF_LDX | BPF_W | BPF_LEN => {
+                    // The length of the data is known.
+                    insn.code = (bpf_class(code) | BPF_IMM) as u16;
+                    insn.k = size_of::<seccomp_data>() as u32;
+                    true
+                }
+                c if c == BPF_LD | BPF_IMM || c == BPF_LDX | BPF_IMM => true,
+                c if c == BPF_LD | BPF_MEM
+                    || c == BPF_LDX | BPF_MEM
+                    || c == BPF_ST
+                    || c == BPF_STX =>
+                {
+                    k < BPF_MEMWORDS
+                }
+                c if c == BPF_ALU | BPF_NEG => true,
+                c if bpf_class(c) == BPF_ALU => {
+                    let op = c & 0xf0;
+                    let valid_op = matches!(
+                        op,
+                        BPF_ADD
+                            | BPF_SUB
+                            | BPF_MUL
+                            | BPF_DIV
+                            | BPF_AND
+                            | BPF_OR
+                            | BPF_XOR
+                            | BPF_LSH
+                            | BPF_RSH
+                    );
+                    let valid_k = match op {
+                        BPF_DIV => k != 0,
+                        BPF_LSH | BPF_RSH => k < 32,
+                        _ => true,
+                    };
+                    valid_op && (c & BPF_X != 0 || valid_k)
+                }
+                c if c == BPF_JMP | BPF_JA => (k as usize) < len - pc - 1,
+                c if bpf_class(c) == BPF_JMP => {
+                    matches!(c & 0xf0, BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET)
+                        && pc + 1 + (insn.jt as usize) < len
+                        && pc + 1 + (insn.jf as usize) < len
+                }
+                c if c == BPF_RET | BPF_K || c == BPF_RET | BPF_A => true,
+                c if c == BPF_MISC | BPF_TAX || c == BPF_MISC | BPF_TXA => true,
+                _ => false,
+            };
+            if !valid {
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        if bpf_class(prog[len - 1].code as u32) != BPF_RET {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(Self {
+            prog,
+            log,
+            prev: None,
+        })
+    }
+
+    /// Runs the program on `data`, returning its `SECCOMP_RET_*` value.
+    fn run(&self, data: &seccomp_data) -> u32 {
+        let word = |offset: u32| {
+            let offset = offset as usize;
+            // SAFETY: `seccomp_data` is plain integers, and the offset is a
+            // checked, aligned offset into it.
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    (data as *const seccomp_data).cast::<u8>(),
+                    size_of::<seccomp_data>(),
+                )
+            };
+            u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+        };
+
+        let (mut a, mut x) = (0u32, 0u32);
+        let mut mem = [0u32; BPF_MEMWORDS as usize];
+        let mut pc = 0;
+        loop {
+            let insn = &self.prog[pc];
+            let code = insn.code as u32;
+            let k = insn.k;
+            pc += 1;
+            match bpf_class(code) {
+                BPF_LD => match code & 0xe0 {
+                    BPF_ABS => a = word(k),
+                    BPF_IMM => a = k,
+                    _ => a = mem[k as usize],
+                },
+                BPF_LDX => match code & 0xe0 {
+                    BPF_IMM => x = k,
+                    _ => x = mem[k as usize],
+                },
+                BPF_ST => mem[k as usize] = a,
+                BPF_STX => mem[k as usize] = x,
+                BPF_ALU => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    a = match code & 0xf0 {
+                        BPF_ADD => a.wrapping_add(operand),
+                        BPF_SUB => a.wrapping_sub(operand),
+                        BPF_MUL => a.wrapping_mul(operand),
+                        BPF_DIV => match a.checked_div(operand) {
+                            Some(a) => a,
+                            // Division by zero aborts the program.
+                            None => return 0,
+                        },
+                        BPF_AND => a & operand,
+                        BPF_OR => a | operand,
+                        BPF_XOR => a ^ operand,
+                        BPF_LSH => a.checked_shl(operand).unwrap_or(0),
+                        BPF_RSH => a.checked_shr(operand).unwrap_or(0),
+                        _ => a.wrapping_neg(),
+                    };
+                }
+                BPF_JMP => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    let taken = match code & 0xf0 {
+                        BPF_JA => {
+                            pc += k as usize;
+                            continue;
+                        }
+                        BPF_JEQ => a == operand,
+                        BPF_JGT => a > operand,
+                        BPF_JGE => a >= operand,
+                        _ => a & operand != 0,
+                    };
+                    pc += if taken { insn.jt } else { insn.jf } as usize;
+                }
+                BPF_RET => return if code & 0x18 == BPF_A { a } else { k },
+                _ => {
+                    if code & 0xf8 == BPF_TAX {
+                        x = a;
+                    } else {
+                        a = x;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The seccomp state of a thread.
+#[derive(Clone, Default)]
+pub struct Seccomp {
+    mode: SeccompMode,
+    filter: Option<Arc<SeccompFilter>>,
+}
+
+impl Seccomp {
+    /// Gets the mode.
+    pub fn mode(&self) -> SeccompMode {
+        self.mode
+    }
+
+    /// Enters strict mode.
+    ///
+    /// Fails with `EINVAL` if the thread is in filter mode.
+    pub fn set_strict(&mut self) -> LinuxResult {
+        if self.mode == SeccompMode::Filter {
+            return Err(LinuxError::EINVAL);
+        }
+        self.mode = SeccompMode::Strict;
+        Ok(())
+    }
+
+    /// Installs `filter` in addition to the existing filters, and enters
+    /// filter mode.
+    ///
+    /// Fails with `EINVAL` if the thread is in strict mode, or `ENOMEM` if the
+    /// filters would be too long.
+    pub fn add_filter(&mut self, mut filter: SeccompFilter) -> LinuxResult {
+        if self.mode == SeccompMode::Strict {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut total = filter.prog.len();
+        let mut prev = self.filter.as_deref();
+        while let Some(it) = prev {
+            total += it.prog.len() + 4;
+            prev = it.prev.as_deref();
+        }
+        if total > MAX_INSNS_PER_PATH {
+            return Err(LinuxError::ENOMEM);
+        }
+        filter.prev = self.filter.take();
+        self.filter = Some(Arc::new(filter));
+        self.mode = SeccompMode::Filter;
+        Ok(())
+    }
+
+    /// Whether the filters of `other` are all among the filters of this
+    /// state, so that `other` can be given this state by
+    /// `SECCOMP_FILTER_FLAG_TSYNC` without losing any of them.
+    pub fn extends(&self, other: &Seccomp) -> bool {
+        if other.mode == SeccompMode::Strict {
+            return false;
+        }
+        let Some(target) = &other.filter else {
+            return true;
+        };
+        let mut filter = self.filter.as_ref();
+        while let Some(it) = filter {
+            if Arc::ptr_eq(it, target) {
+                return true;
+            }
+            filter = it.prev.as_ref();
+        }
+        false
+    }
+
+    /// Runs the filters on the syscall described by `data`.
+    ///
+    /// Returns the `SECCOMP_RET_*` value that takes precedence, and whether
+    /// the filter that returned it asked to log it.
+    pub fn run_filters(&self, data: &seccomp_data) -> (u32, bool) {
+        let mut result = (SECCOMP_RET_ALLOW, false);
+        let mut filter = self.filter.as_deref();
+        while let Some(it) = filter {
+            let ret = it.run(data);
+            // The actions are ordered by precedence as signed integers, with
+            // `SECCOMP_RET_KILL_PROCESS` first.
+            if ((ret & SECCOMP_RET_ACTION_FULL) as i32)
+                < ((result.0 & SECCOMP_RET_ACTION_FULL) as i32)
+            {
+                result = (ret, it.log);
+            }
+            filter = it.prev.as_deref();
+        }
+        result
+    }
+}
@@ -0,0 +1,344 @@
+//! Signal-interruptible waits and job control.
+//!
+//! A thread blocking in a syscall registers the wait queue it sleeps on, so
+//! that sending a signal to it (or to its process) can wake it up. The wait
+//! then fails with [`LinuxError::ERESTART`], which the syscall layer turns
+//! into `EINTR` or a transparent restart of the syscall, depending on the
+//! `SA_RESTART` flag of the handler about to run.
+//!
+//! A process stopped by a job-control signal parks all its threads, either on
+//! their way back to user space or in place if they are in an interruptible
+//! wait, until it is continued by `SIGCONT` or killed.
+
+use core::{
+    cell::Cell,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{TimeValue, monotonic_time};
+use axprocess::{Process, Thread};
+use axsignal::{
+    DefaultSignalAction, SignalAction, SignalActionFlags, SignalDisposition, SignalInfo,
+    SignalSet, Signo,
+};
+use axsync::Mutex;
+use axtask::{AxTaskRef, TaskExtRef, WaitQueue, current};
+
+use crate::task::{ProcessData, ThreadData};
+
+/// The wait queue a thread sleeps on interruptibly, and the task to wake up.
+struct Sleeper {
+    wq: NonNull<WaitQueue>,
+    task: AxTaskRef,
+}
+
+// SAFETY: `wq` is only dereferenced while the sleeper is registered, and the
+// registration is dropped before the wait queue borrow ends.
+unsafe impl Send for Sleeper {}
+unsafe impl Sync for Sleeper {}
+
+/// Per-thread state of interruptible waits.
+pub(crate) struct Interruptible {
+    /// Set when a signal arrives, cleared before checking pending signals.
+    interrupted: AtomicBool,
+    /// The current interruptible wait, if any.
+    sleeper: Mutex<Option<Sleeper>>,
+}
+
+impl Interruptible {
+    pub(crate) fn new() -> Self {
+        Self {
+            interrupted: AtomicBool::new(false),
+            sleeper: Mutex::new(None),
+        }
+    }
+
+    fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Release);
+        if let Some(sleeper) = self.sleeper.lock().as_ref() {
+            // SAFETY: see `Sleeper`.
+            unsafe { sleeper.wq.as_ref() }.notify_task(true, &sleeper.task);
+        }
+    }
+}
+
+/// Unregisters an interruptible wait when dropped, restoring the enclosing one
+/// if any.
+struct SleeperGuard<'a> {
+    state: &'a Interruptible,
+    prev: Option<Sleeper>,
+}
+
+impl<'a> SleeperGuard<'a> {
+    fn new(state: &'a Interruptible, wq: &WaitQueue) -> Self {
+        let prev = state.sleeper.lock().replace(Sleeper {
+            wq: NonNull::from(wq),
+            task: current().as_task_ref().clone(),
+        });
+        Self { state, prev }
+    }
+}
+
+impl Drop for SleeperGuard<'_> {
+    fn drop(&mut self) {
+        *self.state.sleeper.lock() = self.prev.take();
+    }
+}
+
+/// Interrupts the interruptible wait of a thread, if any. Called after a
+/// signal is sent to the thread.
+pub fn interrupt_thread(thr: &Thread) {
+    if let Some(data) = thr.data::<ThreadData>() {
+        data.interruptible.interrupt();
+    }
+}
+
+/// Interrupts the interruptible waits of all threads in a process. Called
+/// after a signal is sent to the process.
+pub fn interrupt_process(proc: &Process) {
+    for thr in proc.threads() {
+        interrupt_thread(&thr);
+    }
+}
+
+/// Signal sending implemented by the syscall layer, which also does the
+/// job-control side effects of `SIGCONT` and stop signals.
+#[crate_interface::def_interface]
+pub trait SendSignalIf {
+    /// Sends a signal to a process, as `kill(2)` does.
+    fn send_signal_process(proc: &Process, sig: SignalInfo);
+}
+
+/// Sends a signal generated by the kernel, such as a timer expiry, to a
+/// process.
+pub(crate) fn send_signal_process(proc: &Process, sig: SignalInfo) {
+    crate_interface::call_interface!(SendSignalIf::send_signal_process(proc, sig));
+}
+
+/// A change of the job-control state of a process, reported by `wait4(2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobEvent {
+    /// The process was stopped by the signal.
+    Stopped(Signo),
+    /// The process was continued by `SIGCONT`.
+    Continued,
+}
+
+/// The encoding of [`JobEvent::Continued`] in [`JobControl::event`]. Stops
+/// are encoded as the signal number.
+const EVENT_CONTINUED: u8 = u8::MAX;
+
+/// Job-control state of a process.
+pub(crate) struct JobControl {
+    /// Whether the process is stopped
+    stopped: AtomicBool,
+    /// The last job-control event not yet reported, or 0
+    event: AtomicU8,
+    /// The wait queue of stopped threads
+    wq: WaitQueue,
+}
+
+impl JobControl {
+    pub(crate) fn new() -> Self {
+        Self {
+            stopped: AtomicBool::new(false),
+            event: AtomicU8::new(0),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn event(&self) -> Option<JobEvent> {
+        match self.event.load(Ordering::Acquire) {
+            0 => None,
+            EVENT_CONTINUED => Some(JobEvent::Continued),
+            signo => Signo::from_repr(signo).map(JobEvent::Stopped),
+        }
+    }
+
+    pub(crate) fn clear_event(&self, event: JobEvent) -> bool {
+        let value = match event {
+            JobEvent::Stopped(signo) => signo as u8,
+            JobEvent::Continued => EVENT_CONTINUED,
+        };
+        self.event
+            .compare_exchange(value, 0, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+/// Stops a process because of the job-control signal `signo`.
+///
+/// Returns `false` if the process is already stopped. Otherwise the stop is
+/// recorded for `wait4(2)`, and the threads of the process are interrupted so
+/// that they park in [`wait_while_stopped`]. The caller is responsible for
+/// notifying the parent.
+pub fn stop_process(proc: &Process, signo: Signo) -> bool {
+    let job = &proc.data::<ProcessData>().unwrap().job;
+    if job.stopped.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+    job.event.store(signo as u8, Ordering::Release);
+    interrupt_process(proc);
+    true
+}
+
+/// Continues a stopped process, as done when it is sent `SIGCONT`.
+///
+/// Returns `false` if the process is not stopped. Otherwise the continuation
+/// is recorded for `wait4(2)` and the threads of the process are woken up. The
+/// caller is responsible for notifying the parent.
+pub fn continue_process(proc: &Process) -> bool {
+    let job = &proc.data::<ProcessData>().unwrap().job;
+    if !job.stopped.swap(false, Ordering::AcqRel) {
+        return false;
+    }
+    job.event.store(EVENT_CONTINUED, Ordering::Release);
+    job.wq.notify_all(false);
+    true
+}
+
+/// Parks the current thread while its process is stopped.
+///
+/// Returns early if `SIGKILL` is pending, so that a stopped process can be
+/// killed.
+pub fn wait_while_stopped() {
+    let curr = current();
+    let job = &curr.task_ext().process_data().job;
+    if !job.is_stopped() {
+        return;
+    }
+    killable_wait_until(&job.wq, || !job.is_stopped());
+}
+
+/// Blocks the current thread on `wq` until `condition` becomes true or
+/// `SIGKILL` is pending. Other signals do not interrupt the wait.
+///
+/// `condition` is checked with the lock of `wq` held, so it must not block.
+pub fn killable_wait_until(wq: &WaitQueue, condition: impl Fn() -> bool) {
+    let curr = current();
+    let thr_data = curr.task_ext().thread_data();
+    let state = &thr_data.interruptible;
+    let _guard = SleeperGuard::new(state, wq);
+    loop {
+        state.interrupted.store(false, Ordering::Release);
+        if thr_data.signal.pending().has(Signo::SIGKILL) || condition() {
+            break;
+        }
+        wq.wait_until(|| condition() || state.interrupted.load(Ordering::Acquire));
+    }
+}
+
+/// Checks whether any unblocked signal pending for the current thread
+/// satisfies `f`.
+fn any_pending_signal(f: impl Fn(Signo, &SignalAction) -> bool) -> bool {
+    let curr = current();
+    let signal = &curr.task_ext().thread_data().signal;
+    let mut pending = signal.pending() & !signal.blocked();
+    let actions = curr.task_ext().process_data().signal.actions();
+    let actions = actions.lock();
+    let all = !SignalSet::default();
+    while let Some(signo) = pending.dequeue(&all) {
+        if f(signo, &actions[signo]) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Checks whether the current thread has a pending signal that should
+/// interrupt a blocking syscall, i.e. one that is neither blocked nor ignored,
+/// or its process is stopped, or its tracer asked it to stop.
+pub fn has_pending_signal() -> bool {
+    let curr = current();
+    curr.task_ext().process_data().job.is_stopped()
+        || curr.task_ext().thread_data().ptrace.lock().interrupt
+        || any_pending_signal(|signo, action| match action.disposition {
+            SignalDisposition::Default => !matches!(
+                signo.default_action(),
+                DefaultSignalAction::Ignore | DefaultSignalAction::Continue
+            ),
+            SignalDisposition::Ignore => false,
+            SignalDisposition::Handler(_) => true,
+        })
+}
+
+/// Checks whether a syscall interrupted by the pending signals of the current
+/// thread should be restarted, i.e. none of them runs a handler installed
+/// without `SA_RESTART`.
+pub fn should_restart_syscall() -> bool {
+    !any_pending_signal(|_, action| {
+        matches!(action.disposition, SignalDisposition::Handler(_))
+            && !action.flags.contains(SignalActionFlags::RESTART)
+    })
+}
+
+/// Blocks the current thread on `wq` until `condition` becomes true, a signal
+/// interrupts it, or the optional `timeout` elapses.
+///
+/// `condition` is checked with the lock of `wq` held, so it must not block.
+///
+/// Returns `ETIMEDOUT` on timeout, and `ERESTART` if interrupted by a signal.
+pub fn interruptible_wait_until(
+    wq: &WaitQueue,
+    timeout: Option<TimeValue>,
+    condition: impl Fn() -> bool,
+) -> LinuxResult {
+    let curr = current();
+    let state = &curr.task_ext().thread_data().interruptible;
+    let _guard = SleeperGuard::new(state, wq);
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+
+    let interrupted = || state.interrupted.load(Ordering::Acquire);
+    loop {
+        // Stop in place, so that the wait goes on once continued.
+        wait_while_stopped();
+        state.interrupted.store(false, Ordering::Release);
+        if has_pending_signal() {
+            return Err(LinuxError::ERESTART);
+        }
+
+        let condition = || interrupted() || condition();
+        match deadline {
+            Some(deadline) => {
+                let Some(timeout) = deadline.checked_sub(monotonic_time()) else {
+                    return Err(LinuxError::ETIMEDOUT);
+                };
+                if wq.wait_timeout_until(timeout, condition) {
+                    return Err(LinuxError::ETIMEDOUT);
+                }
+            }
+            None => wq.wait_until(condition),
+        }
+
+        if !interrupted() {
+            return Ok(());
+        }
+    }
+}
+
+/// Blocks the current thread on `wq` until it is notified, a signal interrupts
+/// it, or the optional `timeout` elapses.
+///
+/// Returns `ETIMEDOUT` on timeout, and `ERESTART` if interrupted by a signal.
+pub fn interruptible_wait(wq: &WaitQueue, timeout: Option<TimeValue>) -> LinuxResult {
+    // Any wakeup after the first check counts as a notification.
+    let woken = Cell::new(false);
+    interruptible_wait_until(wq, timeout, || woken.replace(true))
+}
+
+/// Sleeps for `dur` unless a signal interrupts the sleep.
+///
+/// Returns `ERESTART` if interrupted by a signal.
+pub fn interruptible_sleep(dur: TimeValue) -> LinuxResult {
+    let wq = WaitQueue::new();
+    match interruptible_wait_until(&wq, Some(dur), || false) {
+        Err(LinuxError::ETIMEDOUT) => Ok(()),
+        result => result,
+    }
+}
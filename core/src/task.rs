@@ -3,18 +3,20 @@
 use core::{
     alloc::Layout,
     cell::RefCell,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
     time::Duration,
 };
 
 use alloc::{
+    boxed::Box,
+    collections::btree_set::BTreeSet,
     string::String,
     sync::{Arc, Weak},
     vec::Vec,
 };
 use axerrno::{LinuxError, LinuxResult};
 use axhal::{
-    arch::UspaceContext,
+    arch::{TrapFrame, UspaceContext},
     time::{NANOS_PER_MICROS, NANOS_PER_SEC, monotonic_time_nanos},
 };
 use axmm::{AddrSpace, kernel_aspace};
@@ -25,12 +27,22 @@ use axsignal::{
     api::{ProcessSignalManager, SignalActions, ThreadSignalManager},
 };
 use axsync::{Mutex, RawMutex};
-use axtask::{TaskExtRef, TaskInner, WaitQueue, current};
+use axtask::{AxTaskRef, TaskExtRef, TaskId, TaskInner, WaitQueue, WeakAxTaskRef, current};
+use kernel_elf_parser::AuxvEntry;
 use memory_addr::VirtAddrRange;
-use spin::{Once, RwLock};
+use spin::{Mutex as SpinMutex, Once, RwLock};
 use weak_map::WeakMap;
 
-use crate::{futex::FutexTable, time::TimeStat};
+use crate::{
+    cred::Credentials,
+    futex::FutexTable,
+    mm::FileMappings,
+    ptrace::{FpState, PtraceState},
+    resource::ResourceLimits,
+    seccomp::Seccomp,
+    signal::{Interruptible, JobControl, JobEvent, killable_wait_until},
+    time::{ITimers, PosixTimers, TimeStat, charge_cpu_time},
+};
 
 /// Create a new user task.
 pub fn new_user_task(
@@ -42,7 +54,7 @@ pub fn new_user_task(
         move || {
             let curr = axtask::current();
             if let Some(tid) = set_child_tid {
-                *tid = curr.id().as_u64() as Pid;
+                *tid = curr.task_ext().thread.tid();
             }
 
             let kstack_top = curr.kernel_stack_top().unwrap();
@@ -59,6 +71,17 @@ pub fn new_user_task(
     )
 }
 
+/// Get the user-mode registers of a user task, which are saved in a
+/// [`TrapFrame`] at the top of its kernel stack when it enters the kernel.
+///
+/// They are only up to date while the task is not running in user mode.
+pub fn user_trap_frame(task: &AxTaskRef) -> *mut TrapFrame {
+    let kstack_top = task
+        .kernel_stack_top()
+        .expect("user task without a kernel stack");
+    (kstack_top - core::mem::size_of::<TrapFrame>()).as_mut_ptr_of()
+}
+
 /// Task extended data for the monolithic kernel.
 pub struct TaskExt {
     /// The time statistics
@@ -77,14 +100,18 @@ impl TaskExt {
     }
 
     pub(crate) fn time_stat_from_kernel_to_user(&self, current_tick: usize) {
-        self.time.borrow_mut().switch_into_user_mode(current_tick);
+        let stime_ns = self.time.borrow_mut().switch_into_user_mode(current_tick);
+        charge_cpu_time(self.thread.process(), self.thread.tid(), 0, stime_ns);
     }
 
     pub(crate) fn time_stat_from_user_to_kernel(&self, current_tick: usize) {
-        self.time.borrow_mut().switch_into_kernel_mode(current_tick);
+        let utime_ns = self.time.borrow_mut().switch_into_kernel_mode(current_tick);
+        charge_cpu_time(self.thread.process(), self.thread.tid(), utime_ns, 0);
     }
 
-    pub(crate) fn time_stat_output(&self) -> (usize, usize) {
+    /// Get the user and system CPU time consumed by the thread, in
+    /// nanoseconds.
+    pub fn time_stat_output(&self) -> (usize, usize) {
         self.time.borrow().output()
     }
 
@@ -151,6 +178,41 @@ impl axsignal::api::WaitQueue for WaitQueueWrapper {
     }
 }
 
+/// An event that happens once, which tasks can wait for.
+pub struct Completion {
+    done: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl Completion {
+    /// Create a new [`Completion`] that has not happened.
+    pub const fn new() -> Self {
+        Self {
+            done: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Mark the event as happened, waking up the tasks waiting for it.
+    pub fn complete(&self) {
+        if !self.done.swap(true, Ordering::AcqRel) {
+            self.wq.notify_all(false);
+        }
+    }
+
+    /// Block the current thread until the event happens or `SIGKILL` is
+    /// pending.
+    pub fn wait(&self) {
+        killable_wait_until(&self.wq, || self.done.load(Ordering::Acquire));
+    }
+}
+
+impl Default for Completion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Extended data for [`Thread`].
 pub struct ThreadData {
     /// The clear thread tid field
@@ -162,6 +224,22 @@ pub struct ThreadData {
 
     /// The thread-level signal manager
     pub signal: ThreadSignalManager<RawMutex, WaitQueueWrapper>,
+    /// The state of signal-interruptible waits
+    pub(crate) interruptible: Interruptible,
+    /// The task running the thread
+    task: Once<WeakAxTaskRef>,
+    /// The tracing state
+    pub ptrace: PtraceState,
+    /// The seccomp mode and filters
+    pub seccomp: spin::Mutex<Seccomp>,
+    /// Whether `execve` may not grant privileges, see `PR_SET_NO_NEW_PRIVS`
+    pub no_new_privs: AtomicBool,
+    /// The signal sent to the process when its parent exits, or 0, see
+    /// `PR_SET_PDEATHSIG`
+    pub pdeathsig: AtomicU32,
+    /// The FP/SIMD registers of the thread, saved when it is killed by the
+    /// thread dumping the core of the process
+    pub core_fp: spin::Mutex<Option<Box<FpState>>>,
 }
 
 impl ThreadData {
@@ -172,9 +250,26 @@ impl ThreadData {
             clear_child_tid: AtomicUsize::new(0),
 
             signal: ThreadSignalManager::new(proc.signal.clone()),
+            interruptible: Interruptible::new(),
+            task: Once::new(),
+            ptrace: PtraceState::new(),
+            seccomp: spin::Mutex::new(Seccomp::default()),
+            no_new_privs: AtomicBool::new(false),
+            pdeathsig: AtomicU32::new(0),
+            core_fp: spin::Mutex::new(None),
         }
     }
 
+    /// Record the task running the thread, once it is spawned.
+    pub fn set_task(&self, task: &AxTaskRef) {
+        self.task.call_once(|| Arc::downgrade(task));
+    }
+
+    /// Get the task running the thread, if it has not been dropped.
+    pub fn task(&self) -> Option<AxTaskRef> {
+        self.task.get().and_then(Weak::upgrade)
+    }
+
     /// Get the clear child tid field.
     pub fn clear_child_tid(&self) -> usize {
         self.clear_child_tid.load(Ordering::Relaxed)
@@ -191,17 +286,44 @@ impl ThreadData {
 pub struct ProcessData {
     /// The executable path
     pub exe_path: RwLock<String>,
-    /// The virtual memory address space.
-    pub aspace: Arc<Mutex<AddrSpace>>,
+    /// The virtual memory address space, shared by processes cloned with
+    /// `CLONE_VM`
+    aspace: RwLock<Arc<Mutex<AddrSpace>>>,
     /// The resource namespace
     pub ns: AxNamespace,
     /// The user heap bottom
     heap_bottom: AtomicUsize,
     /// The user heap top
     heap_top: AtomicUsize,
+    /// Whether the process has called `execve` since it was forked
+    execved: AtomicBool,
+    /// The user and group IDs
+    cred: RwLock<Arc<Credentials>>,
+    /// The file mode creation mask
+    umask: AtomicU32,
+    /// The auxiliary vector passed to the executable
+    pub auxv: Mutex<Vec<AuxvEntry>>,
+    /// The resource limits
+    pub rlimits: RwLock<ResourceLimits>,
+    /// The file mappings of the address space
+    pub file_mappings: Mutex<FileMappings>,
+    /// Whether the process may dump core and be traced by unprivileged
+    /// processes, see `PR_SET_DUMPABLE`
+    dumpable: AtomicBool,
+    /// Whether a thread is dumping the core of the process
+    core_dumping: AtomicBool,
+    /// Whether a thread is executing a program, see `execve`
+    group_exec: AtomicBool,
+    /// The threads traced by the process
+    pub(crate) tracees: spin::Mutex<Vec<Arc<Thread>>>,
 
     /// The child exit wait queue
     pub child_exit_wq: WaitQueue,
+    /// The thread exit wait queue
+    pub thread_exit_wq: WaitQueue,
+    /// Completed when the process stops using the address space of its
+    /// parent, which waits for it after `vfork`
+    pub vfork_done: Completion,
     /// The exit signal of the thread
     pub exit_signal: Option<Signo>,
 
@@ -210,6 +332,17 @@ pub struct ProcessData {
 
     /// The futex table.
     pub futex_table: FutexTable,
+
+    /// The user CPU time consumed by all threads, in nanoseconds
+    pub(crate) utime_ns: AtomicUsize,
+    /// The system CPU time consumed by all threads, in nanoseconds
+    pub(crate) stime_ns: AtomicUsize,
+    /// The interval timers
+    pub(crate) itimers: Mutex<ITimers>,
+    /// The POSIX timers
+    pub(crate) posix_timers: Mutex<PosixTimers>,
+    /// The job-control state
+    pub(crate) job: JobControl,
 }
 
 impl ProcessData {
@@ -219,26 +352,71 @@ impl ProcessData {
         aspace: Arc<Mutex<AddrSpace>>,
         signal_actions: Arc<Mutex<SignalActions>>,
         exit_signal: Option<Signo>,
+        cred: Arc<Credentials>,
     ) -> Self {
         Self {
             exe_path: RwLock::new(exe_path),
-            aspace,
+            aspace: RwLock::new(aspace),
             ns: AxNamespace::new_thread_local(),
             heap_bottom: AtomicUsize::new(axconfig::plat::USER_HEAP_BASE),
             heap_top: AtomicUsize::new(axconfig::plat::USER_HEAP_BASE),
+            execved: AtomicBool::new(false),
+            cred: RwLock::new(cred),
+            umask: AtomicU32::new(0o022),
+            auxv: Mutex::default(),
+            rlimits: RwLock::default(),
+            file_mappings: Mutex::default(),
+            dumpable: AtomicBool::new(true),
+            core_dumping: AtomicBool::new(false),
+            group_exec: AtomicBool::new(false),
+            tracees: spin::Mutex::new(Vec::new()),
 
             child_exit_wq: WaitQueue::new(),
+            thread_exit_wq: WaitQueue::new(),
+            vfork_done: Completion::new(),
             exit_signal,
 
             signal: Arc::new(ProcessSignalManager::new(
                 signal_actions,
-                axconfig::plat::SIGNAL_TRAMPOLINE,
+                crate::vdso::sigreturn_address(),
             )),
 
             futex_table: FutexTable::new(),
+
+            utime_ns: AtomicUsize::new(0),
+            stime_ns: AtomicUsize::new(0),
+            itimers: Mutex::default(),
+            posix_timers: Mutex::default(),
+            job: JobControl::new(),
         }
     }
 
+    /// Get the user and system CPU time consumed by all threads, in
+    /// nanoseconds.
+    pub fn cpu_time(&self) -> (usize, usize) {
+        (
+            self.utime_ns.load(Ordering::Relaxed),
+            self.stime_ns.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Get the virtual memory address space.
+    pub fn aspace(&self) -> Arc<Mutex<AddrSpace>> {
+        self.aspace.read().clone()
+    }
+
+    /// Whether the address space is shared with other processes.
+    pub fn is_aspace_shared(&self) -> bool {
+        Arc::strong_count(&self.aspace.read()) > 1
+    }
+
+    /// Replace the virtual memory address space, such as when `execve` leaves
+    /// the address space shared with other processes.
+    pub fn set_aspace(&self, aspace: Arc<Mutex<AddrSpace>>) {
+        let mut old = core::mem::replace(&mut *self.aspace.write(), aspace);
+        release_aspace(&mut old);
+    }
+
     /// Get the bottom address of the user heap.
     pub fn get_heap_bottom(&self) -> usize {
         self.heap_bottom.load(Ordering::Acquire)
@@ -259,22 +437,132 @@ impl ProcessData {
         self.heap_top.store(top, Ordering::Release)
     }
 
+    /// Check whether the process has called `execve` since it was forked.
+    pub fn has_execved(&self) -> bool {
+        self.execved.load(Ordering::Acquire)
+    }
+
+    /// Record that the process has called `execve`.
+    pub fn set_execved(&self) {
+        self.execved.store(true, Ordering::Release)
+    }
+
+    /// Get the credentials of the process.
+    pub fn cred(&self) -> Arc<Credentials> {
+        self.cred.read().clone()
+    }
+
+    /// Replace the credentials of the process.
+    ///
+    /// The process is no longer dumpable if its effective or filesystem IDs
+    /// change, so that it does not leak what it got hold of with the old
+    /// ones.
+    pub fn set_cred(&self, cred: Credentials) {
+        let mut curr = self.cred.write();
+        if cred.ids_changed_from(&curr) {
+            self.set_dumpable(false);
+        }
+        *curr = Arc::new(cred);
+    }
+
+    /// Get the file mode creation mask.
+    pub fn umask(&self) -> u32 {
+        self.umask.load(Ordering::Relaxed)
+    }
+
+    /// Set the file mode creation mask, returning the previous one.
+    pub fn replace_umask(&self, umask: u32) -> u32 {
+        self.umask.swap(umask, Ordering::Relaxed)
+    }
+
+    /// Whether the process may dump core, and be traced by processes without
+    /// `CAP_SYS_PTRACE`.
+    pub fn is_dumpable(&self) -> bool {
+        self.dumpable.load(Ordering::Acquire)
+    }
+
+    /// Set whether the process is dumpable.
+    pub fn set_dumpable(&self, dumpable: bool) {
+        self.dumpable.store(dumpable, Ordering::Release)
+    }
+
+    /// Mark the process as dumping core.
+    ///
+    /// Returns `false` if another thread is already dumping it.
+    pub fn start_core_dump(&self) -> bool {
+        !self.core_dumping.swap(true, Ordering::AcqRel)
+    }
+
+    /// Whether a thread is dumping the core of the process, and the other
+    /// threads are to exit quietly.
+    pub fn is_dumping_core(&self) -> bool {
+        self.core_dumping.load(Ordering::Acquire)
+    }
+
+    /// Mark the process as executing a program in one of its threads, which
+    /// waits for the other threads to exit.
+    ///
+    /// Returns `false` if another thread is already doing so, or is dumping
+    /// the core.
+    pub fn start_group_exec(&self) -> bool {
+        !self.is_dumping_core() && !self.group_exec.swap(true, Ordering::AcqRel)
+    }
+
+    /// Unmark the process as executing a program, once the other threads
+    /// have exited or the execution failed.
+    pub fn end_group_exec(&self) {
+        self.group_exec.store(false, Ordering::Release);
+    }
+
+    /// Whether a thread is executing a program, and the other threads are to
+    /// exit quietly.
+    pub fn is_group_exec(&self) -> bool {
+        self.group_exec.load(Ordering::Acquire)
+    }
+
     /// Linux manual: A "clone" child is one which delivers no signal, or a
     /// signal other than SIGCHLD to its parent upon termination.
     pub fn is_clone_child(&self) -> bool {
         self.exit_signal != Some(Signo::SIGCHLD)
     }
+
+    /// Whether the process is stopped by a job-control signal.
+    pub fn is_stopped(&self) -> bool {
+        self.job.is_stopped()
+    }
+
+    /// The last job-control state change of the process not yet reported to
+    /// its parent, if any.
+    pub fn job_event(&self) -> Option<JobEvent> {
+        self.job.event()
+    }
+
+    /// Marks a job-control state change as reported to the parent.
+    ///
+    /// Returns `false` if it has already been reported or superseded.
+    pub fn clear_job_event(&self, event: JobEvent) -> bool {
+        self.job.clear_event(event)
+    }
+}
+
+/// Drops a reference to a user address space. The last one removes the
+/// kernel mappings copied into it first, which it does not own.
+fn release_aspace(aspace: &mut Arc<Mutex<AddrSpace>>) {
+    let Some(aspace) = Arc::get_mut(aspace) else {
+        return;
+    };
+    if !cfg!(target_arch = "aarch64") && !cfg!(target_arch = "loongarch64") {
+        // See [`crate::mm::copy_from_kernel`]
+        let kernel = kernel_aspace().lock();
+        aspace
+            .get_mut()
+            .clear_mappings(VirtAddrRange::from_start_size(kernel.base(), kernel.size()));
+    }
 }
 
 impl Drop for ProcessData {
     fn drop(&mut self) {
-        if !cfg!(target_arch = "aarch64") && !cfg!(target_arch = "loongarch64") {
-            // See [`crate::new_user_aspace`]
-            let kernel = kernel_aspace().lock();
-            self.aspace
-                .lock()
-                .clear_mappings(VirtAddrRange::from_start_size(kernel.base(), kernel.size()));
-        }
+        release_aspace(self.aspace.get_mut());
     }
 }
 
@@ -319,12 +607,18 @@ pub fn add_thread_to_table(thread: &Arc<Thread>) {
     }
     process_table.insert(process.pid(), process);
 
+    add_process_group_to_table(&process.group());
+}
+
+/// Add the process group and possibly its session to the corresponding tables.
+///
+/// Called when a process creates a new process group or session.
+pub fn add_process_group_to_table(process_group: &Arc<ProcessGroup>) {
     let mut process_group_table = PROCESS_GROUP_TABLE.write();
-    let process_group = process.group();
     if process_group_table.contains_key(&process_group.pgid()) {
         return;
     }
-    process_group_table.insert(process_group.pgid(), &process_group);
+    process_group_table.insert(process_group.pgid(), process_group);
 
     let mut session_table = SESSION_TABLE.write();
     let session = process_group.session();
@@ -334,6 +628,56 @@ pub fn add_thread_to_table(thread: &Arc<Thread>) {
     session_table.insert(session.sid(), &session);
 }
 
+/// Makes `thread` the leader of its thread group, taking the process ID as
+/// its thread ID, see [`Thread::become_leader`].
+///
+/// Returns the former thread ID.
+pub fn make_thread_leader(thread: &Arc<Thread>) -> Pid {
+    let mut thread_table = THREAD_TABLE.write();
+    let old_tid = thread.become_leader();
+    thread_table.remove(&old_tid);
+    thread_table.insert(thread.tid(), thread);
+    old_tid
+}
+
+/// Whether `id` is the ID of a thread, process, process group or session.
+pub fn is_id_in_use(id: Pid) -> bool {
+    THREAD_TABLE.read().get(&id).is_some()
+        || PROCESS_TABLE.read().get(&id).is_some()
+        || PROCESS_GROUP_TABLE.read().get(&id).is_some()
+        || SESSION_TABLE.read().get(&id).is_some()
+}
+
+/// The limit of thread IDs chosen with `clone3`, like `pid_max` on Linux.
+pub const PID_MAX: Pid = 4 * 1024 * 1024;
+
+/// The thread IDs reserved by [`reserve_id`].
+static RESERVED_IDS: SpinMutex<BTreeSet<Pid>> = SpinMutex::new(BTreeSet::new());
+
+/// A thread ID chosen for a new thread, reserved until it is dropped, after
+/// the thread is added to the tables.
+pub struct ReservedId(Pid);
+
+impl Drop for ReservedId {
+    fn drop(&mut self) {
+        RESERVED_IDS.lock().remove(&self.0);
+    }
+}
+
+/// Reserves `id`, chosen for a new thread, so that neither a new task nor
+/// another thread choosing it gets it.
+///
+/// Fails with `EEXIST` if `id` is in use or reserved already.
+pub fn reserve_id(id: Pid) -> LinuxResult<ReservedId> {
+    let mut reserved = RESERVED_IDS.lock();
+    // New tasks will not get the ID, but an existing one may use it.
+    TaskId::reserve(id as u64);
+    if is_id_in_use(id) || !reserved.insert(id) {
+        return Err(LinuxError::EEXIST);
+    }
+    Ok(ReservedId(id))
+}
+
 /// Lists all processes.
 pub fn processes() -> Vec<Arc<Process>> {
     PROCESS_TABLE.read().values().collect()
@@ -1,7 +1,62 @@
+//! Time accounting and interval timers.
+
+use core::{
+    mem,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{TimeValue, monotonic_time, monotonic_time_nanos};
+use axprocess::{Pid, Process};
+use axsignal::{SignalInfo, Signo};
+use linux_raw_sys::general::SI_KERNEL;
+
+use crate::{
+    signal::send_signal_process,
+    task::ProcessData,
+    timer::{TimerId, add_timer, cancel_timer},
+};
+
+/// The offset applied to the hardware wall clock by `clock_settime(2)`, in
+/// nanoseconds.
+static WALL_TIME_OFFSET: AtomicI64 = AtomicI64::new(0);
+
+/// Returns the current wall-clock time, as set by `clock_settime(2)`.
+pub fn wall_time() -> TimeValue {
+    let nanos = axhal::time::wall_time_nanos() as i64 + WALL_TIME_OFFSET.load(Ordering::Relaxed);
+    TimeValue::from_nanos(nanos.max(0) as _)
+}
+
+/// Returns the offset from the monotonic clock to the wall clock, in
+/// nanoseconds.
+pub(crate) fn wall_time_offset_nanos() -> i64 {
+    axhal::time::epochoffset_nanos() as i64 + WALL_TIME_OFFSET.load(Ordering::Relaxed)
+}
+
+/// Sets the current wall-clock time.
+pub fn set_wall_time(time: TimeValue) {
+    let offset = time.as_nanos() as i64 - axhal::time::wall_time_nanos() as i64;
+    WALL_TIME_OFFSET.store(offset, Ordering::Relaxed);
+    crate::vdso::update_wall_offset(wall_time_offset_nanos());
+    rearm_wall_timers();
+}
+
+/// Converts a wall-clock time into the monotonic time at which the wall clock
+/// reaches it, as long as it is not set in the meantime.
+fn wall_to_monotonic(time: TimeValue) -> TimeValue {
+    TimeValue::from_nanos((time.as_nanos() as i64 - wall_time_offset_nanos()).max(0) as _)
+}
+
 numeric_enum_macro::numeric_enum! {
     #[repr(i32)]
     #[allow(non_camel_case_types)]
     #[derive(Eq, PartialEq, Debug, Clone, Copy)]
+    /// The type of an interval timer. See `setitimer(2)`.
     pub enum TimerType {
     /// 表示目前没有任何计时器(不在linux规范中，是os自己规定的)
     NONE = -1,
@@ -23,14 +78,24 @@ impl From<usize> for TimerType {
     }
 }
 
+impl TimerType {
+    /// The signal sent when the timer expires.
+    pub fn signo(&self) -> Option<Signo> {
+        match self {
+            TimerType::NONE => None,
+            TimerType::REAL => Some(Signo::SIGALRM),
+            TimerType::VIRTUAL => Some(Signo::SIGVTALRM),
+            TimerType::PROF => Some(Signo::SIGPROF),
+        }
+    }
+}
+
+/// The CPU time accounting of a thread.
 pub struct TimeStat {
     utime_ns: usize,
     stime_ns: usize,
     user_timestamp: usize,
     kernel_timestamp: usize,
-    timer_type: TimerType,
-    timer_interval_ns: usize,
-    timer_remained_ns: usize,
 }
 
 impl Default for TimeStat {
@@ -40,86 +105,423 @@ impl Default for TimeStat {
 }
 
 impl TimeStat {
+    /// Creates a new [`TimeStat`] starting from the current time.
     pub fn new() -> Self {
+        let now = monotonic_time_nanos() as usize;
         Self {
             utime_ns: 0,
             stime_ns: 0,
-            user_timestamp: 0,
-            kernel_timestamp: 0,
-            timer_type: TimerType::NONE,
-            timer_interval_ns: 0,
-            timer_remained_ns: 0,
+            user_timestamp: now,
+            kernel_timestamp: now,
         }
     }
 
+    /// Returns the user and system time in nanoseconds.
     pub fn output(&self) -> (usize, usize) {
         (self.utime_ns, self.stime_ns)
     }
 
-    pub fn reset(&mut self, current_timestamp: usize) {
-        self.utime_ns = 0;
-        self.stime_ns = 0;
-        self.user_timestamp = 0;
+    /// Accounts a switch from user mode into kernel mode.
+    ///
+    /// Returns the time spent in user mode since the last switch.
+    pub fn switch_into_kernel_mode(&mut self, current_timestamp: usize) -> usize {
+        let delta = current_timestamp.saturating_sub(self.user_timestamp);
+        self.utime_ns += delta;
         self.kernel_timestamp = current_timestamp;
+        delta
     }
 
-    pub fn switch_into_kernel_mode(&mut self, current_timestamp: usize) {
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
-        self.utime_ns += delta;
-        self.kernel_timestamp = now_time_ns;
-        if self.timer_type != TimerType::NONE {
-            self.update_timer(delta);
+    /// Accounts a switch from kernel mode into user mode.
+    ///
+    /// Returns the time spent in kernel mode since the last switch.
+    pub fn switch_into_user_mode(&mut self, current_timestamp: usize) -> usize {
+        let delta = current_timestamp.saturating_sub(self.kernel_timestamp);
+        self.stime_ns += delta;
+        self.user_timestamp = current_timestamp;
+        delta
+    }
+}
+
+/// A one-shot or periodic timer measured against either the monotonic clock
+/// or the CPU time of a process or thread.
+#[derive(Default)]
+struct IntervalTimer {
+    /// The reload value in nanoseconds, zero for a one-shot timer.
+    interval_ns: usize,
+    /// The remaining CPU time in nanoseconds, zero if the timer is disarmed.
+    ///
+    /// Only used by CPU-time timers. Wall-clock timers use `timer` instead.
+    remained_ns: usize,
+    /// The kernel timer backing a wall-clock timer.
+    timer: Option<TimerId>,
+}
+
+impl IntervalTimer {
+    /// Gets the interval and the remaining time.
+    fn get(&self) -> (TimeValue, TimeValue) {
+        let remained = match self.timer {
+            Some(id) => id.deadline().saturating_sub(monotonic_time()),
+            None => TimeValue::from_nanos(self.remained_ns as _),
         };
+        (TimeValue::from_nanos(self.interval_ns as _), remained)
     }
 
-    pub fn switch_into_user_mode(&mut self, current_timestamp: usize) {
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
-        self.stime_ns += delta;
-        self.user_timestamp = now_time_ns;
-        if self.timer_type == TimerType::REAL || self.timer_type == TimerType::PROF {
-            self.update_timer(delta);
+    fn disarm(&mut self) {
+        if let Some(id) = self.timer.take() {
+            cancel_timer(id);
         }
+        self.remained_ns = 0;
     }
 
-    pub fn switch_from_old_task(&mut self, current_timestamp: usize) {
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
-        self.stime_ns += delta;
-        self.kernel_timestamp = now_time_ns;
-        if self.timer_type == TimerType::REAL || self.timer_type == TimerType::PROF {
-            self.update_timer(delta);
+    /// Charges CPU time to a CPU-time timer.
+    ///
+    /// Returns the number of expirations.
+    fn charge(&mut self, delta_ns: usize) -> usize {
+        if self.remained_ns == 0 || delta_ns == 0 {
+            return 0;
+        }
+        if self.remained_ns > delta_ns {
+            self.remained_ns -= delta_ns;
+            return 0;
+        }
+        let over = delta_ns - self.remained_ns;
+        if self.interval_ns == 0 {
+            self.remained_ns = 0;
+            return 1;
         }
+        self.remained_ns = self.interval_ns - over % self.interval_ns;
+        1 + over / self.interval_ns
     }
 
-    pub fn switch_to_new_task(&mut self, current_timestamp: usize) {
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
-        self.kernel_timestamp = now_time_ns;
-        if self.timer_type == TimerType::REAL {
-            self.update_timer(delta);
+    /// Handles the expiry of the kernel timer with the given deadline.
+    ///
+    /// Returns the number of expirations and the next deadline, or `None` if
+    /// the timer has been re-armed or disarmed in the meantime.
+    fn expire(&mut self, deadline: TimeValue) -> Option<(usize, Option<TimeValue>)> {
+        if self.timer.is_none_or(|id| id.deadline() != deadline) {
+            return None;
         }
+        self.timer = None;
+        if self.interval_ns == 0 {
+            return Some((1, None));
+        }
+        let interval = self.interval_ns as u128;
+        let count = monotonic_time().saturating_sub(deadline).as_nanos() / interval + 1;
+        let next = deadline + TimeValue::from_nanos((count * interval) as _);
+        Some((count as usize, Some(next)))
     }
+}
+
+/// The interval timers of a process. See `setitimer(2)`.
+#[derive(Default)]
+pub struct ITimers([IntervalTimer; 3]);
 
-    pub fn set_timer(
-        &mut self,
-        timer_interval_ns: usize,
-        timer_remained_ns: usize,
-        timer_type: usize,
-    ) -> bool {
-        self.timer_type = timer_type.into();
-        self.timer_interval_ns = timer_interval_ns;
-        self.timer_remained_ns = timer_remained_ns;
-        self.timer_type != TimerType::NONE
+fn arm_itimer(proc: Weak<Process>, deadline: TimeValue) -> TimerId {
+    add_timer(deadline, move || {
+        let Some(proc) = proc.upgrade() else {
+            return;
+        };
+        if proc.is_zombie() {
+            return;
+        }
+        let Some(data) = proc.data::<ProcessData>() else {
+            return;
+        };
+
+        let mut itimers = data.itimers.lock();
+        let timer = &mut itimers.0[TimerType::REAL as usize];
+        let Some((_, next)) = timer.expire(deadline) else {
+            return;
+        };
+        timer.timer = next.map(|next| arm_itimer(Arc::downgrade(&proc), next));
+        drop(itimers);
+
+        send_signal_process(&proc, SignalInfo::new(Signo::SIGALRM, SI_KERNEL as _));
+    })
+}
+
+/// Gets the interval and the remaining time of an interval timer of a
+/// process.
+pub fn get_itimer(proc: &Process, ty: TimerType) -> (TimeValue, TimeValue) {
+    let data = proc.data::<ProcessData>().unwrap();
+    data.itimers.lock().0[ty as usize].get()
+}
+
+/// Arms an interval timer of a process, or disarms it if `value` is zero.
+///
+/// Returns the previous interval and remaining time of the timer.
+pub fn set_itimer(
+    proc: &Arc<Process>,
+    ty: TimerType,
+    interval: TimeValue,
+    value: TimeValue,
+) -> (TimeValue, TimeValue) {
+    let data = proc.data::<ProcessData>().unwrap();
+    let mut itimers = data.itimers.lock();
+    let timer = &mut itimers.0[ty as usize];
+    let old = timer.get();
+
+    timer.disarm();
+    timer.interval_ns = interval.as_nanos() as _;
+    if !value.is_zero() {
+        if ty == TimerType::REAL {
+            timer.timer = Some(arm_itimer(Arc::downgrade(proc), monotonic_time() + value));
+        } else {
+            timer.remained_ns = value.as_nanos() as _;
+        }
     }
+    old
+}
 
-    pub fn update_timer(&mut self, delta: usize) {
-        if self.timer_remained_ns == 0 {
+/// The clock a POSIX timer is measured against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PosixTimerClock {
+    /// The wall clock.
+    Realtime,
+    /// The monotonic clock.
+    Monotonic,
+    /// The CPU time consumed by all threads of the process.
+    ProcessCpuTime,
+    /// The CPU time consumed by a thread.
+    ThreadCpuTime(Pid),
+}
+
+/// The notification of a POSIX timer expiry, called with the timer ID and the
+/// overrun count.
+pub type PosixTimerNotify = Arc<dyn Fn(i32, usize) + Send + Sync>;
+
+struct PosixTimer {
+    clock: PosixTimerClock,
+    timer: IntervalTimer,
+    /// The overrun count of the last expiry.
+    overrun: usize,
+    /// How to notify the expiry, or `None` for `SIGEV_NONE`.
+    notify: Option<PosixTimerNotify>,
+    /// The next deadline of a timer armed with an absolute time on the wall
+    /// clock, which moves the timer along when the wall clock is set.
+    wall_deadline: Option<TimeValue>,
+}
+
+impl PosixTimer {
+    /// Records `count` expirations and returns the notification to deliver.
+    fn fire(&mut self, count: usize) -> Option<(PosixTimerNotify, usize)> {
+        self.overrun = count.saturating_sub(1);
+        self.notify.clone().map(|notify| (notify, self.overrun))
+    }
+}
+
+/// The POSIX timers of a process. See `timer_create(2)`.
+#[derive(Default)]
+pub struct PosixTimers(BTreeMap<i32, PosixTimer>);
+
+fn arm_posix_timer(proc: Weak<Process>, id: i32, deadline: TimeValue) -> TimerId {
+    add_timer(deadline, move || {
+        let Some(proc) = proc.upgrade() else {
+            return;
+        };
+        if proc.is_zombie() {
             return;
         }
-        if self.timer_remained_ns > delta {
-            self.timer_remained_ns -= delta;
+        let Some(data) = proc.data::<ProcessData>() else {
+            return;
+        };
+
+        let mut timers = data.posix_timers.lock();
+        let Some(timer) = timers.0.get_mut(&id) else {
+            return;
+        };
+        let Some((count, next)) = timer.timer.expire(deadline) else {
+            return;
+        };
+        timer.timer.timer = next.map(|next| arm_posix_timer(Arc::downgrade(&proc), id, next));
+        if let Some(wall) = &mut timer.wall_deadline {
+            *wall += TimeValue::from_nanos((count * timer.timer.interval_ns) as _);
+        }
+        let notify = timer.fire(count);
+        drop(timers);
+
+        if let Some((notify, overrun)) = notify {
+            notify(id, overrun);
         }
+    })
+}
+
+/// Creates a disarmed POSIX timer in a process.
+///
+/// Returns the ID of the new timer.
+pub fn create_posix_timer(
+    proc: &Process,
+    clock: PosixTimerClock,
+    notify: Option<PosixTimerNotify>,
+) -> i32 {
+    let data = proc.data::<ProcessData>().unwrap();
+    let mut timers = data.posix_timers.lock();
+    let id = (0..).find(|id| !timers.0.contains_key(id)).unwrap();
+    timers.0.insert(
+        id,
+        PosixTimer {
+            clock,
+            timer: IntervalTimer::default(),
+            overrun: 0,
+            notify,
+            wall_deadline: None,
+        },
+    );
+    id
+}
+
+/// Gets the clock of a POSIX timer.
+pub fn posix_timer_clock(proc: &Process, id: i32) -> LinuxResult<PosixTimerClock> {
+    let data = proc.data::<ProcessData>().unwrap();
+    let timers = data.posix_timers.lock();
+    Ok(timers.0.get(&id).ok_or(LinuxError::EINVAL)?.clock)
+}
+
+/// Gets the interval and the remaining time of a POSIX timer.
+pub fn get_posix_timer(proc: &Process, id: i32) -> LinuxResult<(TimeValue, TimeValue)> {
+    let data = proc.data::<ProcessData>().unwrap();
+    let timers = data.posix_timers.lock();
+    Ok(timers.0.get(&id).ok_or(LinuxError::EINVAL)?.timer.get())
+}
+
+/// Arms a POSIX timer to expire after `value`, or disarms it if `value` is
+/// zero.
+///
+/// If `absolute`, `value` is instead the time on the wall or monotonic clock
+/// of the timer when it expires. A timer on the wall clock then expires when
+/// the wall clock reaches that time, even if it is set in the meantime.
+/// CPU-time timers only take relative values.
+///
+/// Returns the previous interval and remaining time of the timer.
+pub fn set_posix_timer(
+    proc: &Arc<Process>,
+    id: i32,
+    interval: TimeValue,
+    value: TimeValue,
+    absolute: bool,
+) -> LinuxResult<(TimeValue, TimeValue)> {
+    let data = proc.data::<ProcessData>().unwrap();
+    let mut timers = data.posix_timers.lock();
+    let timer = timers.0.get_mut(&id).ok_or(LinuxError::EINVAL)?;
+    let old = timer.timer.get();
+
+    timer.timer.disarm();
+    timer.timer.interval_ns = interval.as_nanos() as _;
+    timer.overrun = 0;
+    timer.wall_deadline = None;
+    if !value.is_zero() {
+        match timer.clock {
+            PosixTimerClock::Realtime | PosixTimerClock::Monotonic => {
+                let deadline = if !absolute {
+                    monotonic_time() + value
+                } else if timer.clock == PosixTimerClock::Realtime {
+                    timer.wall_deadline = Some(value);
+                    wall_to_monotonic(value)
+                } else {
+                    value
+                };
+                timer.timer.timer = Some(arm_posix_timer(Arc::downgrade(proc), id, deadline));
+            }
+            PosixTimerClock::ProcessCpuTime | PosixTimerClock::ThreadCpuTime(_) => {
+                timer.timer.remained_ns = value.as_nanos() as _;
+            }
+        }
+    }
+    Ok(old)
+}
+
+/// Re-arms the POSIX timers armed with an absolute time on the wall clock
+/// after it is set, so that they expire when it reaches that time.
+fn rearm_wall_timers() {
+    for proc in crate::task::processes() {
+        let Some(data) = proc.data::<ProcessData>() else {
+            continue;
+        };
+        let mut timers = data.posix_timers.lock();
+        for (&id, timer) in timers.0.iter_mut() {
+            let (Some(wall), Some(old)) = (timer.wall_deadline, timer.timer.timer) else {
+                continue;
+            };
+            // Otherwise it is expiring right now, and re-armed by itself.
+            if cancel_timer(old) {
+                let deadline = wall_to_monotonic(wall);
+                timer.timer.timer = Some(arm_posix_timer(Arc::downgrade(&proc), id, deadline));
+            }
+        }
+    }
+}
+
+/// Gets the overrun count of the last expiry of a POSIX timer.
+pub fn posix_timer_overrun(proc: &Process, id: i32) -> LinuxResult<usize> {
+    let data = proc.data::<ProcessData>().unwrap();
+    let timers = data.posix_timers.lock();
+    Ok(timers.0.get(&id).ok_or(LinuxError::EINVAL)?.overrun)
+}
+
+/// Deletes a POSIX timer.
+pub fn delete_posix_timer(proc: &Process, id: i32) -> LinuxResult<()> {
+    let data = proc.data::<ProcessData>().unwrap();
+    let mut timers = data.posix_timers.lock();
+    timers
+        .0
+        .remove(&id)
+        .ok_or(LinuxError::EINVAL)?
+        .timer
+        .disarm();
+    Ok(())
+}
+
+/// Deletes all POSIX timers of a process, as done by `execve`.
+pub fn clear_posix_timers(proc: &Process) {
+    let data = proc.data::<ProcessData>().unwrap();
+    let mut timers = data.posix_timers.lock();
+    for (_, mut timer) in mem::take(&mut timers.0) {
+        timer.timer.disarm();
+    }
+}
+
+/// Charges CPU time spent by a thread to the CPU-time timers of its process,
+/// notifying the ones that expired.
+pub(crate) fn charge_cpu_time(proc: &Process, tid: Pid, utime_ns: usize, stime_ns: usize) {
+    if utime_ns == 0 && stime_ns == 0 {
+        return;
+    }
+    let data = proc.data::<ProcessData>().unwrap();
+    data.utime_ns.fetch_add(utime_ns, Ordering::Relaxed);
+    data.stime_ns.fetch_add(stime_ns, Ordering::Relaxed);
+
+    let mut itimers = data.itimers.lock();
+    let virt = itimers.0[TimerType::VIRTUAL as usize].charge(utime_ns) > 0;
+    let prof = itimers.0[TimerType::PROF as usize].charge(utime_ns + stime_ns) > 0;
+    drop(itimers);
+    if virt {
+        send_signal_process(proc, SignalInfo::new(Signo::SIGVTALRM, SI_KERNEL as _));
+    }
+    if prof {
+        send_signal_process(proc, SignalInfo::new(Signo::SIGPROF, SI_KERNEL as _));
+    }
+
+    let mut timers = data.posix_timers.lock();
+    let notifies: Vec<_> = timers
+        .0
+        .iter_mut()
+        .filter_map(|(&id, timer)| {
+            let count = match timer.clock {
+                PosixTimerClock::ProcessCpuTime => timer.timer.charge(utime_ns + stime_ns),
+                PosixTimerClock::ThreadCpuTime(t) if t == tid => {
+                    timer.timer.charge(utime_ns + stime_ns)
+                }
+                _ => 0,
+            };
+            if count == 0 {
+                return None;
+            }
+            let (notify, overrun) = timer.fire(count)?;
+            Some((notify, id, overrun))
+        })
+        .collect();
+    drop(timers);
+    for (notify, id, overrun) in notifies {
+        notify(id, overrun);
     }
 }
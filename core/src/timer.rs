@@ -0,0 +1,99 @@
+//! Kernel timers that run their callbacks in task context.
+//!
+//! Timers are kept in a deadline-ordered list served by a dedicated kernel
+//! task, which sleeps on a [`WaitQueue`] (and thus on axtask's timer list)
+//! until the earliest deadline. Running callbacks in a task rather than in the
+//! timer interrupt allows them to take sleeping locks, e.g. to send signals.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use alloc::{boxed::Box, collections::btree_map::BTreeMap};
+use axhal::time::{TimeValue, monotonic_time};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use spin::Once;
+
+type TimerCallback = Box<dyn FnOnce() + Send>;
+
+/// A handle to a timer added by [`add_timer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId {
+    deadline: TimeValue,
+    id: u64,
+}
+
+impl TimerId {
+    /// The monotonic deadline of the timer.
+    pub fn deadline(&self) -> TimeValue {
+        self.deadline
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static TIMERS: Mutex<BTreeMap<(TimeValue, u64), TimerCallback>> = Mutex::new(BTreeMap::new());
+static TIMERS_CHANGED: AtomicBool = AtomicBool::new(false);
+static TIMER_WQ: WaitQueue = WaitQueue::new();
+static TIMER_WORKER: Once = Once::new();
+
+fn timer_worker() {
+    loop {
+        let now = monotonic_time();
+        loop {
+            let callback = {
+                let mut timers = TIMERS.lock();
+                match timers.first_key_value() {
+                    Some((&(deadline, _), _)) if deadline <= now => {
+                        timers.pop_first().map(|(_, callback)| callback)
+                    }
+                    _ => None,
+                }
+            };
+            let Some(callback) = callback else {
+                break;
+            };
+            callback();
+        }
+
+        let next = TIMERS
+            .lock()
+            .first_key_value()
+            .map(|(&(deadline, _), _)| deadline);
+        let changed = || TIMERS_CHANGED.swap(false, Ordering::AcqRel);
+        match next {
+            Some(deadline) => {
+                TIMER_WQ.wait_timeout_until(deadline.saturating_sub(monotonic_time()), changed);
+            }
+            None => TIMER_WQ.wait_until(changed),
+        }
+    }
+}
+
+/// Adds a timer that calls `callback` once the monotonic clock reaches
+/// `deadline`.
+pub fn add_timer(deadline: TimeValue, callback: impl FnOnce() + Send + 'static) -> TimerId {
+    TIMER_WORKER.call_once(|| {
+        axtask::spawn_raw(
+            timer_worker,
+            "timer".into(),
+            axconfig::plat::KERNEL_STACK_SIZE,
+        );
+    });
+
+    let id = TimerId {
+        deadline,
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+    };
+    TIMERS
+        .lock()
+        .insert((id.deadline, id.id), Box::new(callback));
+    TIMERS_CHANGED.store(true, Ordering::Release);
+    TIMER_WQ.notify_one(false);
+    id
+}
+
+/// Cancels a timer.
+///
+/// Returns `false` if the timer has already fired or been cancelled.
+pub fn cancel_timer(id: TimerId) -> bool {
+    TIMERS.lock().remove(&(id.deadline, id.id)).is_some()
+}
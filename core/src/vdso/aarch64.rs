@@ -0,0 +1,98 @@
+vdso_image! {
+    machine: "183",
+    flags: "0",
+    prefix: "__kernel_",
+    code: "
+.macro vdso_nanos
+    adr x9, .Lvdso_head
+    sub x9, x9, #4096
+    isb
+    mrs x10, cntpct_el0
+    ldr x11, [x9]
+    subs x10, x10, x11
+    csel x10, xzr, x10, lt
+    ldr x11, [x9, #16]
+    mul x12, x10, x11
+    umulh x13, x10, x11
+    extr x10, x13, x12, #48
+    ldr x11, [x9, #8]
+    add x10, x10, x11
+.endm
+
+.Lvdso_clock_gettime:
+    cmp w0, #7
+    b.hi .Lvdso_clock_gettime_syscall
+    cmp w0, #2
+    b.eq .Lvdso_clock_gettime_syscall
+    cmp w0, #3
+    b.eq .Lvdso_clock_gettime_syscall
+    vdso_nanos
+    cbz w0, .Lvdso_clock_gettime_wall
+    cmp w0, #5
+    b.ne .Lvdso_clock_gettime_split
+.Lvdso_clock_gettime_wall:
+    ldr x11, [x9, #24]
+    add x10, x10, x11
+.Lvdso_clock_gettime_split:
+    movz x11, #0xca00
+    movk x11, #0x3b9a, lsl #16
+    udiv x12, x10, x11
+    msub x13, x12, x11, x10
+    stp x12, x13, [x1]
+    mov x0, #0
+    ret
+.Lvdso_clock_gettime_syscall:
+    mov x8, #113
+    svc #0
+    ret
+
+.Lvdso_gettimeofday:
+    cbz x1, .Lvdso_gettimeofday_tv
+    str xzr, [x1]
+.Lvdso_gettimeofday_tv:
+    cbz x0, .Lvdso_gettimeofday_ret
+    vdso_nanos
+    ldr x11, [x9, #24]
+    add x10, x10, x11
+    movz x11, #0xca00
+    movk x11, #0x3b9a, lsl #16
+    udiv x12, x10, x11
+    msub x13, x12, x11, x10
+    mov x11, #1000
+    udiv x13, x13, x11
+    stp x12, x13, [x0]
+.Lvdso_gettimeofday_ret:
+    mov x0, #0
+    ret
+
+.Lvdso_getcpu:
+    adr x9, .Lvdso_head
+    sub x9, x9, #4096
+    ldr x10, [x9, #32]
+    cmp x10, #1
+    b.ne .Lvdso_getcpu_syscall
+    cbz x0, .Lvdso_getcpu_node
+    str wzr, [x0]
+.Lvdso_getcpu_node:
+    cbz x1, .Lvdso_getcpu_ret
+    str wzr, [x1]
+.Lvdso_getcpu_ret:
+    mov x0, #0
+    ret
+.Lvdso_getcpu_syscall:
+    mov x8, #168
+    svc #0
+    ret
+
+.Lvdso_rt_sigreturn:
+    mov x8, #139
+    svc #0
+"
+}
+
+/// Reads the counter used by the vDSO.
+pub fn read_counter() -> u64 {
+    let cnt: u64;
+    unsafe { core::arch::asm!("mrs {}, cntpct_el0", out(reg) cnt) };
+    cnt
+}
@@ -0,0 +1,103 @@
+vdso_image! {
+    machine: "258",
+    flags: "0x43",
+    prefix: "__vdso_",
+    code: "
+.macro vdso_nanos
+    la.pcrel $t1, .Lvdso_head
+    lu12i.w $t0, 1
+    sub.d $t1, $t1, $t0
+    rdtime.d $t2, $zero
+    ld.d $t3, $t1, 0
+    sub.d $t2, $t2, $t3
+    bge $t2, $zero, 1f
+    move $t2, $zero
+1:
+    ld.d $t3, $t1, 16
+    mul.d $t4, $t2, $t3
+    mulh.du $t5, $t2, $t3
+    srli.d $t4, $t4, 48
+    slli.d $t5, $t5, 16
+    or $t2, $t4, $t5
+    ld.d $t3, $t1, 8
+    add.d $t2, $t2, $t3
+.endm
+
+.Lvdso_clock_gettime:
+    ori $t0, $zero, 7
+    bltu $t0, $a0, .Lvdso_clock_gettime_syscall
+    ori $t0, $zero, 2
+    beq $a0, $t0, .Lvdso_clock_gettime_syscall
+    ori $t0, $zero, 3
+    beq $a0, $t0, .Lvdso_clock_gettime_syscall
+    vdso_nanos
+    beqz $a0, .Lvdso_clock_gettime_wall
+    ori $t0, $zero, 5
+    bne $a0, $t0, .Lvdso_clock_gettime_split
+.Lvdso_clock_gettime_wall:
+    ld.d $t3, $t1, 24
+    add.d $t2, $t2, $t3
+.Lvdso_clock_gettime_split:
+    li.d $t0, 1000000000
+    div.du $t3, $t2, $t0
+    mod.du $t4, $t2, $t0
+    st.d $t3, $a1, 0
+    st.d $t4, $a1, 8
+    move $a0, $zero
+    jr $ra
+.Lvdso_clock_gettime_syscall:
+    li.w $a7, 113
+    syscall 0
+    jr $ra
+
+.Lvdso_gettimeofday:
+    beqz $a1, .Lvdso_gettimeofday_tv
+    st.d $zero, $a1, 0
+.Lvdso_gettimeofday_tv:
+    beqz $a0, .Lvdso_gettimeofday_ret
+    vdso_nanos
+    ld.d $t3, $t1, 24
+    add.d $t2, $t2, $t3
+    li.d $t0, 1000000000
+    div.du $t3, $t2, $t0
+    mod.du $t4, $t2, $t0
+    ori $t0, $zero, 1000
+    div.du $t4, $t4, $t0
+    st.d $t3, $a0, 0
+    st.d $t4, $a0, 8
+.Lvdso_gettimeofday_ret:
+    move $a0, $zero
+    jr $ra
+
+.Lvdso_getcpu:
+    la.pcrel $t1, .Lvdso_head
+    lu12i.w $t0, 1
+    sub.d $t1, $t1, $t0
+    ld.d $t2, $t1, 32
+    ori $t0, $zero, 1
+    bne $t2, $t0, .Lvdso_getcpu_syscall
+    beqz $a0, .Lvdso_getcpu_node
+    st.w $zero, $a0, 0
+.Lvdso_getcpu_node:
+    beqz $a1, .Lvdso_getcpu_ret
+    st.w $zero, $a1, 0
+.Lvdso_getcpu_ret:
+    move $a0, $zero
+    jr $ra
+.Lvdso_getcpu_syscall:
+    li.w $a7, 168
+    syscall 0
+    jr $ra
+
+.Lvdso_rt_sigreturn:
+    li.w $a7, 139
+    syscall 0
+"
+}
+
+/// Reads the counter used by the vDSO.
+pub fn read_counter() -> u64 {
+    let time: u64;
+    unsafe { core::arch::asm!("rdtime.d {}, $zero", out(reg) time) };
+    time
+}
@@ -0,0 +1,246 @@
+//! The virtual dynamic shared object (vDSO).
+//!
+//! The vDSO is a tiny ELF shared object mapped into every user address space
+//! at [`axconfig::plat::VDSO`] + [`PAGE_SIZE_4K`], right after a read-only
+//! data page maintained by the kernel. It serves `clock_gettime`,
+//! `gettimeofday` and `getcpu` without entering the kernel, by reading the
+//! hardware counter and converting it with the parameters in the data page,
+//! and hosts the default signal-return trampoline.
+//!
+//! The image is hand-written in assembly for each architecture, so that its
+//! code only uses PC-relative addressing and can run at any address.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axerrno::AxResult;
+use axhal::{mem::virt_to_phys, paging::MappingFlags, time::monotonic_time_nanos};
+use axmm::AddrSpace;
+use memory_addr::{PAGE_SIZE_4K, VirtAddr};
+use spin::Once;
+
+/// Emits the vDSO image: an ELF header, a dynamic section exporting the entry
+/// points of `$code` under `$prefix`, and the code itself.
+///
+/// `$code` must define the labels `.Lvdso_clock_gettime`,
+/// `.Lvdso_gettimeofday`, `.Lvdso_getcpu` and `.Lvdso_rt_sigreturn`. It can
+/// find the data page at `.Lvdso_head - 4096`.
+macro_rules! vdso_image {
+    (machine: $machine:literal, flags: $flags:literal, prefix: $prefix:literal, code: $code:literal) => {
+        core::arch::global_asm!(concat!(
+            "
+.section .text
+.balign 4096
+.global vdso_start
+vdso_start:
+.Lvdso_head:
+    .byte 0x7f, 0x45, 0x4c, 0x46, 2, 1, 1, 0
+    .zero 8
+    .short 3
+    .short ",
+            $machine,
+            "
+    .long 1
+    .quad 0
+    .quad .Lvdso_phdr - .Lvdso_head
+    .quad 0
+    .long ",
+            $flags,
+            "
+    .short 64, 56, 2
+    .short 64, 0, 0
+
+.Lvdso_phdr:
+    .long 1, 5
+    .quad 0, 0, 0
+    .quad vdso_end - .Lvdso_head, vdso_end - .Lvdso_head
+    .quad 4096
+
+    .long 2, 4
+    .quad .Lvdso_dynamic - .Lvdso_head
+    .quad .Lvdso_dynamic - .Lvdso_head
+    .quad .Lvdso_dynamic - .Lvdso_head
+    .quad .Lvdso_dynamic_end - .Lvdso_dynamic
+    .quad .Lvdso_dynamic_end - .Lvdso_dynamic
+    .quad 8
+
+.balign 8
+.Lvdso_dynamic:
+    .quad 4, .Lvdso_hash - .Lvdso_head
+    .quad 5, .Lvdso_strtab - .Lvdso_head
+    .quad 6, .Lvdso_symtab - .Lvdso_head
+    .quad 10, .Lvdso_strtab_end - .Lvdso_strtab
+    .quad 11, 24
+    .quad 0, 0
+.Lvdso_dynamic_end:
+
+.Lvdso_hash:
+    .long 1, 5
+    .long 4
+    .long 0, 0, 1, 2, 3
+
+.balign 8
+.Lvdso_symtab:
+    .zero 24
+    .long .Lvdso_str_clock_gettime - .Lvdso_strtab
+    .byte 0x12, 0
+    .short 1
+    .quad .Lvdso_clock_gettime - .Lvdso_head, 0
+    .long .Lvdso_str_gettimeofday - .Lvdso_strtab
+    .byte 0x12, 0
+    .short 1
+    .quad .Lvdso_gettimeofday - .Lvdso_head, 0
+    .long .Lvdso_str_getcpu - .Lvdso_strtab
+    .byte 0x12, 0
+    .short 1
+    .quad .Lvdso_getcpu - .Lvdso_head, 0
+    .long .Lvdso_str_rt_sigreturn - .Lvdso_strtab
+    .byte 0x12, 0
+    .short 1
+    .quad .Lvdso_rt_sigreturn - .Lvdso_head, 0
+
+.Lvdso_strtab:
+    .byte 0
+.Lvdso_str_clock_gettime:
+    .asciz \"",
+            $prefix,
+            "clock_gettime\"
+.Lvdso_str_gettimeofday:
+    .asciz \"",
+            $prefix,
+            "gettimeofday\"
+.Lvdso_str_getcpu:
+    .asciz \"",
+            $prefix,
+            "getcpu\"
+.Lvdso_str_rt_sigreturn:
+    .asciz \"",
+            $prefix,
+            "rt_sigreturn\"
+.Lvdso_strtab_end:
+
+.balign 16
+",
+            $code,
+            "
+.global vdso_rt_sigreturn
+.set vdso_rt_sigreturn, .Lvdso_rt_sigreturn
+
+.balign 4096
+.global vdso_end
+vdso_end:
+"
+        ));
+    };
+}
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "loongarch64")]
+mod loongarch64;
+#[cfg(target_arch = "riscv64")]
+mod riscv64;
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+
+#[cfg(target_arch = "aarch64")]
+use self::aarch64::read_counter;
+#[cfg(target_arch = "loongarch64")]
+use self::loongarch64::read_counter;
+#[cfg(target_arch = "riscv64")]
+use self::riscv64::read_counter;
+#[cfg(target_arch = "x86_64")]
+use self::x86_64::read_counter;
+
+unsafe extern "C" {
+    fn vdso_start();
+    fn vdso_end();
+    fn vdso_rt_sigreturn();
+}
+
+/// The shift applied to `delta * mult` when converting counter ticks to
+/// nanoseconds.
+const VDSO_SHIFT: u32 = 48;
+
+/// The data page shared with the vDSO. The layout must match the offsets used
+/// by the assembly.
+#[repr(C, align(4096))]
+struct VdsoData {
+    /// The counter value at `base_nanos`.
+    cycle_last: AtomicU64,
+    /// The monotonic time in nanoseconds at `cycle_last`.
+    base_nanos: AtomicU64,
+    /// The tick to nanosecond multiplier, scaled by `1 << VDSO_SHIFT`.
+    mult: AtomicU64,
+    /// The offset from the monotonic clock to the wall clock, in nanoseconds.
+    wall_offset: AtomicU64,
+    /// The number of CPUs.
+    nr_cpus: AtomicU64,
+}
+
+static VDSO_DATA: VdsoData = VdsoData {
+    cycle_last: AtomicU64::new(0),
+    base_nanos: AtomicU64::new(0),
+    mult: AtomicU64::new(0),
+    wall_offset: AtomicU64::new(0),
+    nr_cpus: AtomicU64::new(0),
+};
+static VDSO_DATA_INIT: Once = Once::new();
+
+fn init_data() {
+    VDSO_DATA_INIT.call_once(|| {
+        let mult = axhal::time::ticks_to_nanos(1 << VDSO_SHIFT);
+        let base_nanos = monotonic_time_nanos();
+        let cycle_last = read_counter();
+
+        VDSO_DATA.cycle_last.store(cycle_last, Ordering::Relaxed);
+        VDSO_DATA.base_nanos.store(base_nanos, Ordering::Relaxed);
+        VDSO_DATA.mult.store(mult, Ordering::Relaxed);
+        VDSO_DATA
+            .nr_cpus
+            .store(axconfig::SMP as u64, Ordering::Relaxed);
+        update_wall_offset(crate::time::wall_time_offset_nanos());
+    });
+}
+
+/// Updates the wall clock offset seen by the vDSO.
+pub(crate) fn update_wall_offset(offset: i64) {
+    VDSO_DATA
+        .wall_offset
+        .store(offset as u64, Ordering::Release);
+}
+
+fn image_size() -> usize {
+    vdso_end as usize - vdso_start as usize
+}
+
+/// The user address of the vDSO ELF header, for `AT_SYSINFO_EHDR`.
+pub fn vdso_base() -> VirtAddr {
+    VirtAddr::from_usize(axconfig::plat::VDSO + PAGE_SIZE_4K)
+}
+
+/// The user address of the signal-return trampoline in the vDSO.
+pub fn sigreturn_address() -> usize {
+    vdso_base().as_usize() + (vdso_rt_sigreturn as usize - vdso_start as usize)
+}
+
+/// Map the vDSO and its data page to the user address space.
+pub fn map_vdso(aspace: &mut AddrSpace) -> AxResult {
+    init_data();
+
+    let data_paddr = virt_to_phys(VirtAddr::from_ptr_of(&VDSO_DATA));
+    aspace.map_linear(
+        axconfig::plat::VDSO.into(),
+        data_paddr,
+        PAGE_SIZE_4K,
+        MappingFlags::READ | MappingFlags::USER,
+    )?;
+
+    let image_paddr = virt_to_phys((vdso_start as usize).into());
+    aspace.map_linear(
+        vdso_base(),
+        image_paddr,
+        image_size(),
+        MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+vdso_image! {
+    machine: "243",
+    flags: "0x5",
+    prefix: "__vdso_",
+    code: "
+.macro vdso_nanos
+    lla t1, .Lvdso_head
+    li t0, 4096
+    sub t1, t1, t0
+    rdtime t2
+    ld t3, 0(t1)
+    sub t2, t2, t3
+    bgez t2, 1f
+    li t2, 0
+1:
+    ld t3, 16(t1)
+    mul t4, t2, t3
+    mulhu t5, t2, t3
+    srli t4, t4, 48
+    slli t5, t5, 16
+    or t2, t4, t5
+    ld t3, 8(t1)
+    add t2, t2, t3
+.endm
+
+.Lvdso_clock_gettime:
+    li t0, 7
+    bgtu a0, t0, .Lvdso_clock_gettime_syscall
+    li t0, 2
+    beq a0, t0, .Lvdso_clock_gettime_syscall
+    li t0, 3
+    beq a0, t0, .Lvdso_clock_gettime_syscall
+    vdso_nanos
+    beqz a0, .Lvdso_clock_gettime_wall
+    li t0, 5
+    bne a0, t0, .Lvdso_clock_gettime_split
+.Lvdso_clock_gettime_wall:
+    ld t3, 24(t1)
+    add t2, t2, t3
+.Lvdso_clock_gettime_split:
+    li t0, 1000000000
+    divu t3, t2, t0
+    remu t4, t2, t0
+    sd t3, 0(a1)
+    sd t4, 8(a1)
+    li a0, 0
+    ret
+.Lvdso_clock_gettime_syscall:
+    li a7, 113
+    ecall
+    ret
+
+.Lvdso_gettimeofday:
+    beqz a1, .Lvdso_gettimeofday_tv
+    sd zero, 0(a1)
+.Lvdso_gettimeofday_tv:
+    beqz a0, .Lvdso_gettimeofday_ret
+    vdso_nanos
+    ld t3, 24(t1)
+    add t2, t2, t3
+    li t0, 1000000000
+    divu t3, t2, t0
+    remu t4, t2, t0
+    li t0, 1000
+    divu t4, t4, t0
+    sd t3, 0(a0)
+    sd t4, 8(a0)
+.Lvdso_gettimeofday_ret:
+    li a0, 0
+    ret
+
+.Lvdso_getcpu:
+    lla t1, .Lvdso_head
+    li t0, 4096
+    sub t1, t1, t0
+    ld t2, 32(t1)
+    li t0, 1
+    bne t2, t0, .Lvdso_getcpu_syscall
+    beqz a0, .Lvdso_getcpu_node
+    sw zero, 0(a0)
+.Lvdso_getcpu_node:
+    beqz a1, .Lvdso_getcpu_ret
+    sw zero, 0(a1)
+.Lvdso_getcpu_ret:
+    li a0, 0
+    ret
+.Lvdso_getcpu_syscall:
+    li a7, 168
+    ecall
+    ret
+
+.Lvdso_rt_sigreturn:
+    li a7, 139
+    ecall
+"
+}
+
+/// Reads the counter used by the vDSO.
+pub fn read_counter() -> u64 {
+    let time: u64;
+    unsafe { core::arch::asm!("rdtime {}", out(reg) time) };
+    time
+}
@@ -0,0 +1,98 @@
+vdso_image! {
+    machine: "62",
+    flags: "0",
+    prefix: "__vdso_",
+    code: "
+.macro vdso_nanos
+    lea r8, [rip + .Lvdso_head - 4096]
+    lfence
+    rdtsc
+    shl rdx, 32
+    or rax, rdx
+    sub rax, [r8]
+    jns 2f
+    xor eax, eax
+2:
+    mul qword ptr [r8 + 16]
+    shrd rax, rdx, 48
+    add rax, [r8 + 8]
+.endm
+
+.Lvdso_clock_gettime:
+    cmp edi, 7
+    ja .Lvdso_clock_gettime_syscall
+    cmp edi, 2
+    je .Lvdso_clock_gettime_syscall
+    cmp edi, 3
+    je .Lvdso_clock_gettime_syscall
+    vdso_nanos
+    test edi, edi
+    je .Lvdso_clock_gettime_wall
+    cmp edi, 5
+    jne .Lvdso_clock_gettime_split
+.Lvdso_clock_gettime_wall:
+    add rax, [r8 + 24]
+.Lvdso_clock_gettime_split:
+    xor edx, edx
+    mov ecx, 1000000000
+    div rcx
+    mov [rsi], rax
+    mov [rsi + 8], rdx
+    xor eax, eax
+    ret
+.Lvdso_clock_gettime_syscall:
+    mov eax, 228
+    syscall
+    ret
+
+.Lvdso_gettimeofday:
+    test rsi, rsi
+    je .Lvdso_gettimeofday_tv
+    mov qword ptr [rsi], 0
+.Lvdso_gettimeofday_tv:
+    test rdi, rdi
+    je .Lvdso_gettimeofday_ret
+    vdso_nanos
+    add rax, [r8 + 24]
+    xor edx, edx
+    mov ecx, 1000000000
+    div rcx
+    mov [rdi], rax
+    mov rax, rdx
+    xor edx, edx
+    mov ecx, 1000
+    div rcx
+    mov [rdi + 8], rax
+.Lvdso_gettimeofday_ret:
+    xor eax, eax
+    ret
+
+.Lvdso_getcpu:
+    lea r8, [rip + .Lvdso_head - 4096]
+    cmp qword ptr [r8 + 32], 1
+    jne .Lvdso_getcpu_syscall
+    test rdi, rdi
+    je .Lvdso_getcpu_node
+    mov dword ptr [rdi], 0
+.Lvdso_getcpu_node:
+    test rsi, rsi
+    je .Lvdso_getcpu_ret
+    mov dword ptr [rsi], 0
+.Lvdso_getcpu_ret:
+    xor eax, eax
+    ret
+.Lvdso_getcpu_syscall:
+    mov eax, 309
+    syscall
+    ret
+
+.Lvdso_rt_sigreturn:
+    mov eax, 15
+    syscall
+"
+}
+
+/// Reads the counter used by the vDSO.
+pub fn read_counter() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
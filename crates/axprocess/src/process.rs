@@ -0,0 +1,375 @@
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    any::Any,
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use kspin::SpinNoIrq;
+use lazyinit::LazyInit;
+use weak_map::{StrongMap, WeakMap};
+
+use crate::{Pid, ProcessGroup, Session, Thread, ThreadBuilder};
+
+pub(crate) struct ThreadGroup {
+    pub(crate) threads: WeakMap<Pid, Weak<Thread>>,
+    pub(crate) exit_code: i32,
+    pub(crate) group_exited: bool,
+}
+
+impl Default for ThreadGroup {
+    fn default() -> Self {
+        Self {
+            threads: WeakMap::new(),
+            exit_code: 0,
+            group_exited: false,
+        }
+    }
+}
+
+/// A process.
+pub struct Process {
+    pid: Pid,
+    is_zombie: AtomicBool,
+    pub(crate) tg: SpinNoIrq<ThreadGroup>,
+
+    data: Box<dyn Any + Send + Sync>,
+
+    is_child_subreaper: AtomicBool,
+    children: SpinNoIrq<StrongMap<Pid, Arc<Process>>>,
+    parent: SpinNoIrq<Weak<Process>>,
+
+    group: SpinNoIrq<Arc<ProcessGroup>>,
+}
+
+impl Process {
+    /// The [`Process`] ID.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// The data associated with the [`Process`].
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data.downcast_ref::<T>()
+    }
+
+    /// Returns `true` if the [`Process`] is the init process.
+    ///
+    /// This is a convenience method for checking if the [`Process`]
+    /// [`Arc::ptr_eq`]s with the init process, which is cheaper than
+    /// calling [`init_proc`] or testing if [`Process::parent`] is `None`.
+    pub fn is_init(self: &Arc<Self>) -> bool {
+        Arc::ptr_eq(self, INIT_PROC.get().unwrap())
+    }
+}
+
+/// Parent & children
+impl Process {
+    /// The parent [`Process`].
+    pub fn parent(&self) -> Option<Arc<Process>> {
+        self.parent.lock().upgrade()
+    }
+
+    /// The child [`Process`]es.
+    pub fn children(&self) -> Vec<Arc<Process>> {
+        self.children.lock().values().cloned().collect()
+    }
+
+    /// Returns `true` if the [`Process`] is a child subreaper.
+    ///
+    /// Orphaned descendants of a child subreaper are inherited by it rather
+    /// than by the init process.
+    pub fn is_child_subreaper(&self) -> bool {
+        self.is_child_subreaper.load(Ordering::Acquire)
+    }
+
+    /// Marks or unmarks the [`Process`] as a child subreaper.
+    pub fn set_child_subreaper(&self, subreaper: bool) {
+        self.is_child_subreaper.store(subreaper, Ordering::Release);
+    }
+
+    /// Finds the [`Process`] to inherit the children of this one when it
+    /// exits: the nearest living ancestor which is a child subreaper, or the
+    /// init process.
+    fn find_reaper(&self) -> Arc<Process> {
+        let mut ancestor = self.parent();
+        while let Some(process) = ancestor {
+            if process.is_child_subreaper() && !process.is_zombie() {
+                return process;
+            }
+            ancestor = process.parent();
+        }
+        INIT_PROC.get().unwrap().clone()
+    }
+}
+
+/// [`ProcessGroup`] & [`Session`]
+impl Process {
+    /// The [`ProcessGroup`] that the [`Process`] belongs to.
+    pub fn group(&self) -> Arc<ProcessGroup> {
+        self.group.lock().clone()
+    }
+
+    fn set_group(self: &Arc<Self>, group: &Arc<ProcessGroup>) {
+        let mut self_group = self.group.lock();
+
+        self_group.processes.lock().remove(&self.pid);
+
+        group.processes.lock().insert(self.pid, self);
+
+        *self_group = group.clone();
+    }
+
+    /// Creates a new [`Session`] and new [`ProcessGroup`] and moves the
+    /// [`Process`] to it.
+    ///
+    /// If the [`Process`] is already a session leader, this method does
+    /// nothing and returns `None`.
+    ///
+    /// Otherwise, it returns the new [`Session`] and [`ProcessGroup`].
+    ///
+    /// The caller has to ensure that the new [`ProcessGroup`] does not conflict
+    /// with any existing [`ProcessGroup`]. Thus, the [`Process`] must not
+    /// be a [`ProcessGroup`] leader.
+    ///
+    /// Checking [`Session`] conflicts is unnecessary.
+    pub fn create_session(self: &Arc<Self>) -> Option<(Arc<Session>, Arc<ProcessGroup>)> {
+        if self.group.lock().session.sid() == self.pid {
+            return None;
+        }
+
+        let new_session = Session::new(self.pid);
+        let new_group = ProcessGroup::new(self.pid, &new_session);
+        self.set_group(&new_group);
+
+        Some((new_session, new_group))
+    }
+
+    /// Creates a new [`ProcessGroup`] and moves the [`Process`] to it.
+    ///
+    /// If the [`Process`] is already a group leader, this method does nothing
+    /// and returns `None`.
+    ///
+    /// Otherwise, it returns the new [`ProcessGroup`].
+    ///
+    /// The caller has to ensure that the new [`ProcessGroup`] does not conflict
+    /// with any existing [`ProcessGroup`].
+    pub fn create_group(self: &Arc<Self>) -> Option<Arc<ProcessGroup>> {
+        if self.group.lock().pgid() == self.pid {
+            return None;
+        }
+
+        let new_group = ProcessGroup::new(self.pid, &self.group.lock().session);
+        self.set_group(&new_group);
+
+        Some(new_group)
+    }
+
+    /// Moves the [`Process`] to a specified [`ProcessGroup`].
+    ///
+    /// Returns `true` if the move succeeded. The move failed if the
+    /// [`ProcessGroup`] is not in the same [`Session`] as the [`Process`].
+    ///
+    /// If the [`Process`] is already in the specified [`ProcessGroup`], this
+    /// method does nothing and returns `true`.
+    pub fn move_to_group(self: &Arc<Self>, group: &Arc<ProcessGroup>) -> bool {
+        if Arc::ptr_eq(&self.group.lock(), group) {
+            return true;
+        }
+
+        if !Arc::ptr_eq(&self.group.lock().session, &group.session) {
+            return false;
+        }
+
+        self.set_group(group);
+        true
+    }
+}
+
+/// Threads
+impl Process {
+    /// Creates a new [`Thread`] in this [`Process`].
+    pub fn new_thread(self: &Arc<Self>, tid: Pid) -> ThreadBuilder {
+        ThreadBuilder::new(tid, self.clone())
+    }
+
+    /// The [`Thread`]s in this [`Process`].
+    pub fn threads(&self) -> Vec<Arc<Thread>> {
+        self.tg.lock().threads.values().collect()
+    }
+
+    /// Returns `true` if the [`Process`] is group exited.
+    pub fn is_group_exited(&self) -> bool {
+        self.tg.lock().group_exited
+    }
+
+    /// Marks the [`Process`] as group exited.
+    pub fn group_exit(&self) {
+        self.tg.lock().group_exited = true;
+    }
+
+    /// The exit code of the [`Process`].
+    pub fn exit_code(&self) -> i32 {
+        self.tg.lock().exit_code
+    }
+}
+
+/// Status & exit
+impl Process {
+    /// Returns `true` if the [`Process`] is a zombie process.
+    pub fn is_zombie(&self) -> bool {
+        self.is_zombie.load(Ordering::Acquire)
+    }
+
+    /// Terminates the [`Process`], marking it as a zombie process.
+    ///
+    /// Child processes are inherited by the init process or by the nearest
+    /// subreaper process.
+    ///
+    /// This method panics if the [`Process`] is the init process.
+    pub fn exit(self: &Arc<Self>) {
+        if self.is_init() {
+            panic!("init process cannot exit");
+        }
+
+        let mut children = self.children.lock(); // Acquire the lock first
+        self.is_zombie.store(true, Ordering::Release);
+
+        loop {
+            let reaper = self.find_reaper();
+            let mut reaper_children = reaper.children.lock();
+            // The reaper marks itself a zombie with the lock held when it
+            // exits, after which it cannot take in children anymore.
+            if reaper.is_zombie() {
+                continue;
+            }
+
+            let reaper_weak = Arc::downgrade(&reaper);
+            for (pid, child) in core::mem::take(&mut *children) {
+                *child.parent.lock() = reaper_weak.clone();
+                reaper_children.insert(pid, child);
+            }
+            break;
+        }
+    }
+
+    /// Frees a zombie [`Process`]. Removes it from the parent.
+    ///
+    /// This method panics if the [`Process`] is not a zombie.
+    pub fn free(&self) {
+        assert!(self.is_zombie(), "only zombie process can be freed");
+
+        if let Some(parent) = self.parent() {
+            parent.children.lock().remove(&self.pid);
+        }
+    }
+}
+
+impl fmt::Debug for Process {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut builder = f.debug_struct("Process");
+        builder.field("pid", &self.pid);
+
+        let tg = self.tg.lock();
+        if tg.group_exited {
+            builder.field("group_exited", &tg.group_exited);
+        }
+        if self.is_zombie() {
+            builder.field("exit_code", &tg.exit_code);
+        }
+
+        if let Some(parent) = self.parent() {
+            builder.field("parent", &parent.pid());
+        }
+        builder.field("group", &self.group());
+        builder.finish()
+    }
+}
+
+/// Builder
+impl Process {
+    /// Creates a init [`Process`].
+    ///
+    /// This function can be called multiple times, but
+    /// [`ProcessBuilder::build`] on the the result must be called only once.
+    pub fn new_init(pid: Pid) -> ProcessBuilder {
+        ProcessBuilder {
+            pid,
+            parent: None,
+            data: Box::new(()),
+        }
+    }
+
+    /// Creates a child [`Process`].
+    pub fn fork(self: &Arc<Process>, pid: Pid) -> ProcessBuilder {
+        ProcessBuilder {
+            pid,
+            parent: Some(self.clone()),
+            data: Box::new(()),
+        }
+    }
+}
+
+/// A builder for creating a new [`Process`].
+pub struct ProcessBuilder {
+    pid: Pid,
+    parent: Option<Arc<Process>>,
+    data: Box<dyn Any + Send + Sync>,
+}
+
+impl ProcessBuilder {
+    /// Sets the data associated with the [`Process`].
+    pub fn data<T: Any + Send + Sync>(self, data: T) -> Self {
+        Self {
+            data: Box::new(data),
+            ..self
+        }
+    }
+
+    /// Finishes the builder and returns a new [`Process`].
+    pub fn build(self) -> Arc<Process> {
+        let Self { pid, parent, data } = self;
+
+        let group = parent.as_ref().map_or_else(
+            || {
+                let session = Session::new(pid);
+                ProcessGroup::new(pid, &session)
+            },
+            |p| p.group(),
+        );
+
+        let process = Arc::new(Process {
+            pid,
+            is_zombie: AtomicBool::new(false),
+            tg: SpinNoIrq::new(ThreadGroup::default()),
+            data,
+            is_child_subreaper: AtomicBool::new(false),
+            children: SpinNoIrq::new(StrongMap::new()),
+            parent: SpinNoIrq::new(parent.as_ref().map(Arc::downgrade).unwrap_or_default()),
+            group: SpinNoIrq::new(group.clone()),
+        });
+
+        group.processes.lock().insert(pid, &process);
+
+        if let Some(parent) = parent {
+            parent.children.lock().insert(pid, process.clone());
+        } else {
+            INIT_PROC.init_once(process.clone());
+        }
+
+        process
+    }
+}
+
+static INIT_PROC: LazyInit<Arc<Process>> = LazyInit::new();
+
+/// Gets the init process.
+///
+/// This function panics if the init process has not been initialized yet.
+pub fn init_proc() -> Arc<Process> {
+    INIT_PROC.get().unwrap().clone()
+}
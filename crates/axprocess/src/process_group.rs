@@ -0,0 +1,56 @@
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::fmt;
+
+use kspin::SpinNoIrq;
+use weak_map::WeakMap;
+
+use crate::{Pid, Process, Session};
+
+/// A [`ProcessGroup`] is a collection of [`Process`]es.
+pub struct ProcessGroup {
+    pgid: Pid,
+    pub(crate) session: Arc<Session>,
+    pub(crate) processes: SpinNoIrq<WeakMap<Pid, Weak<Process>>>,
+}
+
+impl ProcessGroup {
+    /// Create a new [`ProcessGroup`] within a [`Session`].
+    pub(crate) fn new(pgid: Pid, session: &Arc<Session>) -> Arc<Self> {
+        let group = Arc::new(Self {
+            pgid,
+            session: session.clone(),
+            processes: SpinNoIrq::new(WeakMap::new()),
+        });
+        session.process_groups.lock().insert(pgid, &group);
+        group
+    }
+}
+
+impl ProcessGroup {
+    /// The [`ProcessGroup`] ID.
+    pub fn pgid(&self) -> Pid {
+        self.pgid
+    }
+
+    /// The [`Session`] that the [`ProcessGroup`] belongs to.
+    pub fn session(&self) -> Arc<Session> {
+        self.session.clone()
+    }
+
+    /// The [`Process`]es that belong to this [`ProcessGroup`].
+    pub fn processes(&self) -> Vec<Arc<Process>> {
+        self.processes.lock().values().collect()
+    }
+}
+
+impl fmt::Debug for ProcessGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessGroup")
+            .field("pgid", &self.pgid)
+            .field("session", &self.session)
+            .finish()
+    }
+}
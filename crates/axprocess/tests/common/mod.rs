@@ -0,0 +1,31 @@
+use std::sync::{
+    Arc, Once,
+    atomic::{AtomicU32, Ordering},
+};
+
+use axprocess::Process;
+
+static PID: AtomicU32 = AtomicU32::new(0);
+
+fn alloc_pid() -> u32 {
+    PID.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Gets the init process, creating it on first use.
+pub fn init_proc() -> Arc<Process> {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        Process::new_init(alloc_pid()).build();
+    });
+    axprocess::init_proc()
+}
+
+pub trait ProcessExt {
+    fn new_child(&self) -> Self;
+}
+
+impl ProcessExt for Arc<Process> {
+    fn new_child(&self) -> Self {
+        self.fork(alloc_pid()).build()
+    }
+}
@@ -0,0 +1,131 @@
+use axhal::arch::{FpState, TrapFrame};
+
+use crate::{SignalSet, SignalStack};
+
+core::arch::global_asm!(
+    "
+.section .text
+.balign 4096
+.global signal_trampoline
+signal_trampoline:
+    mov x8, #139
+    svc #0
+
+.fill 4096 - (. - signal_trampoline), 1, 0
+"
+);
+
+/// `FPSIMD_MAGIC`, identifying a [`FpsimdContext`] record.
+const FPSIMD_MAGIC: u32 = 0x4650_8001;
+
+/// `struct fpsimd_context`, the record of the FP/SIMD registers.
+#[repr(C)]
+#[derive(Clone)]
+struct FpsimdContext {
+    magic: u32,
+    size: u32,
+    fpsr: u32,
+    fpcr: u32,
+    vregs: [u128; 32],
+}
+
+/// The space for the records of extra registers, which are the FP/SIMD
+/// registers followed by a null record ending the list.
+#[repr(C, align(16))]
+#[derive(Clone)]
+struct MContextReserved {
+    fpsimd: FpsimdContext,
+    rest: [u8; 4096 - size_of::<FpsimdContext>()],
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct MContext {
+    fault_address: u64,
+    regs: [u64; 31],
+    sp: u64,
+    pc: u64,
+    pstate: u64,
+    __reserved: MContextReserved,
+}
+
+impl MContext {
+    pub fn new(tf: &TrapFrame) -> Self {
+        Self {
+            fault_address: 0,
+            regs: tf.r,
+            sp: tf.usp,
+            pc: tf.elr,
+            pstate: tf.spsr,
+            __reserved: MContextReserved {
+                fpsimd: FpsimdContext {
+                    magic: 0,
+                    size: 0,
+                    fpsr: 0,
+                    fpcr: 0,
+                    vregs: [0; 32],
+                },
+                rest: [0; 4096 - size_of::<FpsimdContext>()],
+            },
+        }
+    }
+
+    pub fn restore(&self, tf: &mut TrapFrame) {
+        tf.r = self.regs;
+        tf.usp = self.sp;
+        tf.elr = self.pc;
+        tf.spsr = self.pstate;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct UContext {
+    pub flags: usize,
+    pub link: usize,
+    pub stack: SignalStack,
+    pub sigmask: SignalSet,
+    __unused: [u8; 1024 / 8 - size_of::<SignalSet>()],
+    pub mcontext: MContext,
+}
+
+impl UContext {
+    pub fn new(tf: &TrapFrame, sigmask: SignalSet) -> Self {
+        Self {
+            flags: 0,
+            link: 0,
+            stack: SignalStack::default(),
+            sigmask,
+            __unused: [0; 1024 / 8 - size_of::<SignalSet>()],
+            mcontext: MContext::new(tf),
+        }
+    }
+
+    /// Saves the FP/SIMD state of the current CPU in place.
+    pub fn save_fp(&mut self) {
+        let mut state = FpState::default();
+        state.save();
+        self.mcontext.__reserved.fpsimd = FpsimdContext {
+            magic: FPSIMD_MAGIC,
+            size: size_of::<FpsimdContext>() as u32,
+            fpsr: state.fpsr,
+            fpcr: state.fpcr,
+            vregs: state.regs,
+        };
+    }
+
+    /// Restores the FP/SIMD state of the current CPU, if it is in the
+    /// context.
+    pub fn restore_fp(&self, _tf: &mut TrapFrame) {
+        let fpsimd = &self.mcontext.__reserved.fpsimd;
+        if fpsimd.magic != FPSIMD_MAGIC {
+            return;
+        }
+        FpState {
+            regs: fpsimd.vregs,
+            fpcr: fpsimd.fpcr,
+            fpsr: fpsimd.fpsr,
+        }
+        .restore();
+    }
+}
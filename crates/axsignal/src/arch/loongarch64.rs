@@ -0,0 +1,135 @@
+use core::mem;
+
+use axhal::arch::{FpStatus, GeneralRegisters, TrapFrame};
+
+use crate::{SignalSet, SignalStack};
+
+core::arch::global_asm!(
+    "
+.section .text
+.balign 4096
+.global signal_trampoline
+signal_trampoline:
+    li.w    $a7, 139
+    syscall 0
+
+.fill 4096 - (. - signal_trampoline), 1, 0
+"
+);
+
+/// `SC_USED_FP`, set in `sc_flags` if the context has the FP state.
+const SC_USED_FP: u32 = 1;
+/// `FPU_CTX_MAGIC`, identifying a [`FpuContext`] record.
+const FPU_CTX_MAGIC: u32 = 0x4650_5501;
+
+/// `struct sctx_info`, the header of a record of extra registers.
+#[repr(C)]
+#[derive(Clone, Default)]
+struct SctxInfo {
+    magic: u32,
+    size: u32,
+    padding: u64,
+}
+
+/// `struct fpu_context`, the record of the floating-point registers.
+#[repr(C)]
+#[derive(Clone, Default)]
+struct FpuContext {
+    regs: [u64; 32],
+    fcc: u64,
+    fcsr: u32,
+}
+
+/// The records of extra registers, which are the floating-point registers
+/// followed by a null record ending the list.
+#[repr(C, align(16))]
+#[derive(Clone, Default)]
+struct ExtContext {
+    fpu_info: SctxInfo,
+    fpu: FpuContext,
+    end: SctxInfo,
+}
+
+#[repr(C, align(16))]
+#[derive(Clone)]
+pub struct MContext {
+    sc_pc: u64,
+    sc_regs: [u64; 32],
+    sc_flags: u32,
+    sc_extcontext: ExtContext,
+}
+
+impl MContext {
+    pub fn new(tf: &TrapFrame) -> Self {
+        Self {
+            sc_pc: tf.era as _,
+            sc_regs: unsafe { mem::transmute::<GeneralRegisters, [u64; 32]>(tf.regs) },
+            sc_flags: 0,
+            sc_extcontext: ExtContext::default(),
+        }
+    }
+
+    pub fn restore(&self, tf: &mut TrapFrame) {
+        tf.era = self.sc_pc as _;
+        unsafe {
+            tf.regs = mem::transmute::<[u64; 32], GeneralRegisters>(self.sc_regs);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct UContext {
+    pub flags: usize,
+    pub link: usize,
+    pub stack: SignalStack,
+    pub sigmask: SignalSet,
+    __unused: [u8; 1024 / 8 - size_of::<SignalSet>()],
+    pub mcontext: MContext,
+}
+
+impl UContext {
+    pub fn new(tf: &TrapFrame, sigmask: SignalSet) -> Self {
+        Self {
+            flags: 0,
+            link: 0,
+            stack: SignalStack::default(),
+            sigmask,
+            __unused: [0; 1024 / 8 - size_of::<SignalSet>()],
+            mcontext: MContext::new(tf),
+        }
+    }
+
+    /// Saves the floating-point state of the current CPU in place.
+    pub fn save_fp(&mut self) {
+        let mut status = FpStatus::default();
+        status.save();
+        let ext = &mut self.mcontext.sc_extcontext;
+        ext.fpu_info = SctxInfo {
+            magic: FPU_CTX_MAGIC,
+            size: (size_of::<SctxInfo>() + size_of::<FpuContext>()) as u32,
+            padding: 0,
+        };
+        ext.fpu = FpuContext {
+            regs: status.fp,
+            fcc: u64::from_le_bytes(status.fcc),
+            fcsr: status.fcsr as u32,
+        };
+        self.mcontext.sc_flags |= SC_USED_FP;
+    }
+
+    /// Restores the floating-point state of the current CPU, if it is in the
+    /// context.
+    pub fn restore_fp(&self, _tf: &mut TrapFrame) {
+        let ext = &self.mcontext.sc_extcontext;
+        if ext.fpu_info.magic != FPU_CTX_MAGIC {
+            return;
+        }
+        FpStatus {
+            fp: ext.fpu.regs,
+            fcc: ext.fpu.fcc.to_le_bytes(),
+            fcsr: ext.fpu.fcsr as usize,
+        }
+        .restore();
+    }
+}
@@ -0,0 +1,109 @@
+use axhal::arch::{FpStatus, GeneralRegisters, TrapFrame};
+
+use crate::{SignalSet, SignalStack};
+
+core::arch::global_asm!(
+    "
+.section .text
+.balign 4096
+.global signal_trampoline
+signal_trampoline:
+    li a7, 139
+    ecall
+
+.fill 4096 - (. - signal_trampoline), 1, 0
+"
+);
+
+/// The `FS` field of `sstatus`, all of whose bits are set when the FP state
+/// is dirty.
+const SSTATUS_FS: usize = 0b11 << 13;
+
+/// `union __riscv_fp_state`, in the layout of the D extension.
+///
+/// The V extension is not supported. Linux appends the vector state,
+/// `struct __riscv_v_ext_state`, to the reserved space as an extension with
+/// its own header, but there is none to save: axhal keeps `sstatus.VS` off in
+/// user space and does not switch vector registers, `AT_HWCAP` reports no V,
+/// and vector instructions raise `SIGILL`. The zeroed reserved space reads as
+/// the end header, which tells programs there is no vector state.
+#[repr(C)]
+#[derive(Clone)]
+struct FpRegs {
+    f: [u64; 32],
+    fcsr: u32,
+    _reserved: [u32; 67],
+}
+
+#[repr(C, align(16))]
+#[derive(Clone)]
+pub struct MContext {
+    pub pc: usize,
+    regs: GeneralRegisters,
+    fpstate: FpRegs,
+}
+
+impl MContext {
+    pub fn new(tf: &TrapFrame) -> Self {
+        Self {
+            pc: tf.sepc,
+            regs: tf.regs,
+            fpstate: FpRegs {
+                f: [0; 32],
+                fcsr: 0,
+                _reserved: [0; 67],
+            },
+        }
+    }
+
+    pub fn restore(&self, tf: &mut TrapFrame) {
+        tf.sepc = self.pc;
+        tf.regs = self.regs;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct UContext {
+    pub flags: usize,
+    pub link: usize,
+    pub stack: SignalStack,
+    pub sigmask: SignalSet,
+    __unused: [u8; 1024 / 8 - size_of::<SignalSet>()],
+    pub mcontext: MContext,
+}
+
+impl UContext {
+    pub fn new(tf: &TrapFrame, sigmask: SignalSet) -> Self {
+        Self {
+            flags: 0,
+            link: 0,
+            stack: SignalStack::default(),
+            sigmask,
+            __unused: [0; 1024 / 8 - size_of::<SignalSet>()],
+            mcontext: MContext::new(tf),
+        }
+    }
+
+    /// Saves the floating-point state of the current CPU in place.
+    pub fn save_fp(&mut self) {
+        let mut status = FpStatus::default();
+        status.save();
+        self.mcontext.fpstate.f = status.fp;
+        self.mcontext.fpstate.fcsr = status.fcsr as u32;
+    }
+
+    /// Restores the floating-point state of the current CPU, marking it dirty
+    /// in `tf` to be saved on context switches.
+    pub fn restore_fp(&self, tf: &mut TrapFrame) {
+        let status = FpStatus {
+            fp: self.mcontext.fpstate.f,
+            fcsr: self.mcontext.fpstate.fcsr as usize,
+            ..Default::default()
+        };
+        status.restore();
+        if tf.sstatus & SSTATUS_FS != 0 {
+            tf.sstatus |= SSTATUS_FS;
+        }
+    }
+}
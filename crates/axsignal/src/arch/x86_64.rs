@@ -0,0 +1,183 @@
+use axhal::arch::{ExtendedState, TrapFrame};
+
+use crate::{SignalSet, SignalStack};
+
+core::arch::global_asm!(
+    "
+.section .text
+.code64
+.balign 4096
+.global signal_trampoline
+signal_trampoline:
+    mov rax, 0xf
+    syscall
+
+.fill 4096 - (. - signal_trampoline), 1, 0
+"
+);
+
+/// `FP_XSTATE_MAGIC1`, marking an XSAVE area in the software-reserved bytes
+/// of the legacy region.
+const FP_XSTATE_MAGIC1: u32 = 0x4650_5853;
+/// `FP_XSTATE_MAGIC2`, following an XSAVE area.
+const FP_XSTATE_MAGIC2: u32 = 0x4650_5845;
+/// `UC_FP_XSTATE`, set in `uc_flags` if the FP state is an XSAVE area.
+const UC_FP_XSTATE: usize = 1;
+
+/// The FP/SIMD state in a signal frame, which `fpstate` in [`MContext`] points
+/// to.
+#[repr(C)]
+#[derive(Clone)]
+struct FpState {
+    state: ExtendedState,
+    magic2: u32,
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct MContext {
+    r8: usize,
+    r9: usize,
+    r10: usize,
+    r11: usize,
+    r12: usize,
+    r13: usize,
+    r14: usize,
+    r15: usize,
+    rdi: usize,
+    rsi: usize,
+    rbp: usize,
+    rbx: usize,
+    rdx: usize,
+    rax: usize,
+    rcx: usize,
+    rsp: usize,
+    rip: usize,
+    eflags: usize,
+    cs: u16,
+    gs: u16,
+    fs: u16,
+    _pad: u16,
+    err: usize,
+    trapno: usize,
+    oldmask: usize,
+    cr2: usize,
+    fpstate: usize,
+    _reserved1: [usize; 8],
+}
+
+impl MContext {
+    pub fn new(tf: &TrapFrame) -> Self {
+        Self {
+            r8: tf.r8 as _,
+            r9: tf.r9 as _,
+            r10: tf.r10 as _,
+            r11: tf.r11 as _,
+            r12: tf.r12 as _,
+            r13: tf.r13 as _,
+            r14: tf.r14 as _,
+            r15: tf.r15 as _,
+            rdi: tf.rdi as _,
+            rsi: tf.rsi as _,
+            rbp: tf.rbp as _,
+            rbx: tf.rbx as _,
+            rdx: tf.rdx as _,
+            rax: tf.rax as _,
+            rcx: tf.rcx as _,
+            rsp: tf.rsp as _,
+            rip: tf.rip as _,
+            eflags: tf.rflags as _,
+            cs: tf.cs as _,
+            gs: 0,
+            fs: 0,
+            _pad: 0,
+            err: tf.error_code as _,
+            trapno: tf.vector as _,
+            oldmask: 0,
+            cr2: 0,
+            fpstate: 0,
+            _reserved1: [0; 8],
+        }
+    }
+
+    pub fn restore(&self, tf: &mut TrapFrame) {
+        tf.r8 = self.r8 as _;
+        tf.r9 = self.r9 as _;
+        tf.r10 = self.r10 as _;
+        tf.r11 = self.r11 as _;
+        tf.r12 = self.r12 as _;
+        tf.r13 = self.r13 as _;
+        tf.r14 = self.r14 as _;
+        tf.r15 = self.r15 as _;
+        tf.rdi = self.rdi as _;
+        tf.rsi = self.rsi as _;
+        tf.rbp = self.rbp as _;
+        tf.rbx = self.rbx as _;
+        tf.rdx = self.rdx as _;
+        tf.rax = self.rax as _;
+        tf.rcx = self.rcx as _;
+        tf.rsp = self.rsp as _;
+        tf.rip = self.rip as _;
+        tf.rflags = self.eflags as _;
+        tf.cs = self.cs as _;
+        tf.error_code = self.err as _;
+        tf.vector = self.trapno as _;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct UContext {
+    pub flags: usize,
+    pub link: usize,
+    pub stack: SignalStack,
+    pub mcontext: MContext,
+    pub sigmask: SignalSet,
+    fpstate: FpState,
+}
+
+impl UContext {
+    pub fn new(tf: &TrapFrame, sigmask: SignalSet) -> Self {
+        Self {
+            flags: 0,
+            link: 0,
+            stack: SignalStack::default(),
+            mcontext: MContext::new(tf),
+            sigmask,
+            fpstate: FpState {
+                state: ExtendedState::new(),
+                magic2: 0,
+            },
+        }
+    }
+
+    /// Saves the FP/SIMD state of the current CPU in place, and resets it to
+    /// the initial state for the signal handler.
+    pub fn save_fp(&mut self) {
+        let fpstate = &mut self.fpstate;
+        fpstate.state.save();
+        let xfeatures = ExtendedState::xsave_mask();
+        if xfeatures != 0 {
+            // Describes the XSAVE area as `struct _fpx_sw_bytes`.
+            let xstate_size = size_of::<ExtendedState>() as u32;
+            let sw = &mut fpstate.state.fxsave_area.sw_reserved;
+            sw.fill(0);
+            sw[0] = FP_XSTATE_MAGIC1;
+            sw[1] = xstate_size + size_of::<u32>() as u32;
+            sw[2] = xfeatures as u32;
+            sw[3] = (xfeatures >> 32) as u32;
+            sw[4] = xstate_size;
+            fpstate.magic2 = FP_XSTATE_MAGIC2;
+            self.flags |= UC_FP_XSTATE;
+        }
+        self.mcontext.fpstate = fpstate as *const _ as usize;
+        ExtendedState::new().restore();
+    }
+
+    /// Restores the FP/SIMD state of the current CPU.
+    pub fn restore_fp(&self, _tf: &mut TrapFrame) {
+        let mut state = self.fpstate.state.clone();
+        state.sanitize();
+        state.restore();
+    }
+}
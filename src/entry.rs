@@ -4,10 +4,15 @@ use axhal::arch::UspaceContext;
 use axprocess::{Pid, init_proc};
 use axsignal::Signo;
 use axsync::Mutex;
-use starry_api::file::FD_TABLE;
+use axtask::TaskExtRef;
+use starry_api::file::{FD_TABLE, console};
 use starry_core::{
-    mm::{copy_from_kernel, load_user_app, map_trampoline, new_user_aspace_empty},
+    cred::Credentials,
+    mm::{
+        copy_from_kernel, load_user_app, map_trampoline, new_user_aspace_empty, prepare_user_app,
+    },
     task::{ProcessData, TaskExt, ThreadData, add_thread_to_table, new_user_task},
+    vdso::map_vdso,
 };
 
 pub fn run_user_app(args: &[String], envs: &[String]) -> Option<i32> {
@@ -15,6 +20,7 @@ pub fn run_user_app(args: &[String], envs: &[String]) -> Option<i32> {
         .and_then(|mut it| {
             copy_from_kernel(&mut it)?;
             map_trampoline(&mut it)?;
+            map_vdso(&mut it)?;
             Ok(it)
         })
         .expect("Failed to create user address space");
@@ -23,10 +29,11 @@ pub fn run_user_app(args: &[String], envs: &[String]) -> Option<i32> {
     let (dir, name) = exe_path.rsplit_once('/').unwrap_or(("", &exe_path));
     set_current_dir(dir).expect("Failed to set current dir");
 
-    let (entry_vaddr, ustack_top) = load_user_app(&mut uspace, args, envs)
+    let app = prepare_user_app(&exe_path, args)
+        .and_then(|app| load_user_app(&mut uspace, &app, envs))
         .unwrap_or_else(|e| panic!("Failed to load user app: {}", e));
 
-    let uctx = UspaceContext::new(entry_vaddr.into(), ustack_top, 2333);
+    let uctx = UspaceContext::new(app.entry.into(), app.ustack_top, 2333);
 
     let mut task = new_user_task(name, uctx, None);
     task.ctx_mut().set_page_table_root(uspace.page_table_root());
@@ -36,7 +43,10 @@ pub fn run_user_app(args: &[String], envs: &[String]) -> Option<i32> {
         Arc::new(Mutex::new(uspace)),
         Arc::default(),
         Some(Signo::SIGCHLD),
+        Arc::new(Credentials::root()),
     );
+    *process_data.auxv.lock() = app.auxv;
+    *process_data.file_mappings.lock() = app.file_mappings;
 
     FD_TABLE
         .deref_from(&process_data.ns)
@@ -50,16 +60,23 @@ pub fn run_user_app(args: &[String], envs: &[String]) -> Option<i32> {
 
     let tid = task.id().as_u64() as Pid;
     let process = init_proc().fork(tid).data(process_data).build();
+    // The app leads its own session, with the console as its controlling
+    // terminal, taken over from any previous app.
+    process.create_session();
 
     let thread = process
         .new_thread(tid)
         .data(ThreadData::new(process.data().unwrap()))
         .build();
     add_thread_to_table(&thread);
+    console()
+        .set_controlling(&process, true)
+        .expect("Failed to set the controlling terminal");
 
     task.init_task_ext(TaskExt::new(thread));
 
     let task = axtask::spawn_task(task);
+    task.task_ext().thread_data().set_task(&task);
 
     // TODO: we need a way to wait on the process but not only the main task
     task.join()
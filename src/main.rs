@@ -16,6 +16,8 @@ fn main() {
     // Create a init process
     axprocess::Process::new_init(axtask::current().id().as_u64() as _).build();
 
+    starry_api::file::mount_devpts().expect("Failed to mount devpts");
+
     let testcases = option_env!("AX_TESTCASES_LIST")
         .unwrap_or_else(|| "Please specify the testcases list by making user_apps")
         .split(',')
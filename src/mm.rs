@@ -3,9 +3,11 @@ use axhal::{
     paging::MappingFlags,
     trap::{PAGE_FAULT, register_trap_handler},
 };
+use axsignal::Signo;
 use axtask::{TaskExtRef, current};
-use linux_raw_sys::general::SIGSEGV;
-use starry_api::do_exit;
+use linux_raw_sys::general::{SEGV_ACCERR, SEGV_MAPERR, SIGSEGV};
+use memory_addr::VirtAddrRange;
+use starry_api::{do_exit, signal::send_fault_signal};
 use starry_core::mm::is_accessing_user_memory;
 
 #[register_trap_handler(PAGE_FAULT)]
@@ -19,13 +21,23 @@ fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool)
     }
 
     let curr = current();
-    if !curr
-        .task_ext()
-        .process_data()
-        .aspace
-        .lock()
-        .handle_page_fault(vaddr, access_flags)
-    {
+    let aspace = curr.task_ext().process_data().aspace();
+    let mut aspace = aspace.lock();
+    if aspace.handle_page_fault(vaddr, access_flags) {
+        return true;
+    }
+    if is_user {
+        // A mapped address was accessed without the permission to, otherwise
+        // the address itself is invalid.
+        let mapped = aspace.check_region_access(
+            VirtAddrRange::from_start_size(vaddr, 1),
+            MappingFlags::empty(),
+        );
+        drop(aspace);
+        let code = if mapped { SEGV_ACCERR } else { SEGV_MAPERR };
+        send_fault_signal(Signo::SIGSEGV, code, vaddr);
+    } else {
+        drop(aspace);
         warn!(
             "{} ({:?}): segmentation fault at {:#x}, exit!",
             curr.id_name(),
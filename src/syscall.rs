@@ -3,15 +3,34 @@ use axhal::{
     arch::TrapFrame,
     trap::{SYSCALL, register_trap_handler},
 };
-use starry_api::*;
+use starry_api::{
+    ptrace::{ptrace_syscall_entry, ptrace_syscall_exit},
+    seccomp::seccomp_syscall_entry,
+    signal::restart_syscall,
+    *,
+};
 use starry_core::task::{time_stat_from_kernel_to_user, time_stat_from_user_to_kernel};
 use syscalls::Sysno;
 
 #[register_trap_handler(SYSCALL)]
 fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
+    time_stat_from_user_to_kernel();
+    // The tracer or the seccomp filters may skip the syscall, setting its
+    // return value themselves.
+    let syscall_num = ptrace_syscall_entry(tf, syscall_num)
+        .filter(|&syscall_num| seccomp_syscall_entry(tf, syscall_num));
+    if let Some(syscall_num) = syscall_num {
+        let ans = do_syscall(tf, syscall_num);
+        tf.set_retval(ans as _);
+    }
+    ptrace_syscall_exit(tf);
+    time_stat_from_kernel_to_user();
+    tf.retval() as _
+}
+
+fn do_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
     let sysno = Sysno::from(syscall_num as u32);
     info!("Syscall {}", sysno);
-    time_stat_from_user_to_kernel();
     let result = match sysno {
         // fs ctl
         Sysno::ioctl => sys_ioctl(tf.arg0() as _, tf.arg1() as _, tf.arg2().into()),
@@ -32,6 +51,40 @@ fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
         Sysno::unlink => sys_unlink(tf.arg0().into()),
         Sysno::getcwd => sys_getcwd(tf.arg0().into(), tf.arg1() as _),
 
+        // fs perm
+        Sysno::fchmodat => sys_fchmodat(tf.arg0() as _, tf.arg1().into(), tf.arg2() as _, 0),
+        Sysno::fchmodat2 => sys_fchmodat(
+            tf.arg0() as _,
+            tf.arg1().into(),
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::fchmod => sys_fchmod(tf.arg0() as _, tf.arg1() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::chmod => sys_chmod(tf.arg0().into(), tf.arg1() as _),
+        Sysno::fchownat => sys_fchownat(
+            tf.arg0() as _,
+            tf.arg1().into(),
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::fchown => sys_fchown(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::chown => sys_chown(tf.arg0().into(), tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::lchown => sys_lchown(tf.arg0().into(), tf.arg1() as _, tf.arg2() as _),
+        Sysno::umask => sys_umask(tf.arg0() as _),
+        Sysno::faccessat => sys_faccessat(tf.arg0() as _, tf.arg1().into(), tf.arg2() as _),
+        Sysno::faccessat2 => sys_faccessat2(
+            tf.arg0() as _,
+            tf.arg1().into(),
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::access => sys_access(tf.arg0().into(), tf.arg1() as _),
+
         // fd ops
         Sysno::openat => sys_openat(
             tf.arg0() as _,
@@ -115,6 +168,12 @@ fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
         Sysno::getpid => sys_getpid(),
         Sysno::getppid => sys_getppid(),
         Sysno::gettid => sys_gettid(),
+        Sysno::getpgid => sys_getpgid(tf.arg0() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::getpgrp => sys_getpgrp(),
+        Sysno::setpgid => sys_setpgid(tf.arg0() as _, tf.arg1() as _),
+        Sysno::getsid => sys_getsid(tf.arg0() as _),
+        Sysno::setsid => sys_setsid(),
 
         // task sched
         Sysno::sched_yield => sys_sched_yield(),
@@ -123,6 +182,8 @@ fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
         // task ops
         Sysno::execve => sys_execve(tf, tf.arg0().into(), tf.arg1().into(), tf.arg2().into()),
         Sysno::set_tid_address => sys_set_tid_address(tf.arg0()),
+        Sysno::prctl => sys_prctl(tf.arg0() as _, tf.arg1(), tf.arg2(), tf.arg3(), tf.arg4()),
+        Sysno::seccomp => sys_seccomp(tf.arg0() as _, tf.arg1() as _, tf.arg2()),
         #[cfg(target_arch = "x86_64")]
         Sysno::arch_prctl => sys_arch_prctl(tf, tf.arg0() as _, tf.arg1() as _),
 
@@ -135,11 +196,29 @@ fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
             tf.arg3(),
             tf.arg4(),
         ),
+        Sysno::clone3 => sys_clone3(tf, tf.arg0(), tf.arg1()),
         #[cfg(target_arch = "x86_64")]
         Sysno::fork => sys_fork(tf),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::vfork => sys_vfork(tf),
         Sysno::exit => sys_exit(tf.arg0() as _),
         Sysno::exit_group => sys_exit_group(tf.arg0() as _),
-        Sysno::wait4 => sys_waitpid(tf.arg0() as _, tf.arg1().into(), tf.arg2() as _),
+        Sysno::wait4 => sys_waitpid(
+            tf.arg0() as _,
+            tf.arg1().into(),
+            tf.arg2() as _,
+            tf.arg3().into(),
+        ),
+        Sysno::waitid => sys_waitid(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2().into(),
+            tf.arg3() as _,
+            tf.arg4().into(),
+        ),
+        Sysno::ptrace => sys_ptrace(tf.arg0() as _, tf.arg1() as _, tf.arg2(), tf.arg3()),
+        Sysno::pidfd_open => sys_pidfd_open(tf.arg0() as _, tf.arg1() as _),
+        Sysno::pidfd_getfd => sys_pidfd_getfd(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
 
         // signal
         Sysno::rt_sigprocmask => sys_rt_sigprocmask(
@@ -166,6 +245,12 @@ fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
         Sysno::kill => sys_kill(tf.arg0() as _, tf.arg1() as _),
         Sysno::tkill => sys_tkill(tf.arg0() as _, tf.arg1() as _),
         Sysno::tgkill => sys_tgkill(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::pidfd_send_signal => sys_pidfd_send_signal(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2().into(),
+            tf.arg3() as _,
+        ),
         Sysno::rt_sigqueueinfo => sys_rt_sigqueueinfo(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -189,25 +274,74 @@ fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
             tf.arg5() as _,
         ),
 
-        // sys
+        // cred
         Sysno::getuid => sys_getuid(),
         Sysno::geteuid => sys_geteuid(),
         Sysno::getgid => sys_getgid(),
         Sysno::getegid => sys_getegid(),
+        Sysno::setuid => sys_setuid(tf.arg0() as _),
+        Sysno::setgid => sys_setgid(tf.arg0() as _),
+        Sysno::setreuid => sys_setreuid(tf.arg0() as _, tf.arg1() as _),
+        Sysno::setregid => sys_setregid(tf.arg0() as _, tf.arg1() as _),
+        Sysno::setresuid => sys_setresuid(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::setresgid => sys_setresgid(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::getresuid => sys_getresuid(tf.arg0().into(), tf.arg1().into(), tf.arg2().into()),
+        Sysno::getresgid => sys_getresgid(tf.arg0().into(), tf.arg1().into(), tf.arg2().into()),
+        Sysno::setfsuid => sys_setfsuid(tf.arg0() as _),
+        Sysno::setfsgid => sys_setfsgid(tf.arg0() as _),
+        Sysno::getgroups => sys_getgroups(tf.arg0() as _, tf.arg1().into()),
+        Sysno::setgroups => sys_setgroups(tf.arg0() as _, tf.arg1().into()),
+        Sysno::capget => sys_capget(tf.arg0().into(), tf.arg1().into()),
+        Sysno::capset => sys_capset(tf.arg0().into(), tf.arg1().into()),
+
+        // sys
         Sysno::uname => sys_uname(tf.arg0().into()),
+        Sysno::getcpu => sys_getcpu(tf.arg0().into(), tf.arg1().into()),
+        Sysno::prlimit64 => sys_prlimit64(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2().into(),
+            tf.arg3().into(),
+        ),
 
         // time
         Sysno::gettimeofday => sys_gettimeofday(tf.arg0().into()),
         Sysno::times => sys_times(tf.arg0().into()),
+        Sysno::settimeofday => sys_settimeofday(tf.arg0().into(), tf.arg1().into()),
         Sysno::clock_gettime => sys_clock_gettime(tf.arg0() as _, tf.arg1().into()),
+        Sysno::clock_getres => sys_clock_getres(tf.arg0() as _, tf.arg1().into()),
+        Sysno::clock_settime => sys_clock_settime(tf.arg0() as _, tf.arg1().into()),
+        Sysno::clock_nanosleep => sys_clock_nanosleep(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2().into(),
+            tf.arg3().into(),
+        ),
+        Sysno::getitimer => sys_getitimer(tf.arg0() as _, tf.arg1().into()),
+        Sysno::setitimer => sys_setitimer(tf.arg0() as _, tf.arg1().into(), tf.arg2().into()),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::alarm => sys_alarm(tf.arg0() as _),
+        Sysno::timer_create => sys_timer_create(tf.arg0() as _, tf.arg1().into(), tf.arg2().into()),
+        Sysno::timer_settime => sys_timer_settime(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2().into(),
+            tf.arg3().into(),
+        ),
+        Sysno::timer_gettime => sys_timer_gettime(tf.arg0() as _, tf.arg1().into()),
+        Sysno::timer_getoverrun => sys_timer_getoverrun(tf.arg0() as _),
+        Sysno::timer_delete => sys_timer_delete(tf.arg0() as _),
 
         _ => {
             warn!("Unimplemented syscall: {}", sysno);
             Err(LinuxError::ENOSYS)
         }
     };
-    let ans = result.unwrap_or_else(|err| -err.code() as _);
-    time_stat_from_kernel_to_user();
+    let ans = match result {
+        Ok(ans) => ans,
+        Err(LinuxError::ERESTART) => restart_syscall(tf, syscall_num),
+        Err(err) => -err.code() as _,
+    };
     info!("Syscall {:?} return {}", sysno, ans);
     ans
 }